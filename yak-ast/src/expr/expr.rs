@@ -1,12 +1,27 @@
 use crate::expr::pratt::{Affix, Associativity, NoError, PrattParser, Precedence, Result};
 use crate::{
-    ArithOp, Balance, BinaryExprStmt, BitwiseOp, BooleanOp, EnumValueStmt, Expr, FuncValueStmt,
-    LogicalOp, Op, Parse, StructValueStmt, TupleValueStmt, UnaryExprStmt, UnaryOp, Value,
-    ValueStmt,
+    ArithOp, AssignOp, Balance, BinaryExprStmt, BitwiseOp, BooleanOp, ClosureValueStmt,
+    CollectionValueStmt, EnumValueStmt, Expr, ExprStmt, FuncTypeStmt, FuncValueStmt, LogicalOp, Op,
+    Parse, PostfixExprStmt, PostfixOp, StructValueStmt, TupleFieldValueStmt, TupleValueStmt,
+    TypeStmt, UnaryExprStmt, UnaryOp, Value, ValueStmt,
 };
+use yak_core::types::types::TUPLE_TYPE;
 use yak_lexer::token::TokenType as Ty;
 use yak_lexer::Token;
 
+#[cfg(not(test))]
+use log::error;
+#[cfg(test)]
+use std::println as error;
+
+// Statement parse errors are logged like expression errors
+impl From<anyhow::Error> for NoError {
+    fn from(err: anyhow::Error) -> Self {
+        error!("Err: #{:}", &err);
+        NoError
+    }
+}
+
 // Precedence value ordering
 // (not all of these are currently implemented)
 // 0  non-binding operators
@@ -22,7 +37,7 @@ use yak_lexer::Token;
 // 100 infix operator + -
 // 110 infix operator * / % //
 // 120 infix operator **
-// >> 130 cast type as
+// 130 cast type as
 // 140	unary operators
 //      + - !
 // 150 return errors ?
@@ -42,6 +57,22 @@ where
     // Query information about an operator (Affix, Precedence, Associativity)
     fn query(&mut self, tok: &Self::Input) -> Result<Affix> {
         let affix = match tok.ty {
+            // Assignment
+            // = += -= *= /= //= %= **= &= |= ^= <<= >>=
+            Ty::OpAssignEq
+            | Ty::OpAssignAdd
+            | Ty::OpAssignSub
+            | Ty::OpAssignMul
+            | Ty::OpAssignDiv
+            | Ty::OpAssignFloorDiv
+            | Ty::OpAssignMod
+            | Ty::OpAssignPow
+            | Ty::OpAssignBitwiseAnd
+            | Ty::OpAssignBitwiseOr
+            | Ty::OpAssignBitwiseXOr
+            | Ty::OpAssignBitwiseShiftL
+            | Ty::OpAssignBitwiseShiftR => Affix::Infix(Precedence(10), Associativity::Right),
+
             // Logical
             // || &&
             Ty::OpLogicalOr => Affix::Infix(Precedence(30), Associativity::Left),
@@ -74,13 +105,16 @@ where
             Ty::OpMod => Affix::Infix(Precedence(110), Associativity::Left),
             Ty::OpPow => Affix::Infix(Precedence(120), Associativity::Left),
 
-            // KwAs goes here...
-            // Ty::KwAs => Affix::Infix(Precedence(130), Associativity::Left),
+            // as (the rhs is a type)
+            Ty::KwAs => Affix::Postfix(Precedence(130)),
             // - + !
             Ty::OpUnaryMinus => Affix::Prefix(Precedence(140)),
             Ty::OpUnaryPlus => Affix::Prefix(Precedence(140)),
             Ty::OpUnaryNot | Ty::PunctExclamation => Affix::Prefix(Precedence(140)),
 
+            // ?
+            Ty::PunctQuestion => Affix::Postfix(Precedence(150)),
+
             // Literals
             Ty::PunctParenL => Affix::Nilfix,
             Ty::LitString(_) => Affix::Nilfix,
@@ -91,14 +125,34 @@ where
             Ty::IdTrait(_) => Affix::Nilfix,
             Ty::IdType(_) => Affix::Nilfix,
             Ty::IdVar(_) => Affix::Nilfix,
+            Ty::KwSelf => Affix::Nilfix,
+            // Closures (i.e. `fn { x: int } int => x + 1`)
+            Ty::KwFn => Affix::Nilfix,
+            // Builtin types (i.e. `Map:new {}` or `Set[int]:from {}`)
+            Ty::BuiltinTypeOption
+            | Ty::BuiltinTypeList
+            | Ty::BuiltinTypeMap
+            | Ty::BuiltinTypeMaybe
+            | Ty::BuiltinTypeNone
+            | Ty::BuiltinTypeSet
+            | Ty::BuiltinTypeString => Affix::Nilfix,
             _ => {
-                println!("ExprParser.query: unreachable token #{:?}", &tok);
-                unreachable!()
+                error!("ExprParser.query: unexpected token #{:?}", &tok);
+                return Err(NoError);
             }
         };
         Ok(affix)
     }
 
+    // Query an operator which follows an expression
+    fn query_next(&mut self, tok: &Self::Input) -> Result<Affix> {
+        match tok.ty {
+            // value:func { ... }, value.field and value[index]
+            Ty::IdFunc(_) | Ty::PunctDot | Ty::PunctBracketL => Ok(Affix::Postfix(Precedence(160))),
+            _ => <Self as PrattParser<I>>::query(self, tok),
+        }
+    }
+
     // Construct a primary expression, e.g. a number
     fn primary(&mut self, tok: Self::Input, inputs: &mut core::iter::Peekable<I>) -> Result<Expr> {
         let result = match tok.ty {
@@ -175,29 +229,13 @@ where
             Ty::IdFunc(_) => {
                 // this should take the entire func call
                 // and is similar to a StructValueStmt
-                let mut balance = Balance::default();
-                balance.brace_l += 1;
-
                 let mut group: Vec<Token> = vec![];
-                while let Some(next_tok) = inputs.next() {
-                    match next_tok.ty {
-                        Ty::PunctBraceL => {
-                            balance.brace_l += 1;
-                        }
-                        Ty::PunctBraceR => {
-                            balance.brace_r += 1;
-                        }
-                        _ => {}
-                    }
-                    if balance.balanced_braces() {
-                        break;
-                    } else {
-                        group.push(next_tok);
-                    }
+                if let Some(Ty::PunctBraceL) = inputs.peek().map(|t| &t.ty) {
+                    group = take_group(inputs, Ty::PunctBraceL, Ty::PunctBraceR);
                 }
                 // make this a stack
                 group.reverse();
-                // add the IdType back
+                // add the IdFunc back
                 group.push(tok);
 
                 let func_val_stmt =
@@ -206,6 +244,117 @@ where
                     value: Value::Func(func_val_stmt),
                 }))
             }
+            Ty::KwSelf => Ok(Expr::Value(ValueStmt {
+                value: Value::Var(tok.ty.into()),
+            })),
+            Ty::KwFn => {
+                // the signature is everything before `=>`
+                // and the body is the next expression
+                let mut depth = 0usize;
+                let mut group: Vec<Token> = vec![];
+                for next_tok in inputs.by_ref() {
+                    match next_tok.ty {
+                        Ty::PunctBraceL => depth += 1,
+                        Ty::PunctBraceR => depth -= 1,
+                        Ty::PunctFatArrow if depth == 0 => break,
+                        _ => {}
+                    }
+                    group.push(next_tok);
+                }
+                // make this a stack
+                group.reverse();
+                let func_type = FuncTypeStmt::parse(&mut group)?;
+                match self.parse_peekable(inputs) {
+                    Ok(expr) => Ok(Expr::Value(ValueStmt {
+                        value: Value::Closure(ClosureValueStmt {
+                            func_type,
+                            body: Box::new(ExprStmt { expr }),
+                        }),
+                    })),
+                    Err(err) => {
+                        error!("Err: #{:}", &err);
+                        Err(NoError)
+                    }
+                }
+            }
+            Ty::BuiltinTypeNone
+                if !matches!(
+                    inputs.peek().map(|t| &t.ty),
+                    Some(Ty::PunctBracketL) | Some(Ty::IdFunc(_))
+                ) =>
+            {
+                Ok(Expr::Value(ValueStmt { value: Value::None }))
+            }
+            Ty::IdType(_)
+            | Ty::IdTrait(_)
+            | Ty::BuiltinTypeOption
+            | Ty::BuiltinTypeList
+            | Ty::BuiltinTypeMap
+            | Ty::BuiltinTypeMaybe
+            | Ty::BuiltinTypeNone
+            | Ty::BuiltinTypeSet
+            | Ty::BuiltinTypeString
+                if matches!(
+                    inputs.peek().map(|t| &t.ty),
+                    Some(Ty::PunctBracketL) | Some(Ty::IdFunc(_))
+                ) =>
+            {
+                // Types with generics or static type functions:
+                // - Type[T] { ... }
+                // - Type:func { ... }
+                // - Type[K V]:from { ... }
+                // - ^Trait:func { ... } (trait object calls)
+                let mut type_group = vec![tok];
+                if let Some(Ty::PunctBracketL) = inputs.peek().map(|t| &t.ty) {
+                    type_group.extend(take_group(inputs, Ty::PunctBracketL, Ty::PunctBracketR));
+                }
+
+                match inputs.peek().map(|t| t.ty.clone()) {
+                    Some(Ty::IdFunc(name)) => {
+                        let func_tok = inputs.next().unwrap();
+                        let mut group = vec![func_tok];
+                        if let Some(Ty::PunctBraceL) = inputs.peek().map(|t| &t.ty) {
+                            group.extend(take_group(inputs, Ty::PunctBraceL, Ty::PunctBraceR));
+                        }
+                        if name == ":from" {
+                            type_group.extend(group);
+                            type_group.reverse();
+                            let collection_value = CollectionValueStmt::parse(&mut type_group)?;
+                            Ok(Expr::Value(ValueStmt {
+                                value: Value::Collection(collection_value),
+                            }))
+                        } else {
+                            type_group.reverse();
+                            group.reverse();
+                            let mut func_val_stmt = FuncValueStmt::parse(&mut group)?;
+                            func_val_stmt.func_type = Some(TypeStmt::parse(&mut type_group)?);
+                            Ok(Expr::Value(ValueStmt {
+                                value: Value::Func(func_val_stmt),
+                            }))
+                        }
+                    }
+                    _ => {
+                        // generic struct or tuple value
+                        let type_len = type_group.len();
+                        if let Some(Ty::PunctBraceL) = inputs.peek().map(|t| &t.ty) {
+                            type_group.extend(take_group(inputs, Ty::PunctBraceL, Ty::PunctBraceR));
+                        }
+                        let is_struct = is_struct_value(&type_group[type_len..]);
+                        type_group.reverse();
+                        if is_struct {
+                            let struct_value = StructValueStmt::parse(&mut type_group)?;
+                            Ok(Expr::Value(ValueStmt {
+                                value: Value::Struct(struct_value),
+                            }))
+                        } else {
+                            let tup_value = TupleValueStmt::parse(&mut type_group)?;
+                            Ok(Expr::Value(ValueStmt {
+                                value: Value::Tuple(tup_value),
+                            }))
+                        }
+                    }
+                }
+            }
             Ty::IdType(_) => {
                 // IdType can be one of:
                 // - StructValueStmt
@@ -221,47 +370,40 @@ where
 
                 // This should take the entire `Type {}` value call
                 // and is either a StructValueStmt or TupleValueStmt
-                let mut balance = Balance::default();
-                balance.brace_l += 1;
-
                 let mut group: Vec<Token> = vec![];
-                while let Some(next_tok) = inputs.next() {
-                    match next_tok.ty {
-                        Ty::PunctBraceL => {
-                            balance.brace_l += 1;
-                        }
-                        Ty::PunctBraceR => {
-                            balance.brace_r += 1;
-                        }
-                        _ => {}
-                    }
-                    if balance.balanced_braces() {
-                        break;
-                    } else {
-                        group.push(next_tok);
+                // enum variants (i.e. `MyEnum::IdType`)
+                while let Some(Ty::PunctDoubleColon) = inputs.peek().map(|t| &t.ty) {
+                    group.extend(inputs.next());
+                    group.extend(inputs.next());
+                }
+                let enum_len = group.len();
+                match inputs.peek().map(|t| &t.ty) {
+                    Some(Ty::PunctBraceL) => {
+                        group.extend(take_group(inputs, Ty::PunctBraceL, Ty::PunctBraceR));
                     }
+                    // multiline struct values don't have braces
+                    Some(Ty::IdVar(_)) => group.extend(inputs.by_ref()),
+                    _ => {}
                 }
+                let is_struct = is_struct_value(&group[enum_len..]);
                 // make this a stack
                 group.reverse();
                 // add the IdType back
                 group.push(tok);
 
-                let expr = if group.contains(&Token { ty: Ty::PunctColon }) {
-                    // StructValueStmt
-                    let struct_value = StructValueStmt::parse(&mut group)
-                        .expect("unable to parse StructValueStmt");
-                    Ok(Expr::Value(ValueStmt {
-                        value: Value::Struct(struct_value),
-                    }))
-                } else if group.contains(&Token {
-                    ty: Ty::PunctDoubleColon,
-                }) {
+                let expr = if enum_len > 0 {
                     // EnumValueStmt
                     let enum_value =
                         EnumValueStmt::parse(&mut group).expect("unable to parse EnumValueStmt");
                     Ok(Expr::Value(ValueStmt {
                         value: Value::Enum(enum_value),
                     }))
+                } else if is_struct {
+                    // StructValueStmt
+                    let struct_value = StructValueStmt::parse(&mut group)?;
+                    Ok(Expr::Value(ValueStmt {
+                        value: Value::Struct(struct_value),
+                    }))
                 } else {
                     // TupleValueStmt
                     let tup_value =
@@ -297,21 +439,38 @@ where
                         group.push(next_tok);
                     }
                 }
-                match self.parse(&mut group.into_iter()) {
-                    Ok(expr) => Ok(expr),
-                    Err(err) => {
-                        println!("Err: #{:}", &err);
-                        unreachable!()
+                // commas make an anonymous tuple (i.e. `(1, "a")`)
+                let mut fields = vec![];
+                for element in split_commas(group) {
+                    match self.parse(&mut element.into_iter()) {
+                        Ok(expr) => fields.push(TupleFieldValueStmt {
+                            field_value: ExprStmt { expr },
+                        }),
+                        Err(err) => {
+                            error!("Err: #{:}", &err);
+                            return Err(NoError);
+                        }
                     }
                 }
+                if fields.len() == 1 {
+                    Ok(fields.remove(0).field_value.expr)
+                } else {
+                    Ok(Expr::Value(ValueStmt {
+                        value: Value::Tuple(TupleValueStmt {
+                            tuple_type: TypeStmt {
+                                type_name: TUPLE_TYPE.into(),
+                                ..Default::default()
+                            },
+                            fields,
+                        }),
+                    }))
+                }
                 // unreachable!()
                 // Err(PrattError::UserError("Missing closing paren for group"))
             }
 
-            // We don't expect traits inside expressions
-            // Ty::IdTrait(v) => {}
             _ => {
-                println!("ExprParser.primary: unreachable token #{:?}", &tok);
+                error!("ExprParser.primary: unreachable token #{:?}", &tok);
                 unreachable!()
             }
         };
@@ -322,6 +481,20 @@ where
     // convert Token => Operator and return an Expr
     fn infix(&mut self, lhs: Expr, tok: Self::Input, rhs: Expr) -> Result<Expr> {
         let op = match tok.ty {
+            // Assignment
+            Ty::OpAssignEq => Op::Assign(AssignOp::Eq),
+            Ty::OpAssignAdd => Op::Assign(AssignOp::Add),
+            Ty::OpAssignSub => Op::Assign(AssignOp::Sub),
+            Ty::OpAssignMul => Op::Assign(AssignOp::Mul),
+            Ty::OpAssignDiv => Op::Assign(AssignOp::Div),
+            Ty::OpAssignFloorDiv => Op::Assign(AssignOp::FloorDiv),
+            Ty::OpAssignMod => Op::Assign(AssignOp::Mod),
+            Ty::OpAssignPow => Op::Assign(AssignOp::Pow),
+            Ty::OpAssignBitwiseAnd => Op::Assign(AssignOp::BitwiseAnd),
+            Ty::OpAssignBitwiseOr => Op::Assign(AssignOp::BitwiseOr),
+            Ty::OpAssignBitwiseXOr => Op::Assign(AssignOp::BitwiseXOr),
+            Ty::OpAssignBitwiseShiftL => Op::Assign(AssignOp::BitwiseShiftL),
+            Ty::OpAssignBitwiseShiftR => Op::Assign(AssignOp::BitwiseShiftR),
             // Logical
             Ty::OpLogicalOr => Op::Logical(LogicalOp::Or),
             Ty::OpLogicalAnd => Op::Logical(LogicalOp::And),
@@ -347,7 +520,7 @@ where
             Ty::OpBitwiseShiftL => Op::Bitwise(BitwiseOp::ShiftL),
             Ty::OpBitwiseShiftR => Op::Bitwise(BitwiseOp::ShiftR),
             _ => {
-                println!("ExprParser.infix: unreachable token #{:?}", &tok);
+                error!("ExprParser.infix: unreachable token #{:?}", &tok);
                 unreachable!()
             }
         };
@@ -355,6 +528,7 @@ where
             lhs: Box::new(lhs),
             op: op,
             rhs: Box::new(rhs),
+            span: tok.span,
         }))
     }
 
@@ -365,7 +539,7 @@ where
             Ty::OpUnaryMinus => UnaryOp::Minus,
             Ty::OpUnaryNot | Ty::PunctExclamation => UnaryOp::Not,
             _ => {
-                println!("ExprParser.prefix: unreachable token #{:?}", &tok);
+                error!("ExprParser.prefix: unreachable token #{:?}", &tok);
                 unreachable!()
             }
         };
@@ -376,14 +550,145 @@ where
     }
 
     // Construct a unary postfix expression, e.g. 1?
-    fn postfix(&mut self, _lhs: Expr, tok: Self::Input) -> Result<Expr> {
-        let _op = match tok.ty {
-            // TokenTree::Postfix('?') => UnOpKind::Try,
+    fn postfix(
+        &mut self,
+        lhs: Expr,
+        tok: Self::Input,
+        inputs: &mut core::iter::Peekable<I>,
+    ) -> Result<Expr> {
+        let op = match tok.ty {
+            Ty::PunctQuestion => PostfixOp::Try,
+            Ty::KwAs => {
+                let mut type_group = match inputs.next() {
+                    Some(next_tok) => vec![next_tok],
+                    None => {
+                        error!("ExprParser.postfix: expected a type after as");
+                        return Err(NoError);
+                    }
+                };
+                match TypeStmt::parse(&mut type_group) {
+                    Ok(type_stmt) => PostfixOp::Cast(type_stmt),
+                    Err(err) => {
+                        error!("Err: #{:}", &err);
+                        return Err(NoError);
+                    }
+                }
+            }
+            Ty::IdFunc(_) => {
+                // take the method args, a brace on the next line isn't part of the call
+                let mut group = vec![tok];
+                if let Some(next) = inputs.peek() {
+                    if next.ty == Ty::PunctBraceL && next.span.ln == group[0].span.ln {
+                        group.extend(take_group(inputs, Ty::PunctBraceL, Ty::PunctBraceR));
+                    }
+                }
+                // make this a stack
+                group.reverse();
+                let func_val_stmt = FuncValueStmt::parse(&mut group)?;
+                PostfixOp::Method(func_val_stmt)
+            }
+            Ty::PunctBracketL => {
+                // take the index up to the closing bracket
+                let mut depth = 1usize;
+                let mut group: Vec<Token> = vec![];
+                for next_tok in inputs.by_ref() {
+                    match next_tok.ty {
+                        Ty::PunctBracketL => depth += 1,
+                        Ty::PunctBracketR => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    group.push(next_tok);
+                }
+                match self.parse(&mut group.into_iter()) {
+                    Ok(expr) => PostfixOp::Index(Box::new(ExprStmt { expr })),
+                    Err(err) => {
+                        error!("Err: #{:}", &err);
+                        return Err(NoError);
+                    }
+                }
+            }
+            Ty::PunctDot => match inputs.next().map(|t| t.ty) {
+                Some(Ty::IdVar(field)) => PostfixOp::Field(field),
+                next => {
+                    error!("ExprParser.postfix: expected a field name, got #{:?}", next);
+                    return Err(NoError);
+                }
+            },
             _ => {
-                println!("ExprParser.postfix: unreachable token #{:?}", &tok);
+                error!("ExprParser.postfix: unreachable token #{:?}", &tok);
                 unreachable!()
             }
         };
-        // Ok(Expr::UnOp(op, Box::new(lhs)))
+        Ok(Expr::Postfix(PostfixExprStmt {
+            lhs: Box::new(lhs),
+            op,
+        }))
+    }
+}
+
+// Splits the tokens of a paren group at its top-level commas
+fn split_commas(group: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut elements = vec![vec![]];
+    let mut depth = 0usize;
+    for tok in group {
+        match tok.ty {
+            Ty::PunctComma if depth == 0 => {
+                elements.push(vec![]);
+                continue;
+            }
+            Ty::PunctParenL | Ty::PunctBracketL | Ty::PunctBraceL => depth += 1,
+            Ty::PunctParenR | Ty::PunctBracketR | Ty::PunctBraceR => {
+                depth = depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+        if let Some(element) = elements.last_mut() {
+            element.push(tok);
+        }
+    }
+    // allow a trailing comma (i.e. `(1, "a",)`)
+    if elements.len() > 1 && elements.last().is_some_and(|element| element.is_empty()) {
+        elements.pop();
+    }
+    elements
+}
+
+// Struct values name their first field (i.e. `Type { field: ... }`)
+// and tuple values don't (i.e. `Type { value1 value2 }`)
+fn is_struct_value(body: &[Token]) -> bool {
+    let body = match body.first() {
+        Some(tok) if tok.ty == Ty::PunctBraceL => &body[1..],
+        _ => body,
+    };
+    matches!(
+        (body.first().map(|t| &t.ty), body.get(1).map(|t| &t.ty)),
+        (Some(Ty::PunctBraceR), _) | (Some(Ty::IdVar(_)), Some(Ty::PunctColon))
+    )
+}
+
+// Take a balanced `open ... close` group from the inputs
+// (including the open and close tokens)
+fn take_group<I>(inputs: &mut core::iter::Peekable<I>, open: Ty, close: Ty) -> Vec<Token>
+where
+    I: Iterator<Item = Token>,
+{
+    let mut depth = 0usize;
+    let mut group: Vec<Token> = vec![];
+    for next_tok in inputs.by_ref() {
+        if next_tok.ty == open {
+            depth += 1;
+        } else if next_tok.ty == close {
+            depth -= 1;
+        }
+        group.push(next_tok);
+        if depth == 0 {
+            break;
+        }
     }
+    group
 }
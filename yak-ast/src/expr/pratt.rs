@@ -94,6 +94,13 @@ where
 
     fn query(&mut self, input: &Self::Input) -> core::result::Result<Affix, Self::Error>;
 
+    // Query an operator which follows an expression
+    // (tokens like `:func` are values at the start of an expression
+    // and postfix operators after one)
+    fn query_next(&mut self, input: &Self::Input) -> core::result::Result<Affix, Self::Error> {
+        self.query(input)
+    }
+
     fn primary(
         &mut self,
         input: Self::Input,
@@ -117,6 +124,7 @@ where
         &mut self,
         lhs: Self::Output,
         op: Self::Input,
+        inputs: &mut core::iter::Peekable<Inputs>,
     ) -> core::result::Result<Self::Output, Self::Error>;
 
     fn parse(
@@ -143,7 +151,7 @@ where
             let mut nbp = self.nbp(info);
             let mut node = self.nud(head, tail, info);
             while let Some(head) = tail.peek() {
-                let info = self.query(head).map_err(PrattError::UserError)?;
+                let info = self.query_next(head).map_err(PrattError::UserError)?;
                 let lbp = self.lbp(info);
                 if rbp < lbp && lbp < nbp {
                     let head = tail.next().unwrap();
@@ -195,7 +203,7 @@ where
                 };
                 self.infix(lhs, head, rhs?).map_err(PrattError::UserError)
             }
-            Affix::Postfix(_) => self.postfix(lhs, head).map_err(PrattError::UserError),
+            Affix::Postfix(_) => self.postfix(lhs, head, tail).map_err(PrattError::UserError),
             Affix::Nilfix => Err(PrattError::UnexpectedNilfix(head)),
             Affix::Prefix(_) => Err(PrattError::UnexpectedPrefix(head)),
         }
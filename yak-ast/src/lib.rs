@@ -11,11 +11,13 @@ use expr::pratt::PrattParser;
 use std::fs;
 use std::path::PathBuf;
 use yak_core::models::yak_package::{
-    Symbol, YakDependency, YakExport, YakFile, YakImport, YakPackage, YakSymbol,
+    Symbol, YakDependency, YakExport, YakFile, YakImport, YakLink, YakPackage, YakProfile,
+    YakSymbol,
 };
 use yak_core::models::yak_version::YakVersion;
+use yak_core::types::types::TUPLE_TYPE;
 use yak_lexer::token::TokenType as Ty;
-use yak_lexer::{Lexer, Token};
+use yak_lexer::{Lexer, Span, Token};
 
 #[cfg(not(test))]
 use log::{debug, error, info, warn};
@@ -76,6 +78,9 @@ pub struct Parsed {
     pub structs: Vec<StructStmt>,
     pub traits: Vec<TraitStmt>,
     pub tests: Vec<TestStmt>,
+    pub test_cases: Vec<TestCaseStmt>,
+    pub tuples: Vec<TupleTypeStmt>,
+    pub types: Vec<NewTypeStmt>,
 }
 
 #[derive(Debug)]
@@ -129,7 +134,11 @@ impl Ast {
     pub fn parse_file(&mut self, file: PathBuf) -> Result<()> {
         let src = fs::read_to_string(&file)
             .with_context(|| format!("unable to read file: {}", &file.display()))?;
-        let mut lexer = Lexer::from_source(&src);
+        self.parse_source(file, &src)
+    }
+    // Parses the source of a file which may not be saved (i.e. open in an editor)
+    pub fn parse_source(&mut self, file: PathBuf, src: &str) -> Result<()> {
+        let mut lexer = Lexer::from_source(src);
         lexer.parse();
         self.files.push(file);
         self.stack = lexer.tokens_as_stack();
@@ -137,6 +146,7 @@ impl Ast {
         Ok(())
     }
     pub fn parse(&mut self) -> Result<()> {
+        join_brace_lines(&mut self.stack);
         // parse top-level statements
         while let Some(token) = self.stack.pop() {
             // debug!("ast.parse {:?}", token.ty);
@@ -187,6 +197,7 @@ impl Ast {
                 | Ty::KwTest
                 | Ty::KwTestCase
                 | Ty::KwTrait
+                | Ty::KwTuple
                 | Ty::KwType => {
                     let mut stack = self.take_toplevel_stmt();
                     match token.ty {
@@ -213,16 +224,26 @@ impl Ast {
                         Ty::KwFn => {
                             let stmt = FuncStmt::parse(&mut stack);
                             match stmt {
-                                Ok(func_stmt) => {
+                                Ok(mut func_stmt) => {
+                                    func_stmt.file = self.files.last().cloned();
                                     debug!("func_stmt {:#?}", func_stmt);
                                     self.parsed.funcs.push(func_stmt);
                                 }
                                 Err(err) => return Some(err),
                             }
                         }
-                        // todo
                         Ty::KwImpl => {
-                            todo!("parse impl")
+                            let stmt = ImplTraitStmt::parse(&mut stack);
+                            match stmt {
+                                Ok(mut impl_stmt) => {
+                                    for func_stmt in impl_stmt.funcs.iter_mut() {
+                                        func_stmt.file = self.files.last().cloned();
+                                    }
+                                    debug!("impl_stmt {:#?}", impl_stmt);
+                                    self.parsed.impl_traits.push(impl_stmt);
+                                }
+                                Err(err) => return Some(err),
+                            }
                         }
                         Ty::KwLet => {
                             let stmt = LetStmt::parse(&mut stack);
@@ -254,21 +275,57 @@ impl Ast {
                                 Err(err) => return Some(err),
                             }
                         }
-                        // todo
                         Ty::KwTest => {
-                            todo!("parse test")
+                            let stmt = TestStmt::parse(&mut stack);
+                            match stmt {
+                                Ok(mut test_stmt) => {
+                                    test_stmt.file = self.files.last().cloned();
+                                    debug!("test_stmt {:#?}", test_stmt);
+                                    self.parsed.tests.push(test_stmt);
+                                }
+                                Err(err) => return Some(err),
+                            }
                         }
-                        // todo
                         Ty::KwTestCase => {
-                            todo!("parse testcase")
+                            let stmt = TestCaseStmt::parse(&mut stack);
+                            match stmt {
+                                Ok(mut test_case_stmt) => {
+                                    test_case_stmt.file = self.files.last().cloned();
+                                    debug!("test_case_stmt {:#?}", test_case_stmt);
+                                    self.parsed.test_cases.push(test_case_stmt);
+                                }
+                                Err(err) => return Some(err),
+                            }
                         }
-                        // todo
                         Ty::KwTrait => {
-                            todo!("parse ^Trait")
+                            let stmt = TraitStmt::parse(&mut stack);
+                            match stmt {
+                                Ok(trait_stmt) => {
+                                    debug!("trait_stmt {:#?}", trait_stmt);
+                                    self.parsed.traits.push(trait_stmt);
+                                }
+                                Err(err) => return Some(err),
+                            }
+                        }
+                        Ty::KwTuple => {
+                            let stmt = TupleTypeStmt::parse(&mut stack);
+                            match stmt {
+                                Ok(tuple_stmt) => {
+                                    debug!("tuple_stmt {:#?}", tuple_stmt);
+                                    self.parsed.tuples.push(tuple_stmt);
+                                }
+                                Err(err) => return Some(err),
+                            }
                         }
-                        // todo
                         Ty::KwType => {
-                            todo!("parse type")
+                            let stmt = NewTypeStmt::parse(&mut stack);
+                            match stmt {
+                                Ok(new_type_stmt) => {
+                                    debug!("new_type_stmt {:#?}", new_type_stmt);
+                                    self.parsed.types.push(new_type_stmt);
+                                }
+                                Err(err) => return Some(err),
+                            }
                         }
                         _ => {}
                     }
@@ -303,6 +360,11 @@ impl Ast {
 // **** Utility functions
 //
 
+// Check for a token type (ignoring the token span)
+fn contains_ty(tokens: &[Token], ty: Ty) -> bool {
+    tokens.iter().any(|tok| tok.ty == ty)
+}
+
 // Take until match
 fn take_all_until_match_any(tokens: &mut Vec<Token>, matches: Vec<Ty>) -> Vec<Token> {
     let mut taken: Vec<Token> = vec![];
@@ -317,6 +379,56 @@ fn take_all_until_match_any(tokens: &mut Vec<Token>, matches: Vec<Ty>) -> Vec<To
     taken
 }
 
+// Joins the lines inside `{ }` so arg lists can span lines
+// (newlines become spaces and indents are skipped). Tokens keep
+// their spans so locations still point at the lines they're on.
+fn join_brace_lines(stack: &mut Vec<Token>) {
+    let mut braces = 0usize;
+    let mut joined = Vec::with_capacity(stack.len());
+    while let Some(token) = stack.pop() {
+        match token.ty {
+            Ty::PunctBraceL => braces += 1,
+            Ty::PunctBraceR => braces = braces.saturating_sub(1),
+            Ty::NL if braces > 0 => {
+                joined.push(Token {
+                    ty: Ty::Sp,
+                    span: token.span,
+                });
+                continue;
+            }
+            Ty::Indent(_) if braces > 0 => continue,
+            // args don't parse comments, these end at the newline
+            // so they can't hide a brace (i.e. `a: int # {`)
+            Ty::Comment(_) if braces > 0 => continue,
+            _ => {}
+        }
+        joined.push(token);
+    }
+    joined.reverse();
+    *stack = joined;
+}
+
+// Take until a `close` token without a matching `open` token
+// (i.e. the args of `{ f: fn { x: int } int }`)
+fn take_all_until_unbalanced(tokens: &mut Vec<Token>, open: Ty, close: Ty) -> Vec<Token> {
+    let mut taken: Vec<Token> = vec![];
+    let mut depth = 0usize;
+    while let Some(tok) = tokens.pop() {
+        if tok.ty == open {
+            depth += 1;
+        } else if tok.ty == close {
+            if depth == 0 {
+                tokens.push(tok);
+                break;
+            }
+            depth -= 1;
+        }
+        taken.push(tok);
+    }
+    taken.reverse();
+    taken
+}
+
 fn take_all_include_pattern(tokens: &mut Vec<Token>, pattern: Vec<Ty>) -> Result<Vec<Token>> {
     if pattern.len() == 0 {
         bail!("expected pattern length");
@@ -413,6 +525,14 @@ fn ty_into_op(ty: Ty) -> Result<Op, Error> {
         Ty::OpAssignSub => Op::Assign(AssignOp::Sub),
         Ty::OpAssignDiv => Op::Assign(AssignOp::Div),
         Ty::OpAssignMul => Op::Assign(AssignOp::Mul),
+        Ty::OpAssignPow => Op::Assign(AssignOp::Pow),
+        Ty::OpAssignMod => Op::Assign(AssignOp::Mod),
+        Ty::OpAssignFloorDiv => Op::Assign(AssignOp::FloorDiv),
+        Ty::OpAssignBitwiseAnd => Op::Assign(AssignOp::BitwiseAnd),
+        Ty::OpAssignBitwiseOr => Op::Assign(AssignOp::BitwiseOr),
+        Ty::OpAssignBitwiseXOr => Op::Assign(AssignOp::BitwiseXOr),
+        Ty::OpAssignBitwiseShiftL => Op::Assign(AssignOp::BitwiseShiftL),
+        Ty::OpAssignBitwiseShiftR => Op::Assign(AssignOp::BitwiseShiftR),
         Ty::OpLogicalAnd => Op::Logical(LogicalOp::And),
         Ty::OpLogicalOr => Op::Logical(LogicalOp::Or),
         Ty::OpBitwiseAnd => Op::Bitwise(BitwiseOp::And),
@@ -435,9 +555,15 @@ fn into_type_stmt_generics(stack: &mut Vec<Token>) -> Result<Vec<TypeStmt>, Erro
     while let Some(tok) = stack.pop() {
         let mut type_stmt = TypeStmt::default();
         match tok.ty {
-            Ty::IdType(id) => {
+            // trait bounds follow their type parameter (i.e. `[T ^Display]`)
+            Ty::IdType(id) | Ty::IdTrait(id) => {
                 type_stmt.type_name = id;
             }
+            // i.e. `List[(int, String)]`
+            Ty::PunctParenL => {
+                generics.push(parse_tuple_type(stack)?);
+                continue;
+            }
             _ => {
                 if Ty::primitives().contains(&tok.ty) || Ty::builtins().contains(&tok.ty) {
                     // convert the type to String
@@ -482,6 +608,153 @@ fn into_type_stmt_generics(stack: &mut Vec<Token>) -> Result<Vec<TypeStmt>, Erro
     Ok(generics)
 }
 
+// Parses an anonymous tuple type after its `(` (i.e. `(int, String)`)
+// into the builtin tuple type with the element types as generics
+fn parse_tuple_type(stack: &mut Vec<Token>) -> Result<TypeStmt, Error> {
+    let mut elements = vec![];
+    let mut element: Vec<Token> = vec![];
+    let mut depth = 0usize;
+    loop {
+        let tok = match stack.pop() {
+            Some(tok) => tok,
+            None => bail!("tuple type expected a closing paren"),
+        };
+        match tok.ty {
+            Ty::PunctParenR | Ty::PunctComma if depth == 0 => {
+                if !element.is_empty() {
+                    element.reverse();
+                    elements.push(TypeStmt::parse(&mut element)?);
+                    element.clear();
+                }
+                if tok.ty == Ty::PunctParenR {
+                    break;
+                }
+                continue;
+            }
+            Ty::PunctParenL | Ty::PunctBracketL => depth += 1,
+            Ty::PunctParenR | Ty::PunctBracketR => depth -= 1,
+            Ty::Sp => continue,
+            _ => {}
+        }
+        element.push(tok);
+    }
+    if elements.len() < 2 {
+        bail!("tuple types need at least two element types (i.e. `(int, String)`)");
+    }
+    Ok(TypeStmt {
+        type_name: TUPLE_TYPE.into(),
+        generics: Some(Box::new(elements)),
+        func_type: None,
+    })
+}
+
+// Parse a list of expressions separated by whitespace
+// (i.e. `{ key1 "a" 1 + 2 }`)
+fn parse_positional_exprs(stack: &mut Vec<Token>) -> Result<Vec<ExprStmt>, Error> {
+    let mut cleaned = remove_newline_indent(stack)?;
+    cleaned.reverse();
+    let mut exprs: Vec<ExprStmt> = vec![];
+    for group in split_detached_calls(cleaned) {
+        let mut inputs = group.into_iter().peekable();
+        while inputs.peek().is_some() {
+            match ExprParser.parse_peekable(&mut inputs) {
+                Ok(expr) => exprs.push(ExprStmt { expr }),
+                Err(e) => bail!(e.to_string()),
+            }
+        }
+    }
+    Ok(exprs)
+}
+
+// Method calls are attached to their value (i.e. `{ x:func {} }`)
+// so a function after a space starts the next value (i.e. `{ x :func {} }`)
+fn split_detached_calls(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut groups: Vec<Vec<Token>> = vec![];
+    let mut group: Vec<Token> = vec![];
+    for tok in tokens {
+        if let (Ty::IdFunc(_), Some(prev)) = (&tok.ty, group.last()) {
+            let attached = prev.span.ln == tok.span.ln && prev.span.end == tok.span.start;
+            let ends_value = matches!(
+                prev.ty,
+                Ty::IdVar(_)
+                    | Ty::IdPackage(_)
+                    | Ty::KwSelf
+                    | Ty::LitString(_)
+                    | Ty::LitNumber(_)
+                    | Ty::LitBoolean(_)
+                    | Ty::PunctBraceR
+                    | Ty::PunctParenR
+                    | Ty::PunctQuestion
+            );
+            if ends_value && !attached {
+                groups.push(std::mem::take(&mut group));
+            }
+        }
+        group.push(tok);
+    }
+    if !group.is_empty() {
+        groups.push(group);
+    }
+    groups
+}
+
+// Split the body of a `trait` or `impl` statement into
+// a token stack per `fn` member
+fn take_member_funcs(stack: &mut Vec<Token>) -> Result<Vec<Vec<Token>>, Error> {
+    let mut funcs: Vec<Vec<Token>> = vec![];
+    let mut func: Option<Vec<Token>> = None;
+    let mut member_indent: Option<usize> = None;
+    while let Some(tok) = stack.pop() {
+        match tok.ty {
+            Ty::Indent(indent) if member_indent.is_none() || member_indent == Some(indent) => {
+                match stack.last().map(|next| &next.ty) {
+                    Some(Ty::KwFn) => {
+                        // start a new member function
+                        stack.pop();
+                        member_indent = Some(indent);
+                        if let Some(mut taken) = func.take() {
+                            taken.reverse();
+                            funcs.push(taken);
+                        }
+                        func = Some(vec![]);
+                    }
+                    Some(Ty::Comment(_)) => {
+                        stack.pop();
+                    }
+                    Some(Ty::KwType) => {
+                        // associated types aren't supported yet
+                        let line = take_all_until_match_any(stack, vec![Ty::NL]);
+                        warn!("skipping unsupported member: {:?}", line);
+                    }
+                    Some(Ty::NL) | None => {}
+                    Some(next) => {
+                        bail!("expected fn member but found: {:?}", next);
+                    }
+                }
+            }
+            _ => match func.as_mut() {
+                Some(taken) => taken.push(tok),
+                None => match tok.ty {
+                    Ty::NL | Ty::Indent(_) | Ty::Comment(_) => {}
+                    _ => bail!("expected fn member but found: {:?}", tok.ty),
+                },
+            },
+        }
+    }
+    if let Some(mut taken) = func.take() {
+        taken.reverse();
+        funcs.push(taken);
+    }
+    // signatures without a body
+    // shouldn't end with newlines or indentation
+    for func in funcs.iter_mut() {
+        if !contains_ty(func, Ty::PunctFatArrow) {
+            *func = remove_newline_indent(func)?;
+        }
+    }
+    Ok(funcs)
+}
+
 //
 // **** Traits
 //
@@ -524,6 +797,8 @@ pub struct PackageStmt {
     pub dependencies: Vec<PackageDependencyStmt>,
     pub imports: Vec<PackageImportStmt>,
     pub exports: PackageExportStmt,
+    pub profiles: Vec<PackageProfileStmt>,
+    pub link: PackageLinkStmt,
 }
 
 impl PackageStmt {
@@ -556,13 +831,18 @@ impl PackageStmt {
         pkg.pkg_imports = self.imports.into_iter().map(|imp| imp.into()).collect();
         // convert exports
         pkg.pkg_exports = self.exports.into();
+        // convert profiles
+        for profile in self.profiles {
+            pkg.pkg_profiles.push(profile.into_yak_profile()?);
+        }
+        // convert link settings
+        pkg.pkg_link = self.link.into_yak_link()?;
         Ok(pkg)
     }
 }
 
 impl Parse for PackageStmt {
     fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
-        info!("PackageStmt {:?}", stack);
         // remove newlines and indentation
         let mut pkg_stmt = PackageStmt::default();
         while let Some(token) = stack.pop() {
@@ -908,9 +1188,6 @@ impl Parse for PackageStmt {
                                                                             Ty::IdTrait(name) => {
                                                                                 sym_stmt.as_symbol =
                                                                                 Some(PackageSymbol::Trait(name));
-                                                                                imp_stmt
-                                                                                    .symbols
-                                                                                    .push(sym_stmt);
                                                                             }
                                                                             _ => {
                                                                                 bail!("failed to parse package import. KwAs doesn't match IdTrait")
@@ -925,6 +1202,7 @@ impl Parse for PackageStmt {
                                                                 }
                                                             }
                                                         }
+                                                        imp_stmt.symbols.push(sym_stmt);
                                                     }
                                                     Ty::PunctBraceR => {
                                                         pkg_stmt.imports.push(imp_stmt);
@@ -955,6 +1233,27 @@ impl Parse for PackageStmt {
                         }
                     }
                 }
+                Ty::KwProfile => {
+                    let mut profile_stmt = PackageProfileStmt::default();
+                    while let Some(next) = stack.pop() {
+                        match next.ty {
+                            Ty::IdVar(name) => {
+                                profile_stmt.name = name;
+                                break;
+                            }
+                            Ty::Sp => {}
+                            _ => {
+                                stack.push(next);
+                                bail!("failed to parse package profile. Expected IdVar")
+                            }
+                        }
+                    }
+                    profile_stmt.settings = parse_package_settings(stack, "profile")?;
+                    pkg_stmt.profiles.push(profile_stmt);
+                }
+                Ty::KwLink => {
+                    pkg_stmt.link.settings = parse_package_settings(stack, "link")?;
+                }
                 Ty::KwFiles => {
                     // debug!("files stack: {:?}\n", stack);
                     let mut files = take_all_include_pattern(
@@ -1004,6 +1303,52 @@ impl Parse for PackageStmt {
     }
 }
 
+// Parses the `{ name value ... }` settings of a profile or link block
+fn parse_package_settings(stack: &mut Vec<Token>, block: &str) -> Result<Vec<(String, String)>> {
+    let mut settings =
+        take_all_include_pattern(stack, vec![Ty::NL, Ty::Indent(0), Ty::PunctBraceR])?;
+    let mut settings = remove_newline_indent_space(&mut settings)?;
+    if let Some(next) = settings.pop() {
+        match next.ty {
+            Ty::PunctBraceL => {}
+            _ => {
+                bail!("failed to parse package {}. Expected PunctBraceL", block)
+            }
+        }
+    }
+    let mut parsed = vec![];
+    // iterate `name value` settings
+    while let Some(next) = settings.pop() {
+        match next.ty {
+            Ty::IdVar(name) => {
+                let value = match settings.pop().map(|value| value.ty) {
+                    Some(Ty::LitNumber(value))
+                    | Some(Ty::LitBoolean(value))
+                    | Some(Ty::IdVar(value))
+                    | Some(Ty::IdPackage(value)) => value,
+                    Some(Ty::LitString(value)) => clean_quotes(value),
+                    _ => bail!(
+                        "failed to parse package {}. Expected a value for {}",
+                        block,
+                        name
+                    ),
+                };
+                parsed.push((name, value));
+            }
+            Ty::PunctBraceR => {
+                break;
+            }
+            _ => {
+                bail!(
+                    "failed to parse package {}. Expected IdVar or PunctBraceR",
+                    block
+                )
+            }
+        }
+    }
+    Ok(parsed)
+}
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct PackageDependencyStmt {
     pub package_id: String,
@@ -1051,6 +1396,77 @@ impl Into<YakImport> for PackageImportStmt {
     }
 }
 
+// Build settings for the debug or release profile
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackageProfileStmt {
+    pub name: String,
+    // (setting name, value)
+    pub settings: Vec<(String, String)>,
+}
+
+impl PackageProfileStmt {
+    fn into_yak_profile(self) -> Result<YakProfile> {
+        if self.name != "debug" && self.name != "release" {
+            bail!("unknown profile {} (expected debug or release)", self.name);
+        }
+        let mut profile = YakProfile {
+            name: self.name,
+            ..Default::default()
+        };
+        for (name, value) in self.settings {
+            let flag = || match value.as_str() {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => bail!("profile setting {} expects true or false", name),
+            };
+            match name.as_str() {
+                "opt_level" => {
+                    if !["0", "1", "2", "3", "s", "z"].contains(&value.as_str()) {
+                        bail!("unknown opt_level {} (expected 0, 1, 2, 3, s or z)", value);
+                    }
+                    profile.opt_level = Some(value.clone());
+                }
+                "overflow_checks" => profile.overflow_checks = Some(flag()?),
+                "debug_assertions" => profile.debug_assertions = Some(flag()?),
+                "debug_info" => profile.debug_info = Some(flag()?),
+                _ => bail!("unknown profile setting {}", name),
+            }
+        }
+        Ok(profile)
+    }
+}
+
+// Linker settings (i.e. `link { linker ld.lld lib static }`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackageLinkStmt {
+    // (setting name, value)
+    pub settings: Vec<(String, String)>,
+}
+
+impl PackageLinkStmt {
+    fn into_yak_link(self) -> Result<YakLink> {
+        let mut link = YakLink::default();
+        for (name, value) in self.settings {
+            match name.as_str() {
+                "linker" => {
+                    if !["cc", "clang", "ld.lld"].contains(&value.as_str()) {
+                        bail!("unknown linker {} (expected cc, clang or ld.lld)", value);
+                    }
+                    link.linker = Some(value);
+                }
+                "lib" => {
+                    if value != "static" && value != "shared" {
+                        bail!("unknown lib {} (expected static or shared)", value);
+                    }
+                    link.lib = Some(value);
+                }
+                _ => bail!("unknown link setting {}", name),
+            }
+        }
+        Ok(link)
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct PackageExportStmt {
     symbols: Vec<PackageSymbolStmt>,
@@ -1237,6 +1653,8 @@ impl Parse for VarTypeStmt {
 pub struct TypeStmt {
     pub type_name: String,
     pub generics: Option<Box<Vec<TypeStmt>>>,
+    // function types (i.e. `fn { x: int } int`)
+    pub func_type: Option<Box<FuncTypeStmt>>,
 }
 impl Parse for TypeStmt {
     // TypeStmt
@@ -1249,16 +1667,33 @@ impl Parse for TypeStmt {
 
         if let Some(tok) = stack.pop() {
             match tok.ty {
-                Ty::IdType(id) => {
+                Ty::IdType(id) | Ty::IdTrait(id) => {
                     type_stmt.type_name = id;
                 }
+                // resolved to the impl type when lowered
+                Ty::SpecialTypeSelf => {
+                    type_stmt.type_name = tok.ty.into();
+                    return Ok(type_stmt);
+                }
+                // anonymous tuples (i.e. `(int, String)`)
+                Ty::PunctParenL => return parse_tuple_type(stack),
+                // function types take the rest of the stack
+                // (i.e. `fn { x: int } int`)
+                Ty::KwFn => {
+                    type_stmt.type_name = "fn".into();
+                    type_stmt.func_type = Some(Box::new(FuncTypeStmt::parse(stack)?));
+                    return Ok(type_stmt);
+                }
                 _ => {
-                    if Ty::primitives().contains(&tok.ty) || Ty::builtins().contains(&tok.ty) {
+                    if Ty::primitives().contains(&tok.ty) {
                         // convert the type to String
                         type_stmt.type_name = tok.ty.into();
                         // a primitive will never have generics...
                         // so return early (this cleans up how we might parse tuple types)
                         return Ok(type_stmt);
+                    } else if Ty::builtins().contains(&tok.ty) {
+                        // builtins can have generics (i.e. Map[K V])
+                        type_stmt.type_name = tok.ty.into();
                     } else {
                         debug!("failed on: {:?}", tok);
                         bail!("type statement expected a type identity");
@@ -1719,9 +2154,13 @@ impl Parse for StructValueStmt {
         }
 
         let mut field: Vec<Token> = vec![];
+        // nested struct values have their own fields
+        let mut depth = 0usize;
         while let Some(next) = stack.pop() {
             match next.ty {
-                Ty::IdVar(_) => {
+                Ty::PunctBraceL => depth += 1,
+                Ty::PunctBraceR => depth = depth.saturating_sub(1),
+                Ty::IdVar(_) if depth == 0 => {
                     // Handle expr statements by checking
                     // if the next token is a colon. This make everything
                     // after the colon and expression
@@ -1843,15 +2282,28 @@ pub struct TupleStmt {
     pub types: Vec<TypeStmt>,
 }
 impl Parse for TupleStmt {
-    fn parse(mut stack: &mut Vec<Token>) -> Result<Self, Error> {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
         debug!("TupleStmt parse {:?}", stack);
         let mut tuple_type = TupleStmt::default();
-        loop {
-            let type_stmt = TypeStmt::parse(&mut stack)?;
-            tuple_type.types.push(type_stmt);
-            if stack.len() == 0 {
-                break;
+        while let Some(tok) = stack.pop() {
+            // take the type and its generics (i.e. `Map[K V]`)
+            let mut type_stack = vec![tok];
+            if let Some(Ty::PunctBracketL) = stack.last().map(|t| &t.ty) {
+                let mut depth = 0usize;
+                while let Some(next) = stack.pop() {
+                    match next.ty {
+                        Ty::PunctBracketL => depth += 1,
+                        Ty::PunctBracketR => depth -= 1,
+                        _ => {}
+                    }
+                    type_stack.push(next);
+                    if depth == 0 {
+                        break;
+                    }
+                }
             }
+            type_stack.reverse();
+            tuple_type.types.push(TypeStmt::parse(&mut type_stack)?);
         }
         Ok(tuple_type)
     }
@@ -1861,19 +2313,186 @@ impl Parse for TupleStmt {
     }
 }
 
+//
+// Tuple type statement (i.e. `tuple Pair[String int]`
+// or `tuple Pair[K V] { K V }` with type parameters)
+//
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TupleTypeStmt {
+    pub tuple_name: String,
+    // type parameters and their bounds (empty unless the
+    // element types are in braces)
+    pub generics: Vec<TypeStmt>,
+    pub tuple: TupleStmt,
+}
+
+impl Parse for TupleTypeStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("TupleTypeStmt parse {:?}", stack);
+        let mut tuple_type_stmt = TupleTypeStmt::default();
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::IdType(id), ..
+            }) => tuple_type_stmt.tuple_name = id,
+            _ => bail!("tuple statement expected a type identity"),
+        }
+        let mut cleaned = remove_newline_indent(stack)?;
+        let mut brackets = match take_group(&mut cleaned, Ty::PunctBracketL, Ty::PunctBracketR) {
+            Some(brackets) => brackets,
+            None => bail!(
+                "tuple statement {} expected element types (i.e. `tuple {}[String int]`)",
+                &tuple_type_stmt.tuple_name,
+                &tuple_type_stmt.tuple_name
+            ),
+        };
+        // `[K V] { K V }` declares type parameters in the brackets
+        if let Some(braces) = take_group(&mut cleaned, Ty::PunctBraceL, Ty::PunctBraceR) {
+            tuple_type_stmt.generics = TupleStmt::parse(&mut brackets)?.types;
+            brackets = braces;
+        }
+        if !cleaned.is_empty() {
+            bail!(
+                "unexpected tokens after tuple statement {}",
+                &tuple_type_stmt.tuple_name
+            );
+        }
+        tuple_type_stmt.tuple = TupleStmt::parse(&mut brackets)?;
+        if tuple_type_stmt.tuple.types.is_empty() {
+            bail!(
+                "tuple {} needs at least one element type",
+                &tuple_type_stmt.tuple_name
+            );
+        }
+        Ok(tuple_type_stmt)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+// Takes the tokens between `open` and its matching `close` from the top
+// of a reversed stack (the returned tokens are reversed too)
+fn take_group(stack: &mut Vec<Token>, open: Ty, close: Ty) -> Option<Vec<Token>> {
+    if stack.last().map(|tok| &tok.ty) != Some(&open) {
+        return None;
+    }
+    stack.pop();
+    let mut group = vec![];
+    let mut depth = 1usize;
+    while let Some(tok) = stack.pop() {
+        if tok.ty == open {
+            depth += 1;
+        } else if tok.ty == close {
+            depth -= 1;
+            if depth == 0 {
+                group.reverse();
+                return Some(group);
+            }
+        }
+        group.push(tok);
+    }
+    None
+}
+
+//
+// New type statement
+// (i.e. `type Names = List[String]` is an alias and `type UserId int64` is a newtype)
+//
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NewTypeStmt {
+    pub type_name: String,
+    // aliases are interchangeable with their type
+    pub is_alias: bool,
+    pub new_type: TypeStmt,
+}
+
+impl Parse for NewTypeStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("NewTypeStmt parse {:?}", stack);
+        let mut new_type_stmt = NewTypeStmt::default();
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::IdType(id), ..
+            }) => new_type_stmt.type_name = id,
+            _ => bail!("type statement expected a type identity"),
+        }
+        let mut cleaned = remove_newline_indent(stack)?;
+        match cleaned.last().map(|tok| &tok.ty) {
+            Some(Ty::OpAssignEq) => {
+                cleaned.pop();
+                new_type_stmt.is_alias = true;
+            }
+            Some(Ty::PunctBracketL) => bail!(
+                "generic type {} isn't supported yet",
+                &new_type_stmt.type_name
+            ),
+            Some(_) => {}
+            None => bail!(
+                "type {} expected a type (i.e. `type {} = String`)",
+                &new_type_stmt.type_name,
+                &new_type_stmt.type_name
+            ),
+        }
+        new_type_stmt.new_type = TypeStmt::parse(&mut cleaned)?;
+        if !cleaned.is_empty() {
+            bail!(
+                "unexpected tokens after type {}: {:?}",
+                &new_type_stmt.type_name,
+                cleaned
+            );
+        }
+        Ok(new_type_stmt)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 //
 // Tuple value statement
 //
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct TupleValueStmt {
-    fields: Vec<TupleFieldValueStmt>,
+    pub tuple_type: TypeStmt,
+    pub fields: Vec<TupleFieldValueStmt>,
 }
 
 impl Parse for TupleValueStmt {
     fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
         debug!("TupleValueStmt parse {:?}", stack);
-        warn!("TupleValueStmt parsing not currently implemented!!!");
-        let tup_val = TupleValueStmt::default();
+        let mut tup_val = TupleValueStmt::default();
+
+        // parse type statement (the type args are taken
+        // first so the field values can't be mistaken for them)
+        let mut type_stack: Vec<Token> = take_all_until_match_any(stack, vec![Ty::PunctBraceL])
+            .into_iter()
+            .filter(|tok| tok.ty != Ty::Sp)
+            .collect();
+        tup_val.tuple_type = TypeStmt::parse(&mut type_stack)?;
+
+        // eat starting and ending braces
+        if let Some(next) = stack.pop() {
+            match next.ty {
+                Ty::PunctBraceL => {
+                    // last token should be PunctBraceR
+                    if let Some(tok) = stack.first() {
+                        if tok.ty == Ty::PunctBraceR {
+                            stack.remove(0);
+                        }
+                    }
+                }
+                _ => stack.push(next),
+            }
+        }
+
+        // fields are positional (i.e. `MyTuple { value1 value2 }`)
+        tup_val.fields = parse_positional_exprs(stack)?
+            .into_iter()
+            .map(|field_value| TupleFieldValueStmt { field_value })
+            .collect();
+
         Ok(tup_val)
     }
     fn validate(&self) -> Result<(), Error> {
@@ -1888,8 +2507,9 @@ pub struct TupleFieldValueStmt {
 impl Parse for TupleFieldValueStmt {
     fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
         debug!("TupleFieldValueStmt {:?}", stack);
-        let tup_field_val = TupleFieldValueStmt::default();
-        Ok(tup_field_val)
+        Ok(TupleFieldValueStmt {
+            field_value: ExprStmt::parse(stack)?,
+        })
     }
     fn validate(&self) -> Result<(), Error> {
         todo!()
@@ -1901,8 +2521,12 @@ impl Parse for TupleFieldValueStmt {
 //
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct FuncValueStmt {
+    // static type functions (i.e. `Map:new {}`)
+    pub func_type: Option<TypeStmt>,
     pub func_name: String,
     pub args: Vec<FuncArgValueStmt>,
+    // location of the function name
+    pub span: Span,
 }
 
 impl Parse for FuncValueStmt {
@@ -1914,6 +2538,7 @@ impl Parse for FuncValueStmt {
             match tok.ty {
                 Ty::IdFunc(name) => {
                     func_val.func_name = name;
+                    func_val.span = tok.span;
                 }
                 _ => {
                     bail!("expected IdFunc to parse FuncValueStmt");
@@ -1921,66 +2546,69 @@ impl Parse for FuncValueStmt {
             }
         }
 
-        // eat starting and ending braces
-        if let Some(next) = stack.pop() {
-            match next.ty {
-                Ty::PunctBraceL => {
-                    // last token should be PunctBraceR
-                    match stack.first() {
-                        Some(tok) => {
-                            if tok.ty == Ty::PunctBraceR {
-                                stack.remove(0);
-                            }
-                        }
-                        None => {}
+        func_val.args = parse_named_args(stack)?;
+
+        Ok(func_val)
+    }
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+// Parses named args wrapped in braces (i.e. `{ a: 1 b: x + 1 }`)
+fn parse_named_args(stack: &mut Vec<Token>) -> Result<Vec<FuncArgValueStmt>, Error> {
+    let mut args = vec![];
+    // eat starting and ending braces
+    if let Some(next) = stack.pop() {
+        match next.ty {
+            Ty::PunctBraceL => {
+                // last token should be PunctBraceR
+                if let Some(tok) = stack.first() {
+                    if tok.ty == Ty::PunctBraceR {
+                        stack.remove(0);
                     }
                 }
-                _ => stack.push(next),
             }
+            _ => stack.push(next),
         }
+    }
 
-        let mut arg: Vec<Token> = vec![];
-        while let Some(next) = stack.pop() {
-            match next.ty {
-                Ty::IdVar(_) => {
-                    let mut next_colon = false;
-                    if let Some(next) = stack.pop() {
-                        match next.ty {
-                            Ty::PunctColon => {
-                                next_colon = true;
-                            }
-                            _ => {}
-                        }
-                        stack.push(next);
-                    }
-
-                    if next_colon && arg.len() > 0 {
-                        arg.reverse();
-                        func_val.args.push(FuncArgValueStmt::parse(&mut arg)?);
-                        if arg.len() > 0 {
-                            debug!(
-                                "arg variable should have no length after parsing FuncArgValueStmt"
-                            );
-                            arg.clear();
-                        }
+    let mut arg: Vec<Token> = vec![];
+    // only split args at the top-level
+    // (struct values and nested calls have their own `name:` tokens)
+    let mut depth = 0;
+    while let Some(next) = stack.pop() {
+        match next.ty {
+            Ty::PunctBraceL => depth += 1,
+            Ty::PunctBraceR => depth -= 1,
+            Ty::IdVar(_) | Ty::KwSelf if depth == 0 => {
+                let mut next_colon = false;
+                if let Some(next) = stack.pop() {
+                    next_colon = next.ty == Ty::PunctColon;
+                    stack.push(next);
+                }
+
+                if next_colon && !arg.is_empty() {
+                    arg.reverse();
+                    args.push(FuncArgValueStmt::parse(&mut arg)?);
+                    if !arg.is_empty() {
+                        debug!("arg variable should have no length after parsing FuncArgValueStmt");
+                        arg.clear();
                     }
                 }
-                _ => {}
             }
-            arg.push(next);
-        }
-
-        // flush arg if it has a length
-        if arg.len() > 0 {
-            arg.reverse();
-            func_val.args.push(FuncArgValueStmt::parse(&mut arg)?);
+            _ => {}
         }
-
-        Ok(func_val)
+        arg.push(next);
     }
-    fn validate(&self) -> Result<(), Error> {
-        Ok(())
+
+    // flush arg if it has a length
+    if !arg.is_empty() {
+        arg.reverse();
+        args.push(FuncArgValueStmt::parse(&mut arg)?);
     }
+
+    Ok(args)
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -1999,6 +2627,10 @@ impl Parse for FuncArgValueStmt {
                 Ty::IdVar(name) => {
                     func_arg_val.arg_name = name;
                 }
+                // receiver for static calls (i.e. `Map:len { self: my_map }`)
+                Ty::KwSelf => {
+                    func_arg_val.arg_name = tok.ty.into();
+                }
                 _ => {
                     bail!("FuncArgValueStmt expected IdVar");
                 }
@@ -2028,6 +2660,59 @@ impl Parse for FuncArgValueStmt {
     }
 }
 
+//
+// Collection value statement
+//
+// Builtin collection literals: `Type:from { item1 item2 ... }`.
+// Map items alternate between keys and values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CollectionValueStmt {
+    pub collection_type: TypeStmt,
+    pub items: Vec<ExprStmt>,
+}
+
+impl Parse for CollectionValueStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("CollectionValueStmt parse {:?}", stack);
+        let mut collection_val = CollectionValueStmt::default();
+
+        // parse type statement
+        let mut type_stack = take_all_until_match_any(stack, vec![Ty::IdFunc(":from".into())]);
+        collection_val.collection_type = TypeStmt::parse(&mut type_stack)?;
+
+        // IdFunc
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::IdFunc(name),
+                ..
+            }) if name == ":from" => {}
+            _ => bail!("CollectionValueStmt expected :from after the collection type"),
+        }
+
+        // eat starting and ending braces
+        if let Some(next) = stack.pop() {
+            match next.ty {
+                Ty::PunctBraceL => {
+                    // last token should be PunctBraceR
+                    if let Some(tok) = stack.first() {
+                        if tok.ty == Ty::PunctBraceR {
+                            stack.remove(0);
+                        }
+                    }
+                }
+                _ => stack.push(next),
+            }
+        }
+
+        collection_val.items = parse_positional_exprs(stack)?;
+
+        Ok(collection_val)
+    }
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 //
 // Let statement
 //
@@ -2096,6 +2781,7 @@ pub enum Expr {
     Value(ValueStmt),
     Unary(UnaryExprStmt),
     Binary(BinaryExprStmt),
+    Postfix(PostfixExprStmt),
 }
 
 impl Default for Expr {
@@ -2122,6 +2808,7 @@ pub enum Value {
     Float(f64),
     Func(FuncValueStmt),
     Struct(StructValueStmt),
+    Collection(CollectionValueStmt),
 
     // Anything defined by:
     // const, let, func args
@@ -2131,6 +2818,7 @@ pub enum Value {
     Package(String),
     Enum(EnumValueStmt),
     Tuple(TupleValueStmt),
+    Closure(ClosureValueStmt),
 }
 
 impl Default for Value {
@@ -2139,6 +2827,17 @@ impl Default for Value {
     }
 }
 
+//
+// Closure value statement
+//
+// Anonymous functions with a single expression body
+// (i.e. `fn { x: int } int => x + 1`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClosureValueStmt {
+    pub func_type: FuncTypeStmt,
+    pub body: Box<ExprStmt>,
+}
+
 //
 // Expression statements and operators
 //
@@ -2148,11 +2847,19 @@ pub struct UnaryExprStmt {
     pub rhs: Box<Expr>,
 }
 
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PostfixExprStmt {
+    pub lhs: Box<Expr>,
+    pub op: PostfixOp,
+}
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct BinaryExprStmt {
     pub lhs: Box<Expr>,
     pub op: Op,
     pub rhs: Box<Expr>,
+    // location of the operator
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -2306,14 +3013,37 @@ impl Default for UnaryOp {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum PostfixOp {
+    #[default]
+    None,
+    // ? returns early from the function
+    // if an Option is None or a Maybe is an error
+    Try,
+    // value:func { ... } calls a method with the value as `self`
+    Method(FuncValueStmt),
+    // value.field
+    Field(String),
+    // value[index]
+    Index(Box<ExprStmt>),
+    // value as Type
+    Cast(TypeStmt),
+}
+
 //
 // Function statements
 //
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct FuncStmt {
     pub func_name: String,
+    // type parameters (i.e. `fn :id[T]` or `fn :show[T ^Display]`)
+    pub generics: Vec<TypeStmt>,
     pub func_type: FuncTypeStmt,
     pub func_body: FuncBodyStmt,
+    // the source file this function was parsed from
+    pub file: Option<PathBuf>,
+    // location of the function name
+    pub span: Span,
 }
 
 impl Parse for FuncStmt {
@@ -2326,12 +3056,31 @@ impl Parse for FuncStmt {
             match tok.ty {
                 Ty::IdFunc(name) => {
                     func_stmt.func_name = name;
+                    func_stmt.span = tok.span;
                 }
                 _ => {
                     bail!("expected IdFunc");
                 }
             }
         }
+        // type parameters
+        if let Some(tok) = stack.last() {
+            if tok.ty == Ty::PunctBracketL {
+                stack.pop();
+                let mut inner = vec![];
+                loop {
+                    match stack.pop() {
+                        Some(tok) if tok.ty == Ty::PunctBracketR => break,
+                        Some(tok) if matches!(tok.ty, Ty::IdType(_) | Ty::IdTrait(_)) => {
+                            inner.push(tok)
+                        }
+                        _ => bail!("function {} expected type parameters", &func_stmt.func_name),
+                    }
+                }
+                inner.reverse();
+                func_stmt.generics = into_type_stmt_generics(&mut inner)?;
+            }
+        }
         // this should take
         func_stmt.func_type = FuncTypeStmt::parse(stack)?;
 
@@ -2384,8 +3133,9 @@ impl Parse for FuncTypeStmt {
             match tok.ty {
                 Ty::PunctBraceL => {
                     // collect input args here...
+                    // (function typed args have their own braces)
                     let mut input_type_stack =
-                        take_all_until_match_any(stack, vec![Ty::PunctBraceR]);
+                        take_all_until_unbalanced(stack, Ty::PunctBraceL, Ty::PunctBraceR);
                     debug!("input_type: {:?}", &input_type_stack);
                     if input_type_stack.len() > 0 {
                         let input_type = FuncInputTypeStmt::parse(&mut input_type_stack)?;
@@ -2434,9 +3184,14 @@ impl Parse for FuncInputTypeStmt {
         // parse input args
         let mut cleaned: Vec<Token> = remove_newline_indent(stack)?;
         let mut arg: Vec<Token> = vec![];
+        // only split args at the top-level
+        // (function types have their own `name:` tokens)
+        let mut depth = 0;
         while let Some(next) = cleaned.pop() {
             match next.ty {
-                Ty::IdVar(_) => {
+                Ty::PunctBraceL => depth += 1,
+                Ty::PunctBraceR => depth -= 1,
+                Ty::IdVar(_) if depth == 0 => {
                     if arg.len() > 0 {
                         arg.reverse();
                         func_in_ty_stmt
@@ -2565,7 +3320,7 @@ impl Parse for FuncBodyStmt {
                     let mut block_stack = take_all_until_match_any(stack, vec![Ty::Indent(indent)]);
                     // println!("block_stack before: {:?}", &block_stack);
 
-                    if block_stack.contains(&Token { ty: Ty::KwIf }) {
+                    if contains_ty(&block_stack, Ty::KwIf) {
                         debug!(
                             "block stack contains if stmt. continue parsing and take entire stmt {:?}",
                             &stack
@@ -2589,13 +3344,11 @@ impl Parse for FuncBodyStmt {
 
                             let mut next_stack =
                                 take_all_until_match_any(stack, vec![Ty::Indent(indent)]);
-                            if next_stack.contains(&Token { ty: Ty::KwElseIf })
-                                || next_stack.contains(&Token { ty: Ty::KwElse })
+                            if contains_ty(&next_stack, Ty::KwElseIf)
+                                || contains_ty(&next_stack, Ty::KwElse)
                             {
                                 // merge w/ block_stack
-                                next_stack.append(&mut vec![Token {
-                                    ty: Ty::Indent(indent),
-                                }]);
+                                next_stack.append(&mut vec![Token::new(Ty::Indent(indent))]);
                                 next_stack.append(&mut block_stack);
                                 block_stack = next_stack;
                             } else {
@@ -2623,6 +3376,78 @@ impl Parse for FuncBodyStmt {
     }
 }
 
+//
+// Trait statements
+//
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraitStmt {
+    pub trait_type: TypeStmt,
+    pub funcs: Vec<FuncStmt>,
+}
+
+impl Parse for TraitStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("TraitStmt parse {:?}", &stack);
+        let mut trait_stmt = TraitStmt::default();
+
+        // parse trait type statement
+        let mut type_stack = take_all_until_match_any(stack, vec![Ty::NL]);
+        trait_stmt.trait_type = TypeStmt::parse(&mut type_stack)?;
+
+        // parse trait functions
+        for mut func_stack in take_member_funcs(stack)? {
+            trait_stmt.funcs.push(FuncStmt::parse(&mut func_stack)?);
+        }
+
+        Ok(trait_stmt)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+// Struct and Enum trait implementations
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImplTraitStmt {
+    pub impl_type: TypeStmt,
+    // None for inherent impl blocks
+    pub impl_trait: Option<TypeStmt>,
+    pub funcs: Vec<FuncStmt>,
+}
+
+impl Parse for ImplTraitStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("ImplTraitStmt parse {:?}", &stack);
+        let mut impl_stmt = ImplTraitStmt::default();
+
+        // parse `Type ^Trait` statement
+        let mut type_stack = take_all_until_match_any(stack, vec![Ty::NL]);
+        type_stack.reverse();
+        let trait_pos = type_stack
+            .iter()
+            .position(|tok| matches!(tok.ty, Ty::IdTrait(_)));
+        if let Some(pos) = trait_pos {
+            let mut trait_stack = type_stack.split_off(pos);
+            trait_stack.reverse();
+            impl_stmt.impl_trait = Some(TypeStmt::parse(&mut trait_stack)?);
+        }
+        type_stack.reverse();
+        impl_stmt.impl_type = TypeStmt::parse(&mut type_stack)?;
+
+        // parse impl functions
+        for mut func_stack in take_member_funcs(stack)? {
+            impl_stmt.funcs.push(FuncStmt::parse(&mut func_stack)?);
+        }
+
+        Ok(impl_stmt)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 //
 // Block statements
 //
@@ -2631,6 +3456,8 @@ pub struct BlockStmt {
     indent: usize,
     pub blocks: Vec<Block>,
     pub return_type: Option<TypeStmt>,
+    // location of the first statement token
+    pub span: Span,
 }
 
 impl ParseSelf for BlockStmt {
@@ -2643,6 +3470,10 @@ impl ParseSelf for BlockStmt {
 
         let mut indent = self.indent.clone();
         while let Some(tok) = stack.pop() {
+            if self.blocks.is_empty() && !matches!(tok.ty, Ty::NL | Ty::Indent(_) | Ty::Comment(_))
+            {
+                self.span = tok.span;
+            }
             match tok.ty {
                 Ty::NL => {}
                 Ty::Indent(val) => {
@@ -2670,7 +3501,13 @@ impl ParseSelf for BlockStmt {
                     self.blocks.push(block);
                 }
                 Ty::KwFor => {
-                    todo!("block for stmt");
+                    let for_stmt = ForInStmt {
+                        indent,
+                        ..ForInStmt::default()
+                    }
+                    .parse(stack)?;
+                    let block = Block::ForIn(for_stmt);
+                    self.blocks.push(block);
                 }
                 Ty::KwIf => {
                     // add back if so we can parse later using a while loop
@@ -2691,6 +3528,21 @@ impl ParseSelf for BlockStmt {
                     let block = Block::Return(ret_stmt);
                     self.blocks.push(block);
                 }
+                // method calls (i.e. `my_list:push { item: 1 }`) and
+                // field or index assignments (i.e. `p.x += 1`, `m["a"] = 1`)
+                Ty::IdVar(_) | Ty::IdPackage(_) | Ty::KwSelf
+                    if !matches!(tok.ty, Ty::IdVar(_))
+                        || !stack.last().is_some_and(|t| {
+                            t.ty == Ty::PunctColon || Ty::assign_operators().contains(&t.ty)
+                        }) =>
+                {
+                    // take until NL
+                    stack.push(tok);
+                    let mut expr_stack = take_all_until_match_any(stack, vec![Ty::NL]);
+                    let expr_stmt = ExprStmt::parse(&mut expr_stack)?;
+                    let block = Block::Expr(expr_stmt);
+                    self.blocks.push(block);
+                }
                 Ty::IdVar(_) => {
                     // take until NL
                     stack.push(tok);
@@ -2699,7 +3551,13 @@ impl ParseSelf for BlockStmt {
                     let block = Block::Assign(assign_stmt);
                     self.blocks.push(block);
                 }
-                Ty::IdFunc(_) => {
+                Ty::IdFunc(_)
+                | Ty::IdType(_)
+                | Ty::BuiltinTypeMap
+                | Ty::BuiltinTypeSet
+                | Ty::BuiltinTypeList
+                | Ty::BuiltinTypeOption
+                | Ty::BuiltinTypeString => {
                     // take until NL
                     stack.push(tok);
                     let expr_stmt = ExprStmt::parse(stack)?;
@@ -2913,6 +3771,69 @@ impl Parse for ConditionStmt {
     }
 }
 
+//
+// For statements
+//
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ForInStmt {
+    indent: usize,
+    // for k v in ...
+    pub vars: Vec<String>,
+    pub iter: ExprStmt,
+    pub blocks: Vec<BlockStmt>,
+}
+
+impl ParseSelf for ForInStmt {
+    fn parse(mut self, stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("ForInStmt parse {:?}", stack);
+
+        // take loop variables until KwIn
+        let vars_stack = take_all_until_match_any(stack, vec![Ty::KwIn]);
+        for tok in vars_stack.into_iter().rev() {
+            match tok.ty {
+                Ty::IdVar(name) => self.vars.push(name),
+                _ => bail!("for statement expected variable names before `in`"),
+            }
+        }
+        if self.vars.is_empty() {
+            bail!("for statement expected at least one variable name");
+        }
+
+        // should be KwIn
+        if let Some(tok) = stack.pop() {
+            match tok.ty {
+                Ty::KwIn => {}
+                _ => {
+                    stack.push(tok);
+                    bail!("expected KwIn");
+                }
+            }
+        }
+
+        // take iterator expression until NL
+        let mut iter_stack = take_all_until_match_any(stack, vec![Ty::NL]);
+        self.iter = ExprStmt::parse(&mut iter_stack)?;
+
+        loop {
+            let mut block_stack = take_all_until_match_any(stack, vec![Ty::Indent(self.indent)]);
+            if block_stack.is_empty() {
+                break;
+            }
+            let block_stmt = BlockStmt {
+                indent: self.indent,
+                ..BlockStmt::default()
+            }
+            .parse(&mut block_stack)?;
+            self.blocks.push(block_stmt);
+        }
+
+        Ok(self)
+    }
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 ///
 ///
 ///
@@ -2932,9 +3853,6 @@ impl Parse for ConditionStmt {
 #[derive(Debug, Clone, Default, PartialEq)]
 struct ForStmt {}
 
-#[derive(Debug, Clone, Default, PartialEq)]
-struct ForInStmt {}
-
 #[derive(Debug, Clone, Default, PartialEq)]
 struct MatchStmt {
     value: ValueStmt,
@@ -2950,15 +3868,161 @@ struct MatchArmStmt {
 #[derive(Debug, Clone, Default, PartialEq)]
 struct WhileStmt {}
 
+//
+// Test statement (i.e. `test "adds numbers" =>` followed by the test body)
+//
 #[derive(Debug, Clone, Default, PartialEq)]
-struct TraitStmt {}
+pub struct TestStmt {
+    pub test_name: String,
+    pub test_body: FuncBodyStmt,
+    // the source file this test was parsed from
+    pub file: Option<PathBuf>,
+    // location of the test name
+    pub span: Span,
+}
 
-// Struct and Enum trait implementations
-#[derive(Debug, Clone, Default, PartialEq)]
-struct ImplTraitStmt {}
+impl Parse for TestStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("TestStmt parse {:?}", stack);
+        let mut test_stmt = TestStmt::default();
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::LitString(name),
+                span,
+            }) => {
+                test_stmt.test_name = clean_quotes(name);
+                test_stmt.span = span;
+            }
+            _ => bail!("test statement expected a name (i.e. `test \"my test\" =>`)"),
+        }
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::PunctFatArrow,
+                ..
+            }) => {
+                test_stmt.test_body = FuncBodyStmt::parse(stack)?;
+            }
+            _ => bail!("test {:?} expected a body", &test_stmt.test_name),
+        }
+        Ok(test_stmt)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
 
+//
+// Test case statement
+// A table of named rows which each run the test body
+// with the row values bound to the input names:
+//
+// testcase "adds numbers" { a: int b: int sum: int } =>
+//   case "small" { a: 1 b: 2 sum: 3 }
+//   case "zero" { a: 0 b: 0 sum: 0 }
+//   :assert_eq { left: :add { a: a b: b } right: sum }
+//
 #[derive(Debug, Clone, Default, PartialEq)]
-struct TestStmt {}
+pub struct TestCaseStmt {
+    pub test_name: String,
+    pub input_type: FuncInputTypeStmt,
+    pub rows: Vec<TestCaseRowStmt>,
+    pub test_body: FuncBodyStmt,
+    // the source file this test case was parsed from
+    pub file: Option<PathBuf>,
+    // location of the test case name
+    pub span: Span,
+}
+
+impl Parse for TestCaseStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("TestCaseStmt parse {:?}", stack);
+        let mut test_case_stmt = TestCaseStmt::default();
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::LitString(name),
+                span,
+            }) => {
+                test_case_stmt.test_name = clean_quotes(name);
+                test_case_stmt.span = span;
+            }
+            _ => bail!(
+                "testcase statement expected a name (i.e. `testcase \"my test\" {{ a: int }} =>`)"
+            ),
+        }
+        let test_name = test_case_stmt.test_name.clone();
+        let func_type = FuncTypeStmt::parse(stack)?;
+        match func_type {
+            FuncTypeStmt {
+                is_self: false,
+                input_type: Some(input_type),
+                output_type: None,
+            } if !input_type.args.is_empty() => test_case_stmt.input_type = input_type,
+            _ => bail!(
+                "testcase {:?} expected input names and types (i.e. `{{ a: int }}`)",
+                &test_name
+            ),
+        }
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::PunctFatArrow,
+                ..
+            }) => {}
+            _ => bail!("testcase {:?} expected a body", &test_name),
+        }
+        // rows come before the body
+        loop {
+            match stack.pop() {
+                Some(Token { ty: Ty::NL, .. }) => continue,
+                Some(tok) => match (&tok.ty, stack.last().map(|next| &next.ty)) {
+                    (Ty::Indent(_), Some(Ty::KwCase)) => {
+                        stack.pop();
+                        let mut row = take_all_until_match_any(stack, vec![Ty::NL]);
+                        test_case_stmt.rows.push(TestCaseRowStmt::parse(&mut row)?);
+                    }
+                    _ => {
+                        stack.push(tok);
+                        break;
+                    }
+                },
+                None => break,
+            }
+        }
+        if test_case_stmt.rows.is_empty() {
+            bail!("testcase {:?} expected at least one case row", &test_name);
+        }
+        test_case_stmt.test_body = FuncBodyStmt::parse(stack)?;
+        Ok(test_case_stmt)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
 
+// A test case row (i.e. `case "small" { a: 1 b: 2 sum: 3 }`)
 #[derive(Debug, Clone, Default, PartialEq)]
-struct TestCaseStmt {}
+pub struct TestCaseRowStmt {
+    pub row_name: String,
+    pub args: Vec<FuncArgValueStmt>,
+}
+
+impl Parse for TestCaseRowStmt {
+    fn parse(stack: &mut Vec<Token>) -> Result<Self, Error> {
+        debug!("TestCaseRowStmt parse {:?}", stack);
+        let mut row_stmt = TestCaseRowStmt::default();
+        match stack.pop() {
+            Some(Token {
+                ty: Ty::LitString(name),
+                ..
+            }) => row_stmt.row_name = clean_quotes(name),
+            _ => bail!("testcase row expected a name (i.e. `case \"my row\" {{ a: 1 }}`)"),
+        }
+        row_stmt.args = parse_named_args(stack)?;
+        Ok(row_stmt)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
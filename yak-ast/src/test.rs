@@ -5,16 +5,17 @@ use crate::{
     expr::expr::ExprParser, ArithOp, AssignOp, AssignStmt, Ast, BinaryExprStmt, Block, BlockStmt,
     ConstStmt, Expr, ExprStmt, FuncArgValueStmt, FuncBodyStmt, FuncInputArgTypeStmt,
     FuncInputTypeStmt, FuncOutputTypeStmt, FuncStmt, FuncTypeStmt, FuncValueStmt, Op,
-    PackageDependencyStmt, PackageExportStmt, PackageFileStmt, PackageImportStmt, PackageStmt,
-    PackageSymbol, PackageSymbolStmt, ReturnStmt, StructFieldStmt, StructFieldValueStmt,
-    StructStmt, StructValueStmt, TypeStmt, Value, ValueStmt, VarTypeStmt,
+    PackageDependencyStmt, PackageExportStmt, PackageFileStmt, PackageImportStmt, PackageLinkStmt,
+    PackageProfileStmt, PackageStmt, PackageSymbol, PackageSymbolStmt, PostfixExprStmt, PostfixOp,
+    ReturnStmt, StructFieldStmt, StructFieldValueStmt, StructStmt, StructValueStmt, TypeStmt,
+    Value, ValueStmt, VarTypeStmt,
 };
 #[cfg(test)]
 use yak_lexer::token::TokenType as Ty;
 #[cfg(test)]
 use yak_lexer::Lexer;
 #[cfg(test)]
-use yak_lexer::Token;
+use yak_lexer::{Span, Token};
 
 #[test]
 fn test_package() {
@@ -112,6 +113,10 @@ export {
                     symbol: PackageSymbol::Type("Type2".into()),
                     as_symbol: Some(PackageSymbol::Type("TypeOther".into())),
                 },
+                PackageSymbolStmt {
+                    symbol: PackageSymbol::Trait("^Trait1".into()),
+                    as_symbol: None,
+                },
                 PackageSymbolStmt {
                     symbol: PackageSymbol::Trait("^Trait2".into()),
                     as_symbol: Some(PackageSymbol::Trait("^TraitOther".into())),
@@ -145,6 +150,8 @@ export {
             ]
             .to_vec(),
         },
+        profiles: vec![],
+        link: PackageLinkStmt::default(),
     };
 
     let mut ast = Ast::from_source(src);
@@ -153,6 +160,104 @@ export {
     assert_eq!(ast.parsed.package, expected);
 }
 
+#[test]
+fn test_package_profiles() {
+    let src = "
+package \"my.pkg\"
+profile debug {
+  opt_level 1
+}
+profile release {
+  opt_level z
+  overflow_checks true
+  debug_assertions false
+  debug_info true
+}
+";
+    let mut ast = Ast::from_source(src);
+    ast.parse_package().unwrap();
+    let expected = vec![
+        PackageProfileStmt {
+            name: "debug".into(),
+            settings: vec![("opt_level".into(), "1".into())],
+        },
+        PackageProfileStmt {
+            name: "release".into(),
+            settings: vec![
+                ("opt_level".into(), "z".into()),
+                ("overflow_checks".into(), "true".into()),
+                ("debug_assertions".into(), "false".into()),
+                ("debug_info".into(), "true".into()),
+            ],
+        },
+    ];
+    assert_eq!(ast.parsed.package.profiles, expected);
+
+    let pkg = ast
+        .parsed
+        .package
+        .clone()
+        .into_yak_package(true, None, ".".into(), None)
+        .unwrap();
+    let release = pkg.get_profile("release").unwrap();
+    assert_eq!(release.opt_level.as_deref(), Some("z"));
+    assert_eq!(release.overflow_checks, Some(true));
+    assert_eq!(release.debug_assertions, Some(false));
+    assert_eq!(release.debug_info, Some(true));
+    assert_eq!(pkg.get_profile("debug").unwrap().overflow_checks, None);
+
+    // unknown levels fail when the package is converted
+    let mut ast = Ast::from_source("package \"my.pkg\"\nprofile release {\n  opt_level 4\n}\n");
+    ast.parse_package().unwrap();
+    let err = ast
+        .parsed
+        .package
+        .clone()
+        .into_yak_package(true, None, ".".into(), None)
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown opt_level 4"));
+}
+
+#[test]
+fn test_package_link() {
+    let src = "
+package \"my.pkg\"
+link {
+  linker ld.lld
+  lib \"static\"
+}
+";
+    let mut ast = Ast::from_source(src);
+    ast.parse_package().unwrap();
+    let expected = PackageLinkStmt {
+        settings: vec![
+            ("linker".into(), "ld.lld".into()),
+            ("lib".into(), "static".into()),
+        ],
+    };
+    assert_eq!(ast.parsed.package.link, expected);
+
+    let pkg = ast
+        .parsed
+        .package
+        .clone()
+        .into_yak_package(true, None, ".".into(), None)
+        .unwrap();
+    assert_eq!(pkg.pkg_link.linker.as_deref(), Some("ld.lld"));
+    assert_eq!(pkg.pkg_link.lib.as_deref(), Some("static"));
+
+    // unknown linkers fail when the package is converted
+    let mut ast = Ast::from_source("package \"my.pkg\"\nlink {\n  linker gold\n}\n");
+    ast.parse_package().unwrap();
+    let err = ast
+        .parsed
+        .package
+        .clone()
+        .into_yak_package(true, None, ".".into(), None)
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown linker gold"));
+}
+
 #[cfg(test)]
 fn pratt_parser(src: &str) -> Result<Expr, PrattError<Token, NoError>> {
     let mut lexer = Lexer::from_source(src);
@@ -373,6 +478,7 @@ const result = :func1 {
             expr: ExprStmt {
                 expr: Expr::Value(ValueStmt {
                     value: Value::Func(FuncValueStmt {
+                        func_type: None,
                         func_name: ":func1".into(),
                         args: [
                             FuncArgValueStmt {
@@ -387,11 +493,23 @@ const result = :func1 {
                                             rhs: Box::new(Expr::Value(ValueStmt {
                                                 value: Value::Var("b".into()),
                                             })),
+                                            span: Span {
+                                                ln: 3,
+                                                pos: 35,
+                                                start: 10,
+                                                end: 11,
+                                            },
                                         })),
                                         op: Op::Arith(ArithOp::Add),
                                         rhs: Box::new(Expr::Value(ValueStmt {
                                             value: Value::Var("c".into()),
                                         })),
+                                        span: Span {
+                                            ln: 3,
+                                            pos: 39,
+                                            start: 14,
+                                            end: 15,
+                                        },
                                     }),
                                 },
                             },
@@ -407,16 +525,34 @@ const result = :func1 {
                                             rhs: Box::new(Expr::Value(ValueStmt {
                                                 value: Value::Int(2),
                                             })),
+                                            span: Span {
+                                                ln: 4,
+                                                pos: 51,
+                                                start: 9,
+                                                end: 10,
+                                            },
                                         })),
                                         op: Op::Arith(ArithOp::Div),
                                         rhs: Box::new(Expr::Value(ValueStmt {
                                             value: Value::Int(3),
                                         })),
+                                        span: Span {
+                                            ln: 4,
+                                            pos: 56,
+                                            start: 14,
+                                            end: 15,
+                                        },
                                     }),
                                 },
                             },
                         ]
                         .to_vec(),
+                        span: Span {
+                            ln: 2,
+                            pos: 22,
+                            start: 16,
+                            end: 22,
+                        },
                     }),
                 }),
             },
@@ -464,6 +600,7 @@ const struct_var =
                         struct_type: TypeStmt {
                             type_name: "MyStruct".into(),
                             generics: None,
+                            func_type: None,
                         },
                         fields: vec![StructFieldValueStmt {
                             field_name: "a".into(),
@@ -517,6 +654,7 @@ const struct_var =
                         struct_type: TypeStmt {
                             type_name: "MyStruct".into(),
                             generics: None,
+                            func_type: None,
                         },
                         fields: vec![
                             StructFieldValueStmt {
@@ -610,6 +748,7 @@ const struct_var =
                         struct_type: TypeStmt {
                             type_name: "MyStruct".into(),
                             generics: None,
+                            func_type: None,
                         },
                         fields: [
                             StructFieldValueStmt {
@@ -628,7 +767,19 @@ const struct_var =
                                             rhs: Box::new(Expr::Value(ValueStmt {
                                                 value: Value::Var("var1".into()),
                                             })),
+                                            span: Span {
+                                                ln: 4,
+                                                pos: 50,
+                                                start: 19,
+                                                end: 20,
+                                            },
                                         })),
+                                        span: Span {
+                                            ln: 4,
+                                            pos: 46,
+                                            start: 15,
+                                            end: 16,
+                                        },
                                     }),
                                 },
                             },
@@ -702,10 +853,169 @@ const struct_var =
 fn test_var_basic_type_list_expr() {}
 
 #[test]
-fn test_var_basic_type_set_expr() {}
+fn test_var_basic_type_set_expr() {
+    let src = "const set_var = Set[int]:from { 1 2 3 }";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Collection(collection),
+        }) => {
+            assert_eq!(collection.collection_type.type_name, "Set");
+            assert_eq!(
+                collection.collection_type.generics,
+                Some(Box::new(vec![TypeStmt {
+                    type_name: "int32".into(),
+                    generics: None,
+                    func_type: None,
+                }]))
+            );
+            assert_eq!(collection.items.len(), 3);
+        }
+        _ => panic!("expected a collection value, got {:?}", expr),
+    }
+}
+
+#[test]
+fn test_var_basic_type_map_expr() {
+    let src = "const map_var = Map[String int]:from { \"a\" 1 \"b\" 2 }";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Collection(collection),
+        }) => {
+            assert_eq!(collection.collection_type.type_name, "Map");
+            assert_eq!(collection.items.len(), 4);
+        }
+        _ => panic!("expected a collection value, got {:?}", expr),
+    }
+}
+
+#[test]
+fn test_var_static_type_func_expr() {
+    let src = "const len = Map:len { self: my_map }";
+
+    let expected = &FuncValueStmt {
+        func_type: Some(TypeStmt {
+            type_name: "Map".into(),
+            generics: None,
+            func_type: None,
+        }),
+        func_name: ":len".into(),
+        args: vec![FuncArgValueStmt {
+            arg_name: "self".into(),
+            arg_value: ExprStmt {
+                expr: Expr::Value(ValueStmt {
+                    value: Value::Var("my_map".into()),
+                }),
+            },
+        }],
+        span: Span {
+            ln: 1,
+            pos: 19,
+            start: 16,
+            end: 20,
+        },
+    };
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    assert_eq!(
+        expr,
+        &Expr::Value(ValueStmt {
+            value: Value::Func(expected.clone()),
+        })
+    );
+}
+
+#[test]
+fn test_var_trait_func_expr() {
+    let src = "const area = ^Shape:area { self: shape }";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Func(func),
+        }) => {
+            assert_eq!(func.func_type.as_ref().unwrap().type_name, "^Shape");
+            assert_eq!(func.func_name, ":area");
+            assert_eq!(func.args.len(), 1);
+        }
+        _ => panic!("expected a func value, got {:?}", expr),
+    }
+}
 
 #[test]
-fn test_var_basic_type_fn_expr() {}
+fn test_var_basic_type_fn_expr() {
+    let src = "const add = fn { x: int y: int } int => x + y";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Closure(closure),
+        }) => {
+            let input_type = closure.func_type.input_type.as_ref().unwrap();
+            let args: Vec<&str> = input_type
+                .args
+                .iter()
+                .map(|arg| arg.arg_name.as_str())
+                .collect();
+            assert_eq!(args, vec!["x", "y"]);
+            assert_eq!(
+                closure
+                    .func_type
+                    .output_type
+                    .as_ref()
+                    .unwrap()
+                    .output_type
+                    .type_name,
+                "int32"
+            );
+            assert!(matches!(closure.body.expr, Expr::Binary(_)));
+        }
+        _ => panic!("expected a closure value, got {:?}", expr),
+    }
+}
+
+#[test]
+fn test_var_fn_call_arg_expr() {
+    let src = "const y = :apply { f: fn { x: int } int => x * n value: 2 }";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Func(func),
+        }) => {
+            let args: Vec<&str> = func.args.iter().map(|arg| arg.arg_name.as_str()).collect();
+            assert_eq!(args, vec!["f", "value"]);
+            assert!(matches!(
+                func.args[0].arg_value.expr,
+                Expr::Value(ValueStmt {
+                    value: Value::Closure(_)
+                })
+            ));
+        }
+        _ => panic!("expected a func value, got {:?}", expr),
+    }
+}
 
 #[test]
 fn test_var_basic_type_option_expr() {}
@@ -740,6 +1050,7 @@ fn :fn1 {} String =>
 ";
     let expected = &FuncStmt {
         func_name: ":fn1".into(),
+        generics: vec![],
         func_type: FuncTypeStmt {
             is_self: false,
             input_type: None,
@@ -747,6 +1058,7 @@ fn :fn1 {} String =>
                 output_type: TypeStmt {
                     type_name: "String".into(),
                     generics: None,
+                    func_type: None,
                 },
             }),
         },
@@ -763,9 +1075,22 @@ fn :fn1 {} String =>
                 })]
                 .to_vec(),
                 return_type: None,
+                span: Span {
+                    ln: 3,
+                    pos: 31,
+                    start: 3,
+                    end: 9,
+                },
             }]
             .to_vec(),
         },
+        file: None,
+        span: Span {
+            ln: 2,
+            pos: 8,
+            start: 4,
+            end: 8,
+        },
     };
 
     let mut ast = Ast::from_source(src);
@@ -774,6 +1099,79 @@ fn :fn1 {} String =>
     assert_eq!(ast.parsed.funcs.get(0), Some(expected));
 }
 
+#[test]
+fn test_fn_generics() {
+    let src = "
+fn :pair[K V] { key: K value: V } Map[K V] =>
+  return Map:from { key value }
+";
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    assert_eq!(func.func_name, ":pair");
+    let generics: Vec<&str> = func
+        .generics
+        .iter()
+        .map(|generic| generic.type_name.as_str())
+        .collect();
+    assert_eq!(generics, vec!["K", "V"]);
+    let input_type = func.func_type.input_type.as_ref().unwrap();
+    assert_eq!(input_type.args[0].arg_type.type_name, "K");
+}
+
+#[test]
+fn test_fn_generic_bounds() {
+    let src = "
+fn :show[T ^Display ^Eq U] { value: T other: U } String =>
+  return T:display { self: value }
+";
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    let generics: Vec<&str> = func
+        .generics
+        .iter()
+        .map(|generic| generic.type_name.as_str())
+        .collect();
+    assert_eq!(generics, vec!["T", "^Display", "^Eq", "U"]);
+}
+
+#[test]
+fn test_fn_func_type_args() {
+    let src = "
+fn :apply { f: fn { x: int } int value: int } fn {} int =>
+  return fn {} int => :f { x: value }
+";
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    let input_type = func.func_type.input_type.as_ref().unwrap();
+    assert_eq!(input_type.args.len(), 2);
+    let f_type = &input_type.args[0].arg_type;
+    assert_eq!(f_type.type_name, "fn");
+    let f_func_type = f_type.func_type.as_ref().unwrap();
+    assert_eq!(
+        f_func_type.input_type.as_ref().unwrap().args[0].arg_name,
+        "x"
+    );
+    assert_eq!(
+        f_func_type
+            .output_type
+            .as_ref()
+            .unwrap()
+            .output_type
+            .type_name,
+        "int32"
+    );
+    assert_eq!(input_type.args[1].arg_type.type_name, "int32");
+    let output_type = &func.func_type.output_type.as_ref().unwrap().output_type;
+    assert_eq!(output_type.type_name, "fn");
+    assert!(output_type.func_type.as_ref().unwrap().input_type.is_none());
+}
+
 #[test]
 fn test_fn_type_signature() {
     let src = "
@@ -786,6 +1184,7 @@ fn :fn1 self {
 
     let expected = &FuncStmt {
         func_name: ":fn1".into(),
+        generics: vec![],
         func_type: FuncTypeStmt {
             is_self: true,
             input_type: Some(FuncInputTypeStmt {
@@ -795,6 +1194,7 @@ fn :fn1 self {
                         arg_type: TypeStmt {
                             type_name: "String".into(),
                             generics: None,
+                            func_type: None,
                         },
                     },
                     FuncInputArgTypeStmt {
@@ -802,6 +1202,7 @@ fn :fn1 self {
                         arg_type: TypeStmt {
                             type_name: "int32".into(),
                             generics: None,
+                            func_type: None,
                         },
                     },
                 ]
@@ -811,6 +1212,7 @@ fn :fn1 self {
                 output_type: TypeStmt {
                     type_name: "String".into(),
                     generics: None,
+                    func_type: None,
                 },
             }),
         },
@@ -827,9 +1229,22 @@ fn :fn1 self {
                 })]
                 .to_vec(),
                 return_type: None,
+                span: Span {
+                    ln: 6,
+                    pos: 64,
+                    start: 3,
+                    end: 9,
+                },
             }]
             .to_vec(),
         },
+        file: None,
+        span: Span {
+            ln: 2,
+            pos: 8,
+            start: 4,
+            end: 8,
+        },
     };
 
     let mut ast = Ast::from_source(src);
@@ -879,7 +1294,9 @@ struct MyStruct[X]
             generics: Some(Box::new(vec![TypeStmt {
                 type_name: "X".into(),
                 generics: None,
+                func_type: None,
             }])),
+            func_type: None,
         },
         fields: vec![StructFieldStmt {
             field_name: "f1".into(),
@@ -888,7 +1305,9 @@ struct MyStruct[X]
                 generics: Some(Box::new(vec![TypeStmt {
                     type_name: "X".into(),
                     generics: None,
+                    func_type: None,
                 }])),
+                func_type: None,
             },
         }],
     };
@@ -897,3 +1316,930 @@ struct MyStruct[X]
     assert_eq!(ast.parsed.errors.len(), 0);
     assert_eq!(ast.parsed.structs.get(0), Some(expected));
 }
+
+#[test]
+fn test_var_func_struct_arg_expr() {
+    let src = "const found = Set:contains { self: my_set item: Point { x: 1 } }";
+
+    let expected = &FuncValueStmt {
+        func_type: Some(TypeStmt {
+            type_name: "Set".into(),
+            generics: None,
+            func_type: None,
+        }),
+        func_name: ":contains".into(),
+        args: vec![
+            FuncArgValueStmt {
+                arg_name: "self".into(),
+                arg_value: ExprStmt {
+                    expr: Expr::Value(ValueStmt {
+                        value: Value::Var("my_set".into()),
+                    }),
+                },
+            },
+            FuncArgValueStmt {
+                arg_name: "item".into(),
+                arg_value: ExprStmt {
+                    expr: Expr::Value(ValueStmt {
+                        value: Value::Struct(StructValueStmt {
+                            struct_type: TypeStmt {
+                                type_name: "Point".into(),
+                                generics: None,
+                                func_type: None,
+                            },
+                            fields: vec![StructFieldValueStmt {
+                                field_name: "x".into(),
+                                field_value: ExprStmt {
+                                    expr: Expr::Value(ValueStmt {
+                                        value: Value::Int(1),
+                                    }),
+                                },
+                            }],
+                        }),
+                    }),
+                },
+            },
+        ],
+        span: Span {
+            ln: 1,
+            pos: 26,
+            start: 18,
+            end: 27,
+        },
+    };
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    assert_eq!(
+        expr,
+        &Expr::Value(ValueStmt {
+            value: Value::Func(expected.clone()),
+        })
+    );
+}
+
+#[test]
+fn test_var_try_expr() {
+    let src = "const x = :func1 { a: 1 }? + 2";
+
+    let expected = Expr::Binary(BinaryExprStmt {
+        lhs: Box::new(Expr::Postfix(PostfixExprStmt {
+            lhs: Box::new(Expr::Value(ValueStmt {
+                value: Value::Func(FuncValueStmt {
+                    func_type: None,
+                    func_name: ":func1".into(),
+                    args: vec![FuncArgValueStmt {
+                        arg_name: "a".into(),
+                        arg_value: ExprStmt {
+                            expr: Expr::Value(ValueStmt {
+                                value: Value::Int(1),
+                            }),
+                        },
+                    }],
+                    span: Span {
+                        ln: 1,
+                        pos: 16,
+                        start: 11,
+                        end: 17,
+                    },
+                }),
+            })),
+            op: PostfixOp::Try,
+        })),
+        op: Op::Arith(ArithOp::Add),
+        rhs: Box::new(Expr::Value(ValueStmt {
+            value: Value::Int(2),
+        })),
+        span: Span {
+            ln: 1,
+            pos: 28,
+            start: 28,
+            end: 29,
+        },
+    });
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    assert_eq!(expr, &expected);
+}
+
+#[test]
+fn test_var_method_call_expr() {
+    let src = "const x = p:moved { dx: 1 }.x + 2";
+
+    let expected = Expr::Binary(BinaryExprStmt {
+        lhs: Box::new(Expr::Postfix(PostfixExprStmt {
+            lhs: Box::new(Expr::Postfix(PostfixExprStmt {
+                lhs: Box::new(Expr::Value(ValueStmt {
+                    value: Value::Var("p".into()),
+                })),
+                op: PostfixOp::Method(FuncValueStmt {
+                    func_type: None,
+                    func_name: ":moved".into(),
+                    args: vec![FuncArgValueStmt {
+                        arg_name: "dx".into(),
+                        arg_value: ExprStmt {
+                            expr: Expr::Value(ValueStmt {
+                                value: Value::Int(1),
+                            }),
+                        },
+                    }],
+                    span: Span {
+                        ln: 1,
+                        pos: 17,
+                        start: 12,
+                        end: 18,
+                    },
+                }),
+            })),
+            op: PostfixOp::Field("x".into()),
+        })),
+        op: Op::Arith(ArithOp::Add),
+        rhs: Box::new(Expr::Value(ValueStmt {
+            value: Value::Int(2),
+        })),
+        span: Span {
+            ln: 1,
+            pos: 31,
+            start: 31,
+            end: 32,
+        },
+    });
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    assert_eq!(expr, &expected);
+}
+
+#[test]
+fn test_var_method_call_positional_expr() {
+    // `:f {}` after a space is the next value
+    let src = "const x = Set[int]:from { a:len {} :f {} }";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Collection(collection),
+        }) => {
+            assert_eq!(collection.items.len(), 2);
+            assert!(matches!(
+                &collection.items[0].expr,
+                Expr::Postfix(PostfixExprStmt {
+                    op: PostfixOp::Method(_),
+                    ..
+                })
+            ));
+        }
+        _ => panic!("expected a collection value, got {:?}", expr),
+    }
+}
+
+#[cfg(test)]
+fn collection_items(src: &str) -> Vec<Expr> {
+    let mut ast = Ast::from_source(src);
+    assert!(ast.parse().is_ok(), "unable to parse {:?}", src);
+    assert_eq!(ast.parsed.errors.len(), 0);
+    match &ast.parsed.consts.first().unwrap().assign.expr.expr {
+        Expr::Value(ValueStmt {
+            value: Value::Collection(collection),
+        }) => collection
+            .items
+            .iter()
+            .map(|item| item.expr.clone())
+            .collect(),
+        expr => panic!("expected a collection value, got {:?}", expr),
+    }
+}
+
+#[test]
+fn test_var_method_call_detached_lines() {
+    // one value per line, nothing to split
+    let items = collection_items("const x = Set[int]:from {\n  a:len\n  b\n  c:len\n}");
+    assert_eq!(items.len(), 3);
+    assert!(matches!(&items[1], Expr::Value(ValueStmt { value: Value::Var(name) }) if name == "b"));
+
+    // a brace on the next line isn't taken as the method args
+    let src = "const x = Set[int]:from {\n  a:get\n  { index: 0 }\n}";
+    assert!(Ast::from_source(src).parse().is_err());
+    let items = collection_items("const x = Set[int]:from {\n  a:get { index: 0 }\n}");
+    assert_eq!(items.len(), 1);
+
+    // args which start on the method line can continue on the next lines
+    let items = collection_items("const x = Set[int]:from {\n  a:get {\n    index: 0\n  }\n  b\n}");
+    assert_eq!(items.len(), 2);
+    match &items[0] {
+        Expr::Postfix(PostfixExprStmt {
+            op: PostfixOp::Method(func_val),
+            ..
+        }) => assert_eq!(func_val.args.len(), 1),
+        expr => panic!("expected a method call, got {:?}", expr),
+    }
+}
+
+#[test]
+fn test_expr_bad_method_args() {
+    assert!(pratt_parser("x:foo { a: }").is_err());
+    assert!(pratt_parser("x:foo { a: 1 }").is_ok());
+}
+
+#[test]
+fn test_fn_method_call_stmt() {
+    let src = "
+fn :main {} =>
+  names:insert { key: \"a\" value: 1 }
+  let x = 1
+";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    let blocks: Vec<&Block> = func
+        .func_body
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .collect();
+    assert_eq!(blocks.len(), 2);
+    assert!(matches!(
+        blocks[0],
+        Block::Expr(ExprStmt {
+            expr: Expr::Postfix(PostfixExprStmt {
+                op: PostfixOp::Method(_),
+                ..
+            })
+        })
+    ));
+}
+
+#[test]
+fn test_fn_assign_stmts() {
+    let src = "
+fn :main {} =>
+  x <<= 2
+  p.x += 1
+  m[\"a\"] = 2
+";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    let blocks: Vec<&Block> = func
+        .func_body
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .collect();
+    assert_eq!(blocks.len(), 3);
+    match blocks[0] {
+        Block::Assign(assign) => {
+            assert_eq!(assign.var_type.var_name, "x");
+            assert_eq!(assign.op, Op::Assign(AssignOp::BitwiseShiftL));
+        }
+        block => panic!("expected an assign statement, got {:?}", block),
+    }
+    let expected = Expr::Binary(BinaryExprStmt {
+        lhs: Box::new(Expr::Value(ValueStmt {
+            value: Value::Package("p.x".into()),
+        })),
+        op: Op::Assign(AssignOp::Add),
+        rhs: Box::new(Expr::Value(ValueStmt {
+            value: Value::Int(1),
+        })),
+        span: Span {
+            ln: 4,
+            pos: 34,
+            start: 7,
+            end: 9,
+        },
+    });
+    assert_eq!(blocks[1], &Block::Expr(ExprStmt { expr: expected }));
+    let expected = Expr::Binary(BinaryExprStmt {
+        lhs: Box::new(Expr::Postfix(PostfixExprStmt {
+            lhs: Box::new(Expr::Value(ValueStmt {
+                value: Value::Var("m".into()),
+            })),
+            op: PostfixOp::Index(Box::new(ExprStmt {
+                expr: Expr::Value(ValueStmt {
+                    value: Value::String("a".into()),
+                }),
+            })),
+        })),
+        op: Op::Assign(AssignOp::Eq),
+        rhs: Box::new(Expr::Value(ValueStmt {
+            value: Value::Int(2),
+        })),
+        span: Span {
+            ln: 5,
+            pos: 47,
+            start: 10,
+            end: 11,
+        },
+    });
+    assert_eq!(blocks[2], &Block::Expr(ExprStmt { expr: expected }));
+}
+
+#[test]
+fn test_var_index_expr() {
+    let src = "const x = m[k + 1]:unwrap {}";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Postfix(PostfixExprStmt {
+            lhs,
+            op: PostfixOp::Method(_),
+        }) => assert!(matches!(
+            lhs.as_ref(),
+            Expr::Postfix(PostfixExprStmt {
+                op: PostfixOp::Index(_),
+                ..
+            })
+        )),
+        _ => panic!("expected a method call, got {:?}", expr),
+    }
+}
+
+#[test]
+fn test_var_cast_expr() {
+    let src = "const x = a * b as int64";
+
+    let expected = Expr::Binary(BinaryExprStmt {
+        lhs: Box::new(Expr::Value(ValueStmt {
+            value: Value::Var("a".into()),
+        })),
+        op: Op::Arith(ArithOp::Mul),
+        rhs: Box::new(Expr::Postfix(PostfixExprStmt {
+            lhs: Box::new(Expr::Value(ValueStmt {
+                value: Value::Var("b".into()),
+            })),
+            op: PostfixOp::Cast(TypeStmt {
+                type_name: "int64".into(),
+                generics: None,
+                func_type: None,
+            }),
+        })),
+        span: Span {
+            ln: 1,
+            pos: 13,
+            start: 13,
+            end: 14,
+        },
+    });
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    assert_eq!(expr, &expected);
+}
+
+#[test]
+fn test_var_none_expr() {
+    let src = "const x: Option[int] = None";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    assert_eq!(expr, &Expr::Value(ValueStmt { value: Value::None }));
+}
+
+#[test]
+fn test_struct_generic_bounds() {
+    let src = "
+struct Labeled[T ^Display]
+  value: T
+";
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let struct_type = &ast.parsed.structs.first().unwrap().struct_type;
+    let generics: Vec<&str> = struct_type
+        .generics
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|generic| generic.type_name.as_str())
+        .collect();
+    assert_eq!(generics, vec!["T", "^Display"]);
+}
+
+#[test]
+fn test_trait() {
+    let src = "
+trait ^Hash
+  fn :hash self {} uint64
+";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let trait_stmt = ast.parsed.traits.first().unwrap();
+    assert_eq!(trait_stmt.trait_type.type_name, "^Hash");
+    assert_eq!(trait_stmt.funcs.len(), 1);
+    assert_eq!(trait_stmt.funcs[0].func_name, ":hash");
+    assert!(trait_stmt.funcs[0].func_type.is_self);
+}
+
+#[test]
+fn test_impl_trait() {
+    let src = "
+impl Point ^Eq
+  fn :eq self { other: Point } bool =>
+    return self.x == other.x
+
+  fn :other self {} bool =>
+    return true
+";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let impl_stmt = ast.parsed.impl_traits.first().unwrap();
+    assert_eq!(impl_stmt.impl_type.type_name, "Point");
+    assert_eq!(
+        impl_stmt.impl_trait.as_ref().map(|t| t.type_name.as_str()),
+        Some("^Eq")
+    );
+    assert_eq!(impl_stmt.funcs.len(), 2);
+    assert_eq!(impl_stmt.funcs[0].func_name, ":eq");
+    assert_eq!(impl_stmt.funcs[1].func_name, ":other");
+}
+
+#[test]
+fn test_fn_for_in() {
+    let src = "
+fn :fn1 { counts: Map[String int] } int =>
+  let total = 0
+  for k v in counts
+    total = total + v
+  return total
+";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    let for_stmt = func
+        .func_body
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .find_map(|block| match block {
+            Block::ForIn(for_stmt) => Some(for_stmt),
+            _ => None,
+        })
+        .expect("expected a for statement");
+    assert_eq!(for_stmt.vars, vec!["k".to_string(), "v".to_string()]);
+    assert_eq!(
+        for_stmt.iter.expr,
+        Expr::Value(ValueStmt {
+            value: Value::Var("counts".into()),
+        })
+    );
+    assert_eq!(for_stmt.blocks.len(), 1);
+}
+
+#[test]
+fn test_expr_spans() {
+    let src = "
+fn :fn1 {} int =>
+  return :div { a: 1 } / 2
+";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    let return_stmt = func
+        .func_body
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .find_map(|block| match block {
+            Block::Return(return_stmt) => Some(return_stmt),
+            _ => None,
+        })
+        .expect("expected a return statement");
+    let binary = match &return_stmt.expr.expr {
+        Expr::Binary(binary) => binary,
+        expr => panic!("expected a binary expression: {:?}", expr),
+    };
+    assert_eq!((binary.span.ln, binary.span.start), (3, 24));
+    let func_val = match binary.lhs.as_ref() {
+        Expr::Value(ValueStmt {
+            value: Value::Func(func_val),
+        }) => func_val,
+        expr => panic!("expected a function value: {:?}", expr),
+    };
+    assert_eq!((func_val.span.ln, func_val.span.start), (3, 10));
+}
+
+#[test]
+fn test_stmt_spans() {
+    let src = "
+fn :fn1 { a: int } int =>
+  let x = a + 1
+  if x > 2 then
+    x = 2
+  return x
+
+test \"one\" =>
+  :assert { value: true }
+";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    assert_eq!((func.span.ln, func.span.start), (2, 4));
+    let lines: Vec<usize> = func
+        .func_body
+        .blocks
+        .iter()
+        .map(|block| block.span.ln)
+        .collect();
+    assert_eq!(lines, vec![3, 4, 6]);
+    let if_stmt = func
+        .func_body
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .find_map(|block| match block {
+            Block::If(if_stmt) => Some(if_stmt),
+            _ => None,
+        })
+        .expect("expected an if statement");
+    let inner_lines: Vec<usize> = if_stmt
+        .if_cond
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .filter_map(|block| match block {
+            Block::Block(inner) => Some(inner.span.ln),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(inner_lines, vec![5]);
+    let test = ast.parsed.tests.first().unwrap();
+    assert_eq!(test.span.ln, 8);
+    assert_eq!(test.test_body.blocks[0].span.ln, 9);
+}
+
+#[test]
+fn test_tuple_type_stmt() {
+    let src = "tuple Pair[String Map[String int]]";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let tuple = ast.parsed.tuples.first().unwrap();
+    assert_eq!(tuple.tuple_name, "Pair");
+    let names: Vec<&str> = tuple
+        .tuple
+        .types
+        .iter()
+        .map(|ty| ty.type_name.as_str())
+        .collect();
+    assert_eq!(names, vec!["String", "Map"]);
+    assert!(tuple.tuple.types[1].generics.is_some());
+}
+
+#[test]
+fn test_generic_tuple_type_stmt() {
+    let src = "tuple Pair[K ^Hash V] { K List[V] }";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let tuple = ast.parsed.tuples.first().unwrap();
+    assert_eq!(tuple.tuple_name, "Pair");
+    let params: Vec<&str> = tuple
+        .generics
+        .iter()
+        .map(|ty| ty.type_name.as_str())
+        .collect();
+    assert_eq!(params, vec!["K", "^Hash", "V"]);
+    let names: Vec<&str> = tuple
+        .tuple
+        .types
+        .iter()
+        .map(|ty| ty.type_name.as_str())
+        .collect();
+    assert_eq!(names, vec!["K", "List"]);
+}
+
+#[test]
+fn test_var_generic_tuple_value_expr() {
+    let src = "const x = Pair[String int] { \"a\" 1 }";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Tuple(tuple),
+        }) => {
+            assert_eq!(tuple.tuple_type.type_name, "Pair");
+            let generics: Vec<&str> = tuple
+                .tuple_type
+                .generics
+                .iter()
+                .flat_map(|generics| generics.iter())
+                .map(|ty| ty.type_name.as_str())
+                .collect();
+            assert_eq!(generics, vec!["String", "int32"]);
+            assert_eq!(tuple.fields.len(), 2);
+        }
+        _ => panic!("expected tuple value, found {:?}", expr),
+    }
+}
+
+#[test]
+fn test_anonymous_tuple_types() {
+    let src = "
+fn :swap { pair: (int, String) other: List[(bool, int)] } (String, int) =>
+  return (pair.1, pair.0)
+";
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let func = ast.parsed.funcs.first().unwrap();
+    let input_type = func.func_type.input_type.as_ref().unwrap();
+    assert_eq!(input_type.args.len(), 2);
+    let pair = &input_type.args[0].arg_type;
+    assert_eq!(pair.type_name, "()");
+    let elements: Vec<&str> = pair
+        .generics
+        .iter()
+        .flat_map(|generics| generics.iter())
+        .map(|ty| ty.type_name.as_str())
+        .collect();
+    assert_eq!(elements, vec!["int32", "String"]);
+    let other = &input_type.args[1].arg_type;
+    assert_eq!(other.generics.as_ref().unwrap()[0].type_name, "()");
+    let output = &func.func_type.output_type.as_ref().unwrap().output_type;
+    assert_eq!(output.type_name, "()");
+}
+
+#[test]
+fn test_var_anonymous_tuple_value_expr() {
+    let src = "const x = (1, \"a\" + \"b\", (2 + 3) * 4).2";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Postfix(PostfixExprStmt {
+            lhs,
+            op: PostfixOp::Field(field),
+        }) => {
+            assert_eq!(field, "2");
+            match lhs.as_ref() {
+                Expr::Value(ValueStmt {
+                    value: Value::Tuple(tuple),
+                }) => {
+                    assert_eq!(tuple.tuple_type.type_name, "()");
+                    assert_eq!(tuple.fields.len(), 3);
+                    // parens without commas still group
+                    assert!(matches!(
+                        tuple.fields[2].field_value.expr,
+                        Expr::Binary(BinaryExprStmt {
+                            op: Op::Arith(ArithOp::Mul),
+                            ..
+                        })
+                    ));
+                }
+                _ => panic!("expected tuple value, found {:?}", lhs),
+            }
+        }
+        _ => panic!("expected field access, found {:?}", expr),
+    }
+}
+
+#[test]
+fn test_var_tuple_value_expr() {
+    let src = "const x = Pair { \"a\" 1 + 2 }.1";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let expr = &ast.parsed.consts.first().unwrap().assign.expr.expr;
+    match expr {
+        Expr::Postfix(PostfixExprStmt {
+            lhs,
+            op: PostfixOp::Field(field),
+        }) => {
+            assert_eq!(field, "1");
+            match lhs.as_ref() {
+                Expr::Value(ValueStmt {
+                    value: Value::Tuple(tuple),
+                }) => {
+                    assert_eq!(tuple.tuple_type.type_name, "Pair");
+                    assert_eq!(tuple.fields.len(), 2);
+                }
+                _ => panic!("expected tuple value, found {:?}", lhs),
+            }
+        }
+        _ => panic!("expected field access, found {:?}", expr),
+    }
+}
+
+#[test]
+fn test_new_type_stmts() {
+    let src = "type Names = Map[String int]\n\ntype UserId int64";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    assert_eq!(ast.parsed.types.len(), 2);
+
+    let alias = ast.parsed.types.first().unwrap();
+    assert_eq!(alias.type_name, "Names");
+    assert!(alias.is_alias);
+    assert_eq!(alias.new_type.type_name, "Map");
+    assert_eq!(alias.new_type.generics.as_ref().map(|g| g.len()), Some(2));
+
+    let new_type = ast.parsed.types.get(1).unwrap();
+    assert_eq!(new_type.type_name, "UserId");
+    assert!(!new_type.is_alias);
+    assert_eq!(
+        new_type.new_type,
+        TypeStmt {
+            type_name: "int64".into(),
+            generics: None,
+            func_type: None,
+        }
+    );
+}
+
+#[test]
+fn test_test_stmt() {
+    let src = "test \"adds numbers\" =>\n  const x = 1 + 2\n  :assert_eq { left: x right: 3 }\n";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let test = ast.parsed.tests.first().unwrap();
+    assert_eq!(test.test_name, "adds numbers");
+    assert_eq!(test.test_body.blocks.len(), 2);
+}
+
+#[test]
+fn test_test_case_stmt() {
+    let src = "testcase \"adds\" { a: int sum: int } =>\n  case \"one\" { a: 1 sum: 2 }\n  case \"two\" { a: 2 sum: 3 }\n  const x = a + 1\n  :assert_eq { left: x right: sum }\n";
+
+    let mut ast = Ast::from_source(src);
+    let _ = ast.parse();
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let test_case = ast.parsed.test_cases.first().unwrap();
+    assert_eq!(test_case.test_name, "adds");
+    assert_eq!(test_case.input_type.args.len(), 2);
+    let rows: Vec<&str> = test_case
+        .rows
+        .iter()
+        .map(|row| row.row_name.as_str())
+        .collect();
+    assert_eq!(rows, vec!["one", "two"]);
+    assert_eq!(test_case.rows[1].args[0].arg_name, "a");
+    assert_eq!(test_case.test_body.blocks.len(), 2);
+}
+
+#[test]
+fn test_multi_line_args() {
+    let one_line =
+        "fn :add { a: int b: int } int =>\n  const x = :add { a: 1 b: P { x: 1 } }\n  return x\n";
+    let multi_line = "fn :add {\n  a: int\n  # the second arg\n  b: int\n} int =>\n  const x = :add {\n    a: 1\n    b: P {\n      x: 1\n    }\n  }\n  return x\n";
+
+    let mut expected = Ast::from_source(one_line);
+    assert!(expected.parse().is_ok());
+    let mut ast = Ast::from_source(multi_line);
+    assert!(ast.parse().is_ok());
+    assert_eq!(ast.parsed.errors.len(), 0);
+    assert_eq!(ast.parsed.funcs.len(), 1);
+    // the layouts only differ by source locations
+    assert_eq!(
+        without_spans(&ast.parsed.funcs),
+        without_spans(&expected.parsed.funcs)
+    );
+
+    // the comment between the args is skipped
+    let func = &ast.parsed.funcs[0];
+    let input_type = func.func_type.input_type.as_ref().unwrap();
+    let arg_names: Vec<&str> = input_type
+        .args
+        .iter()
+        .map(|arg| arg.arg_name.as_str())
+        .collect();
+    assert_eq!(arg_names, vec!["a", "b"]);
+    let blocks: Vec<&Block> = func
+        .func_body
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .collect();
+    assert_eq!(blocks.len(), 2);
+    // the call keeps the line it starts on
+    match blocks[0] {
+        Block::Const(ConstStmt {
+            assign:
+                AssignStmt {
+                    expr:
+                        ExprStmt {
+                            expr:
+                                Expr::Value(ValueStmt {
+                                    value: Value::Func(call),
+                                }),
+                        },
+                    ..
+                },
+        }) => {
+            assert_eq!(call.func_name, ":add");
+            assert_eq!(call.args.len(), 2);
+            assert_eq!(call.span.ln, 6);
+        }
+        block => panic!("expected a const call, got {:?}", block),
+    }
+    assert!(matches!(blocks[1], Block::Return(_)));
+}
+
+#[test]
+fn test_multi_line_args_comments() {
+    // comments inside the args are skipped (braces in them don't open a group)
+    let src = "fn :main {} =>\n  const x = :add {\n    # the { first arg\n    a: 1 # one\n    b: Point {\n      # x\n      x: 2\n    }\n  }\n";
+    let mut ast = Ast::from_source(src);
+    assert!(ast.parse().is_ok());
+    assert_eq!(ast.parsed.errors.len(), 0);
+    let blocks: Vec<&Block> = ast.parsed.funcs[0]
+        .func_body
+        .blocks
+        .iter()
+        .flat_map(|block| block.blocks.iter())
+        .collect();
+    assert_eq!(blocks.len(), 1);
+    match blocks[0] {
+        Block::Const(ConstStmt { assign }) => match &assign.expr.expr {
+            Expr::Value(ValueStmt {
+                value: Value::Func(call),
+            }) => {
+                let arg_names: Vec<&str> =
+                    call.args.iter().map(|arg| arg.arg_name.as_str()).collect();
+                assert_eq!(arg_names, vec!["a", "b"]);
+                assert!(matches!(
+                    &call.args[1].arg_value.expr,
+                    Expr::Value(ValueStmt {
+                        value: Value::Struct(StructValueStmt { fields, .. }),
+                    }) if fields.len() == 1
+                ));
+            }
+            expr => panic!("expected a call, got {:?}", expr),
+        },
+        block => panic!("expected a const, got {:?}", block),
+    }
+}
+
+// Debug output with every `span: Span { .. }` removed
+#[cfg(test)]
+fn without_spans<T: std::fmt::Debug>(value: &T) -> String {
+    let mut debug = format!("{:?}", value);
+    while let Some(start) = debug.find("span: Span {") {
+        let end = start + debug[start..].find('}').unwrap() + 1;
+        debug.replace_range(start..end, "");
+    }
+    debug
+}
+
+#[test]
+fn test_expr_bad_static_call_args() {
+    // bad args are parse errors (not panics)
+    assert!(pratt_parser("Map[String int]:new { key: }").is_err());
+    assert!(pratt_parser("Map[String int]:from { 1 }").is_ok());
+}
+
+#[test]
+fn test_expr_bad_closure_signature() {
+    assert!(pratt_parser("fn { x: } => x").is_err());
+    assert!(pratt_parser("fn { x: int } => x").is_ok());
+}
+
+#[test]
+fn test_expr_bad_tuple_and_struct_values() {
+    assert!(pratt_parser("(1, *)").is_err());
+    assert!(pratt_parser("Point { x: }").is_err());
+    assert!(pratt_parser("(1, 2)").is_ok());
+}
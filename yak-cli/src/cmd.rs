@@ -1,2 +1,6 @@
 pub(crate) mod build;
+pub(crate) mod check;
+pub(crate) mod fmt;
 pub(crate) mod get;
+pub(crate) mod lsp;
+pub(crate) mod test;
@@ -1,12 +1,44 @@
+use crate::cmd::check::check;
+use crate::message::{print_artifact, print_error, MessageFormat};
 use anyhow::Result;
 use clap::{arg, Args};
 use log::info;
+use yak_compiler::compiler::{Emit, LibKind, Linker, OptLevel, TargetOpts};
 
 #[derive(Args, Debug)]
 pub(crate) struct BuildArgs {
     /// Yak package folder path
     #[arg(index = 1, default_value_t = String::from("."))]
     path: String,
+    /// Build with the release profile (optimized, no overflow checks or debug assertions)
+    #[arg(long)]
+    release: bool,
+    /// Optimization level (0, 1, 2, 3, s or z; defaults to the profile's)
+    #[arg(long)]
+    opt_level: Option<OptLevel>,
+    /// Intermediate artifacts to write to the target directory
+    /// (tokens, ast, hir, llvm-ir, asm, obj)
+    #[arg(long, value_delimiter = ',')]
+    emit: Vec<Emit>,
+    /// Target triple to generate code for (defaults to the host,
+    /// other targets build in target/{triple})
+    #[arg(long)]
+    target: Option<String>,
+    /// Target cpu (defaults to the host cpu or "generic" for other targets)
+    #[arg(long)]
+    target_cpu: Option<String>,
+    /// Target features (i.e. "+avx2,+fma")
+    #[arg(long)]
+    target_features: Option<String>,
+    /// Linker driver (cc, clang or ld.lld; defaults to the package `link` setting or cc)
+    #[arg(long)]
+    linker: Option<Linker>,
+    /// Library output for packages without a :main (static or shared)
+    #[arg(long)]
+    lib_kind: Option<LibKind>,
+    /// Print diagnostics and artifacts as text or one json object per line
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
 }
 
 pub(crate) fn call(args: &BuildArgs) -> Result<()> {
@@ -16,8 +48,26 @@ pub(crate) fn call(args: &BuildArgs) -> Result<()> {
         pkg_root: true,
         pkg_as_pkg_id: None,
         path: path,
+        release: args.release,
+        opt_level: args.opt_level,
+        test: false,
+        emit: args.emit.clone(),
+        target: TargetOpts {
+            triple: args.target.clone(),
+            cpu: args.target_cpu.clone(),
+            features: args.target_features.clone(),
+        },
+        linker: args.linker,
+        lib_kind: args.lib_kind,
     };
-    let pkg = yak_pkg::build(build_args)?;
-    info!("{:#?}", &pkg);
+    // build errors don't have source locations
+    // so json diagnostics come from checking the package first
+    if args.message_format.is_json() {
+        check(&args.path, args.message_format)?;
+    }
+    let pkg = yak_pkg::build(build_args).map_err(|err| print_error(err, args.message_format))?;
+    for artifact in pkg.artifacts() {
+        print_artifact(artifact, args.message_format);
+    }
     Ok(())
 }
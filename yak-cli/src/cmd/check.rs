@@ -0,0 +1,47 @@
+use crate::message::{print_diagnostic, MessageFormat};
+use anyhow::{bail, Result};
+use clap::Args;
+use log::info;
+use std::path::Path;
+use yak_lsp::Severity;
+
+#[derive(Args, Debug)]
+pub(crate) struct CheckArgs {
+    /// Yak package folder path
+    #[arg(index = 1, default_value_t = String::from("."))]
+    path: String,
+    /// Print diagnostics as text or one json object per line
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+}
+
+pub(crate) fn call(args: &CheckArgs) -> Result<()> {
+    info!("check args: {:?}", args);
+    let warnings = check(&args.path, args.message_format)?;
+    if !args.message_format.is_json() {
+        println!("checked: {} ({} warning(s))", &args.path, warnings);
+    }
+    Ok(())
+}
+
+// Type checks the package and its dependencies without codegen or linking,
+// prints the diagnostics and returns the number of warnings
+pub(crate) fn check(path: &str, format: MessageFormat) -> Result<usize> {
+    let (_, pkg_local_path) = yak_pkg::find_pkg_file(path)?;
+    // dependencies resolve (and download) like they do for builds
+    let deps = yak_pkg::resolve_deps(&pkg_local_path)?;
+    info!("check dependencies: {:?}", &deps);
+    let diagnostics = yak_lsp::check_package(Path::new(&pkg_local_path), &deps);
+    let (mut errors, mut warnings) = (0, 0);
+    for diagnostic in &diagnostics {
+        match diagnostic.severity {
+            Severity::Error => errors += 1,
+            Severity::Warning => warnings += 1,
+        }
+        print_diagnostic(diagnostic, format);
+    }
+    if errors > 0 {
+        bail!("{} error(s) found", errors);
+    }
+    Ok(warnings)
+}
@@ -0,0 +1,33 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use log::info;
+
+#[derive(Args, Debug)]
+pub(crate) struct FmtArgs {
+    /// Yak package folder path
+    #[arg(index = 1, default_value_t = String::from("."))]
+    path: String,
+    /// Check the files are formatted without writing them
+    #[arg(long)]
+    check: bool,
+}
+
+pub(crate) fn call(args: &FmtArgs) -> Result<()> {
+    info!("fmt args: {:?}", args);
+    let fmt_args = yak_pkg::FmtArgs {
+        path: args.path.clone(),
+        check: args.check,
+    };
+    let files = yak_pkg::fmt(fmt_args)?;
+    for file in &files {
+        if args.check {
+            println!("not formatted: {}", file.display());
+        } else {
+            println!("formatted: {}", file.display());
+        }
+    }
+    if args.check && !files.is_empty() {
+        bail!("{} file(s) aren't formatted (run yak-cli fmt)", files.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use anyhow::Result;
+use clap::Args;
+use log::info;
+
+#[derive(Args, Debug)]
+pub(crate) struct LspArgs {}
+
+pub(crate) fn call(args: &LspArgs) -> Result<()> {
+    info!("lsp args: {:?}", args);
+    yak_lsp::run_stdio()
+}
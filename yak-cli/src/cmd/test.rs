@@ -0,0 +1,152 @@
+use crate::cmd::check::check;
+use crate::message::{print_artifact, print_error, MessageFormat};
+use anyhow::{bail, Result};
+use clap::Args;
+use log::info;
+use serde_json::json;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+use yak_compiler::compiler::Artifact;
+
+// Exit statuses from the yak-std runtime
+const PANIC_EXIT_CODE: i32 = 101;
+const ASSERT_EXIT_CODE: i32 = 102;
+
+#[derive(Args, Debug)]
+pub(crate) struct TestArgs {
+    /// Yak package folder path
+    #[arg(index = 1, default_value_t = String::from("."))]
+    path: String,
+    /// Only run tests whose name contains this value
+    #[arg(long)]
+    filter: Option<String>,
+    /// Build with the release profile (optimized, no overflow checks or debug assertions)
+    #[arg(long)]
+    release: bool,
+    /// Print diagnostics, artifacts and test results as text or one json object per line
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Pass,
+    Fail,
+    Panic,
+    Crash,
+}
+
+pub(crate) fn call(args: &TestArgs) -> Result<()> {
+    info!("test args: {:?}", args);
+    let build_args = yak_pkg::BuildArgs {
+        path: args.path.clone(),
+        release: args.release,
+        ..Default::default()
+    };
+    let format = args.message_format;
+    if format.is_json() {
+        check(&args.path, format)?;
+    }
+    let test_pkg = yak_pkg::test(build_args).map_err(|err| print_error(err, format))?;
+    print_artifact(
+        &Artifact {
+            kind: "bin",
+            file: test_pkg.bin_file.clone(),
+        },
+        format,
+    );
+
+    let mut passed = 0;
+    let mut failed = vec![];
+    let mut filtered = 0;
+    for (test_num, test_name) in test_pkg.tests.iter().enumerate() {
+        if let Some(filter) = &args.filter {
+            if !test_name.contains(filter.as_str()) {
+                filtered += 1;
+                continue;
+            }
+        }
+        // each test runs in its own process
+        let start = Instant::now();
+        let output = Command::new(&test_pkg.bin_file)
+            .arg(test_num.to_string())
+            .output()?;
+        let elapsed = start.elapsed();
+        let outcome = match output.status.code() {
+            Some(0) => Outcome::Pass,
+            Some(ASSERT_EXIT_CODE) => Outcome::Fail,
+            Some(PANIC_EXIT_CODE) => Outcome::Panic,
+            _ => Outcome::Crash,
+        };
+        if format.is_json() {
+            let message = json!({
+                "reason": "test",
+                "name": test_name,
+                "outcome": outcome_name(&outcome).to_lowercase(),
+                "duration_ms": elapsed.as_millis() as u64,
+                "stdout": String::from_utf8_lossy(&output.stdout),
+                "stderr": String::from_utf8_lossy(&output.stderr),
+            });
+            println!("{}", message);
+        } else {
+            println!(
+                "test {} ... {}",
+                test_name,
+                display_outcome(&outcome, elapsed)
+            );
+        }
+        if outcome == Outcome::Pass {
+            passed += 1;
+        } else {
+            failed.push((test_name, output));
+        }
+    }
+
+    if format.is_json() {
+        let message = json!({
+            "reason": "test-result",
+            "passed": passed,
+            "failed": failed.len(),
+            "filtered": filtered,
+        });
+        println!("{}", message);
+    } else {
+        print_failures(&failed);
+        println!(
+            "\ntest result: {}. {} passed; {} failed; {} filtered out",
+            if failed.is_empty() { "ok" } else { "FAILED" },
+            passed,
+            failed.len(),
+            filtered
+        );
+    }
+    if !failed.is_empty() {
+        bail!("{} test(s) failed", failed.len());
+    }
+    Ok(())
+}
+
+// Prints the output of each failed test
+fn print_failures(failed: &[(&String, Output)]) {
+    for (test_name, output) in failed {
+        println!("\n---- {} ----", test_name);
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+        print!("{}", String::from_utf8_lossy(&output.stderr));
+        if output.status.code().is_none() {
+            println!("terminated by signal");
+        }
+    }
+}
+
+fn outcome_name(outcome: &Outcome) -> &'static str {
+    match outcome {
+        Outcome::Pass => "ok",
+        Outcome::Fail => "FAILED",
+        Outcome::Panic => "PANICKED",
+        Outcome::Crash => "CRASHED",
+    }
+}
+
+fn display_outcome(outcome: &Outcome, elapsed: Duration) -> String {
+    format!("{} ({:.2?})", outcome_name(outcome), elapsed)
+}
@@ -1,4 +1,6 @@
 mod cmd;
+mod message;
+mod test;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use log::error;
@@ -30,8 +32,16 @@ struct Cli {
 enum Cmd {
     /// Build Yak Packages
     Build(cmd::build::BuildArgs),
+    /// Type check Yak Packages (without building them)
+    Check(cmd::check::CheckArgs),
+    /// Format Yak Packages
+    Fmt(cmd::fmt::FmtArgs),
     /// Get Yak Packages
     Get(cmd::get::GetArgs),
+    /// Run the Yak language server over stdio
+    Lsp(cmd::lsp::LspArgs),
+    /// Test Yak Packages
+    Test(cmd::test::TestArgs),
 }
 
 fn main() -> Result<()> {
@@ -61,11 +71,18 @@ fn main() -> Result<()> {
 
     let results = match &cli.cmd {
         Cmd::Build(args) => cmd::build::call(args),
+        Cmd::Check(args) => cmd::check::call(args),
+        Cmd::Fmt(args) => cmd::fmt::call(args),
         Cmd::Get(args) => cmd::get::call(args),
+        Cmd::Lsp(args) => cmd::lsp::call(args),
+        Cmd::Test(args) => cmd::test::call(args),
     };
     if results.is_err() {
         let err = results.err().unwrap();
-        error!("{:?}", &err);
+        // errors printed as messages aren't logged again
+        if !err.is::<message::Reported>() {
+            error!("{:?}", &err);
+        }
         std::process::exit(1)
     }
     Ok(())
@@ -0,0 +1,132 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::fmt;
+use yak_compiler::compiler::{Artifact, LinkError};
+use yak_lsp::{Diagnostic, Range};
+
+// How commands print diagnostics and artifacts
+// (json prints one object per line for tools like CI annotators)
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum MessageFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl MessageFormat {
+    pub(crate) fn is_json(self) -> bool {
+        self == MessageFormat::Json
+    }
+}
+
+pub(crate) fn print_diagnostic(diagnostic: &Diagnostic, format: MessageFormat) {
+    if format.is_json() {
+        println!("{}", diagnostic_json(diagnostic));
+        return;
+    }
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let path = diagnostic
+        .path
+        .strip_prefix(&cwd)
+        .unwrap_or(&diagnostic.path);
+    let (line, col) = diagnostic.range.start;
+    println!(
+        "{}[{}]: {}",
+        diagnostic.severity.name(),
+        diagnostic.code,
+        diagnostic.message
+    );
+    println!("  --> {}:{}:{}", path.display(), line + 1, col + 1);
+    for suggestion in &diagnostic.suggestions {
+        println!("  = help: {}", suggestion);
+    }
+}
+
+// An error which was already printed as a message
+// (main only sets the exit status for these)
+#[derive(Debug)]
+pub(crate) struct Reported;
+
+impl fmt::Display for Reported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error already reported")
+    }
+}
+
+impl std::error::Error for Reported {}
+
+// Errors without a location (i.e. linker errors)
+// are printed and returned as `Reported`
+pub(crate) fn print_error(err: anyhow::Error, format: MessageFormat) -> anyhow::Error {
+    if format.is_json() {
+        println!("{}", error_json(&err));
+        return Reported.into();
+    }
+    // the linker output is shown as notes
+    match err.downcast_ref::<LinkError>() {
+        Some(link_error) => {
+            println!(
+                "error[link-error]: {} failed to link {}",
+                link_error.linker, link_error.output_file
+            );
+            for line in link_error.stderr.lines() {
+                println!("  = note: {}", line);
+            }
+        }
+        None => println!("error[build-error]: {:#}", err),
+    }
+    Reported.into()
+}
+
+// Artifacts are only printed as json
+// (human builds log them)
+pub(crate) fn print_artifact(artifact: &Artifact, format: MessageFormat) {
+    if format.is_json() {
+        println!("{}", artifact_json(artifact));
+    }
+}
+
+pub(crate) fn error_json(err: &anyhow::Error) -> Value {
+    let code = if err.downcast_ref::<LinkError>().is_some() {
+        "link-error"
+    } else {
+        "build-error"
+    };
+    json!({
+        "reason": "diagnostic",
+        "code": code,
+        "severity": "error",
+        "message": format!("{:#}", err),
+        "file": null,
+        "range": null,
+        "suggestions": [],
+    })
+}
+
+pub(crate) fn artifact_json(artifact: &Artifact) -> Value {
+    json!({
+        "reason": "artifact",
+        "kind": artifact.kind,
+        "file": artifact.file,
+    })
+}
+
+pub(crate) fn diagnostic_json(diagnostic: &Diagnostic) -> Value {
+    json!({
+        "reason": "diagnostic",
+        "code": diagnostic.code,
+        "severity": diagnostic.severity.name(),
+        "message": diagnostic.message,
+        "file": diagnostic.path.display().to_string(),
+        "range": range_json(&diagnostic.range),
+        "suggestions": diagnostic.suggestions,
+    })
+}
+
+// Lines and columns are 1-based (like the human output)
+fn range_json(range: &Range) -> Value {
+    json!({
+        "start": { "line": range.start.0 + 1, "col": range.start.1 + 1 },
+        "end": { "line": range.end.0 + 1, "col": range.end.1 + 1 },
+    })
+}
@@ -0,0 +1,158 @@
+#[cfg(test)]
+use crate::cmd::check::check;
+#[cfg(test)]
+use crate::message::{
+    artifact_json, diagnostic_json, error_json, print_error, MessageFormat, Reported,
+};
+#[cfg(test)]
+use anyhow::anyhow;
+#[cfg(test)]
+use serde_json::json;
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::PathBuf;
+#[cfg(test)]
+use yak_compiler::compiler::{Artifact, LinkError};
+#[cfg(test)]
+use yak_lsp::{Diagnostic, Range, Severity};
+
+#[test]
+fn message_diagnostic_json() {
+    let diagnostic = Diagnostic {
+        path: PathBuf::from("/pkg/src/main.yak"),
+        range: Range {
+            start: (2, 4),
+            end: (2, 9),
+        },
+        severity: Severity::Error,
+        code: "type-error",
+        message: "expected type int32 but found String".into(),
+        suggestions: vec!["cast the value".into()],
+    };
+    // lines and columns are 1-based
+    assert_eq!(
+        diagnostic_json(&diagnostic),
+        json!({
+            "reason": "diagnostic",
+            "code": "type-error",
+            "severity": "error",
+            "message": "expected type int32 but found String",
+            "file": "/pkg/src/main.yak",
+            "range": {
+                "start": { "line": 3, "col": 5 },
+                "end": { "line": 3, "col": 10 },
+            },
+            "suggestions": ["cast the value"],
+        })
+    );
+
+    let warning = Diagnostic {
+        severity: Severity::Warning,
+        code: "unused",
+        suggestions: vec![],
+        ..diagnostic
+    };
+    let value = diagnostic_json(&warning);
+    assert_eq!(value["severity"], "warning");
+    assert_eq!(value["code"], "unused");
+    assert_eq!(value["suggestions"], json!([]));
+}
+
+#[test]
+fn message_error_json() {
+    let err: anyhow::Error = LinkError {
+        linker: "cc".into(),
+        output_file: "target/bin/my.pkg".into(),
+        stderr: "undefined reference to `main`\n".into(),
+    }
+    .into();
+    assert_eq!(
+        error_json(&err),
+        json!({
+            "reason": "diagnostic",
+            "code": "link-error",
+            "severity": "error",
+            "message": "cc failed to link target/bin/my.pkg: undefined reference to `main`",
+            "file": null,
+            "range": null,
+            "suggestions": [],
+        })
+    );
+
+    // other errors print their causes
+    let err = anyhow!("file not found").context("failed to read yak.pkg");
+    let value = error_json(&err);
+    assert_eq!(value["code"], "build-error");
+    assert_eq!(value["message"], "failed to read yak.pkg: file not found");
+    assert_eq!(value["file"], json!(null));
+}
+
+#[test]
+fn message_errors_are_reported() {
+    // printed errors aren't logged again by main
+    for format in [MessageFormat::Human, MessageFormat::Json] {
+        let err = print_error(anyhow!("failed to read yak.pkg"), format);
+        assert!(err.is::<Reported>());
+    }
+}
+
+#[test]
+fn message_artifact_json() {
+    let artifact = Artifact {
+        kind: "llvm-ir",
+        file: "target/module/my.pkg.ll".into(),
+    };
+    assert_eq!(
+        artifact_json(&artifact),
+        json!({
+            "reason": "artifact",
+            "kind": "llvm-ir",
+            "file": "target/module/my.pkg.ll",
+        })
+    );
+}
+
+// Writes a package with a missing dependency to a temp directory
+#[cfg(test)]
+fn write_check_pkg(name: &str, src: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("yak-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    let pkg = r#"package     "test.check"
+description "A package to check"
+files {
+  "./src/main.yak"
+}
+dependencies {
+  test.missing "../missing"
+}
+"#;
+    fs::write(dir.join("yak.pkg"), pkg).unwrap();
+    fs::write(dir.join("src/main.yak"), src).unwrap();
+    dir
+}
+
+#[test]
+fn check_counts_by_severity() {
+    // the missing dependency is the only warning
+    let dir = write_check_pkg(
+        "warnings",
+        "fn :main {} =>
+  const a = 1
+",
+    );
+    let warnings = check(&dir.display().to_string(), MessageFormat::Json).unwrap();
+    assert_eq!(warnings, 1);
+    let _ = fs::remove_dir_all(&dir);
+
+    let dir = write_check_pkg(
+        "errors",
+        "fn :main {} =>
+  const a: String = 1
+",
+    );
+    let err = check(&dir.display().to_string(), MessageFormat::Json).unwrap_err();
+    assert_eq!(err.to_string(), "1 error(s) found");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,424 @@
+use yak_core::types::types::TypeId;
+
+// Returns true for the builtin collections backed by a runtime handle
+pub fn is_collection(type_id: &TypeId) -> bool {
+    type_id.is("Map") || type_id.is("Set") || type_id.is("List")
+}
+
+// `m[key] += v` reads the current value with this function
+// (it can't be called by name since it isn't a valid identifier)
+pub const INDEX_FUNC: &str = ":[]";
+
+// Builtin type functions backed by the yak-std runtime.
+// Keys, values and items are passed to the runtime as 64-bit words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuiltinMethod {
+    MapNew,
+    MapInsert,
+    MapGet,
+    MapIndex,
+    MapRemove,
+    MapContains,
+    MapLen,
+    SetNew,
+    SetInsert,
+    SetRemove,
+    SetContains,
+    SetLen,
+    ListNew,
+    ListPush,
+    ListGet,
+    ListSet,
+    ListPop,
+    ListLen,
+}
+
+impl BuiltinMethod {
+    // Looks up `Type:func_name`
+    pub fn lookup(type_id: &TypeId, func_name: &str) -> Option<Self> {
+        let method = if type_id.is("Map") {
+            match func_name {
+                ":new" => BuiltinMethod::MapNew,
+                ":insert" => BuiltinMethod::MapInsert,
+                ":get" => BuiltinMethod::MapGet,
+                INDEX_FUNC => BuiltinMethod::MapIndex,
+                ":remove" => BuiltinMethod::MapRemove,
+                ":contains" => BuiltinMethod::MapContains,
+                ":len" => BuiltinMethod::MapLen,
+                _ => return None,
+            }
+        } else if type_id.is("Set") {
+            match func_name {
+                ":new" => BuiltinMethod::SetNew,
+                ":insert" => BuiltinMethod::SetInsert,
+                ":remove" => BuiltinMethod::SetRemove,
+                ":contains" => BuiltinMethod::SetContains,
+                ":len" => BuiltinMethod::SetLen,
+                _ => return None,
+            }
+        } else if type_id.is("List") {
+            match func_name {
+                ":new" => BuiltinMethod::ListNew,
+                ":push" => BuiltinMethod::ListPush,
+                ":get" | INDEX_FUNC => BuiltinMethod::ListGet,
+                ":set" => BuiltinMethod::ListSet,
+                ":pop" => BuiltinMethod::ListPop,
+                ":len" => BuiltinMethod::ListLen,
+                _ => return None,
+            }
+        } else {
+            return None;
+        };
+        Some(method)
+    }
+
+    // Returns the named arguments and types for the collection `type_id`
+    // (i.e. `Map[K V]`, `Set[T]` or `List[T]`)
+    pub fn args(&self, type_id: &TypeId) -> Vec<(&'static str, TypeId)> {
+        let key = type_id.generics.first().cloned();
+        let value = type_id.generics.get(1).cloned();
+        let this = ("self", type_id.clone());
+        match self {
+            BuiltinMethod::MapNew | BuiltinMethod::SetNew | BuiltinMethod::ListNew => vec![],
+            BuiltinMethod::MapInsert => {
+                vec![this, ("key", key.unwrap()), ("value", value.unwrap())]
+            }
+            BuiltinMethod::MapGet
+            | BuiltinMethod::MapIndex
+            | BuiltinMethod::MapRemove
+            | BuiltinMethod::MapContains => vec![this, ("key", key.unwrap())],
+            BuiltinMethod::SetInsert | BuiltinMethod::SetRemove | BuiltinMethod::SetContains => {
+                vec![this, ("item", key.unwrap())]
+            }
+            BuiltinMethod::ListPush => vec![this, ("item", key.unwrap())],
+            BuiltinMethod::ListGet => vec![this, ("index", TypeId::primitive("uint64"))],
+            BuiltinMethod::ListSet => vec![
+                this,
+                ("index", TypeId::primitive("uint64")),
+                ("item", key.unwrap()),
+            ],
+            BuiltinMethod::MapLen
+            | BuiltinMethod::SetLen
+            | BuiltinMethod::ListPop
+            | BuiltinMethod::ListLen => vec![this],
+        }
+    }
+
+    pub fn return_type(&self, type_id: &TypeId) -> Option<TypeId> {
+        let item = type_id.generics.first().cloned();
+        let value = type_id.generics.get(1).cloned();
+        let return_type = match self {
+            BuiltinMethod::MapNew | BuiltinMethod::SetNew | BuiltinMethod::ListNew => {
+                type_id.clone()
+            }
+            // true if the key or item didn't already exist
+            BuiltinMethod::MapInsert | BuiltinMethod::SetInsert => TypeId::primitive("bool"),
+            BuiltinMethod::MapGet | BuiltinMethod::MapRemove => {
+                TypeId::builtin("Option").with_generics(vec![value.unwrap()])
+            }
+            // panics if the key is missing
+            BuiltinMethod::MapIndex => value.unwrap(),
+            BuiltinMethod::SetRemove | BuiltinMethod::MapContains | BuiltinMethod::SetContains => {
+                TypeId::primitive("bool")
+            }
+            BuiltinMethod::MapLen | BuiltinMethod::SetLen | BuiltinMethod::ListLen => {
+                TypeId::primitive("uint64")
+            }
+            BuiltinMethod::ListPush | BuiltinMethod::ListSet => return None,
+            // panics if the index is out of bounds
+            BuiltinMethod::ListGet => item.unwrap(),
+            BuiltinMethod::ListPop => TypeId::builtin("Option").with_generics(vec![item.unwrap()]),
+        };
+        Some(return_type)
+    }
+
+    pub fn is_new(&self) -> bool {
+        matches!(
+            self,
+            BuiltinMethod::MapNew | BuiltinMethod::SetNew | BuiltinMethod::ListNew
+        )
+    }
+
+    // Insert, push and set pass ownership of the words to the runtime
+    // and everything else only borrows them
+    pub fn is_insert(&self) -> bool {
+        matches!(
+            self,
+            BuiltinMethod::MapInsert
+                | BuiltinMethod::SetInsert
+                | BuiltinMethod::ListPush
+                | BuiltinMethod::ListSet
+        )
+    }
+
+    // The yak-std runtime function
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BuiltinMethod::MapNew => "yak_map_new",
+            BuiltinMethod::MapInsert => "yak_map_insert",
+            BuiltinMethod::MapGet | BuiltinMethod::MapIndex => "yak_map_get",
+            BuiltinMethod::MapRemove => "yak_map_remove",
+            BuiltinMethod::MapContains => "yak_map_contains",
+            BuiltinMethod::MapLen => "yak_map_len",
+            BuiltinMethod::SetNew => "yak_set_new",
+            BuiltinMethod::SetInsert => "yak_set_insert",
+            BuiltinMethod::SetRemove => "yak_set_remove",
+            BuiltinMethod::SetContains => "yak_set_contains",
+            BuiltinMethod::SetLen => "yak_set_len",
+            BuiltinMethod::ListNew => "yak_list_new",
+            BuiltinMethod::ListPush => "yak_list_push",
+            BuiltinMethod::ListGet => "yak_list_get",
+            BuiltinMethod::ListSet => "yak_list_set",
+            BuiltinMethod::ListPop => "yak_list_pop",
+            BuiltinMethod::ListLen => "yak_list_len",
+        }
+    }
+}
+
+// Option[T] and Maybe[T E] type functions.
+// These are compiled inline since both types are plain structs:
+// - Option[T] is `{ is_some, T }`
+// - Maybe[T E] is `{ is_ok, T, E }`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueMethod {
+    OptionSome,
+    OptionNone,
+    OptionIsSome,
+    OptionIsNone,
+    OptionUnwrap,
+    OptionUnwrapOr,
+    MaybeOk,
+    MaybeErr,
+    MaybeIsOk,
+    MaybeIsErr,
+    MaybeUnwrap,
+    MaybeUnwrapOr,
+}
+
+impl ValueMethod {
+    // Looks up `Type:func_name`
+    pub fn lookup(type_id: &TypeId, func_name: &str) -> Option<Self> {
+        let method = if type_id.is("Option") {
+            match func_name {
+                ":some" => ValueMethod::OptionSome,
+                ":none" => ValueMethod::OptionNone,
+                ":is_some" => ValueMethod::OptionIsSome,
+                ":is_none" => ValueMethod::OptionIsNone,
+                ":unwrap" => ValueMethod::OptionUnwrap,
+                ":unwrap_or" => ValueMethod::OptionUnwrapOr,
+                _ => return None,
+            }
+        } else if type_id.is("Maybe") {
+            match func_name {
+                ":ok" => ValueMethod::MaybeOk,
+                ":err" => ValueMethod::MaybeErr,
+                ":is_ok" => ValueMethod::MaybeIsOk,
+                ":is_err" => ValueMethod::MaybeIsErr,
+                ":unwrap" => ValueMethod::MaybeUnwrap,
+                ":unwrap_or" => ValueMethod::MaybeUnwrapOr,
+                _ => return None,
+            }
+        } else {
+            return None;
+        };
+        Some(method)
+    }
+
+    // Constructors don't take a self argument
+    pub fn is_constructor(&self) -> bool {
+        matches!(
+            self,
+            ValueMethod::OptionSome
+                | ValueMethod::OptionNone
+                | ValueMethod::MaybeOk
+                | ValueMethod::MaybeErr
+        )
+    }
+
+    // Returns the named arguments and types for `type_id`
+    // (i.e. `Option[T]` or `Maybe[T E]`)
+    pub fn args(&self, type_id: &TypeId) -> Vec<(&'static str, TypeId)> {
+        let value = type_id.generics[0].clone();
+        let this = ("self", type_id.clone());
+        match self {
+            ValueMethod::OptionSome | ValueMethod::MaybeOk => vec![("value", value)],
+            ValueMethod::OptionNone => vec![],
+            ValueMethod::MaybeErr => vec![("error", type_id.generics[1].clone())],
+            ValueMethod::OptionIsSome
+            | ValueMethod::OptionIsNone
+            | ValueMethod::OptionUnwrap
+            | ValueMethod::MaybeIsOk
+            | ValueMethod::MaybeIsErr
+            | ValueMethod::MaybeUnwrap => vec![this],
+            ValueMethod::OptionUnwrapOr | ValueMethod::MaybeUnwrapOr => {
+                vec![this, ("default", value)]
+            }
+        }
+    }
+
+    pub fn return_type(&self, type_id: &TypeId) -> TypeId {
+        match self {
+            ValueMethod::OptionSome
+            | ValueMethod::OptionNone
+            | ValueMethod::MaybeOk
+            | ValueMethod::MaybeErr => type_id.clone(),
+            ValueMethod::OptionIsSome
+            | ValueMethod::OptionIsNone
+            | ValueMethod::MaybeIsOk
+            | ValueMethod::MaybeIsErr => TypeId::primitive("bool"),
+            ValueMethod::OptionUnwrap
+            | ValueMethod::OptionUnwrapOr
+            | ValueMethod::MaybeUnwrap
+            | ValueMethod::MaybeUnwrapOr => type_id.generics[0].clone(),
+        }
+    }
+}
+
+// Builtin functions (i.e. `:hash_str { value: s }`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuiltinFunc {
+    HashInt,
+    HashStr,
+    HashCombine,
+    // `:panic { message: "..." }` never returns
+    Panic,
+    // `:assert { value: x > 0 }` panics if the value is false
+    Assert,
+    // `:assert_eq { left: x right: 2 }` panics with both values if they differ
+    AssertEq,
+    // `:debug_assert { value: x > 0 }` is an `:assert` only compiled with debug assertions
+    DebugAssert,
+}
+
+impl BuiltinFunc {
+    pub fn lookup(func_name: &str) -> Option<Self> {
+        let func = match func_name {
+            ":hash_int" => BuiltinFunc::HashInt,
+            ":hash_str" => BuiltinFunc::HashStr,
+            ":hash_combine" => BuiltinFunc::HashCombine,
+            ":panic" => BuiltinFunc::Panic,
+            ":assert" => BuiltinFunc::Assert,
+            ":assert_eq" => BuiltinFunc::AssertEq,
+            ":debug_assert" => BuiltinFunc::DebugAssert,
+            _ => return None,
+        };
+        Some(func)
+    }
+
+    pub fn args(&self) -> Vec<(&'static str, TypeId)> {
+        match self {
+            BuiltinFunc::HashInt => vec![("value", TypeId::primitive("int64"))],
+            BuiltinFunc::HashStr => vec![("value", TypeId::builtin("String"))],
+            BuiltinFunc::HashCombine => vec![
+                ("seed", TypeId::primitive("uint64")),
+                ("hash", TypeId::primitive("uint64")),
+            ],
+            BuiltinFunc::Panic => vec![("message", TypeId::builtin("String"))],
+            BuiltinFunc::Assert | BuiltinFunc::DebugAssert => {
+                vec![("value", TypeId::primitive("bool"))]
+            }
+            // both values have the same (primitive or String) type
+            BuiltinFunc::AssertEq => vec![
+                ("left", TypeId::generic("T")),
+                ("right", TypeId::generic("T")),
+            ],
+        }
+    }
+
+    pub fn return_type(&self) -> Option<TypeId> {
+        match self {
+            BuiltinFunc::Panic
+            | BuiltinFunc::Assert
+            | BuiltinFunc::AssertEq
+            | BuiltinFunc::DebugAssert => None,
+            _ => Some(TypeId::primitive("uint64")),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BuiltinFunc::HashInt => "yak_hash_int",
+            BuiltinFunc::HashStr => "yak_hash_str",
+            BuiltinFunc::HashCombine => "yak_hash_combine",
+            BuiltinFunc::Panic => "yak_panic",
+            BuiltinFunc::Assert | BuiltinFunc::DebugAssert => "yak_assert",
+            BuiltinFunc::AssertEq => "yak_assert_eq",
+        }
+    }
+}
+
+// How the runtime formats `:assert_eq` values (passed as 64-bit words)
+// Keep in sync with yak-std
+pub fn assert_value_kind(type_id: &TypeId) -> Option<u64> {
+    let kind = if type_id.is("bool") {
+        3
+    } else if type_id.is("String") || type_id.is("str") {
+        4
+    } else if type_id.is_primitive() && type_id.type_name.starts_with("float") {
+        2
+    } else if type_id.is_primitive() && type_id.type_name.starts_with("int") {
+        0
+    } else if type_id.is_primitive() {
+        // uint, byte and char
+        1
+    } else {
+        return None;
+    };
+    Some(kind)
+}
+
+// Names of the builtin traits map and set keys must implement
+pub const TRAIT_HASH: &str = "^Hash";
+pub const TRAIT_EQ: &str = "^Eq";
+
+// Returns true if values of this type can be hashed by the runtime directly
+pub fn is_runtime_hashable(type_id: &TypeId) -> bool {
+    type_id.is_primitive() || type_id.is("String")
+}
+
+// The builtin `^Hash` and `^Eq` implementations of runtime hashable types
+// (i.e. `x:hash {}` where `x: int` or `T ^Hash` is bound to `int`).
+// These call the same runtime functions Maps and Sets use.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashMethod {
+    Hash,
+    Eq,
+}
+
+impl HashMethod {
+    // Looks up `Type:func_name`
+    pub fn lookup(type_id: &TypeId, func_name: &str) -> Option<Self> {
+        if !is_runtime_hashable(type_id) {
+            return None;
+        }
+        match func_name {
+            ":hash" => Some(HashMethod::Hash),
+            ":eq" => Some(HashMethod::Eq),
+            _ => None,
+        }
+    }
+
+    // Looks up the builtin implementation of a trait function
+    pub fn lookup_trait(type_id: &TypeId, trait_name: &str, func_name: &str) -> Option<Self> {
+        match (trait_name, HashMethod::lookup(type_id, func_name)?) {
+            (TRAIT_HASH, HashMethod::Hash) => Some(HashMethod::Hash),
+            (TRAIT_EQ, HashMethod::Eq) => Some(HashMethod::Eq),
+            _ => None,
+        }
+    }
+
+    pub fn args(&self, type_id: &TypeId) -> Vec<(&'static str, TypeId)> {
+        let this = ("self", type_id.clone());
+        match self {
+            HashMethod::Hash => vec![this],
+            HashMethod::Eq => vec![this, ("other", type_id.clone())],
+        }
+    }
+
+    pub fn return_type(&self) -> TypeId {
+        match self {
+            HashMethod::Hash => TypeId::primitive("uint64"),
+            HashMethod::Eq => TypeId::primitive("bool"),
+        }
+    }
+}
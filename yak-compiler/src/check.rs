@@ -0,0 +1,1933 @@
+use crate::builtin::{
+    assert_value_kind, is_collection, is_runtime_hashable, BuiltinFunc, BuiltinMethod, HashMethod,
+    ValueMethod, INDEX_FUNC, TRAIT_EQ, TRAIT_HASH,
+};
+use crate::hir::{
+    AliasDef, Block, CallValue, Capture, ClosureValue, CollectionValue, ExprKind, ExprValue,
+    FunctionArg, FunctionDef, Hir, ImplTraitDef, StructDef, StructField, StructValue, TraitBound,
+    TraitDef,
+};
+use crate::mono::map_function_types;
+use anyhow::{bail, Context, Error, Result};
+use std::collections::HashMap;
+use yak_ast::{Op, UnaryOp};
+use yak_core::types::field::FieldId;
+use yak_core::types::function::{FunctionArgId, FunctionId};
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+use yak_lexer::Span;
+
+// Local variable info
+#[derive(Debug, Clone)]
+struct Local {
+    type_id: TypeId,
+    mutable: bool,
+}
+
+// Block scoped local variables
+#[derive(Debug, Default)]
+struct Scope {
+    frames: Vec<HashMap<String, Local>>,
+}
+
+impl Scope {
+    fn push(&mut self) {
+        self.frames.push(HashMap::new());
+    }
+
+    fn pop(&mut self) {
+        self.frames.pop();
+    }
+
+    fn insert(&mut self, name: &str, local: Local) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.insert(name.into(), local);
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&Local> {
+        self.frames.iter().rev().find_map(|frame| frame.get(name))
+    }
+}
+
+// Per function context
+struct FunctionCtx<'f> {
+    pkg_name: String,
+    return_type: Option<&'f TypeId>,
+    // trait bounds of the function type parameters
+    bounds: &'f [TraitBound],
+    scope: Scope,
+}
+
+// Type checks function bodies and annotates
+// each expression with its resolved type_id.
+pub struct Checker<'a> {
+    hir: &'a Hir,
+}
+
+pub fn check(hir: &mut Hir) -> Result<()> {
+    match check_all(hir).into_iter().next() {
+        Some(check_error) => Err(check_error.error),
+        None => Ok(()),
+    }
+}
+
+// A type error and the function it was found in
+// (None for errors outside of functions)
+#[derive(Debug)]
+pub struct CheckError {
+    pub function_id: Option<FunctionId>,
+    pub error: Error,
+}
+
+// Type checks every function and returns all of the type errors
+// (i.e. so editors can report an error for each function)
+pub fn check_all(hir: &mut Hir) -> Vec<CheckError> {
+    resolve_imports(hir);
+    if let Err(error) = resolve_aliases(hir) {
+        return vec![CheckError {
+            function_id: None,
+            error,
+        }];
+    }
+    let snapshot = hir.clone();
+    let checker = Checker { hir: &snapshot };
+    let mut errors = vec![];
+    if let Err(error) = checker.check_impls() {
+        errors.push(CheckError {
+            function_id: None,
+            error,
+        });
+    }
+    for module in hir.modules.iter() {
+        for struct_def in &module.struct_defs {
+            for field in &struct_def.fields {
+                if let Err(error) = checker.check_known_type(&field.type_id) {
+                    errors.push(CheckError {
+                        function_id: None,
+                        error: error.context(format!(
+                            "type error in struct {} field {}",
+                            display_type(&struct_def.type_id),
+                            &field.field_id.field_name
+                        )),
+                    });
+                }
+            }
+        }
+    }
+    for module in hir.modules.iter_mut() {
+        let pkg_name = module.module_id.name();
+        let test_defs = module.test_defs.clone();
+        for func_def in module.function_defs.iter_mut() {
+            let test_def = test_defs
+                .iter()
+                .find(|test_def| test_def.function_id == func_def.function_id);
+            let checked =
+                checker
+                    .check_function(&pkg_name, func_def)
+                    .with_context(|| match test_def {
+                        Some(test_def) => format!("type error in test {:?}", &test_def.test_name),
+                        None => format!("type error in {}", func_def.function_id.name()),
+                    });
+            if let Err(error) = checked {
+                errors.push(CheckError {
+                    function_id: Some(func_def.function_id.clone()),
+                    error,
+                });
+            }
+        }
+    }
+    errors
+}
+
+// Imported types and traits are lowered with the importing package
+// so they're replaced by the type of the package that defines them
+fn resolve_imports(hir: &mut Hir) {
+    let sources: Vec<Vec<(String, TypeId)>> = hir
+        .modules
+        .iter()
+        .map(|module| {
+            module
+                .imports
+                .iter()
+                .map(|import| {
+                    let mut seen = vec![];
+                    let source = import_source(hir, &import.pkg_name, &import.type_name, &mut seen);
+                    (import.as_name.clone(), source)
+                })
+                .collect()
+        })
+        .collect();
+    for (module, sources) in hir.modules.iter_mut().zip(sources) {
+        if sources.is_empty() {
+            continue;
+        }
+        let pkg_name = module.module_id.name();
+        let f = |type_id: &TypeId| replace_imports(&pkg_name, &sources, type_id);
+        for struct_def in module.struct_defs.iter_mut() {
+            for field in struct_def.fields.iter_mut() {
+                field.type_id = f(&field.type_id);
+            }
+            for bound in struct_def.bounds.iter_mut() {
+                bound.trait_id = f(&bound.trait_id);
+            }
+        }
+        for func_def in module.function_defs.iter_mut() {
+            map_function_types(func_def, &f);
+            for bound in func_def.bounds.iter_mut() {
+                bound.trait_id = f(&bound.trait_id);
+            }
+        }
+        for trait_def in module.trait_defs.iter_mut() {
+            for func_def in trait_def.function_defs.iter_mut() {
+                map_function_types(func_def, &f);
+            }
+        }
+        for impl_def in module.impl_trait_defs.iter_mut() {
+            impl_def.type_id = f(&impl_def.type_id);
+            impl_def.trait_id = impl_def.trait_id.as_ref().map(f);
+        }
+        for alias_def in module.alias_defs.iter_mut() {
+            alias_def.alias_type = f(&alias_def.alias_type);
+        }
+    }
+}
+
+// Returns the type defined by a package for one of its names
+// (following the package's own imports)
+fn import_source(hir: &Hir, pkg_name: &str, type_name: &str, seen: &mut Vec<String>) -> TypeId {
+    let type_id = TypeId::new(pkg_name.into(), type_name.into());
+    if seen.contains(&pkg_name.to_string()) {
+        return type_id;
+    }
+    seen.push(pkg_name.into());
+    let module = hir
+        .modules
+        .iter()
+        .find(|module| module.module_id.name() == pkg_name);
+    let import = module.and_then(|module| {
+        module
+            .imports
+            .iter()
+            .find(|import| import.as_name == type_name)
+    });
+    match import {
+        Some(import) => import_source(hir, &import.pkg_name, &import.type_name, seen),
+        None => type_id,
+    }
+}
+
+fn replace_imports(pkg_name: &str, sources: &[(String, TypeId)], type_id: &TypeId) -> TypeId {
+    let mut type_id = type_id.clone();
+    type_id.generics = type_id
+        .generics
+        .iter()
+        .map(|generic| replace_imports(pkg_name, sources, generic))
+        .collect();
+    if type_id.pkg_name != pkg_name {
+        return type_id;
+    }
+    match sources
+        .iter()
+        .find(|(as_name, _)| as_name == &type_id.type_name)
+    {
+        Some((_, source)) => source.clone().with_generics(type_id.generics),
+        None => type_id,
+    }
+}
+
+// Replaces type aliases with their types everywhere in the hir
+fn resolve_aliases(hir: &mut Hir) -> Result<()> {
+    let aliases: Vec<AliasDef> = hir
+        .modules
+        .iter()
+        .flat_map(|module| module.alias_defs.iter().cloned())
+        .collect();
+    if aliases.is_empty() {
+        return Ok(());
+    }
+    // aliases of aliases resolve to the final type
+    let mut resolved = vec![];
+    for alias_def in &aliases {
+        let mut seen = vec![alias_def.type_id.type_name.clone()];
+        resolved.push(AliasDef {
+            type_id: alias_def.type_id.clone(),
+            alias_type: expand_alias(&aliases, &alias_def.alias_type, &mut seen)?,
+        });
+    }
+    let f = |type_id: &TypeId| replace_aliases(&resolved, type_id);
+    for module in hir.modules.iter_mut() {
+        for struct_def in module.struct_defs.iter_mut() {
+            for field in struct_def.fields.iter_mut() {
+                field.type_id = f(&field.type_id);
+            }
+        }
+        for func_def in module.function_defs.iter_mut() {
+            map_function_types(func_def, &f);
+        }
+        for trait_def in module.trait_defs.iter_mut() {
+            for func_def in trait_def.function_defs.iter_mut() {
+                map_function_types(func_def, &f);
+            }
+        }
+        for impl_def in module.impl_trait_defs.iter_mut() {
+            impl_def.type_id = f(&impl_def.type_id);
+        }
+    }
+    Ok(())
+}
+
+// Returns the alias named by a user type. Imported aliases
+// are named by the package that defines them (see resolve_imports).
+fn find_alias<'a>(aliases: &'a [AliasDef], type_id: &TypeId) -> Option<&'a AliasDef> {
+    if type_id.is_primitive() || type_id.is_builtin() || type_id.is_generic() {
+        return None;
+    }
+    aliases.iter().find(|alias_def| {
+        alias_def.type_id.type_name == type_id.type_name
+            && alias_def.type_id.pkg_name == type_id.pkg_name
+    })
+}
+
+fn expand_alias(aliases: &[AliasDef], type_id: &TypeId, seen: &mut Vec<String>) -> Result<TypeId> {
+    let mut type_id = type_id.clone();
+    type_id.generics = type_id
+        .generics
+        .iter()
+        .map(|generic| expand_alias(aliases, generic, seen))
+        .collect::<Result<_>>()?;
+    match find_alias(aliases, &type_id) {
+        Some(alias_def) => {
+            let name = alias_def.type_id.type_name.clone();
+            if seen.contains(&name) {
+                bail!("type alias {} refers to itself", seen[0]);
+            }
+            seen.push(name);
+            let expanded = expand_alias(aliases, &alias_def.alias_type, seen)?;
+            seen.pop();
+            Ok(expanded)
+        }
+        None => Ok(type_id),
+    }
+}
+
+// `resolved` aliases don't refer to other aliases
+fn replace_aliases(resolved: &[AliasDef], type_id: &TypeId) -> TypeId {
+    let mut type_id = type_id.clone();
+    type_id.generics = type_id
+        .generics
+        .iter()
+        .map(|generic| replace_aliases(resolved, generic))
+        .collect();
+    match find_alias(resolved, &type_id) {
+        Some(alias_def) => alias_def.alias_type.clone(),
+        None => type_id,
+    }
+}
+
+// Returns true if both types are the same.
+// User types are compared by package and name
+// (imports are replaced with the type they refer to).
+pub fn type_eq(a: &TypeId, b: &TypeId) -> bool {
+    let is_str = |t: &TypeId| t.is("String") || t.is("str");
+    if is_str(a) && is_str(b) {
+        return true;
+    }
+    a.type_name == b.type_name
+        && a.pkg_name == b.pkg_name
+        && a.generics.len() == b.generics.len()
+        && a.generics
+            .iter()
+            .zip(b.generics.iter())
+            .all(|(a, b)| type_eq(a, b))
+}
+
+pub fn is_int(type_id: &TypeId) -> bool {
+    type_id.is_primitive()
+        && (type_id.type_name.starts_with("int")
+            || type_id.type_name.starts_with("uint")
+            || type_id.type_name == "byte"
+            || type_id.type_name == "char")
+}
+
+pub fn is_float(type_id: &TypeId) -> bool {
+    type_id.is_primitive() && type_id.type_name.starts_with("float")
+}
+
+fn is_bool(type_id: &TypeId) -> bool {
+    type_id.is("bool")
+}
+
+// Casts convert between numeric types (and from bool to int)
+fn is_castable(from: &TypeId, to: &TypeId) -> bool {
+    match (from, to) {
+        _ if type_eq(from, to) => true,
+        (from, to) if is_int(from) || is_bool(from) => is_int(to) || (is_int(from) && is_float(to)),
+        (from, to) if is_float(from) => is_int(to) || is_float(to),
+        _ => false,
+    }
+}
+
+fn expect_type(expected: &TypeId, found: &TypeId) -> Result<()> {
+    if !type_eq(expected, found) {
+        bail!(
+            "expected type {} but found {}",
+            display_type(expected),
+            display_type(found)
+        );
+    }
+    Ok(())
+}
+
+// Binds the type parameters in `declared` to the matching parts of `found`
+// (i.e. `Option[T]` and `Option[int]` binds `T = int`).
+// The first binding wins and mismatches are reported by expect_type.
+fn unify(params: &[String], declared: &TypeId, found: &TypeId, bindings: &mut [Option<TypeId>]) {
+    if declared.is_generic() {
+        if let Some(index) = params.iter().position(|param| param == &declared.type_name) {
+            if bindings[index].is_none() {
+                bindings[index] = Some(found.clone());
+            }
+        }
+        return;
+    }
+    if declared.type_name != found.type_name || declared.generics.len() != found.generics.len() {
+        return;
+    }
+    for (declared, found) in declared.generics.iter().zip(found.generics.iter()) {
+        unify(params, declared, found, bindings);
+    }
+}
+
+// Substitutes the type parameters bound so far
+fn substitute_bound(type_id: &TypeId, params: &[String], bindings: &[Option<TypeId>]) -> TypeId {
+    let (params, args): (Vec<String>, Vec<TypeId>) = params
+        .iter()
+        .zip(bindings.iter())
+        .filter_map(|(param, binding)| Some((param.clone(), binding.clone()?)))
+        .unzip();
+    type_id.substitute(&params, &args)
+}
+
+// Returns the type arguments or an error for the first unbound parameter
+fn bound_generics(
+    params: &[String],
+    bindings: Vec<Option<TypeId>>,
+    name: &str,
+) -> Result<Vec<TypeId>> {
+    let mut generics = vec![];
+    for (param, binding) in params.iter().zip(bindings) {
+        match binding {
+            Some(binding) => generics.push(binding),
+            None => bail!("unable to infer type parameter {} for {}", param, name),
+        }
+    }
+    Ok(generics)
+}
+
+// The builtin `^Hash` and `^Eq` traits (pkg/yak.builtin/src/traits)
+// which use the trait type in place of `Self`
+fn builtin_trait_def(trait_id: &TypeId) -> Option<TraitDef> {
+    if !trait_id.is_builtin() {
+        return None;
+    }
+    let (func_name, arg_names, return_type) = match trait_id.type_name.as_str() {
+        TRAIT_HASH => (":hash", vec![], "uint64"),
+        TRAIT_EQ => (":eq", vec!["other"], "bool"),
+        _ => return None,
+    };
+    let mut args = vec![FunctionArg {
+        arg_id: FunctionArgId::new("self".into(), 0),
+        type_id: trait_id.clone(),
+    }];
+    for (index, arg_name) in arg_names.into_iter().enumerate() {
+        args.push(FunctionArg {
+            arg_id: FunctionArgId::new(arg_name.into(), index + 1),
+            type_id: trait_id.clone(),
+        });
+    }
+    let func_def = FunctionDef {
+        function_id: FunctionId::new(
+            trait_id.pkg_name.clone(),
+            Some(trait_id.type_name.clone()),
+            func_name.into(),
+        ),
+        generics: vec![],
+        bounds: vec![],
+        args,
+        return_type: Some(TypeId::primitive(return_type)),
+        is_self: true,
+        blocks: vec![],
+        file: None,
+        span: Span::default(),
+    };
+    Some(TraitDef {
+        type_id: trait_id.clone(),
+        function_defs: vec![func_def],
+    })
+}
+
+// Anonymous tuples are checked as a generic tuple
+// with a type parameter per element (i.e. `tuple ()[T0 T1] { T0 T1 }`)
+fn tuple_struct_def(arity: usize) -> StructDef {
+    let generics: Vec<String> = (0..arity).map(|index| format!("T{}", index)).collect();
+    let fields = generics
+        .iter()
+        .enumerate()
+        .map(|(index, param)| StructField {
+            field_id: FieldId::new(index.to_string(), index),
+            type_id: TypeId::generic(param),
+        })
+        .collect();
+    StructDef {
+        type_id: TypeId::tuple(vec![]),
+        generics,
+        bounds: vec![],
+        fields,
+    }
+}
+
+// Replaces the trait type (the trait `Self`) with `self_type`
+fn replace_self(type_id: &TypeId, trait_id: &TypeId, self_type: &TypeId) -> TypeId {
+    if type_id.type_name == trait_id.type_name && type_id.pkg_name == trait_id.pkg_name {
+        return self_type.clone();
+    }
+    let mut type_id = type_id.clone();
+    type_id.generics = type_id
+        .generics
+        .iter()
+        .map(|generic| replace_self(generic, trait_id, self_type))
+        .collect();
+    type_id
+}
+
+// Collects the variables (and `:f` calls) used by a closure body
+// which aren't bound by the closure
+fn free_vars(expr: &ExprValue, bound: &[String], found: &mut Vec<(String, bool)>) {
+    let mut push = |name: &str, is_call: bool| {
+        if !bound.iter().any(|var| var == name) && !found.iter().any(|(var, _)| var == name) {
+            found.push((name.into(), is_call));
+        }
+    };
+    match &expr.kind {
+        ExprKind::Var(name) => push(name, false),
+        ExprKind::Call(call) => {
+            if call.func_type.is_none() {
+                push(call.func_name.trim_start_matches(':'), true);
+            }
+            for arg in &call.args {
+                free_vars(&arg.value, bound, found);
+            }
+        }
+        ExprKind::Field(inner, _)
+        | ExprKind::Unary(_, inner)
+        | ExprKind::Try(inner)
+        | ExprKind::Object(inner)
+        | ExprKind::Cast(inner, _) => free_vars(inner, bound, found),
+        ExprKind::Binary(lhs, _, rhs) => {
+            free_vars(lhs, bound, found);
+            free_vars(rhs, bound, found);
+        }
+        ExprKind::Struct(struct_value) => {
+            for field in &struct_value.fields {
+                free_vars(&field.value, bound, found);
+            }
+        }
+        ExprKind::Collection(collection) => {
+            for item in &collection.items {
+                free_vars(item, bound, found);
+            }
+        }
+        ExprKind::Closure(closure) => {
+            let mut bound = bound.to_vec();
+            bound.extend(closure.args.iter().map(|arg| arg.arg_id.arg_name.clone()));
+            free_vars(&closure.body, &bound, found);
+        }
+        ExprKind::None
+        | ExprKind::Bool(_)
+        | ExprKind::Int(_)
+        | ExprKind::Float(_)
+        | ExprKind::String(_)
+        | ExprKind::OptionNone => {}
+    }
+}
+
+// Returns true if `type_id` is or contains `target`
+fn mentions_type(type_id: &TypeId, target: &TypeId) -> bool {
+    (type_id.type_name == target.type_name && type_id.pkg_name == target.pkg_name)
+        || type_id
+            .generics
+            .iter()
+            .any(|generic| mentions_type(generic, target))
+}
+
+// Formats a function signature (i.e. `self { other: Point } bool`)
+pub fn display_signature(func_def: &FunctionDef, map: &dyn Fn(&TypeId) -> TypeId) -> String {
+    let args: Vec<String> = func_def
+        .args
+        .iter()
+        .skip(if func_def.is_self { 1 } else { 0 })
+        .map(|arg| {
+            format!(
+                "{}: {}",
+                &arg.arg_id.arg_name,
+                display_type(&map(&arg.type_id))
+            )
+        })
+        .collect();
+    let mut signature = String::new();
+    if func_def.is_self {
+        signature.push_str("self ");
+    }
+    if args.is_empty() {
+        signature.push_str("{}");
+    } else {
+        signature.push_str(&format!("{{ {} }}", args.join(" ")));
+    }
+    if let Some(return_type) = &func_def.return_type {
+        signature.push_str(&format!(" {}", display_type(&map(return_type))));
+    }
+    signature
+}
+
+// Formats a type the way it's written in source (i.e. `Map[String int32]`)
+pub fn display_type(type_id: &TypeId) -> String {
+    if type_id.is_function() {
+        let args: Vec<String> = type_id
+            .function_args()
+            .iter()
+            .map(|(name, arg_type)| format!("{}: {}", name, display_type(arg_type)))
+            .collect();
+        let mut display = if args.is_empty() {
+            "fn {}".to_string()
+        } else {
+            format!("fn {{ {} }}", args.join(" "))
+        };
+        if let Some(return_type) = type_id.function_return() {
+            display.push_str(&format!(" {}", display_type(&return_type)));
+        }
+        return display;
+    }
+    if type_id.is_tuple() {
+        let elements: Vec<String> = type_id.generics.iter().map(display_type).collect();
+        return format!("({})", elements.join(", "));
+    }
+    if type_id.generics.is_empty() {
+        return type_id.type_name.clone();
+    }
+    let generics: Vec<String> = type_id.generics.iter().map(display_type).collect();
+    format!("{}[{}]", &type_id.type_name, generics.join(" "))
+}
+
+impl<'a> Checker<'a> {
+    // Verify impls define the functions required by their trait
+    fn check_impls(&self) -> Result<()> {
+        for module in &self.hir.modules {
+            for impl_def in &module.impl_trait_defs {
+                let trait_id = match &impl_def.trait_id {
+                    Some(trait_id) => trait_id,
+                    None => continue,
+                };
+                let trait_def = match self.hir.find_trait(trait_id).cloned() {
+                    Some(trait_def) => trait_def,
+                    None => match builtin_trait_def(trait_id) {
+                        Some(trait_def) => trait_def,
+                        None => bail!(
+                            "impl {} {}: unknown trait",
+                            &impl_def.type_id.type_name,
+                            &trait_id.type_name
+                        ),
+                    },
+                };
+                self.check_impl_trait(impl_def, &trait_def)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Trait functions must be implemented with the same signature
+    // (with the impl type in place of the trait `Self`)
+    fn check_impl_trait(&self, impl_def: &ImplTraitDef, trait_def: &TraitDef) -> Result<()> {
+        let impl_name = format!(
+            "impl {} {}",
+            display_type(&impl_def.type_id),
+            &trait_def.type_id.type_name
+        );
+        let replace =
+            |type_id: &TypeId| replace_self(type_id, &trait_def.type_id, &impl_def.type_id);
+        for trait_func in &trait_def.function_defs {
+            let func_name = &trait_func.function_id.func_name;
+            let impl_func = impl_def
+                .function_ids
+                .iter()
+                .find(|function_id| &function_id.func_name == func_name)
+                .and_then(|function_id| self.hir.find_function(function_id));
+            let impl_func = match impl_func {
+                Some(impl_func) => impl_func,
+                None => bail!("{} is missing function {}", &impl_name, func_name),
+            };
+            let args_match =
+                trait_func.is_self == impl_func.is_self
+                    && trait_func.args.len() == impl_func.args.len()
+                    && trait_func.args.iter().zip(impl_func.args.iter()).all(
+                        |(expected, found)| {
+                            expected.arg_id.arg_name == found.arg_id.arg_name
+                                && type_eq(&replace(&expected.type_id), &found.type_id)
+                        },
+                    );
+            let return_matches = match (&trait_func.return_type, &impl_func.return_type) {
+                (Some(expected), Some(found)) => type_eq(&replace(expected), found),
+                (None, None) => true,
+                _ => false,
+            };
+            if !args_match || !return_matches {
+                bail!(
+                    "{} function {} expected signature `{}` but found `{}`",
+                    &impl_name,
+                    func_name,
+                    display_signature(trait_func, &replace),
+                    display_signature(impl_func, &|type_id: &TypeId| type_id.clone())
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Map keys and Set items need to be hashable
+    fn check_type(&self, ctx: &FunctionCtx, type_id: &TypeId) -> Result<()> {
+        self.check_known_type(type_id)?;
+        let arity = match type_id.type_name.as_str() {
+            "Option" | "Set" | "List" => Some(1),
+            "Maybe" | "Map" => Some(2),
+            _ => None,
+        };
+        if let Some(arity) = arity {
+            if type_id.is_builtin() && type_id.generics.len() != arity {
+                bail!("{} expects {} type arguments", display_type(type_id), arity);
+            }
+        }
+        if type_id.is_trait() {
+            self.check_object_safe(type_id)?;
+        }
+        if let Some(struct_def) = self.user_struct(type_id) {
+            if struct_def.generics.len() != type_id.generics.len() {
+                bail!(
+                    "{} expects {} type arguments",
+                    display_type(type_id),
+                    struct_def.generics.len()
+                );
+            }
+            self.check_bounds(
+                ctx,
+                &struct_def.generics,
+                &struct_def.bounds,
+                &type_id.generics,
+                &display_type(type_id),
+            )?;
+        }
+        if type_id.is("Map") || type_id.is("Set") {
+            if let Some(key) = type_id.generics.first() {
+                self.check_hashable(ctx, key)?;
+            }
+        }
+        for generic in &type_id.generics {
+            self.check_type(ctx, generic)?;
+        }
+        Ok(())
+    }
+
+    // User types (and traits used as types) must be defined
+    // since code generation has no representation for other types
+    fn check_known_type(&self, type_id: &TypeId) -> Result<()> {
+        let known = if type_id.is_trait() {
+            self.hir.find_trait(type_id).is_some()
+        } else {
+            type_id.is_primitive()
+                || type_id.is_builtin()
+                || type_id.is_generic()
+                || self.hir.find_struct(type_id).is_some()
+        };
+        if !known {
+            bail!("unknown type {}", display_type(type_id));
+        }
+        for generic in &type_id.generics {
+            self.check_known_type(generic)?;
+        }
+        Ok(())
+    }
+
+    // Trait objects call functions through a vtable so every trait
+    // function needs a `self` receiver and can't use `Self` otherwise
+    fn check_object_safe(&self, type_id: &TypeId) -> Result<()> {
+        let trait_def = match self.hir.find_trait(type_id) {
+            Some(trait_def) => trait_def,
+            None => bail!("unknown trait {}", &type_id.type_name),
+        };
+        for func_def in &trait_def.function_defs {
+            let func_name = &func_def.function_id.func_name;
+            let reason = if !func_def.is_self {
+                Some("has no self receiver")
+            } else if !func_def.generics.is_empty() {
+                Some("has type parameters")
+            } else if func_def
+                .args
+                .iter()
+                .skip(1)
+                .map(|arg| &arg.type_id)
+                .chain(func_def.return_type.iter())
+                .any(|arg_type| mentions_type(arg_type, &trait_def.type_id))
+            {
+                Some("uses Self outside of the receiver")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                bail!(
+                    "trait {} can't be used as an object: {} {}",
+                    &trait_def.type_id.type_name,
+                    func_name,
+                    reason
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the struct definition for user types
+    fn user_struct(&self, type_id: &TypeId) -> Option<&'a StructDef> {
+        if type_id.is_primitive() || type_id.is_builtin() || type_id.is_generic() {
+            return None;
+        }
+        self.hir.find_struct(type_id)
+    }
+
+    fn check_hashable(&self, ctx: &FunctionCtx, type_id: &TypeId) -> Result<()> {
+        if is_runtime_hashable(type_id) {
+            return Ok(());
+        }
+        if (type_id.is_generic() || self.hir.find_struct(type_id).is_some())
+            && self.satisfies(ctx, type_id, &TypeId::builtin(TRAIT_HASH))
+            && self.satisfies(ctx, type_id, &TypeId::builtin(TRAIT_EQ))
+        {
+            return Ok(());
+        }
+        bail!(
+            "type {} must implement {} and {} to be used as a Map key or Set item",
+            display_type(type_id),
+            TRAIT_HASH,
+            TRAIT_EQ
+        )
+    }
+
+    // Returns true if `type_id` implements the trait
+    // (type parameters implement the traits they're bound by)
+    fn satisfies(&self, ctx: &FunctionCtx, type_id: &TypeId, trait_id: &TypeId) -> bool {
+        if type_id.is_generic() {
+            return ctx
+                .bounds
+                .iter()
+                .any(|bound| bound.param == type_id.type_name && &bound.trait_id == trait_id);
+        }
+        // primitives and Strings have builtin `:hash` and `:eq` functions
+        if builtin_trait_def(trait_id).is_some() && is_runtime_hashable(type_id) {
+            return true;
+        }
+        // trait objects implement their trait
+        if type_id.is_trait() {
+            return type_id == trait_id;
+        }
+        self.hir.implements(type_id, trait_id)
+    }
+
+    // Verify type arguments implement the trait bounds of their parameters
+    fn check_bounds(
+        &self,
+        ctx: &FunctionCtx,
+        params: &[String],
+        bounds: &[TraitBound],
+        generics: &[TypeId],
+        name: &str,
+    ) -> Result<()> {
+        for bound in bounds {
+            let generic = match params.iter().position(|param| param == &bound.param) {
+                Some(index) if index < generics.len() => &generics[index],
+                _ => continue,
+            };
+            if !self.satisfies(ctx, generic, &bound.trait_id) {
+                bail!(
+                    "type {} doesn't implement {} (required by {} for {})",
+                    display_type(generic),
+                    &bound.trait_id.type_name,
+                    name,
+                    &bound.param
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn check_function(&self, pkg_name: &str, func_def: &mut FunctionDef) -> Result<()> {
+        for bound in &func_def.bounds {
+            let trait_name = &bound.trait_id.type_name;
+            if self.hir.find_trait(&bound.trait_id).is_none()
+                && builtin_trait_def(&bound.trait_id).is_none()
+            {
+                bail!("unknown trait {} (bound on {})", trait_name, &bound.param);
+            }
+        }
+        let mut ctx = FunctionCtx {
+            pkg_name: pkg_name.into(),
+            return_type: func_def.return_type.as_ref(),
+            bounds: &func_def.bounds,
+            scope: Scope::default(),
+        };
+        ctx.scope.push();
+        if let Some(return_type) = &func_def.return_type {
+            self.check_type(&ctx, return_type)?;
+        }
+        for arg in &func_def.args {
+            self.check_type(&ctx, &arg.type_id)?;
+            ctx.scope.insert(
+                &arg.arg_id.arg_name,
+                Local {
+                    type_id: arg.type_id.clone(),
+                    mutable: false,
+                },
+            );
+        }
+        self.check_blocks(&mut ctx, &mut func_def.blocks)
+    }
+
+    fn check_blocks(&self, ctx: &mut FunctionCtx, blocks: &mut [Block]) -> Result<()> {
+        ctx.scope.push();
+        for block in blocks.iter_mut() {
+            self.check_block(ctx, block)?;
+        }
+        ctx.scope.pop();
+        Ok(())
+    }
+
+    fn check_block(&self, ctx: &mut FunctionCtx, block: &mut Block) -> Result<()> {
+        match block {
+            Block::Let(let_def) => {
+                if let Some(type_id) = &let_def.type_id {
+                    self.check_type(ctx, type_id)?;
+                }
+                let found = self.check_value(ctx, &mut let_def.value, let_def.type_id.as_ref())?;
+                if let Some(type_id) = &let_def.type_id {
+                    expect_type(type_id, &found)?;
+                }
+                let type_id = let_def.type_id.clone().unwrap_or(found);
+                let_def.type_id = Some(type_id.clone());
+                ctx.scope.insert(
+                    &let_def.var_name,
+                    Local {
+                        type_id,
+                        mutable: let_def.mutable,
+                    },
+                );
+            }
+            Block::Assign(assign_def) => {
+                let local = match ctx.scope.get(&assign_def.var_name) {
+                    Some(local) => local.clone(),
+                    None => bail!("unknown variable {}", &assign_def.var_name),
+                };
+                if !local.mutable && assign_def.fields.is_empty() {
+                    bail!("cannot assign twice to const {}", &assign_def.var_name);
+                }
+                if !local.mutable {
+                    bail!(
+                        "cannot assign to field {} of const {}",
+                        assign_def.fields.join("."),
+                        &assign_def.var_name
+                    );
+                }
+                let type_id = self.check_value(ctx, &mut assign_def.target(), None)?;
+                let found = self.check_value(ctx, &mut assign_def.value, Some(&type_id))?;
+                expect_type(&type_id, &found)?;
+            }
+            Block::Expr(expr) => {
+                self.check_expr(ctx, expr, None)?;
+            }
+            Block::If(if_def) => {
+                for condition in if_def.conditions.iter_mut() {
+                    let found = self.check_value(ctx, &mut condition.expr, None)?;
+                    if !is_bool(&found) {
+                        bail!("if conditions must be bool, found {}", display_type(&found));
+                    }
+                    self.check_blocks(ctx, &mut condition.blocks)?;
+                }
+                if let Some(else_blocks) = if_def.else_blocks.as_mut() {
+                    self.check_blocks(ctx, else_blocks)?;
+                }
+            }
+            Block::ForIn(for_def) => {
+                let iter_type = self.check_value(ctx, &mut for_def.iter, None)?;
+                let var_types = if iter_type.is("Map") {
+                    match for_def.vars.len() {
+                        1 => vec![iter_type.generics[0].clone()],
+                        _ => vec![iter_type.generics[0].clone(), iter_type.generics[1].clone()],
+                    }
+                } else if iter_type.is("Set") || iter_type.is("List") {
+                    if for_def.vars.len() != 1 {
+                        bail!(
+                            "for statements over a {} bind one variable",
+                            &iter_type.type_name
+                        );
+                    }
+                    vec![iter_type.generics[0].clone()]
+                } else {
+                    bail!("type {} isn't iterable", display_type(&iter_type));
+                };
+                ctx.scope.push();
+                for (var, type_id) in for_def.vars.iter().zip(var_types) {
+                    ctx.scope.insert(
+                        var,
+                        Local {
+                            type_id,
+                            mutable: false,
+                        },
+                    );
+                }
+                self.check_blocks(ctx, &mut for_def.blocks)?;
+                ctx.scope.pop();
+            }
+            Block::Return(value) => {
+                let return_type = ctx.return_type.cloned();
+                match (value, return_type) {
+                    (Some(value), Some(return_type)) => {
+                        let found = self.check_value(ctx, value, Some(&return_type))?;
+                        expect_type(&return_type, &found)?;
+                    }
+                    (None, None) => {}
+                    (Some(_), None) => bail!("function without a return type returns a value"),
+                    (None, Some(return_type)) => {
+                        bail!(
+                            "expected a return value of type {}",
+                            display_type(&return_type)
+                        )
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Checks an expression which must produce a value
+    fn check_value(
+        &self,
+        ctx: &mut FunctionCtx,
+        expr: &mut ExprValue,
+        expected: Option<&TypeId>,
+    ) -> Result<TypeId> {
+        let found = match self.check_expr(ctx, expr, expected)? {
+            Some(type_id) => type_id,
+            None => bail!("expression doesn't produce a value"),
+        };
+        // concrete values are converted to the expected trait object
+        if let Some(expected) = expected {
+            if expected.is_trait() && !found.is_trait() && !found.is_generic() {
+                if !self.satisfies(ctx, &found, expected) {
+                    bail!(
+                        "type {} doesn't implement {}",
+                        display_type(&found),
+                        &expected.type_name
+                    );
+                }
+                let inner = std::mem::replace(
+                    expr,
+                    ExprValue {
+                        kind: ExprKind::None,
+                        type_id: Some(expected.clone()),
+                        span: expr.span,
+                    },
+                );
+                expr.kind = ExprKind::Object(Box::new(inner));
+                return Ok(expected.clone());
+            }
+        }
+        Ok(found)
+    }
+
+    fn check_expr(
+        &self,
+        ctx: &mut FunctionCtx,
+        expr: &mut ExprValue,
+        expected: Option<&TypeId>,
+    ) -> Result<Option<TypeId>> {
+        let type_id = match &mut expr.kind {
+            ExprKind::None => None,
+            ExprKind::Bool(_) => Some(TypeId::primitive("bool")),
+            ExprKind::Int(_) => match expected {
+                Some(expected) if is_int(expected) || is_float(expected) => Some(expected.clone()),
+                _ => Some(TypeId::primitive("int32")),
+            },
+            ExprKind::Float(_) => match expected {
+                Some(expected) if is_float(expected) => Some(expected.clone()),
+                _ => Some(TypeId::primitive("float64")),
+            },
+            ExprKind::String(_) => Some(TypeId::builtin("String")),
+            ExprKind::Var(name) => match ctx.scope.get(name) {
+                Some(local) => Some(local.type_id.clone()),
+                None => bail!("unknown variable {}", name),
+            },
+            ExprKind::Field(inner, field_name) => {
+                let inner_type = self.check_value(ctx, inner, None)?;
+                let tuple_def;
+                let struct_def = if inner_type.is_tuple() {
+                    tuple_def = tuple_struct_def(inner_type.generics.len());
+                    &tuple_def
+                } else {
+                    match self.hir.find_struct(&inner_type) {
+                        Some(struct_def) => struct_def,
+                        None => bail!("type {} has no fields", display_type(&inner_type)),
+                    }
+                };
+                match struct_def
+                    .fields
+                    .iter()
+                    .find(|field| &field.field_id.field_name == field_name)
+                {
+                    Some(field) => Some(
+                        field
+                            .type_id
+                            .substitute(&struct_def.generics, &inner_type.generics),
+                    ),
+                    None => bail!(
+                        "type {} has no field {}",
+                        display_type(&inner_type),
+                        field_name
+                    ),
+                }
+            }
+            ExprKind::Unary(op, rhs) => {
+                let rhs_type = self.check_value(ctx, rhs, expected)?;
+                match op {
+                    UnaryOp::Not if is_bool(&rhs_type) || is_int(&rhs_type) => Some(rhs_type),
+                    UnaryOp::Minus | UnaryOp::Plus if is_int(&rhs_type) || is_float(&rhs_type) => {
+                        Some(rhs_type)
+                    }
+                    _ => bail!(
+                        "unary operator {:?} isn't supported for {}",
+                        op,
+                        display_type(&rhs_type)
+                    ),
+                }
+            }
+            ExprKind::Binary(lhs, op, rhs) => Some(self.check_binary(ctx, lhs, op, rhs, expected)?),
+            ExprKind::Call(call) => self.check_call(ctx, call, expected)?,
+            ExprKind::Struct(struct_value) => {
+                Some(self.check_struct(ctx, struct_value, expected)?)
+            }
+            ExprKind::Collection(collection) => {
+                Some(self.check_collection(ctx, collection, expected)?)
+            }
+            ExprKind::OptionNone => match expected {
+                Some(expected) if expected.is("Option") => Some(expected.clone()),
+                _ => bail!("unable to infer the type of None (annotate the variable type)"),
+            },
+            ExprKind::Try(inner) => Some(self.check_try(ctx, inner)?),
+            ExprKind::Cast(inner, type_id) => {
+                let found = self.check_value(ctx, inner, None)?;
+                if !is_castable(&found, type_id) {
+                    bail!(
+                        "cannot cast {} as {}",
+                        display_type(&found),
+                        display_type(type_id)
+                    );
+                }
+                Some(type_id.clone())
+            }
+            ExprKind::Object(_) => expr.type_id.clone(),
+            ExprKind::Closure(closure) => Some(self.check_closure(ctx, closure, expected)?),
+        };
+        expr.type_id = type_id.clone();
+        Ok(type_id)
+    }
+
+    fn check_binary(
+        &self,
+        ctx: &mut FunctionCtx,
+        lhs: &mut ExprValue,
+        op: &Op,
+        rhs: &mut ExprValue,
+        expected: Option<&TypeId>,
+    ) -> Result<TypeId> {
+        // operands of boolean operators don't share the expected type
+        let operand_expected = match op {
+            Op::Arith(_) | Op::Bitwise(_) => expected,
+            _ => None,
+        };
+        // literals take the type of the other operand
+        let (lhs_type, rhs_type) = if matches!(lhs.kind, ExprKind::Int(_) | ExprKind::Float(_)) {
+            let rhs_type = self.check_value(ctx, rhs, operand_expected)?;
+            let lhs_type = self.check_value(ctx, lhs, Some(&rhs_type))?;
+            (lhs_type, rhs_type)
+        } else {
+            let lhs_type = self.check_value(ctx, lhs, operand_expected)?;
+            let rhs_type = self.check_value(ctx, rhs, Some(&lhs_type))?;
+            (lhs_type, rhs_type)
+        };
+        expect_type(&lhs_type, &rhs_type)?;
+        let type_id = match op {
+            Op::Arith(_) if is_int(&lhs_type) || is_float(&lhs_type) => lhs_type,
+            Op::Bitwise(_) if is_int(&lhs_type) => lhs_type,
+            Op::Logical(_) if is_bool(&lhs_type) => lhs_type,
+            Op::Boolean(_) if is_int(&lhs_type) || is_float(&lhs_type) || is_bool(&lhs_type) => {
+                TypeId::primitive("bool")
+            }
+            _ => bail!(
+                "operator {:?} isn't supported for {}",
+                op,
+                display_type(&lhs_type)
+            ),
+        };
+        Ok(type_id)
+    }
+
+    // `value?` unwraps an Option or Maybe and returns None or
+    // the error from the current function
+    fn check_try(&self, ctx: &mut FunctionCtx, inner: &mut ExprValue) -> Result<TypeId> {
+        let inner_type = self.check_value(ctx, inner, None)?;
+        let return_type = ctx.return_type;
+        if inner_type.is("Option") {
+            match return_type {
+                Some(return_type) if return_type.is("Option") => {}
+                _ => bail!("the ? operator on an Option requires the function to return an Option"),
+            }
+        } else if inner_type.is("Maybe") {
+            match return_type {
+                Some(return_type)
+                    if return_type.is("Maybe")
+                        && type_eq(&return_type.generics[1], &inner_type.generics[1]) => {}
+                _ => bail!(
+                    "the ? operator on {} requires the function to return Maybe[T {}]",
+                    display_type(&inner_type),
+                    display_type(&inner_type.generics[1])
+                ),
+            }
+        } else {
+            bail!(
+                "the ? operator expects an Option or Maybe but found {}",
+                display_type(&inner_type)
+            );
+        }
+        Ok(inner_type.generics[0].clone())
+    }
+
+    fn check_struct(
+        &self,
+        ctx: &mut FunctionCtx,
+        struct_value: &mut StructValue,
+        expected: Option<&TypeId>,
+    ) -> Result<TypeId> {
+        let tuple_def;
+        let struct_def = if struct_value.type_id.is_tuple() {
+            tuple_def = tuple_struct_def(struct_value.fields.len());
+            &tuple_def
+        } else {
+            match self.hir.find_struct(&struct_value.type_id) {
+                Some(struct_def) => struct_def,
+                None => bail!("unknown struct {}", &struct_value.type_id.type_name),
+            }
+        };
+        let type_id = if struct_def.generics.is_empty() {
+            struct_def.type_id.clone()
+        } else {
+            self.infer_struct_generics(ctx, struct_value, struct_def, expected)?
+        };
+        self.check_type(ctx, &type_id)?;
+        for field in struct_value.fields.iter_mut() {
+            let field_def = match struct_def
+                .fields
+                .iter()
+                .find(|field_def| field_def.field_id.field_name == field.field_name)
+            {
+                Some(field_def) => field_def,
+                None => bail!(
+                    "struct {} has no field {}",
+                    &struct_def.type_id.type_name,
+                    &field.field_name
+                ),
+            };
+            let field_type = field_def
+                .type_id
+                .substitute(&struct_def.generics, &type_id.generics);
+            let found = self.check_value(ctx, &mut field.value, Some(&field_type))?;
+            expect_type(&field_type, &found)?;
+        }
+        for field_def in &struct_def.fields {
+            if !struct_value
+                .fields
+                .iter()
+                .any(|field| field.field_name == field_def.field_id.field_name)
+            {
+                bail!(
+                    "struct {} is missing field {}",
+                    &struct_def.type_id.type_name,
+                    &field_def.field_id.field_name
+                );
+            }
+        }
+        struct_value.type_id = type_id.clone();
+        Ok(type_id)
+    }
+
+    // Type arguments for a generic struct value come from
+    // `Box[int] { ... }`, the expected type or the field values
+    fn infer_struct_generics(
+        &self,
+        ctx: &mut FunctionCtx,
+        struct_value: &mut StructValue,
+        struct_def: &StructDef,
+        expected: Option<&TypeId>,
+    ) -> Result<TypeId> {
+        let type_id = struct_def.type_id.clone();
+        if !struct_value.type_id.generics.is_empty() {
+            return Ok(type_id.with_generics(struct_value.type_id.generics.clone()));
+        }
+        if let Some(expected) = expected {
+            // (anonymous tuples of another arity are a type mismatch)
+            if expected.type_name == type_id.type_name
+                && expected.generics.len() == struct_def.generics.len()
+            {
+                return Ok(type_id.with_generics(expected.generics.clone()));
+            }
+        }
+        let mut bindings = vec![None; struct_def.generics.len()];
+        for field in struct_value.fields.iter_mut() {
+            if let Some(field_def) = struct_def
+                .fields
+                .iter()
+                .find(|field_def| field_def.field_id.field_name == field.field_name)
+            {
+                let found = self.check_value(ctx, &mut field.value, None)?;
+                unify(
+                    &struct_def.generics,
+                    &field_def.type_id,
+                    &found,
+                    &mut bindings,
+                );
+            }
+        }
+        let generics = bound_generics(&struct_def.generics, bindings, &type_id.type_name)?;
+        Ok(type_id.with_generics(generics))
+    }
+
+    fn check_collection(
+        &self,
+        ctx: &mut FunctionCtx,
+        collection: &mut CollectionValue,
+        expected: Option<&TypeId>,
+    ) -> Result<TypeId> {
+        if !is_collection(&collection.type_id) {
+            bail!(
+                ":from isn't supported for type {}",
+                display_type(&collection.type_id)
+            );
+        }
+        let is_map = collection.type_id.is("Map");
+        if is_map && collection.items.len() % 2 != 0 {
+            bail!("Map:from expects key value pairs");
+        }
+        let arity = if is_map { 2 } else { 1 };
+        let mut type_id = collection.type_id.clone();
+        if type_id.generics.is_empty() {
+            match expected {
+                Some(expected) if type_eq(&expected.clone().with_generics(vec![]), &type_id) => {
+                    type_id = expected.clone();
+                }
+                _ => {
+                    // infer from the first item(s)
+                    if collection.items.len() < arity {
+                        bail!(
+                            "unable to infer the type of {}:from (add type arguments)",
+                            &type_id.type_name
+                        );
+                    }
+                    let mut generics = vec![];
+                    for item in collection.items.iter_mut().take(arity) {
+                        generics.push(self.check_value(ctx, item, None)?);
+                    }
+                    type_id = type_id.with_generics(generics);
+                }
+            }
+        }
+        if type_id.generics.len() != arity {
+            bail!("{} expects {} type arguments", &type_id.type_name, arity);
+        }
+        self.check_type(ctx, &type_id)?;
+        for (i, item) in collection.items.iter_mut().enumerate() {
+            let item_type = &type_id.generics[i % arity];
+            let found = self.check_value(ctx, item, Some(item_type))?;
+            expect_type(item_type, &found)?;
+        }
+        collection.type_id = type_id.clone();
+        Ok(type_id)
+    }
+
+    // Closures are checked like functions with their args and the outer
+    // variables they capture in scope. `let` variables are captured by
+    // reference and everything else by value.
+    fn check_closure(
+        &self,
+        ctx: &mut FunctionCtx,
+        closure: &mut ClosureValue,
+        expected: Option<&TypeId>,
+    ) -> Result<TypeId> {
+        let arg_names: Vec<String> = closure
+            .args
+            .iter()
+            .map(|arg| arg.arg_id.arg_name.clone())
+            .collect();
+        let mut names = vec![];
+        free_vars(&closure.body, &arg_names, &mut names);
+        closure.captures = vec![];
+        for (name, is_call) in names {
+            let local = match ctx.scope.get(&name) {
+                Some(local) => local,
+                None => continue,
+            };
+            // `:f {}` only captures function values
+            if is_call && !local.type_id.is_function() {
+                continue;
+            }
+            closure.captures.push(Capture {
+                var_name: name,
+                type_id: local.type_id.clone(),
+                by_ref: local.mutable,
+            });
+        }
+        // closures without a return type return the expected type
+        // (or the type of their body)
+        let return_type = closure.return_type.clone().or_else(|| {
+            expected
+                .filter(|expected| expected.is_function())
+                .and_then(|expected| expected.function_return())
+        });
+        let mut closure_ctx = FunctionCtx {
+            pkg_name: ctx.pkg_name.clone(),
+            return_type: return_type.as_ref(),
+            bounds: ctx.bounds,
+            scope: Scope::default(),
+        };
+        closure_ctx.scope.push();
+        for arg in &closure.args {
+            self.check_type(&closure_ctx, &arg.type_id)?;
+            closure_ctx.scope.insert(
+                &arg.arg_id.arg_name,
+                Local {
+                    type_id: arg.type_id.clone(),
+                    mutable: false,
+                },
+            );
+        }
+        for capture in &closure.captures {
+            closure_ctx.scope.insert(
+                &capture.var_name,
+                Local {
+                    type_id: capture.type_id.clone(),
+                    mutable: capture.by_ref,
+                },
+            );
+        }
+        let return_type = match &return_type {
+            Some(return_type) => {
+                self.check_type(&closure_ctx, return_type)?;
+                let found =
+                    self.check_value(&mut closure_ctx, &mut closure.body, Some(return_type))?;
+                expect_type(return_type, &found)?;
+                Some(return_type.clone())
+            }
+            None => self.check_expr(&mut closure_ctx, &mut closure.body, None)?,
+        };
+        closure.return_type = return_type.clone();
+        let args = closure
+            .args
+            .iter()
+            .map(|arg| (arg.arg_id.arg_name.clone(), arg.type_id.clone()))
+            .collect();
+        Ok(TypeId::function(args, return_type))
+    }
+
+    fn check_call(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+        expected: Option<&TypeId>,
+    ) -> Result<Option<TypeId>> {
+        // `value:func {}` resolves `:func` from the value type
+        // (inherent, trait impl, trait object or bound functions)
+        if call.receiver && call.func_type.is_none() {
+            let receiver = match call.args.first_mut() {
+                Some(receiver) => receiver,
+                None => bail!("method {} is missing its receiver", &call.func_name),
+            };
+            let receiver_type = self.check_value(ctx, &mut receiver.value, None)?;
+            if call.args.iter().skip(1).any(|arg| arg.arg_name == "self") {
+                bail!(
+                    "method {} already receives self from {}",
+                    &call.func_name,
+                    display_type(&receiver_type)
+                );
+            }
+            if receiver_type.is("List") {
+                index_list_call(call);
+            }
+            call.func_type = Some(receiver_type);
+        }
+        // local function values shadow functions with the same name
+        if call.func_type.is_none() {
+            let value_type = ctx
+                .scope
+                .get(call.func_name.trim_start_matches(':'))
+                .map(|local| local.type_id.clone())
+                .filter(|type_id| type_id.is_function());
+            if let Some(value_type) = value_type {
+                self.check_args(ctx, call, &value_type.function_args())?;
+                call.value_type = Some(value_type.clone());
+                return Ok(value_type.function_return());
+            }
+        }
+        if let Some(func_type) = call.func_type.clone() {
+            if func_type.is_generic() {
+                return self.check_bound_call(ctx, call, &func_type);
+            }
+            if func_type.is_trait() {
+                return self.check_object_call(ctx, call, &func_type);
+            }
+            if let Some(method) = BuiltinMethod::lookup(&func_type, &call.func_name) {
+                return self.check_builtin_method(ctx, call, method, func_type, expected);
+            }
+            if let Some(method) = ValueMethod::lookup(&func_type, &call.func_name) {
+                return self.check_value_method(ctx, call, method, func_type, expected);
+            }
+            if let Some(method) = HashMethod::lookup(&func_type, &call.func_name) {
+                let args: Vec<(String, TypeId)> = method
+                    .args(&func_type)
+                    .into_iter()
+                    .map(|(name, type_id)| (name.to_string(), type_id))
+                    .collect();
+                self.check_args(ctx, call, &args)?;
+                return Ok(Some(method.return_type()));
+            }
+            if func_type.is_builtin() || func_type.is_primitive() {
+                bail!(
+                    "unknown function {}{}",
+                    &func_type.type_name,
+                    &call.func_name
+                );
+            }
+        }
+
+        // user defined functions
+        if let Some(func_def) = self.find_function(ctx, call) {
+            if !func_def.generics.is_empty() {
+                return self.check_generic_call(ctx, call, func_def, expected);
+            }
+            let args: Vec<(String, TypeId)> = func_def
+                .args
+                .iter()
+                .map(|arg| (arg.arg_id.arg_name.clone(), arg.type_id.clone()))
+                .collect();
+            self.check_args(ctx, call, &args)?;
+            call.function_id = Some(func_def.function_id.clone());
+            return Ok(func_def.return_type.clone());
+        }
+
+        // builtin functions
+        if call.func_type.is_none() {
+            if let Some(func) = BuiltinFunc::lookup(&call.func_name) {
+                if func == BuiltinFunc::AssertEq {
+                    return self.check_assert_eq(ctx, call);
+                }
+                let args: Vec<(String, TypeId)> = func
+                    .args()
+                    .into_iter()
+                    .map(|(name, type_id)| (name.to_string(), type_id))
+                    .collect();
+                self.check_args(ctx, call, &args)?;
+                return Ok(func.return_type());
+            }
+        }
+
+        match &call.func_type {
+            Some(func_type) => bail!(
+                "unknown function {}{}",
+                &func_type.type_name,
+                &call.func_name
+            ),
+            None => bail!("unknown function {}", &call.func_name),
+        }
+    }
+
+    // Infers the type arguments of a generic function from `Type[...]:func`,
+    // the expected return type and the call args. The call resolves
+    // to the function instance (i.e. `pkg:id[yak.primitive#int]`)
+    fn check_generic_call(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+        func_def: &FunctionDef,
+        expected: Option<&TypeId>,
+    ) -> Result<Option<TypeId>> {
+        let params = &func_def.generics;
+        let mut bindings: Vec<Option<TypeId>> = vec![None; params.len()];
+        if let Some(func_type) = &call.func_type {
+            for (binding, generic) in bindings.iter_mut().zip(func_type.generics.iter()) {
+                *binding = Some(generic.clone());
+            }
+        }
+        if let (Some(return_type), Some(expected)) = (&func_def.return_type, expected) {
+            unify(params, return_type, expected, &mut bindings);
+        }
+        for arg in &func_def.args {
+            let call_arg = match call
+                .args
+                .iter_mut()
+                .find(|call_arg| call_arg.arg_name == arg.arg_id.arg_name)
+            {
+                Some(call_arg) => call_arg,
+                None => continue,
+            };
+            let partial = substitute_bound(&arg.type_id, params, &bindings);
+            let arg_expected = if partial.has_generic() {
+                None
+            } else {
+                Some(&partial)
+            };
+            let found = self.check_value(ctx, &mut call_arg.value, arg_expected)?;
+            unify(params, &arg.type_id, &found, &mut bindings);
+        }
+        let generics = bound_generics(params, bindings, &call.func_name)?;
+        for generic in &generics {
+            self.check_type(ctx, generic)?;
+        }
+        self.check_bounds(ctx, params, &func_def.bounds, &generics, &call.func_name)?;
+        let args: Vec<(String, TypeId)> = func_def
+            .args
+            .iter()
+            .map(|arg| {
+                (
+                    arg.arg_id.arg_name.clone(),
+                    arg.type_id.substitute(params, &generics),
+                )
+            })
+            .collect();
+        self.check_args(ctx, call, &args)?;
+        let return_type = func_def
+            .return_type
+            .as_ref()
+            .map(|return_type| return_type.substitute(params, &generics));
+        call.function_id = Some(func_def.function_id.clone().with_generics(generics));
+        Ok(return_type)
+    }
+
+    // `T:func {}` on a type parameter resolves `:func` from the trait bounds.
+    // Monomorphization replaces the trait function with the impl function
+    // of the concrete type (static dispatch).
+    fn check_bound_call(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+        func_type: &TypeId,
+    ) -> Result<Option<TypeId>> {
+        let found = ctx
+            .bounds
+            .iter()
+            .filter(|bound| bound.param == func_type.type_name)
+            .filter_map(|bound| {
+                self.hir
+                    .find_trait(&bound.trait_id)
+                    .cloned()
+                    .or_else(|| builtin_trait_def(&bound.trait_id))
+            })
+            .find_map(|trait_def| {
+                let func_def = trait_def
+                    .function_defs
+                    .iter()
+                    .find(|func_def| func_def.function_id.func_name == call.func_name)
+                    .cloned()?;
+                Some((trait_def, func_def))
+            });
+        let (trait_def, trait_func) = match found {
+            Some(found) => found,
+            None => bail!(
+                "type parameter {} has no function {} (missing a trait bound?)",
+                &func_type.type_name,
+                &call.func_name
+            ),
+        };
+        let args: Vec<(String, TypeId)> = trait_func
+            .args
+            .iter()
+            .map(|arg| {
+                (
+                    arg.arg_id.arg_name.clone(),
+                    replace_self(&arg.type_id, &trait_def.type_id, func_type),
+                )
+            })
+            .collect();
+        self.check_args(ctx, call, &args)?;
+        call.function_id = Some(trait_func.function_id.clone());
+        Ok(trait_func
+            .return_type
+            .as_ref()
+            .map(|return_type| replace_self(return_type, &trait_def.type_id, func_type)))
+    }
+
+    // `^Trait:func {}` calls the trait function of a trait object
+    // (compiled to a vtable call)
+    fn check_object_call(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+        func_type: &TypeId,
+    ) -> Result<Option<TypeId>> {
+        self.check_type(ctx, func_type)?;
+        let trait_func = self.hir.find_trait(func_type).and_then(|trait_def| {
+            trait_def
+                .function_defs
+                .iter()
+                .find(|func_def| func_def.function_id.func_name == call.func_name)
+        });
+        let trait_func = match trait_func {
+            Some(trait_func) => trait_func,
+            None => bail!(
+                "trait {} has no function {}",
+                &func_type.type_name,
+                &call.func_name
+            ),
+        };
+        let args: Vec<(String, TypeId)> = trait_func
+            .args
+            .iter()
+            .map(|arg| (arg.arg_id.arg_name.clone(), arg.type_id.clone()))
+            .collect();
+        self.check_args(ctx, call, &args)?;
+        call.function_id = Some(trait_func.function_id.clone());
+        Ok(trait_func.return_type.clone())
+    }
+
+    // Resolves a function call against the current package first
+    fn find_function(&self, ctx: &FunctionCtx, call: &CallValue) -> Option<&'a FunctionDef> {
+        let struct_name = call
+            .func_type
+            .as_ref()
+            .map(|func_type| func_type.type_name.clone());
+        let local_id = FunctionId::new(
+            ctx.pkg_name.clone(),
+            struct_name.clone(),
+            call.func_name.clone(),
+        );
+        if let Some(func_def) = self.hir.find_function(&local_id) {
+            return Some(func_def);
+        }
+        self.hir
+            .modules
+            .iter()
+            .flat_map(|module| module.function_defs.iter())
+            .find(|func_def| {
+                func_def.function_id.func_name == call.func_name
+                    && func_def.function_id.struct_name == struct_name
+            })
+    }
+
+    fn check_builtin_method(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+        method: BuiltinMethod,
+        func_type: TypeId,
+        expected: Option<&TypeId>,
+    ) -> Result<Option<TypeId>> {
+        let arity = if func_type.is("Map") { 2 } else { 1 };
+        let mut type_id = func_type.clone();
+        if type_id.generics.is_empty() {
+            if method.is_new() {
+                // `let m: Map[K V] = Map:new {}`
+                match expected {
+                    Some(expected) if expected.type_name == type_id.type_name => {
+                        type_id = expected.clone();
+                    }
+                    _ => bail!(
+                        "unable to infer type arguments for {}{} (annotate the variable type)",
+                        &type_id.type_name,
+                        &call.func_name
+                    ),
+                }
+            } else {
+                // infer from the receiver
+                let receiver = match call.args.iter_mut().find(|arg| arg.arg_name == "self") {
+                    Some(receiver) => receiver,
+                    None => bail!(
+                        "{}{} expects a self argument",
+                        &type_id.type_name,
+                        &call.func_name
+                    ),
+                };
+                type_id = self.check_value(ctx, &mut receiver.value, None)?;
+            }
+        }
+        if type_id.type_name != func_type.type_name || type_id.generics.len() != arity {
+            bail!(
+                "expected {} receiver but found {}",
+                &func_type.type_name,
+                display_type(&type_id)
+            );
+        }
+        self.check_type(ctx, &type_id)?;
+        let args: Vec<(String, TypeId)> = method
+            .args(&type_id)
+            .into_iter()
+            .map(|(name, type_id)| (name.to_string(), type_id))
+            .collect();
+        self.check_args(ctx, call, &args)?;
+        call.func_type = Some(type_id.clone());
+        Ok(method.return_type(&type_id))
+    }
+
+    fn check_value_method(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+        method: ValueMethod,
+        func_type: TypeId,
+        expected: Option<&TypeId>,
+    ) -> Result<Option<TypeId>> {
+        let arity = if func_type.is("Maybe") { 2 } else { 1 };
+        let mut type_id = func_type.clone();
+        if type_id.generics.is_empty() {
+            match expected {
+                // `let x: Option[int] = Option:some { value: 1 }`
+                Some(expected) if method.is_constructor() && expected.is(&type_id.type_name) => {
+                    type_id = expected.clone();
+                }
+                _ if method == ValueMethod::OptionSome => {
+                    // infer from the value
+                    let value = match call.args.iter_mut().find(|arg| arg.arg_name == "value") {
+                        Some(value) => value,
+                        None => bail!("Option:some expects a value argument"),
+                    };
+                    let value_type = self.check_value(ctx, &mut value.value, None)?;
+                    type_id = type_id.with_generics(vec![value_type]);
+                }
+                _ if method.is_constructor() => bail!(
+                    "unable to infer type arguments for {}{} (annotate the variable type)",
+                    &type_id.type_name,
+                    &call.func_name
+                ),
+                _ => {
+                    // infer from the receiver
+                    let receiver = match call.args.iter_mut().find(|arg| arg.arg_name == "self") {
+                        Some(receiver) => receiver,
+                        None => bail!(
+                            "{}{} expects a self argument",
+                            &type_id.type_name,
+                            &call.func_name
+                        ),
+                    };
+                    type_id = self.check_value(ctx, &mut receiver.value, None)?;
+                }
+            }
+        }
+        if type_id.type_name != func_type.type_name || type_id.generics.len() != arity {
+            bail!(
+                "expected {} but found {}",
+                &func_type.type_name,
+                display_type(&type_id)
+            );
+        }
+        self.check_type(ctx, &type_id)?;
+        let args: Vec<(String, TypeId)> = method
+            .args(&type_id)
+            .into_iter()
+            .map(|(name, type_id)| (name.to_string(), type_id))
+            .collect();
+        self.check_args(ctx, call, &args)?;
+        call.func_type = Some(type_id.clone());
+        Ok(Some(method.return_type(&type_id)))
+    }
+
+    // Checks named call args against the expected args
+    fn check_args(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+        args: &[(String, TypeId)],
+    ) -> Result<()> {
+        for call_arg in call.args.iter_mut() {
+            let arg_type = match args.iter().find(|(name, _)| name == &call_arg.arg_name) {
+                Some((_, arg_type)) => arg_type,
+                None => bail!(
+                    "function {} has no argument {}",
+                    &call.func_name,
+                    &call_arg.arg_name
+                ),
+            };
+            let found = self.check_value(ctx, &mut call_arg.value, Some(arg_type))?;
+            // :hash_int accepts any integer type
+            if call.func_name == ":hash_int" && is_int(&found) {
+                continue;
+            }
+            expect_type(arg_type, &found)?;
+        }
+        for (name, _) in args {
+            if !call.args.iter().any(|call_arg| &call_arg.arg_name == name) {
+                bail!("function {} is missing argument {}", &call.func_name, name);
+            }
+        }
+        Ok(())
+    }
+
+    // `:assert_eq` compares two primitive or String values of the same type
+    // (the right value is checked against the type of the left)
+    fn check_assert_eq(
+        &self,
+        ctx: &mut FunctionCtx,
+        call: &mut CallValue,
+    ) -> Result<Option<TypeId>> {
+        let left = match call.args.iter_mut().find(|arg| arg.arg_name == "left") {
+            Some(arg) => self.check_value(ctx, &mut arg.value, None)?,
+            None => bail!("function :assert_eq is missing argument left"),
+        };
+        if assert_value_kind(&left).is_none() {
+            bail!(
+                ":assert_eq expects primitive or String values but found {} (use :assert instead)",
+                display_type(&left)
+            );
+        }
+        let right = match call.args.iter_mut().find(|arg| arg.arg_name == "right") {
+            Some(arg) => self.check_value(ctx, &mut arg.value, Some(&left))?,
+            None => bail!("function :assert_eq is missing argument right"),
+        };
+        expect_type(&left, &right)?;
+        if let Some(arg) = call
+            .args
+            .iter()
+            .find(|arg| arg.arg_name != "left" && arg.arg_name != "right")
+        {
+            bail!("function :assert_eq has no argument {}", &arg.arg_name);
+        }
+        Ok(None)
+    }
+}
+
+// Indexing is lowered to the Map methods (`value:get { key }` and
+// `value:insert { key value }`) which are `:get { index }` and
+// `:set { index item }` for a List
+fn index_list_call(call: &mut CallValue) {
+    let func_name = match call.func_name.as_str() {
+        ":get" | INDEX_FUNC => ":get",
+        ":insert" => ":set",
+        _ => return,
+    };
+    call.func_name = func_name.into();
+    for arg in call.args.iter_mut() {
+        match arg.arg_name.as_str() {
+            "key" => arg.arg_name = "index".into(),
+            "value" => arg.arg_name = "item".into(),
+            _ => {}
+        }
+    }
+}
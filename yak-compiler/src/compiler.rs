@@ -1,19 +1,39 @@
 // use super::{builder::Builder, context::Context, module::Module};
 
+mod block;
+mod closure;
+mod debug;
+mod drop;
+mod expr;
+mod harness;
+pub(crate) mod link;
+mod object;
+mod option;
+mod panic;
+mod runtime;
+mod types;
+
+pub use link::{LibKind, LinkError, Linker};
+
 use crate::hir::{FunctionDef, Hir, ModuleDef};
 use anyhow::{bail, Context, Error, Result};
+use debug::DebugInfo;
 use inkwell::builder::Builder;
 use inkwell::context::Context as InkwellContext;
 use inkwell::module::Module;
-use inkwell::passes::PassManager;
-use inkwell::types::BasicMetadataTypeEnum;
-use inkwell::AddressSpace;
+use inkwell::passes::{PassManager, PassManagerBuilder};
+use inkwell::targets::{
+    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+};
+use inkwell::types::{BasicMetadataTypeEnum, StructType};
+use inkwell::{AddressSpace, OptimizationLevel};
 // use inkwell::values::{BasicMetadataValueEnum, FloatValue, FunctionValue, PointerValue};
-use inkwell::values::FunctionValue;
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue};
 use log::{error, info};
+use std::collections::HashMap;
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
-use std::process::Command;
+use std::path::Path;
+use std::str::FromStr;
 use yak_core::types::name::Name;
 
 // llvm label for function start
@@ -22,20 +42,244 @@ const FUNC_ENTRY: &str = "enter";
 // make binary files executable
 const BINARY_FILE_MODE: u32 = 0o777;
 
+// A file written by a build (i.e. "bin", "lib" or an emit name)
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub kind: &'static str,
+    pub file: String,
+}
+
+// Intermediate artifacts a build can write to the target directory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    Hir,
+    LlvmIr,
+    Asm,
+    Obj,
+}
+
+impl Emit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Emit::Tokens => "tokens",
+            Emit::Ast => "ast",
+            Emit::Hir => "hir",
+            Emit::LlvmIr => "llvm-ir",
+            Emit::Asm => "asm",
+            Emit::Obj => "obj",
+        }
+    }
+
+    // The target sub directory for the artifacts
+    // (llvm-ir modules are written to `module`)
+    pub fn dir(&self, output_dir: &str) -> String {
+        match self {
+            Emit::LlvmIr => format!("{}/module", output_dir),
+            _ => format!("{}/{}", output_dir, self.name()),
+        }
+    }
+
+    // Writes an artifact file
+    pub fn write(&self, output_dir: &str, file_name: &str, contents: &str) -> Result<Artifact> {
+        let file = format!("{}/{}", self.dir(output_dir), file_name);
+        // file names may include sub directories
+        if let Some(path) = Path::new(&file).parent() {
+            fs::create_dir_all(path)
+                .with_context(|| format!("failed to create emit path: {}", path.display()))?;
+        }
+        fs::write(&file, contents).with_context(|| format!("failed to write file: {}", &file))?;
+        info!("emit {}: {}", self.name(), &file);
+        Ok(Artifact {
+            kind: self.name(),
+            file,
+        })
+    }
+}
+
+impl FromStr for Emit {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let emit = match value {
+            "tokens" => Emit::Tokens,
+            "ast" => Emit::Ast,
+            "hir" => Emit::Hir,
+            "llvm-ir" => Emit::LlvmIr,
+            "asm" => Emit::Asm,
+            "obj" => Emit::Obj,
+            _ => bail!(
+                "unknown emit type: {} (expected tokens, ast, hir, llvm-ir, asm or obj)",
+                value
+            ),
+        };
+        Ok(emit)
+    }
+}
+
+// LLVM optimization levels (`s` and `z` optimize for size)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OptLevel {
+    #[default]
+    O0,
+    O1,
+    O2,
+    O3,
+    Os,
+    Oz,
+}
+
+impl OptLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptLevel::O0 => "0",
+            OptLevel::O1 => "1",
+            OptLevel::O2 => "2",
+            OptLevel::O3 => "3",
+            OptLevel::Os => "s",
+            OptLevel::Oz => "z",
+        }
+    }
+
+    pub(crate) fn llvm_level(&self) -> OptimizationLevel {
+        match self {
+            OptLevel::O0 => OptimizationLevel::None,
+            OptLevel::O1 => OptimizationLevel::Less,
+            OptLevel::O2 | OptLevel::Os | OptLevel::Oz => OptimizationLevel::Default,
+            OptLevel::O3 => OptimizationLevel::Aggressive,
+        }
+    }
+
+    pub(crate) fn size_level(&self) -> u32 {
+        match self {
+            OptLevel::Os => 1,
+            OptLevel::Oz => 2,
+            _ => 0,
+        }
+    }
+
+    // The inlining thresholds clang uses for each level
+    pub(crate) fn inline_threshold(&self) -> Option<u32> {
+        match self {
+            OptLevel::O0 | OptLevel::O1 => None,
+            OptLevel::O2 => Some(225),
+            OptLevel::O3 => Some(250),
+            OptLevel::Os => Some(75),
+            OptLevel::Oz => Some(25),
+        }
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let opt_level = match value {
+            "0" => OptLevel::O0,
+            "1" => OptLevel::O1,
+            "2" => OptLevel::O2,
+            "3" => OptLevel::O3,
+            "s" => OptLevel::Os,
+            "z" => OptLevel::Oz,
+            _ => bail!("unknown opt level: {} (expected 0, 1, 2, 3, s or z)", value),
+        };
+        Ok(opt_level)
+    }
+}
+
+// The machine code target (the host if no triple is given)
+#[derive(Debug, Clone, Default)]
+pub struct TargetOpts {
+    pub triple: Option<String>,
+    pub cpu: Option<String>,
+    pub features: Option<String>,
+}
+
+impl TargetOpts {
+    pub fn target_machine(&self, opt_level: OptLevel) -> Result<TargetMachine> {
+        let config = InitializationConfig::default();
+        // the host cpu and features only apply to the host triple
+        let (triple, cpu, features) = match &self.triple {
+            Some(triple) => {
+                Target::initialize_all(&config);
+                (TargetTriple::create(triple), "generic".into(), "".into())
+            }
+            None => {
+                Target::initialize_native(&config).map_err(Error::msg)?;
+                (
+                    TargetMachine::get_default_triple(),
+                    TargetMachine::get_host_cpu_name().to_str()?.to_string(),
+                    TargetMachine::get_host_cpu_features().to_str()?.to_string(),
+                )
+            }
+        };
+        let cpu = self.cpu.clone().unwrap_or(cpu);
+        let features = self.features.clone().unwrap_or(features);
+        let target = Target::from_triple(&triple)
+            .map_err(|err| anyhow::anyhow!("unknown target {}: {}", triple, err))?;
+        match target.create_target_machine(
+            &triple,
+            &cpu,
+            &features,
+            opt_level.llvm_level(),
+            RelocMode::PIC,
+            CodeModel::Default,
+        ) {
+            Some(machine) => Ok(machine),
+            None => bail!("failed to create target machine for {} ({})", triple, cpu),
+        }
+    }
+}
+
 pub struct CompilerOpts {
     // The package we're building
     pub pkg_id: String,
     pub pkg_local_path: String,
     pub output_dir: String,
+    // panic on integer overflow
+    pub overflow_checks: bool,
+    // compile `:debug_assert` calls
+    pub debug_assertions: bool,
+    // generate DWARF debug info
+    pub debug_info: bool,
+    pub opt_level: OptLevel,
+    pub linker: Linker,
+    // what packages without a :main link into
+    pub lib_kind: LibKind,
+    // the package `export` functions and types (i.e. `:add`, `Point`)
+    pub exports: Vec<String>,
+    // test builds link a harness which runs a single test
+    pub test: bool,
+    // intermediate artifacts to write
+    pub emit: Vec<Emit>,
+    pub target: TargetOpts,
+}
+
+impl CompilerOpts {
+    // The linked binary for packages with a main (or tests)
+    pub fn bin_file(&self) -> String {
+        let suffix = if self.test { "-test" } else { "" };
+        format!("{}/bin/{}{}", &self.output_dir, &self.pkg_id, suffix)
+    }
 }
 
 pub struct Compiler<'a, 'ctx> {
     pub opts: CompilerOpts,
-    pub module_files: Vec<String>,
+    // module object files to link
+    pub object_files: Vec<String>,
+    // files written for the build
+    pub artifacts: Vec<Artifact>,
     pub has_main: bool,
     pub hir: Hir,
     pub context: &'ctx InkwellContext,
     pub builder: &'a Builder<'ctx>,
+    // generates the module object files
+    pub machine: TargetMachine,
+    // llvm struct types by struct type name
+    pub struct_types: HashMap<String, StructType<'ctx>>,
+    // debug info for the module being compiled
+    debug_info: Option<DebugInfo<'ctx>>,
     // pub fpm: &'a PassManager<FunctionValue<'ctx>>,
 }
 
@@ -45,144 +289,162 @@ impl<'a, 'ctx> Compiler<'a, 'ctx> {
         hir: Hir,
         context: &'ctx InkwellContext,
         builder: &'a Builder<'ctx>,
+        machine: TargetMachine,
     ) -> Self {
         Compiler {
             opts,
-            module_files: vec![],
+            object_files: vec![],
+            artifacts: vec![],
             has_main: false,
             hir,
             context,
             builder,
+            machine,
+            struct_types: HashMap::new(),
+            debug_info: None,
         }
     }
 
-    pub fn build(opts: CompilerOpts, hir: Hir) -> Result<()> {
+    // Compiles and links the package and returns the files it wrote
+    pub fn build(opts: CompilerOpts, mut hir: Hir) -> Result<Vec<Artifact>> {
+        crate::check::check(&mut hir)?;
+        crate::mono::monomorphize(&mut hir)?;
+        let mut artifacts = vec![];
+        if opts.emit.contains(&Emit::Hir) {
+            // the hir we generate code from
+            let file_name = format!("{}.hir", &opts.pkg_id);
+            let contents = format!("{:#?}\n", &hir);
+            artifacts.push(Emit::Hir.write(&opts.output_dir, &file_name, &contents)?);
+        }
+        let machine = opts.target.target_machine(opts.opt_level)?;
         let context = &InkwellContext::create();
         let builder = &context.create_builder();
-        let mut compiler = Compiler::new(opts, hir, context, builder);
+        let mut compiler = Compiler::new(opts, hir, context, builder, machine);
         compiler.compile()?;
-        compiler.link()
+        artifacts.append(&mut compiler.artifacts);
+        artifacts.push(compiler.link()?);
+        Ok(artifacts)
     }
 
-    fn link(&self) -> Result<()> {
-        // use clang for now...
-        let mut cmd = Command::new("clang");
-
-        // add module files
-        self.module_files.iter().for_each(|mod_file| {
-            cmd.arg(mod_file);
-        });
+    fn write_module(&mut self, module: &mut Module) -> Result<()> {
+        if let Err(err) = module.verify() {
+            error!("LLVM module verify issue...");
+            module.print_to_stderr();
+            bail!(err.to_string());
+        }
+        self.optimize(module);
+        if self.opts.emit.contains(&Emit::LlvmIr) {
+            let artifact = self.write_llvm_ir(module)?;
+            self.artifacts.push(artifact);
+        }
+        if self.opts.emit.contains(&Emit::Asm) {
+            let artifact = self.write_native(module, Emit::Asm)?;
+            self.artifacts.push(artifact);
+        }
+        // objects are always written since they're linked
+        let artifact = self.write_native(module, Emit::Obj)?;
+        self.object_files.push(artifact.file.clone());
+        if self.opts.emit.contains(&Emit::Obj) {
+            self.artifacts.push(artifact);
+        }
+        Ok(())
+    }
 
-        // assume we have a main function...
-        // add output filename
-        let mut output_path = format!("{}/bin", &self.opts.output_dir);
-        let mut output_file = format!("{}/{}", &output_path, &self.opts.pkg_id);
-
-        // if we don't have a main then
-        // generate a library even though we don't currently do anything
-        // with these...
-        if !self.has_main {
-            output_path = format!("{}/lib", &self.opts.output_dir);
-            output_file = format!("{}/{}.so", &output_path, &self.opts.pkg_id);
-            // static library
-            cmd.arg("-shared");
-            cmd.arg("-undefined");
-            cmd.arg("dynamic_lookup");
+    // Runs the function and module pass pipelines for the opt level
+    fn optimize(&self, module: &Module) {
+        let opt_level = self.opts.opt_level;
+        if opt_level == OptLevel::O0 {
+            return;
+        }
+        let pass_builder = PassManagerBuilder::create();
+        pass_builder.set_optimization_level(opt_level.llvm_level());
+        pass_builder.set_size_level(opt_level.size_level());
+        if let Some(threshold) = opt_level.inline_threshold() {
+            pass_builder.set_inliner_with_threshold(threshold);
         }
 
-        // verbose
-        cmd.arg("-v");
-
-        let _ = fs::create_dir_all(&output_path)
-            .with_context(|| format!("failed to create bin path: {}", &output_path))?;
-        cmd.arg("-o");
-        cmd.arg(&output_file);
-
-        let link_type = if self.has_main { "binary" } else { "library" };
-        info!("Link {} w/ {:#?}", &link_type, &cmd);
-
-        match cmd.output() {
-            Ok(output) => {
-                let status = output.status;
-                if status.success() {
-                    if self.has_main {
-                        let mut perms = fs::metadata(&output_file)?.permissions();
-                        perms.set_mode(BINARY_FILE_MODE);
-                        fs::set_permissions(&output_file, perms)?;
-                    }
-                    info!("Done building {} file {}", &link_type, &output_file);
-                } else {
-                    bail!(
-                        "clang error: {}",
-                        std::str::from_utf8(&output.stderr).unwrap()
-                    );
-                }
-            }
-            Err(err) => {
-                error!("Error running clang command");
-                bail!(err.to_string())
-            }
+        let fpm = PassManager::create(module);
+        pass_builder.populate_function_pass_manager(&fpm);
+        fpm.initialize();
+        for func_value in module.get_functions() {
+            fpm.run_on(&func_value);
         }
-        Ok(())
+        fpm.finalize();
+
+        let mpm = PassManager::create(());
+        pass_builder.populate_module_pass_manager(&mpm);
+        mpm.run_on(module);
+        info!(
+            "optimized module {} (opt level {})",
+            module.get_name().to_str().unwrap_or_default(),
+            opt_level.name()
+        );
     }
 
-    fn write_module(&self, module: &mut Module, module_files: &mut Vec<String>) -> Result<()> {
-        // let i64_type = self.context.i64_type();
-        // self.builder
-        //     .build_return(Some(&i64_type.const_int(0, false)));
-
-        // verify module and print it to file
-        match module.verify() {
-            Ok(_) => {
-                let name = module.get_name().to_str()?;
-                // let path = format!("/tmp/{}", &name);
-                let path = format!("{}/module", &self.opts.output_dir);
-                let _ = fs::create_dir_all(&path)
-                    .with_context(|| format!("failed to create file path: {}", &path))?;
-                let mod_file = format!("{}/{}.ll", &path, &name);
-                let result = module.print_to_file(&mod_file[..]);
-                match result {
-                    Ok(_) => {
-                        module_files.push(mod_file.clone());
-                        info!("write module: {}", mod_file);
-                        info!("======LL=======");
-                        let _ = module.print_to_stderr();
-                        info!("===============");
-                    }
-                    Err(err) => {
-                        error!("Error writing module file {}", &mod_file);
-                        bail!(err.to_string());
-                    }
-                }
-            }
-            Err(err) => {
-                error!("LLVM module verify issue...");
-                module.print_to_stderr();
-                bail!(err.to_string());
-            }
+    fn write_llvm_ir(&self, module: &Module) -> Result<Artifact> {
+        let path = Emit::LlvmIr.dir(&self.opts.output_dir);
+        fs::create_dir_all(&path)
+            .with_context(|| format!("failed to create file path: {}", &path))?;
+        let name = module.get_name().to_str()?;
+        let file = format!("{}/{}.ll", &path, &name);
+        if let Err(err) = module.print_to_file(&file) {
+            error!("Error writing module file {}", &file);
+            bail!(err.to_string());
         }
+        info!("emit {}: {}", Emit::LlvmIr.name(), &file);
+        Ok(Artifact {
+            kind: Emit::LlvmIr.name(),
+            file,
+        })
+    }
 
-        Ok(())
+    // Writes the module assembly or object file for the target machine
+    fn write_native(&self, module: &Module, emit: Emit) -> Result<Artifact> {
+        let (file_type, ext) = match emit {
+            Emit::Asm => (FileType::Assembly, "s"),
+            _ => (FileType::Object, "o"),
+        };
+        let path = emit.dir(&self.opts.output_dir);
+        fs::create_dir_all(&path)
+            .with_context(|| format!("failed to create emit path: {}", &path))?;
+        let name = module.get_name().to_str()?;
+        let file = format!("{}/{}.{}", &path, name, ext);
+        self.machine
+            .write_to_file(module, file_type, Path::new(&file))
+            .map_err(|err| Error::msg(err.to_string()))?;
+        info!("emit {}: {}", emit.name(), &file);
+        Ok(Artifact {
+            kind: emit.name(),
+            file,
+        })
     }
 
     pub fn compile(&mut self) -> Result<()> {
         // iterate hir.modules
-        let mut module_files: Vec<String> = vec![];
-        for module_def in self.hir.modules.iter() {
+        let hir = self.hir.clone();
+        self.create_struct_types(&hir)?;
+        for module_def in hir.modules.iter() {
             let module_name = module_def.module_id.name();
             info!("compile module {}", module_name);
             let module = &mut self.context.create_module(module_name.as_str());
+            module.set_triple(&self.machine.get_triple());
+            module.set_data_layout(&self.machine.get_target_data().get_data_layout());
+            self.debug_info = self.create_debug_info(module, module_def);
             self.compile_constants(module, module_def)?;
             self.compile_functions(module, module_def)?;
             // should come after functions
-            if self.compile_main(module, module_def)? {
+            let has_main = if self.opts.test {
+                self.compile_test_main(module, module_def)?
+            } else {
+                self.compile_main(module, module_def)?
+            };
+            if has_main {
                 self.has_main = true;
             }
             self.compile_structs(module, module_def)?;
-            self.write_module(module, &mut module_files)?;
+            self.finalize_debug_info();
+            self.write_module(module)?;
         }
-        // copy module files
-        self.module_files = module_files;
 
         Ok(())
     }
@@ -211,32 +473,17 @@ impl<'a, 'ctx> Compiler<'a, 'ctx> {
         let func_value = module.add_function("main", func_type, None);
         let basic_block = self.context.append_basic_block(func_value, FUNC_ENTRY);
 
-        // TODO: We don't need this anymore
+        self.builder.position_at_end(basic_block);
         if let Some(func_def) = main {
             info!("compile fn @main");
-            let func_name = func_def.function_id.name();
-            let func_name_str = func_name.as_str();
-            // lookup :main func value
-            if let Some(_func_main) = module.get_function(func_name_str) {
-                // TODO:
-                // 1. copy input args as func_main input args
-                // 2. return func_main call as func return
-                // let argsv = vec![];
-                // match self
-                //     .builder
-                //     .build_call(func_main, argsv.as_slice(), "tmp")
-                //     .try_as_basic_value()
-                //     .left()
-                // {
-                //     Some(value) => Ok(value.into_float_value()),
-                //     None => Err("Invalid call produced."),
-                // };
-            } else {
-                bail!("Missing pkg :main function module function");
+            let func_main = self.get_or_declare_function(module, func_def)?;
+            // :main args aren't passed from the command line yet
+            let mut args: Vec<BasicMetadataValueEnum> = vec![];
+            for arg in &func_def.args {
+                args.push(self.basic_type(&arg.type_id)?.const_zero().into());
             }
+            self.builder.build_call(func_main, &args, "main");
         }
-
-        self.builder.position_at_end(basic_block);
         self.builder
             .build_return(Some(&i64_type.const_int(0, false)));
 
@@ -253,14 +500,21 @@ impl<'a, 'ctx> Compiler<'a, 'ctx> {
     // Compile all module functions
     fn compile_functions(&self, module: &mut Module<'ctx>, module_def: &ModuleDef) -> Result<()> {
         let mut func_defs_results = vec![];
-        module_def.function_defs.iter().try_fold(
-            &mut func_defs_results,
-            |acc, func_def| -> Result<&mut Vec<()>> {
-                let result = self.compile_function(module, module_def, func_def)?;
-                acc.push(result);
-                Ok(acc)
-            },
-        )?;
+        // generic functions are compiled as instances
+        // and tests are only compiled into the test harness
+        module_def
+            .function_defs
+            .iter()
+            .filter(|func_def| func_def.generics.is_empty())
+            .filter(|func_def| self.opts.test || !module_def.is_test(&func_def.function_id))
+            .try_fold(
+                &mut func_defs_results,
+                |acc, func_def| -> Result<&mut Vec<()>> {
+                    let result = self.compile_function(module, module_def, func_def)?;
+                    acc.push(result);
+                    Ok(acc)
+                },
+            )?;
         Ok(())
     }
 
@@ -272,31 +526,10 @@ impl<'a, 'ctx> Compiler<'a, 'ctx> {
         func_def: &FunctionDef,
     ) -> Result<()> {
         info!("compile fn {}", func_def.function_id.name());
-        let func_name = func_def.function_id.name();
-        let func_name_str = func_name.as_str();
-
-        // define return/input type signatures
-        let i64_type = self.context.i64_type();
-        let void_type = self.context.void_type();
-
-        // This creates the type signature in the form of
-        // `return_type (arg_type, ...)`
-        // let func_type =
-        //     i64_type.fn_type(&[i64_type.into(), i64_type.into(), i64_type.into()], false);
-        let func_type =
-            void_type.fn_type(&[i64_type.into(), i64_type.into(), i64_type.into()], false);
-        info!("func_type: {:?}", &func_type);
-
-        let func_value = module.add_function(func_name_str, func_type, None);
+        let func_value = self.get_or_declare_function(module, func_def)?;
         let basic_block = self.context.append_basic_block(func_value, FUNC_ENTRY);
-
         self.builder.position_at_end(basic_block);
-        self.builder.build_return(None);
-        // self.builder.build_unreachable();
-        // self.builder
-        //     .build_return(Some(&i64_type.const_int(0, false)));
-
-        Ok(())
+        self.compile_function_body(module, func_def, func_value)
     }
 
     fn compile_structs(&self, _module: &mut Module, module_def: &ModuleDef) -> Result<()> {
@@ -0,0 +1,364 @@
+use super::debug::DebugScope;
+use super::drop::ownership;
+use super::Compiler;
+use crate::hir::{Block, ForInDef, FunctionDef, If};
+use anyhow::{bail, Result};
+use inkwell::module::Module;
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::{BasicValueEnum, FunctionValue, PointerValue};
+use std::collections::{HashMap, HashSet};
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+use yak_lexer::Span;
+
+// Local variable storage
+#[derive(Debug, Clone)]
+pub(crate) struct Local<'ctx> {
+    pub ptr: PointerValue<'ctx>,
+    pub type_id: TypeId,
+}
+
+// Function compile state
+pub(crate) struct FunctionScope<'ctx> {
+    pub func_value: FunctionValue<'ctx>,
+    // used by `?` to build early returns
+    pub return_type: Option<TypeId>,
+    // the source file reported by runtime panics
+    pub file: String,
+    pub frames: Vec<HashMap<String, Local<'ctx>>>,
+    // Map, Set, List, trait object and closure locals
+    // (and boxed locals) freed at the end of their frame
+    pub owned: HashSet<String>,
+    // locals allocated with malloc instead of alloca (see `Ownership`)
+    pub boxed: HashSet<String>,
+    // the statement being compiled (reported by panics in
+    // expressions without a span, i.e. `list[index]`)
+    pub span: Span,
+    // None without debug info
+    pub debug: Option<DebugScope<'ctx>>,
+}
+
+impl<'ctx> FunctionScope<'ctx> {
+    pub fn new(func_value: FunctionValue<'ctx>, return_type: Option<TypeId>, file: String) -> Self {
+        FunctionScope {
+            func_value,
+            return_type,
+            file,
+            frames: vec![HashMap::new()],
+            owned: HashSet::new(),
+            boxed: HashSet::new(),
+            span: Span::default(),
+            debug: None,
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, local: Local<'ctx>) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.insert(name.into(), local);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Local<'ctx>> {
+        self.frames.iter().rev().find_map(|frame| frame.get(name))
+    }
+}
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Allocas are created in the entry block so loops don't grow the stack
+    pub(crate) fn build_entry_alloca(
+        &self,
+        scope: &FunctionScope<'ctx>,
+        ty: BasicTypeEnum<'ctx>,
+        name: &str,
+    ) -> Result<PointerValue<'ctx>> {
+        let builder = self.context.create_builder();
+        let entry = match scope.func_value.get_first_basic_block() {
+            Some(entry) => entry,
+            None => bail!("function is missing an entry block"),
+        };
+        match entry.get_first_instruction() {
+            Some(instruction) => builder.position_before(&instruction),
+            None => builder.position_at_end(entry),
+        }
+        Ok(builder.build_alloca(ty, name))
+    }
+
+    // Stores a value in a new local variable
+    pub(crate) fn build_local(
+        &self,
+        scope: &mut FunctionScope<'ctx>,
+        name: &str,
+        type_id: &TypeId,
+        value: BasicValueEnum<'ctx>,
+    ) -> Result<()> {
+        self.build_var(scope, name, type_id, value, None)
+    }
+
+    // Stores a function param (`arg_no` starts at 1) in a new local variable
+    pub(crate) fn build_arg(
+        &self,
+        scope: &mut FunctionScope<'ctx>,
+        arg_no: u32,
+        name: &str,
+        type_id: &TypeId,
+        value: BasicValueEnum<'ctx>,
+    ) -> Result<()> {
+        self.build_var(scope, name, type_id, value, Some(arg_no))
+    }
+
+    fn build_var(
+        &self,
+        scope: &mut FunctionScope<'ctx>,
+        name: &str,
+        type_id: &TypeId,
+        value: BasicValueEnum<'ctx>,
+        arg_no: Option<u32>,
+    ) -> Result<()> {
+        let ptr = if arg_no.is_none() && scope.boxed.contains(name) {
+            match self.builder.build_malloc(self.basic_type(type_id)?, name) {
+                Ok(ptr) => ptr,
+                Err(err) => bail!("unable to allocate {}: {}", name, err),
+            }
+        } else {
+            self.build_entry_alloca(scope, self.basic_type(type_id)?, name)?
+        };
+        self.builder.build_store(ptr, value);
+        self.declare_variable(scope, name, type_id, ptr, arg_no)?;
+        scope.insert(
+            name,
+            Local {
+                ptr,
+                type_id: type_id.clone(),
+            },
+        );
+        Ok(())
+    }
+
+    // Returns true if the current block already ends with a terminator
+    pub(crate) fn is_terminated(&self) -> bool {
+        self.builder
+            .get_insert_block()
+            .and_then(|block| block.get_terminator())
+            .is_some()
+    }
+
+    pub(crate) fn compile_function_body(
+        &self,
+        module: &Module<'ctx>,
+        func_def: &FunctionDef,
+        func_value: FunctionValue<'ctx>,
+    ) -> Result<()> {
+        let file = match &func_def.file {
+            Some(file) => file.display().to_string(),
+            None => "<unknown>".into(),
+        };
+        let mut scope = FunctionScope::new(func_value, func_def.return_type.clone(), file);
+        scope.debug = self.debug_function(func_value, func_def)?;
+        let ownership = ownership(func_def);
+        scope.owned = ownership.owned;
+        scope.boxed = ownership.boxed;
+        self.build_frame_push(module, &func_def.function_id.name());
+        for (i, arg) in func_def.args.iter().enumerate() {
+            let param = match func_value.get_nth_param(i as u32) {
+                Some(param) => param,
+                None => bail!("missing function param {}", &arg.arg_id.arg_name),
+            };
+            let arg_no = i as u32 + 1;
+            self.build_arg(
+                &mut scope,
+                arg_no,
+                &arg.arg_id.arg_name,
+                &arg.type_id,
+                param,
+            )?;
+        }
+        self.compile_blocks(module, &mut scope, &func_def.blocks)?;
+        if !self.is_terminated() {
+            match &func_def.return_type {
+                None => self.build_function_return(module, None),
+                // the checker verified explicit returns
+                Some(_) => {
+                    self.builder.build_unreachable();
+                }
+            }
+        }
+        // functions without debug info must not reuse the location
+        self.builder.unset_current_debug_location();
+        Ok(())
+    }
+
+    pub(crate) fn compile_blocks(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        blocks: &[Block],
+    ) -> Result<()> {
+        scope.frames.push(HashMap::new());
+        for block in blocks {
+            // skip dead code after a return
+            if self.is_terminated() {
+                break;
+            }
+            self.compile_block(module, scope, block)?;
+        }
+        if !self.is_terminated() {
+            self.build_frame_drops(module, scope);
+        }
+        scope.frames.pop();
+        Ok(())
+    }
+
+    fn compile_block(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        block: &Block,
+    ) -> Result<()> {
+        self.set_debug_location(scope, &block.span());
+        match block {
+            Block::Let(let_def) => {
+                let value = self.compile_value(module, scope, &let_def.value)?;
+                let type_id = match &let_def.type_id {
+                    Some(type_id) => type_id,
+                    None => bail!("let {} is missing a type", &let_def.var_name),
+                };
+                self.build_local(scope, &let_def.var_name, type_id, value)?;
+            }
+            Block::Assign(assign_def) => {
+                let value = self.compile_value(module, scope, &assign_def.value)?;
+                let local = match scope.get(&assign_def.var_name) {
+                    Some(local) => local,
+                    None => bail!("unknown variable {}", &assign_def.var_name),
+                };
+                // walk the field path (i.e. `p.x.y = 1`)
+                let mut ptr = local.ptr;
+                let mut type_id = local.type_id.clone();
+                for field_name in &assign_def.fields {
+                    let index = self.field_index(&type_id, field_name)?;
+                    ptr = match self.builder.build_struct_gep(ptr, index, field_name) {
+                        Ok(ptr) => ptr,
+                        Err(_) => bail!("unable to get field {}", field_name),
+                    };
+                    type_id = if type_id.is_tuple() {
+                        type_id.generics[index as usize].clone()
+                    } else {
+                        match self.hir.find_struct(&type_id) {
+                            Some(struct_def) => struct_def.fields[index as usize].type_id.clone(),
+                            None => bail!("unknown struct {}", type_id.name()),
+                        }
+                    };
+                }
+                self.builder.build_store(ptr, value);
+            }
+            Block::Expr(expr) => {
+                self.compile_expr(module, scope, expr)?;
+            }
+            Block::If(if_def) => self.compile_if(module, scope, if_def)?,
+            Block::ForIn(for_def) => self.compile_for_in(module, scope, for_def)?,
+            Block::Return(value) => match value {
+                Some(value) => {
+                    let value = self.compile_value(module, scope, value)?;
+                    self.build_return_drops(module, scope);
+                    self.build_function_return(module, Some(value));
+                }
+                None => {
+                    self.build_return_drops(module, scope);
+                    self.build_function_return(module, None);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn compile_if(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        if_def: &If,
+    ) -> Result<()> {
+        let func_value = scope.func_value;
+        let merge_block = self.context.append_basic_block(func_value, "if_end");
+        for condition in &if_def.conditions {
+            // elif conditions are on their own lines
+            self.set_debug_location(scope, &condition.expr.span);
+            let cond = self
+                .compile_value(module, scope, &condition.expr)?
+                .into_int_value();
+            let then_block = self.context.append_basic_block(func_value, "if_then");
+            let next_block = self.context.append_basic_block(func_value, "if_next");
+            self.builder
+                .build_conditional_branch(cond, then_block, next_block);
+
+            self.builder.position_at_end(then_block);
+            self.compile_blocks(module, scope, &condition.blocks)?;
+            if !self.is_terminated() {
+                self.builder.build_unconditional_branch(merge_block);
+            }
+            self.builder.position_at_end(next_block);
+        }
+        if let Some(else_blocks) = &if_def.else_blocks {
+            self.compile_blocks(module, scope, else_blocks)?;
+        }
+        if !self.is_terminated() {
+            self.builder.build_unconditional_branch(merge_block);
+        }
+        self.builder.position_at_end(merge_block);
+        Ok(())
+    }
+
+    // Iterates a Map or Set using the runtime iterator
+    fn compile_for_in(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        for_def: &ForInDef,
+    ) -> Result<()> {
+        let iter_type = match &for_def.iter.type_id {
+            Some(iter_type) => iter_type.clone(),
+            None => bail!("for statement iterator is missing a type"),
+        };
+        let collection = self
+            .compile_value(module, scope, &for_def.iter)?
+            .into_pointer_value();
+
+        let i64_type = self.context.i64_type();
+        let key_ptr = self.build_entry_alloca(scope, i64_type.into(), "iter_key")?;
+        let value_ptr = self.build_entry_alloca(scope, i64_type.into(), "iter_value")?;
+        let iter = self.build_runtime_iter(module, collection, &iter_type);
+
+        let func_value = scope.func_value;
+        let cond_block = self.context.append_basic_block(func_value, "for_cond");
+        let body_block = self.context.append_basic_block(func_value, "for_body");
+        let end_block = self.context.append_basic_block(func_value, "for_end");
+        self.builder.build_unconditional_branch(cond_block);
+
+        self.builder.position_at_end(cond_block);
+        let has_next = self.build_runtime_iter_next(module, iter, key_ptr, value_ptr, &iter_type);
+        self.builder
+            .build_conditional_branch(has_next, body_block, end_block);
+
+        self.builder.position_at_end(body_block);
+        scope.frames.push(HashMap::new());
+        let word_ptrs = [key_ptr, value_ptr];
+        for (i, var) in for_def.vars.iter().enumerate() {
+            let type_id = &iter_type.generics[i];
+            let word = self
+                .builder
+                .build_load(word_ptrs[i], "iter_word")
+                .into_int_value();
+            let value = self.unbox_word(word, type_id)?;
+            self.build_local(scope, var, type_id, value)?;
+        }
+        self.compile_blocks(module, scope, &for_def.blocks)?;
+        scope.frames.pop();
+        // step back to the for statement between iterations
+        self.set_debug_location(scope, &for_def.span);
+        if !self.is_terminated() {
+            self.builder.build_unconditional_branch(cond_block);
+        }
+
+        self.builder.position_at_end(end_block);
+        self.build_runtime_iter_free(module, iter, &iter_type);
+        Ok(())
+    }
+}
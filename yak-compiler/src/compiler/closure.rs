@@ -0,0 +1,245 @@
+use super::block::{FunctionScope, Local};
+use super::Compiler;
+use crate::hir::{CallValue, ClosureValue};
+use anyhow::{bail, Result};
+use inkwell::module::{Linkage, Module};
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType, StructType};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, CallableValue, FunctionValue, StructValue,
+};
+use inkwell::AddressSpace;
+use std::convert::TryFrom;
+use yak_core::types::types::TypeId;
+
+// Function value field indexes
+const FUNC_INDEX: u32 = 0;
+const ENV_INDEX: u32 = 1;
+
+// llvm label for function start
+const CLOSURE_ENTRY: &str = "enter";
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Function values are `{ i8*, i8* }`
+    // (the closure function and its environment)
+    pub(crate) fn function_value_type(&self) -> StructType<'ctx> {
+        let i8_ptr_type = self.i8_ptr_type();
+        self.context
+            .struct_type(&[i8_ptr_type.into(), i8_ptr_type.into()], false)
+    }
+
+    // Closure functions receive their environment as `i8*`
+    // before the function args
+    fn closure_fn_type(&self, type_id: &TypeId) -> Result<FunctionType<'ctx>> {
+        let mut arg_types: Vec<BasicMetadataTypeEnum<'ctx>> = vec![self.i8_ptr_type().into()];
+        for (_, arg_type) in type_id.function_args() {
+            arg_types.push(self.basic_type(&arg_type)?.into());
+        }
+        let func_type = match type_id.function_return() {
+            Some(return_type) => self.basic_type(&return_type)?.fn_type(&arg_types, false),
+            None => self.context.void_type().fn_type(&arg_types, false),
+        };
+        Ok(func_type)
+    }
+
+    // Environments hold the captured values (by value)
+    // or pointers to the captured locals (by reference)
+    fn env_type(&self, closure: &ClosureValue) -> Result<StructType<'ctx>> {
+        let mut field_types: Vec<BasicTypeEnum<'ctx>> = vec![];
+        for capture in &closure.captures {
+            let capture_type = self.basic_type(&capture.type_id)?;
+            if capture.by_ref {
+                field_types.push(capture_type.ptr_type(AddressSpace::default()).into());
+            } else {
+                field_types.push(capture_type);
+            }
+        }
+        Ok(self.context.struct_type(&field_types, false))
+    }
+
+    // Copies the captures to a heap environment and pairs
+    // it with the compiled closure function
+    pub(crate) fn build_closure(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        closure: &ClosureValue,
+        type_id: &TypeId,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let env_type = self.env_type(closure)?;
+        let env = if closure.captures.is_empty() {
+            self.i8_ptr_type().const_null()
+        } else {
+            let mut env_value = env_type.get_undef();
+            for (index, capture) in closure.captures.iter().enumerate() {
+                let local = match scope.get(&capture.var_name) {
+                    Some(local) => local,
+                    None => bail!("unknown variable {}", &capture.var_name),
+                };
+                let value: BasicValueEnum<'ctx> = if capture.by_ref {
+                    local.ptr.into()
+                } else {
+                    self.builder.build_load(local.ptr, &capture.var_name)
+                };
+                env_value = self.insert_field(env_value, value, index as u32)?;
+            }
+            let ptr = match self.builder.build_malloc(env_type, "env") {
+                Ok(ptr) => ptr,
+                Err(err) => bail!("unable to allocate a closure environment: {}", err),
+            };
+            self.builder.build_store(ptr, env_value);
+            self.builder
+                .build_pointer_cast(ptr, self.i8_ptr_type(), "env")
+        };
+        let func_value = self.build_closure_function(module, scope, closure, type_id, env_type)?;
+        let func_ptr = self.builder.build_pointer_cast(
+            func_value.as_global_value().as_pointer_value(),
+            self.i8_ptr_type(),
+            "func",
+        );
+        let value = self.function_value_type().get_undef();
+        let value = self.insert_field(value, func_ptr.into(), FUNC_INDEX)?;
+        let value = self.insert_field(value, env.into(), ENV_INDEX)?;
+        Ok(value.into())
+    }
+
+    // Frees the environment of a function value (null without captures)
+    pub(crate) fn build_closure_free(&self, value: StructValue<'ctx>) {
+        if let Some(env) = self.builder.build_extract_value(value, ENV_INDEX, "env") {
+            self.builder.build_free(env.into_pointer_value());
+        }
+    }
+
+    // Closures are private functions named after the function
+    // which defines them (i.e. `pkg:main$closure0`)
+    fn build_closure_function(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        closure: &ClosureValue,
+        type_id: &TypeId,
+        env_type: StructType<'ctx>,
+    ) -> Result<FunctionValue<'ctx>> {
+        let parent = scope.func_value.get_name().to_string_lossy().to_string();
+        let mut num = 0;
+        let mut func_name = format!("{}$closure{}", parent, num);
+        while module.get_function(&func_name).is_some() {
+            num += 1;
+            func_name = format!("{}$closure{}", parent, num);
+        }
+        let func_type = self.closure_fn_type(type_id)?;
+        let func_value = module.add_function(&func_name, func_type, Some(Linkage::Private));
+
+        let saved_block = self.builder.get_insert_block();
+        let saved_location = self.suspend_debug_location();
+        let entry = self.context.append_basic_block(func_value, CLOSURE_ENTRY);
+        self.builder.position_at_end(entry);
+        let mut closure_scope =
+            FunctionScope::new(func_value, closure.return_type.clone(), scope.file.clone());
+        closure_scope.debug = self.debug_closure(func_value, &func_name, scope, type_id)?;
+        self.build_frame_push(module, &func_name);
+        if !closure.captures.is_empty() {
+            let env = match func_value.get_nth_param(0) {
+                Some(env) => env.into_pointer_value(),
+                None => bail!("closure {} is missing its environment", &func_name),
+            };
+            let env = self.builder.build_pointer_cast(
+                env,
+                env_type.ptr_type(AddressSpace::default()),
+                "env",
+            );
+            let env = self.builder.build_load(env, "env").into_struct_value();
+            for (index, capture) in closure.captures.iter().enumerate() {
+                let value = self.extract_field(env, index as u32)?;
+                if capture.by_ref {
+                    closure_scope.insert(
+                        &capture.var_name,
+                        Local {
+                            ptr: value.into_pointer_value(),
+                            type_id: capture.type_id.clone(),
+                        },
+                    );
+                } else {
+                    self.build_local(
+                        &mut closure_scope,
+                        &capture.var_name,
+                        &capture.type_id,
+                        value,
+                    )?;
+                }
+            }
+        }
+        for (index, arg) in closure.args.iter().enumerate() {
+            let param = match func_value.get_nth_param(index as u32 + 1) {
+                Some(param) => param,
+                None => bail!("missing closure param {}", &arg.arg_id.arg_name),
+            };
+            self.build_arg(
+                &mut closure_scope,
+                index as u32 + 1,
+                &arg.arg_id.arg_name,
+                &arg.type_id,
+                param,
+            )?;
+        }
+        let value = self.compile_expr(module, &mut closure_scope, &closure.body)?;
+        if !self.is_terminated() {
+            match (&closure.return_type, value) {
+                (Some(_), Some(value)) => self.build_function_return(module, Some(value)),
+                (None, _) => self.build_function_return(module, None),
+                (Some(_), None) => bail!("closure {} doesn't produce a value", &func_name),
+            }
+        }
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        self.builder.unset_current_debug_location();
+        self.restore_debug_location(saved_location);
+        Ok(func_value)
+    }
+
+    // Calls the function value stored in a local
+    // with its environment as the first arg
+    pub(crate) fn compile_value_call(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        call: &CallValue,
+        value_type: &TypeId,
+    ) -> Result<Option<BasicValueEnum<'ctx>>> {
+        let var_name = call.func_name.trim_start_matches(':');
+        let local = match scope.get(var_name) {
+            Some(local) => local.clone(),
+            None => bail!("unknown variable {}", var_name),
+        };
+        let value = self
+            .builder
+            .build_load(local.ptr, var_name)
+            .into_struct_value();
+        let func_ptr = self.extract_field(value, FUNC_INDEX)?.into_pointer_value();
+        let mut args: Vec<BasicMetadataValueEnum<'ctx>> =
+            vec![self.extract_field(value, ENV_INDEX)?.into()];
+        for (arg_name, _) in value_type.function_args() {
+            let call_arg = match call
+                .args
+                .iter()
+                .find(|call_arg| call_arg.arg_name == arg_name)
+            {
+                Some(call_arg) => call_arg,
+                None => bail!("missing argument {}", arg_name),
+            };
+            args.push(self.compile_value(module, scope, &call_arg.value)?.into());
+        }
+        let func_ptr = self.builder.build_pointer_cast(
+            func_ptr,
+            self.closure_fn_type(value_type)?
+                .ptr_type(AddressSpace::default()),
+            "func",
+        );
+        let callable = match CallableValue::try_from(func_ptr) {
+            Ok(callable) => callable,
+            Err(_) => bail!("{} isn't a function", var_name),
+        };
+        let call_site = self.builder.build_call(callable, &args, "call");
+        Ok(call_site.try_as_basic_value().left())
+    }
+}
@@ -0,0 +1,550 @@
+use super::block::FunctionScope;
+use super::types::{int_width, is_signed};
+use super::Compiler;
+use crate::check::display_type;
+use crate::hir::{FunctionDef, ModuleDef};
+use anyhow::{bail, Result};
+use inkwell::debug_info::{
+    AsDIScope, DICompileUnit, DIFlags, DIFlagsConstants, DILocation, DISubprogram, DIType,
+    DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::{FlagBehavior, Module};
+use inkwell::types::{BasicTypeEnum, StructType};
+use inkwell::values::{FunctionValue, PointerValue};
+use inkwell::AddressSpace;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+use yak_lexer::Span;
+
+// gdb and lldb both read DWARF 4
+const DWARF_VERSION: u64 = 4;
+
+// debug metadata version expected by llvm
+const DEBUG_METADATA_VERSION: u64 = 3;
+
+// DWARF base type encodings
+const DW_ATE_BOOLEAN: u32 = 0x02;
+const DW_ATE_FLOAT: u32 = 0x04;
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_SIGNED_CHAR: u32 = 0x06;
+const DW_ATE_UNSIGNED: u32 = 0x07;
+const DW_ATE_UNSIGNED_CHAR: u32 = 0x08;
+const DW_ATE_UTF: u32 = 0x10;
+
+// A compile unit for one source file
+pub(crate) struct DebugUnit<'ctx> {
+    pub file: String,
+    pub builder: DebugInfoBuilder<'ctx>,
+    pub compile_unit: DICompileUnit<'ctx>,
+}
+
+// Debug info for the module being compiled
+pub(crate) struct DebugInfo<'ctx> {
+    pub units: Vec<DebugUnit<'ctx>>,
+    // described types by type name
+    types: RefCell<HashMap<String, DIType<'ctx>>>,
+}
+
+// The subprogram of a function and the line being compiled
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DebugScope<'ctx> {
+    // index of the compile unit for the function's file
+    pub unit: usize,
+    pub subprogram: DISubprogram<'ctx>,
+    pub line: u32,
+}
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Creates a compile unit for every source file with functions in the module
+    pub(crate) fn create_debug_info(
+        &self,
+        module: &Module<'ctx>,
+        module_def: &ModuleDef,
+    ) -> Option<DebugInfo<'ctx>> {
+        if !self.opts.debug_info {
+            return None;
+        }
+        let i32_type = self.context.i32_type();
+        module.add_basic_value_flag(
+            "Dwarf Version",
+            FlagBehavior::Warning,
+            i32_type.const_int(DWARF_VERSION, false),
+        );
+        module.add_basic_value_flag(
+            "Debug Info Version",
+            FlagBehavior::Warning,
+            i32_type.const_int(DEBUG_METADATA_VERSION, false),
+        );
+        let producer = format!("yak-compiler {}", env!("CARGO_PKG_VERSION"));
+        let is_optimized = self.opts.opt_level != super::OptLevel::O0;
+        let mut units: Vec<DebugUnit<'ctx>> = vec![];
+        for file in module_def
+            .function_defs
+            .iter()
+            .filter_map(|func_def| func_def.file.as_ref())
+        {
+            let file = file.display().to_string();
+            if units.iter().any(|unit| unit.file == file) {
+                continue;
+            }
+            // debuggers look sources up by directory and file name
+            let path = match std::env::current_dir() {
+                Ok(cwd) => cwd.join(&file),
+                Err(_) => Path::new(&file).to_path_buf(),
+            };
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let directory = path
+                .parent()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default();
+            let (builder, compile_unit) = module.create_debug_info_builder(
+                true,
+                DWARFSourceLanguage::C,
+                &file_name,
+                &directory,
+                &producer,
+                is_optimized,
+                "",
+                0,
+                "",
+                DWARFEmissionKind::Full,
+                0,
+                false,
+                false,
+                "",
+                "",
+            );
+            units.push(DebugUnit {
+                file,
+                builder,
+                compile_unit,
+            });
+        }
+        Some(DebugInfo {
+            units,
+            types: RefCell::new(HashMap::new()),
+        })
+    }
+
+    // Resolves the module debug info (this has to happen before verifying)
+    pub(crate) fn finalize_debug_info(&mut self) {
+        if let Some(debug_info) = self.debug_info.take() {
+            for unit in &debug_info.units {
+                unit.builder.finalize();
+            }
+        }
+    }
+
+    // Creates the subprogram for a function defined in a source file
+    // and sets the location to the function name
+    pub(crate) fn debug_function(
+        &self,
+        func_value: FunctionValue<'ctx>,
+        func_def: &FunctionDef,
+    ) -> Result<Option<DebugScope<'ctx>>> {
+        let debug_info = match &self.debug_info {
+            Some(debug_info) => debug_info,
+            None => return Ok(None),
+        };
+        let file = match &func_def.file {
+            Some(file) => file.display().to_string(),
+            None => return Ok(None),
+        };
+        let unit = match debug_info.units.iter().position(|unit| unit.file == file) {
+            Some(unit) => unit,
+            None => bail!("missing compile unit for {}", &file),
+        };
+        let arg_types: Vec<TypeId> = func_def
+            .args
+            .iter()
+            .map(|arg| arg.type_id.clone())
+            .collect();
+        let scope = self.debug_subprogram(
+            func_value,
+            unit,
+            &func_def.function_id.name(),
+            func_def.span.ln as u32,
+            &arg_types,
+            func_def.return_type.as_ref(),
+        )?;
+        Ok(Some(scope))
+    }
+
+    // Closures are described as functions defined on the current line
+    pub(crate) fn debug_closure(
+        &self,
+        func_value: FunctionValue<'ctx>,
+        func_name: &str,
+        parent: &FunctionScope<'ctx>,
+        type_id: &TypeId,
+    ) -> Result<Option<DebugScope<'ctx>>> {
+        let parent = match parent.debug {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+        let arg_types: Vec<TypeId> = type_id
+            .function_args()
+            .into_iter()
+            .map(|(_, arg_type)| arg_type)
+            .collect();
+        let scope = self.debug_subprogram(
+            func_value,
+            parent.unit,
+            func_name,
+            parent.line,
+            &arg_types,
+            type_id.function_return().as_ref(),
+        )?;
+        Ok(Some(scope))
+    }
+
+    fn debug_subprogram(
+        &self,
+        func_value: FunctionValue<'ctx>,
+        unit: usize,
+        name: &str,
+        line: u32,
+        arg_types: &[TypeId],
+        return_type: Option<&TypeId>,
+    ) -> Result<DebugScope<'ctx>> {
+        let debug_unit = self.debug_unit(unit)?;
+        let file = debug_unit.compile_unit.get_file();
+        let mut param_types = vec![];
+        for arg_type in arg_types {
+            param_types.push(self.debug_type(unit, arg_type)?);
+        }
+        let return_type = match return_type {
+            Some(return_type) => Some(self.debug_type(unit, return_type)?),
+            None => None,
+        };
+        let subroutine_type = debug_unit.builder.create_subroutine_type(
+            file,
+            return_type,
+            &param_types,
+            DIFlags::ZERO,
+        );
+        let subprogram = debug_unit.builder.create_function(
+            debug_unit.compile_unit.as_debug_info_scope(),
+            name,
+            None,
+            file,
+            line,
+            subroutine_type,
+            true,
+            true,
+            line,
+            DIFlags::ZERO,
+            self.opts.opt_level != super::OptLevel::O0,
+        );
+        func_value.set_subprogram(subprogram);
+        let scope = DebugScope {
+            unit,
+            subprogram,
+            line,
+        };
+        self.set_debug_line(&scope, line, 0);
+        Ok(scope)
+    }
+
+    // Sets the location of the following instructions
+    // (spans without a line keep the current location)
+    pub(crate) fn set_debug_location(&self, scope: &mut FunctionScope<'ctx>, span: &Span) {
+        if span.ln == 0 {
+            return;
+        }
+        scope.span = *span;
+        if let Some(debug) = scope.debug.as_mut() {
+            debug.line = span.ln as u32;
+            self.set_debug_line(debug, span.ln as u32, span.start as u32);
+        }
+    }
+
+    fn set_debug_line(&self, scope: &DebugScope<'ctx>, line: u32, column: u32) {
+        if let Some(location) = self.debug_location(scope, line, column) {
+            self.builder
+                .set_current_debug_location(self.context, location);
+        }
+    }
+
+    fn debug_location(
+        &self,
+        scope: &DebugScope<'ctx>,
+        line: u32,
+        column: u32,
+    ) -> Option<DILocation<'ctx>> {
+        let debug_unit = self.debug_unit(scope.unit).ok()?;
+        Some(debug_unit.builder.create_debug_location(
+            self.context,
+            line,
+            column,
+            scope.subprogram.as_debug_info_scope(),
+            None,
+        ))
+    }
+
+    // Unsets the location while building helper functions (i.e. thunks)
+    // and returns the location to restore afterwards
+    pub(crate) fn suspend_debug_location(&self) -> Option<DILocation<'ctx>> {
+        self.debug_info.as_ref()?;
+        let location = self.builder.get_current_debug_location();
+        self.builder.unset_current_debug_location();
+        location
+    }
+
+    pub(crate) fn restore_debug_location(&self, location: Option<DILocation<'ctx>>) {
+        if let Some(location) = location {
+            self.builder
+                .set_current_debug_location(self.context, location);
+        }
+    }
+
+    // Describes a local variable (or function param if `arg_no` is set)
+    // stored at `ptr` on the current line
+    pub(crate) fn declare_variable(
+        &self,
+        scope: &FunctionScope<'ctx>,
+        name: &str,
+        type_id: &TypeId,
+        ptr: PointerValue<'ctx>,
+        arg_no: Option<u32>,
+    ) -> Result<()> {
+        let debug = match &scope.debug {
+            Some(debug) => debug,
+            None => return Ok(()),
+        };
+        let block = match self.builder.get_insert_block() {
+            Some(block) => block,
+            None => bail!("variable {} is declared outside of a block", name),
+        };
+        let debug_unit = self.debug_unit(debug.unit)?;
+        let file = debug_unit.compile_unit.get_file();
+        let ty = self.debug_type(debug.unit, type_id)?;
+        let var_scope = debug.subprogram.as_debug_info_scope();
+        let var_info = match arg_no {
+            Some(arg_no) => debug_unit.builder.create_parameter_variable(
+                var_scope,
+                name,
+                arg_no,
+                file,
+                debug.line,
+                ty,
+                true,
+                DIFlags::ZERO,
+            ),
+            None => debug_unit.builder.create_auto_variable(
+                var_scope,
+                name,
+                file,
+                debug.line,
+                ty,
+                true,
+                DIFlags::ZERO,
+                0,
+            ),
+        };
+        let location = match self.debug_location(debug, debug.line, 0) {
+            Some(location) => location,
+            None => bail!("missing debug location for variable {}", name),
+        };
+        debug_unit.builder.insert_declare_at_end(
+            ptr,
+            Some(var_info),
+            Some(debug_unit.builder.create_expression(vec![])),
+            location,
+            block,
+        );
+        Ok(())
+    }
+
+    fn debug_unit(&self, unit: usize) -> Result<&DebugUnit<'ctx>> {
+        match self
+            .debug_info
+            .as_ref()
+            .and_then(|info| info.units.get(unit))
+        {
+            Some(debug_unit) => Ok(debug_unit),
+            None => bail!("missing compile unit {}", unit),
+        }
+    }
+
+    // Describes a yak type the way debuggers print it
+    pub(crate) fn debug_type(&self, unit: usize, type_id: &TypeId) -> Result<DIType<'ctx>> {
+        let key = type_id.name();
+        if let Some(debug_info) = &self.debug_info {
+            if let Some(ty) = debug_info.types.borrow().get(&key) {
+                return Ok(*ty);
+            }
+        }
+        let debug_unit = self.debug_unit(unit)?;
+        let name = display_type(type_id);
+        let basic_type = self.basic_type(type_id)?;
+        let size = self.debug_size(basic_type);
+        let ty = if let Some(width) = int_width(type_id) {
+            let encoding = match type_id.type_name.as_str() {
+                "bool" => DW_ATE_BOOLEAN,
+                "char" => DW_ATE_UTF,
+                "byte" | "uint8" => DW_ATE_UNSIGNED_CHAR,
+                _ if is_signed(type_id) => DW_ATE_SIGNED,
+                _ => DW_ATE_UNSIGNED,
+            };
+            // bools are stored in a byte
+            let size = if width == 1 { size } else { width as u64 };
+            self.debug_basic_type(debug_unit, &name, size, encoding)?
+        } else if type_id.is("float32") || type_id.is("float") || type_id.is("float64") {
+            self.debug_basic_type(debug_unit, &name, size, DW_ATE_FLOAT)?
+        } else if type_id.is("String") || type_id.is("str") {
+            // strings are c strings
+            let char_type = self.debug_basic_type(debug_unit, "char", 8, DW_ATE_SIGNED_CHAR)?;
+            self.debug_pointer_type(debug_unit, &name, char_type, size)
+        } else if type_id.is("Option") {
+            let generics = &type_id.generics;
+            let members = [("is_some", None), ("value", generics.first())];
+            self.debug_struct_type(unit, &name, basic_type, &members)?
+        } else if type_id.is("Maybe") {
+            let generics = &type_id.generics;
+            let members = [
+                ("is_ok", None),
+                ("value", generics.first()),
+                ("error", generics.get(1)),
+            ];
+            self.debug_struct_type(unit, &name, basic_type, &members)?
+        } else if type_id.is_trait() {
+            let members = [("data", None), ("vtable", None)];
+            self.debug_struct_type(unit, &name, basic_type, &members)?
+        } else if type_id.is_function() {
+            let members = [("func", None), ("env", None)];
+            self.debug_struct_type(unit, &name, basic_type, &members)?
+        } else if type_id.is_tuple() {
+            let field_names: Vec<String> = (0..type_id.generics.len())
+                .map(|index| index.to_string())
+                .collect();
+            let members: Vec<(&str, Option<&TypeId>)> = field_names
+                .iter()
+                .zip(type_id.generics.iter())
+                .map(|(field_name, element)| (field_name.as_str(), Some(element)))
+                .collect();
+            self.debug_struct_type(unit, &name, basic_type, &members)?
+        } else if let Some(struct_def) = self.hir.find_struct(type_id) {
+            let fields = struct_def.fields.clone();
+            let members: Vec<(&str, Option<&TypeId>)> = fields
+                .iter()
+                .map(|field| (field.field_id.field_name.as_str(), Some(&field.type_id)))
+                .collect();
+            self.debug_struct_type(unit, &name, basic_type, &members)?
+        } else {
+            // runtime handles (i.e. Map and Set) are opaque pointers
+            let byte_type = self.debug_basic_type(debug_unit, "byte", 8, DW_ATE_UNSIGNED_CHAR)?;
+            self.debug_pointer_type(debug_unit, &name, byte_type, size)
+        };
+        if let Some(debug_info) = &self.debug_info {
+            debug_info.types.borrow_mut().insert(key, ty);
+        }
+        Ok(ty)
+    }
+
+    fn debug_basic_type(
+        &self,
+        debug_unit: &DebugUnit<'ctx>,
+        name: &str,
+        size: u64,
+        encoding: u32,
+    ) -> Result<DIType<'ctx>> {
+        match debug_unit
+            .builder
+            .create_basic_type(name, size, encoding, DIFlags::ZERO)
+        {
+            Ok(basic_type) => Ok(basic_type.as_type()),
+            Err(err) => bail!("unable to describe type {}: {}", name, err),
+        }
+    }
+
+    fn debug_pointer_type(
+        &self,
+        debug_unit: &DebugUnit<'ctx>,
+        name: &str,
+        pointee: DIType<'ctx>,
+        size: u64,
+    ) -> DIType<'ctx> {
+        debug_unit
+            .builder
+            .create_pointer_type(name, pointee, size, 0, AddressSpace::default())
+            .as_type()
+    }
+
+    // Describes a struct with a member per llvm struct field
+    // (members without a yak type are opaque pointers)
+    fn debug_struct_type(
+        &self,
+        unit: usize,
+        name: &str,
+        basic_type: BasicTypeEnum<'ctx>,
+        members: &[(&str, Option<&TypeId>)],
+    ) -> Result<DIType<'ctx>> {
+        let struct_type: StructType<'ctx> = basic_type.into_struct_type();
+        let target_data = self.machine.get_target_data();
+        let mut elements = vec![];
+        for (index, (member_name, member_type)) in members.iter().enumerate() {
+            let field_type = match struct_type.get_field_type_at_index(index as u32) {
+                Some(field_type) => field_type,
+                None => bail!("type {} is missing field {}", name, member_name),
+            };
+            let ty = match member_type {
+                Some(member_type) => self.debug_type(unit, member_type)?,
+                None if field_type.is_int_type() => {
+                    let debug_unit = self.debug_unit(unit)?;
+                    self.debug_basic_type(debug_unit, "bool", 8, DW_ATE_BOOLEAN)?
+                }
+                None => {
+                    let debug_unit = self.debug_unit(unit)?;
+                    let byte_type =
+                        self.debug_basic_type(debug_unit, "byte", 8, DW_ATE_UNSIGNED_CHAR)?;
+                    let size = self.debug_size(field_type);
+                    self.debug_pointer_type(debug_unit, "ptr", byte_type, size)
+                }
+            };
+            let offset = target_data
+                .offset_of_element(&struct_type, index as u32)
+                .unwrap_or_default();
+            let debug_unit = self.debug_unit(unit)?;
+            let member = debug_unit.builder.create_member_type(
+                debug_unit.compile_unit.as_debug_info_scope(),
+                member_name,
+                debug_unit.compile_unit.get_file(),
+                0,
+                self.debug_size(field_type),
+                target_data.get_abi_alignment(&field_type) * 8,
+                offset * 8,
+                DIFlags::ZERO,
+                ty,
+            );
+            elements.push(member.as_type());
+        }
+        let debug_unit = self.debug_unit(unit)?;
+        let struct_ty = debug_unit.builder.create_struct_type(
+            debug_unit.compile_unit.as_debug_info_scope(),
+            name,
+            debug_unit.compile_unit.get_file(),
+            0,
+            self.debug_size(basic_type),
+            target_data.get_abi_alignment(&basic_type) * 8,
+            DIFlags::ZERO,
+            None,
+            &elements,
+            0,
+            None,
+            name,
+        );
+        Ok(struct_ty.as_type())
+    }
+
+    // The size of a type in bits
+    fn debug_size(&self, ty: BasicTypeEnum<'ctx>) -> u64 {
+        self.machine.get_target_data().get_abi_size(&ty) * 8
+    }
+}
@@ -0,0 +1,244 @@
+use super::block::{FunctionScope, Local};
+use super::Compiler;
+use crate::builtin::{is_collection, BuiltinMethod};
+use crate::hir::{Block, CallValue, ClosureValue, ExprKind, ExprValue, FunctionDef};
+use inkwell::module::Module;
+use std::collections::HashSet;
+
+// Local variables which are freed when they go out of scope
+#[derive(Debug, Default)]
+pub(crate) struct Ownership {
+    // Map, Set, List, trait object and closure locals (and boxed `let` variables)
+    pub owned: HashSet<String>,
+    // `let` variables captured by reference are heap allocated
+    // so the closures which capture them can outlive their frame
+    pub boxed: HashSet<String>,
+}
+
+// A closure literal and the variables it captures by reference
+// (`local` is the variable the closure is assigned to)
+struct ClosureRefs {
+    local: Option<String>,
+    by_ref: Vec<String>,
+}
+
+// A local is owned if every `let` of the name creates a new collection (or
+// boxes a value as a trait object or creates a closure) and the name is only
+// used as the receiver of builtin methods, trait object calls, as a `for`
+// iterator or called as a function value. Anything else (returns, args,
+// fields, captures, assignments) may share the handle (or box or closure
+// environment) so it's never freed. Boxed `let` variables are owned if
+// every closure which captures them is owned.
+pub(crate) fn ownership(func_def: &FunctionDef) -> Ownership {
+    let mut owned = HashSet::new();
+    let mut shared = HashSet::new();
+    let mut closures = vec![];
+    for arg in &func_def.args {
+        shared.insert(arg.arg_id.arg_name.clone());
+    }
+    visit_blocks(&func_def.blocks, &mut owned, &mut shared, &mut closures);
+    owned.retain(|name| !shared.contains(name));
+    let mut boxed = HashSet::new();
+    let mut escaping = HashSet::new();
+    for closure in &closures {
+        let is_owned = closure
+            .local
+            .as_ref()
+            .is_some_and(|local| owned.contains(local));
+        for var_name in &closure.by_ref {
+            boxed.insert(var_name.clone());
+            if !is_owned {
+                escaping.insert(var_name.clone());
+            }
+        }
+    }
+    owned.extend(boxed.difference(&escaping).cloned());
+    Ownership { owned, boxed }
+}
+
+fn visit_blocks(
+    blocks: &[Block],
+    owned: &mut HashSet<String>,
+    shared: &mut HashSet<String>,
+    closures: &mut Vec<ClosureRefs>,
+) {
+    for block in blocks {
+        match block {
+            Block::Let(let_def) => {
+                if is_new_collection(&let_def.value) || is_new_object(&let_def.value) {
+                    owned.insert(let_def.var_name.clone());
+                } else if let ExprKind::Closure(closure) = &let_def.value.kind {
+                    owned.insert(let_def.var_name.clone());
+                    visit_closure(closure, Some(&let_def.var_name), shared, closures);
+                    continue;
+                } else {
+                    shared.insert(let_def.var_name.clone());
+                }
+                visit_expr(&let_def.value, shared, closures);
+            }
+            Block::Assign(assign_def) => {
+                shared.insert(assign_def.var_name.clone());
+                visit_expr(&assign_def.value, shared, closures);
+            }
+            Block::Expr(expr) => visit_expr(expr, shared, closures),
+            Block::If(if_def) => {
+                for condition in &if_def.conditions {
+                    visit_expr(&condition.expr, shared, closures);
+                    visit_blocks(&condition.blocks, owned, shared, closures);
+                }
+                if let Some(else_blocks) = &if_def.else_blocks {
+                    visit_blocks(else_blocks, owned, shared, closures);
+                }
+            }
+            Block::ForIn(for_def) => {
+                shared.extend(for_def.vars.iter().cloned());
+                if !matches!(for_def.iter.kind, ExprKind::Var(_)) {
+                    visit_expr(&for_def.iter, shared, closures);
+                }
+                visit_blocks(&for_def.blocks, owned, shared, closures);
+            }
+            Block::Return(Some(value)) => visit_expr(value, shared, closures),
+            Block::Return(None) => {}
+        }
+    }
+}
+
+fn visit_closure(
+    closure: &ClosureValue,
+    local: Option<&String>,
+    shared: &mut HashSet<String>,
+    closures: &mut Vec<ClosureRefs>,
+) {
+    for capture in &closure.captures {
+        shared.insert(capture.var_name.clone());
+    }
+    closures.push(ClosureRefs {
+        local: local.cloned(),
+        by_ref: closure
+            .captures
+            .iter()
+            .filter(|capture| capture.by_ref)
+            .map(|capture| capture.var_name.clone())
+            .collect(),
+    });
+    visit_expr(&closure.body, shared, closures);
+}
+
+// Marks every variable used by the expression as shared
+// (except for builtin method and trait object call receivers)
+fn visit_expr(expr: &ExprValue, shared: &mut HashSet<String>, closures: &mut Vec<ClosureRefs>) {
+    match &expr.kind {
+        ExprKind::Var(name) => {
+            shared.insert(name.clone());
+        }
+        ExprKind::Call(call) => {
+            for arg in &call.args {
+                let is_receiver = arg.arg_name == "self"
+                    && (is_builtin_method(call) || is_object_call(call))
+                    && matches!(arg.value.kind, ExprKind::Var(_));
+                if !is_receiver {
+                    visit_expr(&arg.value, shared, closures);
+                }
+            }
+        }
+        ExprKind::Field(inner, _)
+        | ExprKind::Unary(_, inner)
+        | ExprKind::Try(inner)
+        | ExprKind::Object(inner)
+        | ExprKind::Cast(inner, _) => visit_expr(inner, shared, closures),
+        ExprKind::Binary(lhs, _, rhs) => {
+            visit_expr(lhs, shared, closures);
+            visit_expr(rhs, shared, closures);
+        }
+        ExprKind::Struct(struct_value) => {
+            for field in &struct_value.fields {
+                visit_expr(&field.value, shared, closures);
+            }
+        }
+        ExprKind::Collection(collection) => {
+            for item in &collection.items {
+                visit_expr(item, shared, closures);
+            }
+        }
+        ExprKind::Closure(closure) => visit_closure(closure, None, shared, closures),
+        ExprKind::None
+        | ExprKind::Bool(_)
+        | ExprKind::Int(_)
+        | ExprKind::Float(_)
+        | ExprKind::String(_)
+        | ExprKind::OptionNone => {}
+    }
+}
+
+fn is_builtin_method(call: &CallValue) -> bool {
+    match &call.func_type {
+        Some(func_type) => BuiltinMethod::lookup(func_type, &call.func_name).is_some(),
+        None => false,
+    }
+}
+
+fn is_object_call(call: &CallValue) -> bool {
+    call.func_type
+        .as_ref()
+        .is_some_and(|func_type| func_type.is_trait())
+}
+
+// A concrete value converted to a trait object (which boxes a copy)
+fn is_new_object(expr: &ExprValue) -> bool {
+    matches!(expr.kind, ExprKind::Object(_))
+}
+
+// `Map[K V]:from {}`, `Map:new {}` and the Set and List equivalents
+fn is_new_collection(expr: &ExprValue) -> bool {
+    match &expr.kind {
+        ExprKind::Collection(_) => true,
+        ExprKind::Call(call) => match &call.func_type {
+            Some(func_type) => BuiltinMethod::lookup(func_type, &call.func_name)
+                .is_some_and(|method| method.is_new()),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Frees the owned locals of the innermost frame
+    pub(crate) fn build_frame_drops(&self, module: &Module<'ctx>, scope: &FunctionScope<'ctx>) {
+        if let Some(frame) = scope.frames.last() {
+            self.build_drops(module, scope, frame.iter());
+        }
+    }
+
+    // Frees every owned local before returning from the function
+    pub(crate) fn build_return_drops(&self, module: &Module<'ctx>, scope: &FunctionScope<'ctx>) {
+        self.build_drops(module, scope, scope.frames.iter().flatten());
+    }
+
+    fn build_drops<'s>(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        locals: impl Iterator<Item = (&'s String, &'s Local<'ctx>)>,
+    ) where
+        'ctx: 's,
+    {
+        for (name, local) in locals {
+            if !scope.owned.contains(name) {
+                continue;
+            }
+            if scope.boxed.contains(name) {
+                // the closures which captured it are freed too
+                self.builder.build_free(local.ptr);
+            } else if is_collection(&local.type_id) {
+                let handle = self.builder.build_load(local.ptr, name);
+                self.build_runtime_free(module, handle.into_pointer_value(), &local.type_id);
+            } else if local.type_id.is_trait() {
+                let object = self.builder.build_load(local.ptr, name);
+                self.build_object_free(object.into_struct_value());
+            } else if local.type_id.is_function() {
+                let value = self.builder.build_load(local.ptr, name);
+                self.build_closure_free(value.into_struct_value());
+            }
+        }
+    }
+}
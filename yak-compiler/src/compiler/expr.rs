@@ -0,0 +1,616 @@
+use super::block::FunctionScope;
+use super::types::{int_width, is_signed};
+use super::Compiler;
+use crate::builtin::{BuiltinFunc, BuiltinMethod, HashMethod, ValueMethod};
+use crate::check::{is_float, is_int};
+use crate::hir::{CallValue, ExprKind, ExprValue, StructValue};
+use anyhow::{bail, Result};
+use inkwell::module::Module;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FloatValue, IntValue};
+use inkwell::{FloatPredicate, IntPredicate};
+use yak_ast::{ArithOp, BitwiseOp, BooleanOp, LogicalOp, Op, UnaryOp};
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+use yak_lexer::Span;
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Compiles an expression which must produce a value
+    pub(crate) fn compile_value(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        expr: &ExprValue,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        match self.compile_expr(module, scope, expr)? {
+            Some(value) => Ok(value),
+            None => bail!("expression doesn't produce a value"),
+        }
+    }
+
+    pub(crate) fn compile_expr(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        expr: &ExprValue,
+    ) -> Result<Option<BasicValueEnum<'ctx>>> {
+        let type_id = match &expr.type_id {
+            Some(type_id) => type_id.clone(),
+            None if expr.kind == ExprKind::None => return Ok(None),
+            None => match &expr.kind {
+                // void function calls
+                ExprKind::Call(call) => return self.compile_call(module, scope, call, &expr.span),
+                _ => bail!("expression is missing a type"),
+            },
+        };
+        let value: BasicValueEnum<'ctx> = match &expr.kind {
+            ExprKind::None => return Ok(None),
+            ExprKind::Bool(value) => self
+                .context
+                .bool_type()
+                .const_int(*value as u64, false)
+                .into(),
+            ExprKind::Int(int) => {
+                if is_float(&type_id) {
+                    let float_type = self.basic_type(&type_id)?.into_float_type();
+                    let value = if int.signed {
+                        int.value as i64 as f64
+                    } else {
+                        int.value as f64
+                    };
+                    float_type.const_float(value).into()
+                } else {
+                    let int_type = self.basic_type(&type_id)?.into_int_type();
+                    int_type.const_int(int.value, int.signed).into()
+                }
+            }
+            ExprKind::Float(float) => {
+                let float_type = self.basic_type(&type_id)?.into_float_type();
+                float_type.const_float(float.value).into()
+            }
+            ExprKind::String(string) => self
+                .builder
+                .build_global_string_ptr(&string.value, "str")
+                .as_pointer_value()
+                .into(),
+            ExprKind::Var(name) => match scope.get(name) {
+                Some(local) => self.builder.build_load(local.ptr, name),
+                None => bail!("unknown variable {}", name),
+            },
+            ExprKind::Field(inner, field_name) => {
+                let inner_type = match &inner.type_id {
+                    Some(inner_type) => inner_type.clone(),
+                    None => bail!("field access is missing a type"),
+                };
+                let struct_value = self
+                    .compile_value(module, scope, inner)?
+                    .into_struct_value();
+                let index = self.field_index(&inner_type, field_name)?;
+                match self
+                    .builder
+                    .build_extract_value(struct_value, index, field_name)
+                {
+                    Some(value) => value,
+                    None => bail!("unable to extract field {}", field_name),
+                }
+            }
+            ExprKind::Unary(op, rhs) => {
+                let rhs_value = self.compile_value(module, scope, rhs)?;
+                match op {
+                    UnaryOp::Plus | UnaryOp::None => rhs_value,
+                    UnaryOp::Minus if is_float(&type_id) => self
+                        .builder
+                        .build_float_neg(rhs_value.into_float_value(), "neg")
+                        .into(),
+                    UnaryOp::Minus => {
+                        let int_value = rhs_value.into_int_value();
+                        if self.opts.overflow_checks {
+                            self.build_neg_checks(
+                                module,
+                                scope,
+                                int_value,
+                                is_signed(&type_id),
+                                &expr.span,
+                            );
+                        }
+                        self.builder.build_int_neg(int_value, "neg").into()
+                    }
+                    UnaryOp::Not => self
+                        .builder
+                        .build_not(rhs_value.into_int_value(), "not")
+                        .into(),
+                }
+            }
+            ExprKind::Binary(lhs, op, rhs) => {
+                let operand_type = match &lhs.type_id {
+                    Some(operand_type) => operand_type.clone(),
+                    None => bail!("binary operand is missing a type"),
+                };
+                let lhs_value = self.compile_value(module, scope, lhs)?;
+                let rhs_value = self.compile_value(module, scope, rhs)?;
+                if is_float(&operand_type) {
+                    self.compile_float_binary(
+                        lhs_value.into_float_value(),
+                        op,
+                        rhs_value.into_float_value(),
+                    )?
+                } else {
+                    self.compile_int_binary(
+                        module,
+                        scope,
+                        lhs_value.into_int_value(),
+                        op,
+                        rhs_value.into_int_value(),
+                        is_signed(&operand_type),
+                        &expr.span,
+                    )?
+                }
+            }
+            ExprKind::Call(call) => match self.compile_call(module, scope, call, &expr.span)? {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            ExprKind::Struct(struct_value) => {
+                self.compile_struct_value(module, scope, struct_value)?
+            }
+            ExprKind::Collection(collection) => {
+                let is_map = collection.type_id.is("Map");
+                let handle = self.build_runtime_new(module, &collection.type_id)?;
+                let arity = if is_map { 2 } else { 1 };
+                for chunk in collection.items.chunks(arity) {
+                    let mut words = vec![handle.into()];
+                    for (i, item) in chunk.iter().enumerate() {
+                        let value = self.compile_value(module, scope, item)?;
+                        words.push(
+                            self.box_word(value, &collection.type_id.generics[i])?
+                                .into(),
+                        );
+                    }
+                    let method = if is_map {
+                        BuiltinMethod::MapInsert
+                    } else if collection.type_id.is("List") {
+                        BuiltinMethod::ListPush
+                    } else {
+                        BuiltinMethod::SetInsert
+                    };
+                    self.build_runtime_call(
+                        module,
+                        scope,
+                        method,
+                        &collection.type_id,
+                        &words,
+                        &expr.span,
+                    )?;
+                }
+                handle.into()
+            }
+            ExprKind::OptionNone => self.build_empty_value(&type_id)?.into(),
+            ExprKind::Try(inner) => self.compile_try(module, scope, inner)?,
+            ExprKind::Object(inner) => {
+                let value = self.compile_value(module, scope, inner)?;
+                let inner_type = match &inner.type_id {
+                    Some(inner_type) => inner_type,
+                    None => bail!("expression is missing a type"),
+                };
+                self.build_object(module, value, inner_type, &type_id)?
+            }
+            ExprKind::Closure(closure) => self.build_closure(module, scope, closure, &type_id)?,
+            ExprKind::Cast(inner, _) => self.compile_cast(module, scope, inner, &type_id)?,
+        };
+        Ok(Some(value))
+    }
+
+    // Numeric conversions (bool values are zero extended)
+    fn compile_cast(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        inner: &ExprValue,
+        type_id: &TypeId,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let from_type = match &inner.type_id {
+            Some(from_type) => from_type.clone(),
+            None => bail!("cast value is missing a type"),
+        };
+        let value = self.compile_value(module, scope, inner)?;
+        let to_type = self.basic_type(type_id)?;
+        let value = match (int_width(&from_type), int_width(type_id)) {
+            (Some(from_width), Some(to_width)) => {
+                let int_value = value.into_int_value();
+                let int_type = to_type.into_int_type();
+                if to_width < from_width {
+                    self.builder
+                        .build_int_truncate(int_value, int_type, "trunc")
+                        .into()
+                } else if to_width == from_width {
+                    int_value.into()
+                } else if is_signed(&from_type) {
+                    self.builder
+                        .build_int_s_extend(int_value, int_type, "sext")
+                        .into()
+                } else {
+                    self.builder
+                        .build_int_z_extend(int_value, int_type, "zext")
+                        .into()
+                }
+            }
+            (Some(_), None) => {
+                let int_value = value.into_int_value();
+                let float_type = to_type.into_float_type();
+                if is_signed(&from_type) {
+                    self.builder
+                        .build_signed_int_to_float(int_value, float_type, "sitofp")
+                        .into()
+                } else {
+                    self.builder
+                        .build_unsigned_int_to_float(int_value, float_type, "uitofp")
+                        .into()
+                }
+            }
+            (None, Some(_)) => {
+                // saturating conversions (`llvm.fptosi.sat.i32.f64`) since out
+                // of range values are poison for `fptosi` and `fptoui`
+                let float_value = value.into_float_value();
+                let int_type = to_type.into_int_type();
+                let float_type = float_value.get_type();
+                let intrinsic = format!(
+                    "llvm.{}.sat.i{}.f{}",
+                    if is_signed(type_id) {
+                        "fptosi"
+                    } else {
+                        "fptoui"
+                    },
+                    int_type.get_bit_width(),
+                    if from_type.is("float32") { 32 } else { 64 }
+                );
+                let func_type = int_type.fn_type(&[float_type.into()], false);
+                let func_value = self.get_or_declare_extern(module, &intrinsic, func_type);
+                match self
+                    .builder
+                    .build_call(func_value, &[float_value.into()], "fptoi")
+                    .try_as_basic_value()
+                    .left()
+                {
+                    Some(int_value) => int_value,
+                    None => bail!("{} didn't return a value", &intrinsic),
+                }
+            }
+            (None, None) => {
+                let float_value = value.into_float_value();
+                let float_type = to_type.into_float_type();
+                match (from_type.is("float32"), type_id.is("float32")) {
+                    (true, false) => self
+                        .builder
+                        .build_float_ext(float_value, float_type, "fpext")
+                        .into(),
+                    (false, true) => self
+                        .builder
+                        .build_float_trunc(float_value, float_type, "fptrunc")
+                        .into(),
+                    _ => float_value.into(),
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    pub(crate) fn field_index(&self, type_id: &TypeId, field_name: &str) -> Result<u32> {
+        if type_id.is_tuple() {
+            return match field_name.parse::<usize>() {
+                Ok(index) if index < type_id.generics.len() => Ok(index as u32),
+                _ => bail!("tuple {} has no field {}", type_id.name(), field_name),
+            };
+        }
+        let struct_def = match self.hir.find_struct(type_id) {
+            Some(struct_def) => struct_def,
+            None => bail!("unknown struct {}", type_id.name()),
+        };
+        match struct_def
+            .fields
+            .iter()
+            .position(|field| field.field_id.field_name == field_name)
+        {
+            Some(index) => Ok(index as u32),
+            None => bail!("struct {} has no field {}", type_id.name(), field_name),
+        }
+    }
+
+    fn compile_struct_value(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        struct_value: &StructValue,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let struct_type = match self.struct_type(&struct_value.type_id) {
+            Some(struct_type) => struct_type,
+            None => bail!("unknown struct {}", struct_value.type_id.name()),
+        };
+        let mut value = struct_type.get_undef();
+        for field in &struct_value.fields {
+            let index = self.field_index(&struct_value.type_id, &field.field_name)?;
+            let field_value = self.compile_value(module, scope, &field.value)?;
+            value =
+                match self
+                    .builder
+                    .build_insert_value(value, field_value, index, &field.field_name)
+                {
+                    Some(value) => value.into_struct_value(),
+                    None => bail!("unable to insert field {}", &field.field_name),
+                };
+        }
+        Ok(value.into())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compile_int_binary(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        lhs: IntValue<'ctx>,
+        op: &Op,
+        rhs: IntValue<'ctx>,
+        signed: bool,
+        span: &Span,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let builder = self.builder;
+        match op {
+            Op::Arith(ArithOp::Add) | Op::Arith(ArithOp::Sub) | Op::Arith(ArithOp::Mul)
+                if self.opts.overflow_checks =>
+            {
+                let value = self.build_checked_arith(module, scope, lhs, op, rhs, signed, span)?;
+                return Ok(value.into());
+            }
+            Op::Arith(ArithOp::Div) | Op::Arith(ArithOp::FloorDiv) | Op::Arith(ArithOp::Mod) => {
+                self.build_division_checks(module, scope, lhs, op, rhs, signed, span);
+            }
+            _ => {}
+        }
+        let value = match op {
+            Op::Arith(ArithOp::Add) => builder.build_int_add(lhs, rhs, "add"),
+            Op::Arith(ArithOp::Sub) => builder.build_int_sub(lhs, rhs, "sub"),
+            Op::Arith(ArithOp::Mul) => builder.build_int_mul(lhs, rhs, "mul"),
+            Op::Arith(ArithOp::FloorDiv) if signed => self.build_floor_div(lhs, rhs),
+            Op::Arith(ArithOp::Div) if signed => builder.build_int_signed_div(lhs, rhs, "div"),
+            Op::Arith(ArithOp::Div) | Op::Arith(ArithOp::FloorDiv) => {
+                builder.build_int_unsigned_div(lhs, rhs, "div")
+            }
+            Op::Arith(ArithOp::Mod) if signed => builder.build_int_signed_rem(lhs, rhs, "rem"),
+            Op::Arith(ArithOp::Mod) => builder.build_int_unsigned_rem(lhs, rhs, "rem"),
+            Op::Bitwise(BitwiseOp::And) | Op::Logical(LogicalOp::And) => {
+                builder.build_and(lhs, rhs, "and")
+            }
+            Op::Bitwise(BitwiseOp::Or) | Op::Logical(LogicalOp::Or) => {
+                builder.build_or(lhs, rhs, "or")
+            }
+            Op::Bitwise(BitwiseOp::XOr) => builder.build_xor(lhs, rhs, "xor"),
+            Op::Bitwise(BitwiseOp::ShiftL) => builder.build_left_shift(lhs, rhs, "shl"),
+            Op::Bitwise(BitwiseOp::ShiftR) => builder.build_right_shift(lhs, rhs, signed, "shr"),
+            Op::Boolean(bool_op) => {
+                let predicate = match (bool_op, signed) {
+                    (BooleanOp::EqEq, _) => IntPredicate::EQ,
+                    (BooleanOp::NotEq, _) => IntPredicate::NE,
+                    (BooleanOp::Gt, true) => IntPredicate::SGT,
+                    (BooleanOp::Gte, true) => IntPredicate::SGE,
+                    (BooleanOp::Lt, true) => IntPredicate::SLT,
+                    (BooleanOp::Lte, true) => IntPredicate::SLE,
+                    (BooleanOp::Gt, false) => IntPredicate::UGT,
+                    (BooleanOp::Gte, false) => IntPredicate::UGE,
+                    (BooleanOp::Lt, false) => IntPredicate::ULT,
+                    (BooleanOp::Lte, false) => IntPredicate::ULE,
+                };
+                builder.build_int_compare(predicate, lhs, rhs, "cmp")
+            }
+            _ => bail!("operator {:?} isn't supported yet", op),
+        };
+        Ok(value.into())
+    }
+
+    // `sdiv` truncates toward zero so round down when the signs differ (i.e. `-7 // 2 == -4`)
+    fn build_floor_div(&self, lhs: IntValue<'ctx>, rhs: IntValue<'ctx>) -> IntValue<'ctx> {
+        let builder = self.builder;
+        let int_type = lhs.get_type();
+        let zero = int_type.const_zero();
+        let quotient = builder.build_int_signed_div(lhs, rhs, "div");
+        let remainder = builder.build_int_signed_rem(lhs, rhs, "rem");
+        let inexact = builder.build_int_compare(IntPredicate::NE, remainder, zero, "inexact");
+        let signs = builder.build_xor(remainder, rhs, "signs");
+        let negative = builder.build_int_compare(IntPredicate::SLT, signs, zero, "negative");
+        let round_down = builder.build_and(inexact, negative, "round_down");
+        let adjust = builder.build_int_z_extend(round_down, int_type, "adjust");
+        builder.build_int_sub(quotient, adjust, "floor_div")
+    }
+
+    fn compile_float_binary(
+        &self,
+        lhs: FloatValue<'ctx>,
+        op: &Op,
+        rhs: FloatValue<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let builder = self.builder;
+        let value: BasicValueEnum<'ctx> = match op {
+            Op::Arith(ArithOp::Add) => builder.build_float_add(lhs, rhs, "fadd").into(),
+            Op::Arith(ArithOp::Sub) => builder.build_float_sub(lhs, rhs, "fsub").into(),
+            Op::Arith(ArithOp::Mul) => builder.build_float_mul(lhs, rhs, "fmul").into(),
+            Op::Arith(ArithOp::Div) => builder.build_float_div(lhs, rhs, "fdiv").into(),
+            Op::Arith(ArithOp::Mod) => builder.build_float_rem(lhs, rhs, "frem").into(),
+            Op::Boolean(bool_op) => {
+                let predicate = match bool_op {
+                    BooleanOp::EqEq => FloatPredicate::OEQ,
+                    BooleanOp::NotEq => FloatPredicate::UNE,
+                    BooleanOp::Gt => FloatPredicate::OGT,
+                    BooleanOp::Gte => FloatPredicate::OGE,
+                    BooleanOp::Lt => FloatPredicate::OLT,
+                    BooleanOp::Lte => FloatPredicate::OLE,
+                };
+                builder
+                    .build_float_compare(predicate, lhs, rhs, "fcmp")
+                    .into()
+            }
+            _ => bail!("operator {:?} isn't supported for floats yet", op),
+        };
+        Ok(value)
+    }
+
+    fn compile_call(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        call: &CallValue,
+        span: &Span,
+    ) -> Result<Option<BasicValueEnum<'ctx>>> {
+        // function values
+        if let Some(value_type) = &call.value_type {
+            return self.compile_value_call(module, scope, call, value_type);
+        }
+        // builtin type functions
+        if let Some(func_type) = &call.func_type {
+            if let Some(method) = BuiltinMethod::lookup(func_type, &call.func_name) {
+                return self.compile_builtin_method(module, scope, call, method, func_type, span);
+            }
+            if let Some(method) = ValueMethod::lookup(func_type, &call.func_name) {
+                let value =
+                    self.compile_value_method(module, scope, call, method, func_type, span)?;
+                return Ok(Some(value));
+            }
+            if let Some(method) = HashMethod::lookup(func_type, &call.func_name) {
+                let value = self.compile_hash_method(module, scope, call, method, func_type)?;
+                return Ok(Some(value));
+            }
+            // trait objects
+            if func_type.is_trait() {
+                return self.compile_object_call(module, scope, call, func_type);
+            }
+        }
+
+        // user defined functions
+        if let Some(function_id) = &call.function_id {
+            let func_def = match self.hir.find_function(function_id) {
+                Some(func_def) => func_def,
+                None => bail!("unknown function {}", function_id.name()),
+            };
+            let func_value = self.get_or_declare_function(module, func_def)?;
+            let mut args: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+            for arg in &func_def.args {
+                let call_arg = match call
+                    .args
+                    .iter()
+                    .find(|call_arg| call_arg.arg_name == arg.arg_id.arg_name)
+                {
+                    Some(call_arg) => call_arg,
+                    None => bail!("missing argument {}", &arg.arg_id.arg_name),
+                };
+                args.push(self.compile_value(module, scope, &call_arg.value)?.into());
+            }
+            let call_site = self.builder.build_call(func_value, &args, "call");
+            return Ok(call_site.try_as_basic_value().left());
+        }
+
+        // builtin functions
+        if let Some(func) = BuiltinFunc::lookup(&call.func_name) {
+            if func == BuiltinFunc::DebugAssert {
+                // the value isn't evaluated without debug assertions
+                if self.opts.debug_assertions {
+                    self.build_assert(module, scope, call, func, span)?;
+                }
+                return Ok(None);
+            }
+            if func == BuiltinFunc::Assert || func == BuiltinFunc::AssertEq {
+                self.build_assert(module, scope, call, func, span)?;
+                return Ok(None);
+            }
+            if func == BuiltinFunc::Panic {
+                let message = match call.args.iter().find(|arg| arg.arg_name == "message") {
+                    Some(arg) => self.compile_value(module, scope, &arg.value)?,
+                    None => bail!("missing argument message"),
+                };
+                self.build_panic(module, scope, message.into_pointer_value(), span);
+                // anything after the panic is unreachable
+                let after = self
+                    .context
+                    .append_basic_block(scope.func_value, "after_panic");
+                self.builder.position_at_end(after);
+                return Ok(None);
+            }
+            let mut args: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+            for (arg_name, arg_type) in func.args() {
+                let call_arg = match call
+                    .args
+                    .iter()
+                    .find(|call_arg| call_arg.arg_name == arg_name)
+                {
+                    Some(call_arg) => call_arg,
+                    None => bail!("missing argument {}", arg_name),
+                };
+                let value = self.compile_value(module, scope, &call_arg.value)?;
+                // the runtime hashes ints as 64-bit words
+                let value = match &call_arg.value.type_id {
+                    Some(found) if is_int(found) && is_int(&arg_type) => {
+                        self.box_word(value, found)?.into()
+                    }
+                    _ => value,
+                };
+                args.push(value.into());
+            }
+            let i64_type = self.context.i64_type();
+            let func_type = match func {
+                BuiltinFunc::HashInt => i64_type.fn_type(&[i64_type.into()], false),
+                BuiltinFunc::HashStr => i64_type.fn_type(&[self.i8_ptr_type().into()], false),
+                BuiltinFunc::HashCombine => {
+                    i64_type.fn_type(&[i64_type.into(), i64_type.into()], false)
+                }
+                BuiltinFunc::Panic
+                | BuiltinFunc::Assert
+                | BuiltinFunc::AssertEq
+                | BuiltinFunc::DebugAssert => unreachable!(),
+            };
+            let func_value = self.get_or_declare_extern(module, func.symbol(), func_type);
+            let call_site = self.builder.build_call(func_value, &args, "call");
+            return Ok(call_site.try_as_basic_value().left());
+        }
+
+        bail!("unresolved function {}", &call.func_name)
+    }
+
+    fn compile_builtin_method(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        call: &CallValue,
+        method: BuiltinMethod,
+        type_id: &TypeId,
+        span: &Span,
+    ) -> Result<Option<BasicValueEnum<'ctx>>> {
+        if method.is_new() {
+            let handle = self.build_runtime_new(module, type_id)?;
+            return Ok(Some(handle.into()));
+        }
+        // self is a handle and everything else is passed as a word
+        let mut words: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        // lookup keys are only borrowed by the runtime
+        let mut borrowed = vec![];
+        for (arg_name, arg_type) in method.args(type_id) {
+            let call_arg = match call
+                .args
+                .iter()
+                .find(|call_arg| call_arg.arg_name == arg_name)
+            {
+                Some(call_arg) => call_arg,
+                None => bail!("missing argument {}", arg_name),
+            };
+            let value = self.compile_value(module, scope, &call_arg.value)?;
+            if arg_name == "self" {
+                words.push(value.into());
+            } else {
+                let word = self.box_word(value, &arg_type)?;
+                if !method.is_insert() {
+                    borrowed.push((word, arg_type));
+                }
+                words.push(word.into());
+            }
+        }
+        let value = self.build_runtime_call(module, scope, method, type_id, &words, span)?;
+        for (word, arg_type) in borrowed {
+            self.build_free_word(module, word, &arg_type)?;
+        }
+        Ok(value)
+    }
+}
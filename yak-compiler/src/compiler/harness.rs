@@ -0,0 +1,95 @@
+use super::{Compiler, FUNC_ENTRY};
+use crate::hir::ModuleDef;
+use anyhow::{bail, Result};
+use inkwell::module::Module;
+use inkwell::AddressSpace;
+use inkwell::IntPredicate;
+
+// libc function used to parse the test index
+const ATOI_SYMBOL: &str = "atoi";
+
+// Exit status for an unknown test index
+const UNKNOWN_TEST_EXIT_CODE: u64 = 2;
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Creates the test harness @main which runs the test at index argv[1]
+    // (i.e. `my.pkg-test 0`). Each test runs in its own process
+    // so a panic only fails the test which caused it.
+    pub(crate) fn compile_test_main(
+        &self,
+        module: &mut Module<'ctx>,
+        module_def: &ModuleDef,
+    ) -> Result<bool> {
+        if !module_def.pkg_root {
+            return Ok(false);
+        }
+        let i32_type = self.context.i32_type();
+        let argv_type = self.i8_ptr_type().ptr_type(AddressSpace::default());
+        let func_type = i32_type.fn_type(&[i32_type.into(), argv_type.into()], false);
+        let func_value = module.add_function("main", func_type, None);
+        let entry = self.context.append_basic_block(func_value, FUNC_ENTRY);
+        let parse_block = self.context.append_basic_block(func_value, "parse_index");
+        let unknown_block = self.context.append_basic_block(func_value, "unknown_test");
+
+        // the test index is required
+        self.builder.position_at_end(entry);
+        let (argc, argv) = match (func_value.get_nth_param(0), func_value.get_nth_param(1)) {
+            (Some(argc), Some(argv)) => (argc.into_int_value(), argv.into_pointer_value()),
+            _ => bail!("test harness main is missing argc or argv"),
+        };
+        let has_index = self.builder.build_int_compare(
+            IntPredicate::SGE,
+            argc,
+            i32_type.const_int(2, false),
+            "has_index",
+        );
+        self.builder
+            .build_conditional_branch(has_index, parse_block, unknown_block);
+
+        // index = atoi(argv[1])
+        self.builder.position_at_end(parse_block);
+        let arg_ptr = unsafe {
+            self.builder.build_in_bounds_gep(
+                argv,
+                &[self.context.i64_type().const_int(1, false)],
+                "arg_ptr",
+            )
+        };
+        let arg = self.builder.build_load(arg_ptr, "arg");
+        let atoi_type = i32_type.fn_type(&[self.i8_ptr_type().into()], false);
+        let atoi = self.get_or_declare_extern(module, ATOI_SYMBOL, atoi_type);
+        let index = match self
+            .builder
+            .build_call(atoi, &[arg.into()], "index")
+            .try_as_basic_value()
+            .left()
+        {
+            Some(index) => index.into_int_value(),
+            None => bail!("{} didn't return a value", ATOI_SYMBOL),
+        };
+
+        // one block per test
+        let mut cases = vec![];
+        for (test_num, test_def) in module_def.test_defs.iter().enumerate() {
+            let func_def = match self.hir.find_function(&test_def.function_id) {
+                Some(func_def) => func_def,
+                None => bail!("unknown test function {:?}", &test_def.test_name),
+            };
+            let test_block = self.context.append_basic_block(func_value, "test");
+            self.builder.position_at_end(test_block);
+            let test_func = self.get_or_declare_function(module, func_def)?;
+            self.builder.build_call(test_func, &[], "test");
+            self.builder
+                .build_return(Some(&i32_type.const_int(0, false)));
+            cases.push((i32_type.const_int(test_num as u64, false), test_block));
+        }
+        self.builder.position_at_end(parse_block);
+        self.builder.build_switch(index, unknown_block, &cases);
+
+        self.builder.position_at_end(unknown_block);
+        self.builder
+            .build_return(Some(&i32_type.const_int(UNKNOWN_TEST_EXIT_CODE, false)));
+
+        Ok(true)
+    }
+}
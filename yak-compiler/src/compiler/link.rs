@@ -0,0 +1,342 @@
+use super::{Artifact, Compiler, BINARY_FILE_MODE};
+use anyhow::{bail, Context, Error, Result};
+use log::info;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use yak_core::models::yak_home::YakHome;
+use yak_core::types::name::Name;
+
+// creates static libraries
+const ARCHIVER: &str = "ar";
+
+// The driver which links objects into binaries and shared objects
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Linker {
+    #[default]
+    Cc,
+    Clang,
+    LdLld,
+}
+
+impl Linker {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Linker::Cc => "cc",
+            Linker::Clang => "clang",
+            Linker::LdLld => "ld.lld",
+        }
+    }
+
+    // ld.lld links through a driver (`-fuse-ld=lld`) so the c runtime
+    // and libc are still found. Other targets link with the gcc cross
+    // toolchain if it's installed (i.e. `aarch64-linux-gnu-gcc`)
+    // or clang (`--target`)
+    pub(crate) fn command(&self, triple: Option<&str>) -> Command {
+        let gcc = match (self, triple) {
+            (Linker::Cc, Some(triple)) => Some(cross_gcc(triple)).filter(|gcc| on_path(gcc)),
+            _ => None,
+        };
+        let mut cmd = match (self, triple, gcc) {
+            (_, _, Some(gcc)) => Command::new(gcc),
+            (Linker::Cc | Linker::LdLld, None, _) => Command::new("cc"),
+            (Linker::Clang, None, _) => Command::new("clang"),
+            (_, Some(triple), None) => {
+                let mut cmd = Command::new("clang");
+                cmd.arg(format!("--target={}", triple));
+                cmd
+            }
+        };
+        if *self == Linker::LdLld {
+            cmd.arg("-fuse-ld=lld");
+        }
+        cmd
+    }
+}
+
+// gcc cross toolchains are named without the vendor
+// (i.e. `aarch64-unknown-linux-gnu` links with `aarch64-linux-gnu-gcc`)
+pub(crate) fn cross_gcc(triple: &str) -> String {
+    let mut parts: Vec<&str> = triple.split('-').collect();
+    if parts.len() == 4 || (parts.len() == 3 && matches!(parts[1], "unknown" | "pc")) {
+        parts.remove(1);
+    }
+    format!("{}-gcc", parts.join("-"))
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|path| path.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+impl FromStr for Linker {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let linker = match value {
+            "cc" => Linker::Cc,
+            "clang" => Linker::Clang,
+            "ld.lld" => Linker::LdLld,
+            _ => bail!("unknown linker: {} (expected cc, clang or ld.lld)", value),
+        };
+        Ok(linker)
+    }
+}
+
+// The library packages without a :main link into
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LibKind {
+    Static,
+    #[default]
+    Shared,
+}
+
+impl LibKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LibKind::Static => "static",
+            LibKind::Shared => "shared",
+        }
+    }
+}
+
+impl FromStr for LibKind {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let lib_kind = match value {
+            "static" => LibKind::Static,
+            "shared" => LibKind::Shared,
+            _ => bail!("unknown lib kind: {} (expected static or shared)", value),
+        };
+        Ok(lib_kind)
+    }
+}
+
+// A linker (or archiver) which failed and what it printed
+#[derive(Debug)]
+pub struct LinkError {
+    pub linker: String,
+    pub output_file: String,
+    pub stderr: String,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} failed to link {}: {}",
+            self.linker,
+            self.output_file,
+            self.stderr.trim_end()
+        )
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Links the module objects into a binary (packages with a :main or tests)
+    // or a static or shared library
+    pub(super) fn link(&self) -> Result<Artifact> {
+        if self.has_main {
+            return self.link_binary();
+        }
+        match self.opts.lib_kind {
+            LibKind::Static => self.link_static(),
+            LibKind::Shared => self.link_shared(),
+        }
+    }
+
+    fn link_binary(&self) -> Result<Artifact> {
+        let output_file = self.opts.bin_file();
+        let mut cmd = self.opts.linker.command(self.opts.target.triple.as_deref());
+        cmd.args(&self.object_files);
+        self.link_runtime(&mut cmd)?;
+        cmd.arg("-o");
+        cmd.arg(&output_file);
+        self.run_linker(cmd, &output_file)?;
+        let mut perms = fs::metadata(&output_file)?.permissions();
+        perms.set_mode(BINARY_FILE_MODE);
+        fs::set_permissions(&output_file, perms)?;
+        Ok(Artifact {
+            kind: "bin",
+            file: output_file,
+        })
+    }
+
+    // Archives the module objects (binaries linking it also need the yak runtime)
+    fn link_static(&self) -> Result<Artifact> {
+        let output_file = format!("{}/lib{}.a", self.lib_dir(), &self.opts.pkg_id);
+        // ar adds to existing archives
+        let _ = fs::remove_file(&output_file);
+        let mut cmd = Command::new(ARCHIVER);
+        cmd.arg("rcs");
+        cmd.arg(&output_file);
+        cmd.args(&self.object_files);
+        self.run_linker(cmd, &output_file)?;
+        Ok(Artifact {
+            kind: "lib",
+            file: output_file,
+        })
+    }
+
+    // Links a shared object which only exports the package `export` symbols
+    fn link_shared(&self) -> Result<Artifact> {
+        let lib_dir = self.lib_dir();
+        fs::create_dir_all(&lib_dir)
+            .with_context(|| format!("failed to create lib path: {}", &lib_dir))?;
+        let symbols = self.exported_symbols()?;
+        let mut cmd = self.opts.linker.command(self.opts.target.triple.as_deref());
+        cmd.arg("-shared");
+        cmd.args(&self.object_files);
+        let output_file = if self.is_darwin() {
+            // mach-o symbols have a leading underscore
+            let exports_file = format!("{}/{}.exports", &lib_dir, &self.opts.pkg_id);
+            let contents: String = symbols.iter().map(|sym| format!("_{}\n", sym)).collect();
+            fs::write(&exports_file, contents)
+                .with_context(|| format!("failed to write file: {}", &exports_file))?;
+            cmd.arg(format!("-Wl,-exported_symbols_list,{}", &exports_file));
+            format!("{}/lib{}.dylib", &lib_dir, &self.opts.pkg_id)
+        } else {
+            let version_file = format!("{}/{}.version", &lib_dir, &self.opts.pkg_id);
+            fs::write(&version_file, version_script(&symbols))
+                .with_context(|| format!("failed to write file: {}", &version_file))?;
+            cmd.arg(format!("-Wl,--version-script={}", &version_file));
+            format!("{}/lib{}.so", &lib_dir, &self.opts.pkg_id)
+        };
+        self.link_runtime(&mut cmd)?;
+        cmd.arg("-o");
+        cmd.arg(&output_file);
+        self.run_linker(cmd, &output_file)?;
+        Ok(Artifact {
+            kind: "lib",
+            file: output_file,
+        })
+    }
+
+    fn lib_dir(&self) -> String {
+        format!("{}/lib", &self.opts.output_dir)
+    }
+
+    // link the yak runtime (Map, Set, panics, etc.)
+    // (runtimes for other targets are installed in `lib/{triple}`)
+    pub(crate) fn link_runtime(&self, cmd: &mut Command) -> Result<()> {
+        let yak_home = YakHome::default();
+        let (lib_dir, install) = match &self.opts.target.triple {
+            Some(triple) => (
+                yak_home.get_home_version_target_lib_dir(triple),
+                format!("make std-install-target TARGET={}", triple),
+            ),
+            None => (
+                yak_home.get_home_version_lib_dir(),
+                "make std-install".into(),
+            ),
+        };
+        if !lib_dir.join("libyak_std.a").exists() {
+            bail!(
+                "yak runtime isn't installed in {} (run `{}`)",
+                lib_dir.display(),
+                install
+            );
+        }
+        cmd.arg(format!("-L{}", lib_dir.display()));
+        cmd.arg("-lyak_std");
+        cmd.arg("-lpthread");
+        cmd.arg("-ldl");
+        cmd.arg("-lm");
+        Ok(())
+    }
+
+    // Failures (and linkers which aren't installed) are LinkErrors
+    pub(crate) fn run_linker(&self, mut cmd: Command, output_file: &str) -> Result<()> {
+        let linker = cmd.get_program().to_string_lossy().to_string();
+        if let Some(path) = Path::new(output_file).parent() {
+            fs::create_dir_all(path)
+                .with_context(|| format!("failed to create output path: {}", path.display()))?;
+        }
+        info!("Link {} w/ {:#?}", output_file, &cmd);
+        let stderr = match cmd.output() {
+            Ok(output) if output.status.success() => {
+                info!("Done linking {}", output_file);
+                return Ok(());
+            }
+            Ok(output) => String::from_utf8_lossy(&output.stderr).to_string(),
+            Err(err) => format!("failed to run {}: {}", linker, err),
+        };
+        Err(LinkError {
+            linker,
+            output_file: output_file.to_string(),
+            stderr,
+        }
+        .into())
+    }
+
+    // The function symbols for the package `export` block.
+    // Functions (`:func`) export themselves and types export
+    // their functions. Generic functions are only instantiated
+    // by callers so they're never exported.
+    pub(crate) fn exported_symbols(&self) -> Result<Vec<String>> {
+        let mut symbols = vec![];
+        let Some(module_def) = self
+            .hir
+            .modules
+            .iter()
+            .find(|module_def| module_def.pkg_root)
+        else {
+            return Ok(symbols);
+        };
+        let functions: Vec<_> = module_def
+            .function_defs
+            .iter()
+            .filter(|func_def| {
+                func_def.generics.is_empty() && func_def.function_id.generics.is_empty()
+            })
+            .map(|func_def| &func_def.function_id)
+            .collect();
+        for export in &self.opts.exports {
+            if export.starts_with(':') {
+                let function_id = functions.iter().find(|function_id| {
+                    function_id.struct_name.is_none() && &function_id.func_name == export
+                });
+                match function_id {
+                    Some(function_id) => symbols.push(function_id.name()),
+                    None => bail!("exported function {} isn't defined", export),
+                }
+            } else {
+                symbols.extend(
+                    functions
+                        .iter()
+                        .filter(|function_id| function_id.struct_name.as_ref() == Some(export))
+                        .map(|function_id| function_id.name()),
+                );
+            }
+        }
+        Ok(symbols)
+    }
+
+    fn is_darwin(&self) -> bool {
+        let triple = self.machine.get_triple();
+        let triple = triple.as_str().to_string_lossy();
+        triple.contains("apple") || triple.contains("darwin")
+    }
+}
+
+// An ld version script which hides everything but the exports
+// (quoted since yak symbols contain `:`, `#` and `.`)
+pub(crate) fn version_script(symbols: &[String]) -> String {
+    let mut script = String::from("{\n");
+    if !symbols.is_empty() {
+        script.push_str("  global:\n");
+        for symbol in symbols {
+            script.push_str(&format!("    \"{}\";\n", symbol));
+        }
+    }
+    script.push_str("  local: *;\n};\n");
+    script
+}
@@ -0,0 +1,257 @@
+use super::block::FunctionScope;
+use super::Compiler;
+use crate::hir::{CallValue, FunctionDef};
+use anyhow::{bail, Result};
+use inkwell::module::{Linkage, Module};
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, FunctionType, StructType};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, CallableValue, PointerValue, StructValue,
+};
+use inkwell::AddressSpace;
+use std::convert::TryFrom;
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+
+// Trait object field indexes
+const DATA_INDEX: u32 = 0;
+const VTABLE_INDEX: u32 = 1;
+
+// llvm label for function start
+const THUNK_ENTRY: &str = "enter";
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Trait objects are fat pointers `{ i8*, i8* }`
+    // (the boxed value and the vtable of the impl)
+    pub(crate) fn object_type(&self) -> StructType<'ctx> {
+        let i8_ptr_type = self.i8_ptr_type();
+        self.context
+            .struct_type(&[i8_ptr_type.into(), i8_ptr_type.into()], false)
+    }
+
+    // Copies a concrete value to the heap and pairs it
+    // with the vtable of `impl Type ^Trait`
+    pub(crate) fn build_object(
+        &self,
+        module: &Module<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        type_id: &TypeId,
+        trait_id: &TypeId,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let ptr = match self.builder.build_malloc(value.get_type(), "object") {
+            Ok(ptr) => ptr,
+            Err(err) => bail!("unable to box {}: {}", type_id.name(), err),
+        };
+        self.builder.build_store(ptr, value);
+        let data = self
+            .builder
+            .build_pointer_cast(ptr, self.i8_ptr_type(), "data");
+        let vtable = self.get_or_build_vtable(module, type_id, trait_id)?;
+        let object = self.object_type().get_undef();
+        let object = self.insert_field(object, data.into(), DATA_INDEX)?;
+        let object = self.insert_field(object, vtable.into(), VTABLE_INDEX)?;
+        Ok(object.into())
+    }
+
+    // Frees the boxed value of a trait object
+    pub(crate) fn build_object_free(&self, object: StructValue<'ctx>) {
+        if let Some(data) = self.builder.build_extract_value(object, DATA_INDEX, "data") {
+            self.builder.build_free(data.into_pointer_value());
+        }
+    }
+
+    // Calls a trait function through the vtable of a trait object.
+    // The boxed value is passed as `self`.
+    pub(crate) fn compile_object_call(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        call: &CallValue,
+        trait_id: &TypeId,
+    ) -> Result<Option<BasicValueEnum<'ctx>>> {
+        let (index, trait_func) = self.find_trait_function(trait_id, &call.func_name)?;
+        let mut args: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        let mut vtable = None;
+        for arg in &trait_func.args {
+            let call_arg = match call
+                .args
+                .iter()
+                .find(|call_arg| call_arg.arg_name == arg.arg_id.arg_name)
+            {
+                Some(call_arg) => call_arg,
+                None => bail!("missing argument {}", &arg.arg_id.arg_name),
+            };
+            let value = self.compile_value(module, scope, &call_arg.value)?;
+            if arg.arg_id.arg_num == 0 {
+                let object = value.into_struct_value();
+                args.push(self.extract_field(object, DATA_INDEX)?.into());
+                vtable = Some(
+                    self.extract_field(object, VTABLE_INDEX)?
+                        .into_pointer_value(),
+                );
+            } else {
+                args.push(value.into());
+            }
+        }
+        let vtable = match vtable {
+            Some(vtable) => vtable,
+            None => bail!("missing argument self"),
+        };
+        let thunk_type = self.thunk_type(trait_func)?;
+        let slots = self.builder.build_pointer_cast(
+            vtable,
+            self.i8_ptr_type().ptr_type(AddressSpace::default()),
+            "slots",
+        );
+        let slot = unsafe {
+            self.builder.build_in_bounds_gep(
+                slots,
+                &[self.context.i32_type().const_int(index as u64, false)],
+                "slot",
+            )
+        };
+        let func_ptr = self.builder.build_load(slot, "func").into_pointer_value();
+        let func_ptr = self.builder.build_pointer_cast(
+            func_ptr,
+            thunk_type.ptr_type(AddressSpace::default()),
+            "func",
+        );
+        let callable = match CallableValue::try_from(func_ptr) {
+            Ok(callable) => callable,
+            Err(_) => bail!("vtable slot {} isn't a function", &call.func_name),
+        };
+        let call_site = self.builder.build_call(callable, &args, "call");
+        Ok(call_site.try_as_basic_value().left())
+    }
+
+    // Returns the vtable slot and the trait function
+    fn find_trait_function(
+        &self,
+        trait_id: &TypeId,
+        func_name: &str,
+    ) -> Result<(usize, &FunctionDef)> {
+        let trait_def = match self.hir.find_trait(trait_id) {
+            Some(trait_def) => trait_def,
+            None => bail!("unknown trait {}", &trait_id.type_name),
+        };
+        match trait_def
+            .function_defs
+            .iter()
+            .enumerate()
+            .find(|(_, func_def)| func_def.function_id.func_name == func_name)
+        {
+            Some(found) => Ok(found),
+            None => bail!(
+                "trait {} has no function {}",
+                &trait_id.type_name,
+                func_name
+            ),
+        }
+    }
+
+    // Vtable functions receive the boxed value as `i8*`
+    fn thunk_type(&self, trait_func: &FunctionDef) -> Result<FunctionType<'ctx>> {
+        let mut arg_types: Vec<BasicMetadataTypeEnum<'ctx>> = vec![self.i8_ptr_type().into()];
+        for arg in trait_func.args.iter().skip(1) {
+            arg_types.push(self.basic_type(&arg.type_id)?.into());
+        }
+        let func_type = match &trait_func.return_type {
+            Some(return_type) => self.basic_type(return_type)?.fn_type(&arg_types, false),
+            None => self.context.void_type().fn_type(&arg_types, false),
+        };
+        Ok(func_type)
+    }
+
+    // Vtables are constant arrays of thunks (in trait function order)
+    // which unbox `self` and call the impl function. Each module
+    // defines the vtables it uses.
+    fn get_or_build_vtable(
+        &self,
+        module: &Module<'ctx>,
+        type_id: &TypeId,
+        trait_id: &TypeId,
+    ) -> Result<PointerValue<'ctx>> {
+        let trait_name = &trait_id.type_name;
+        let vtable_name = format!("{}${}$vtable", type_id.name(), trait_name);
+        if let Some(vtable) = module.get_global(&vtable_name) {
+            return Ok(self.builder.build_pointer_cast(
+                vtable.as_pointer_value(),
+                self.i8_ptr_type(),
+                "vtable",
+            ));
+        }
+        let trait_def = match self.hir.find_trait(trait_id) {
+            Some(trait_def) => trait_def,
+            None => bail!("unknown trait {}", trait_name),
+        };
+        let saved_block = self.builder.get_insert_block();
+        let saved_location = self.suspend_debug_location();
+        let mut slots = vec![];
+        for trait_func in &trait_def.function_defs {
+            let func_name = &trait_func.function_id.func_name;
+            let impl_def = match self
+                .hir
+                .find_impl_function(type_id, trait_id, func_name)
+                .and_then(|impl_id| self.hir.find_function(&impl_id))
+            {
+                Some(impl_def) => impl_def,
+                None => bail!(
+                    "type {} doesn't implement {}{}",
+                    type_id.name(),
+                    trait_name,
+                    func_name
+                ),
+            };
+            let thunk_name = format!("{}${}", impl_def.function_id.name(), trait_name);
+            let thunk = match module.get_function(&thunk_name) {
+                Some(thunk) => thunk,
+                None => {
+                    let thunk_type = self.thunk_type(trait_func)?;
+                    let thunk =
+                        module.add_function(&thunk_name, thunk_type, Some(Linkage::Private));
+                    let entry = self.context.append_basic_block(thunk, THUNK_ENTRY);
+                    self.builder.position_at_end(entry);
+                    let data = thunk.get_nth_param(0).unwrap().into_pointer_value();
+                    let ptr = self.builder.build_pointer_cast(
+                        data,
+                        self.basic_type(type_id)?.ptr_type(AddressSpace::default()),
+                        "self",
+                    );
+                    let mut args: Vec<BasicMetadataValueEnum<'ctx>> =
+                        vec![self.builder.build_load(ptr, "self").into()];
+                    for param in thunk.get_param_iter().skip(1) {
+                        args.push(param.into());
+                    }
+                    let impl_fn = self.get_or_declare_function(module, impl_def)?;
+                    let value = self
+                        .builder
+                        .build_call(impl_fn, &args, "call")
+                        .try_as_basic_value()
+                        .left();
+                    match value {
+                        Some(value) => self.builder.build_return(Some(&value)),
+                        None => self.builder.build_return(None),
+                    };
+                    thunk
+                }
+            };
+            slots.push(
+                thunk
+                    .as_global_value()
+                    .as_pointer_value()
+                    .const_cast(self.i8_ptr_type()),
+            );
+        }
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        self.restore_debug_location(saved_location);
+        let vtable_type = self.i8_ptr_type().array_type(slots.len() as u32);
+        let vtable = module.add_global(vtable_type, None, &vtable_name);
+        vtable.set_linkage(Linkage::Private);
+        vtable.set_constant(true);
+        vtable.set_initializer(&self.i8_ptr_type().const_array(&slots));
+        Ok(self
+            .builder
+            .build_pointer_cast(vtable.as_pointer_value(), self.i8_ptr_type(), "vtable"))
+    }
+}
@@ -0,0 +1,154 @@
+use super::block::FunctionScope;
+use super::Compiler;
+use crate::builtin::ValueMethod;
+use crate::hir::{CallValue, ExprValue};
+use anyhow::{bail, Result};
+use inkwell::module::Module;
+use inkwell::values::{BasicValueEnum, IntValue, StructValue};
+use yak_core::types::types::TypeId;
+use yak_lexer::Span;
+
+// Option and Maybe field indexes
+const FLAG_INDEX: u32 = 0;
+const VALUE_INDEX: u32 = 1;
+const ERROR_INDEX: u32 = 2;
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    pub(crate) fn extract_field(
+        &self,
+        value: StructValue<'ctx>,
+        index: u32,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        match self.builder.build_extract_value(value, index, "field") {
+            Some(field) => Ok(field),
+            None => bail!("unable to extract field {}", index),
+        }
+    }
+
+    pub(crate) fn insert_field(
+        &self,
+        value: StructValue<'ctx>,
+        field: BasicValueEnum<'ctx>,
+        index: u32,
+    ) -> Result<StructValue<'ctx>> {
+        match self
+            .builder
+            .build_insert_value(value, field, index, "field")
+        {
+            Some(value) => Ok(value.into_struct_value()),
+            None => bail!("unable to insert field {}", index),
+        }
+    }
+
+    fn flag(&self, is_set: bool) -> IntValue<'ctx> {
+        self.context.bool_type().const_int(is_set as u64, false)
+    }
+
+    // Returns an empty Option or Maybe (None or an error without a value)
+    pub(crate) fn build_empty_value(&self, type_id: &TypeId) -> Result<StructValue<'ctx>> {
+        Ok(self.basic_type(type_id)?.into_struct_type().const_zero())
+    }
+
+    // `value?` branches to an early return if the value is None or an error
+    pub(crate) fn compile_try(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        inner: &ExprValue,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let inner_type = match &inner.type_id {
+            Some(inner_type) => inner_type.clone(),
+            None => bail!("? operand is missing a type"),
+        };
+        let return_type = match &scope.return_type {
+            Some(return_type) => return_type.clone(),
+            None => bail!("? used in a function without a return type"),
+        };
+        let value = self
+            .compile_value(module, scope, inner)?
+            .into_struct_value();
+        let flag = self.extract_field(value, FLAG_INDEX)?.into_int_value();
+
+        let ok_block = self.context.append_basic_block(scope.func_value, "try_ok");
+        let return_block = self
+            .context
+            .append_basic_block(scope.func_value, "try_return");
+        self.builder
+            .build_conditional_branch(flag, ok_block, return_block);
+
+        self.builder.position_at_end(return_block);
+        let mut early = self.build_empty_value(&return_type)?;
+        if inner_type.is("Maybe") {
+            let error = self.extract_field(value, ERROR_INDEX)?;
+            early = self.insert_field(early, error, ERROR_INDEX)?;
+        }
+        self.build_return_drops(module, scope);
+        self.build_function_return(module, Some(early.into()));
+
+        self.builder.position_at_end(ok_block);
+        self.extract_field(value, VALUE_INDEX)
+    }
+
+    pub(crate) fn compile_value_method(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        call: &CallValue,
+        method: ValueMethod,
+        type_id: &TypeId,
+        span: &Span,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let mut args = vec![];
+        for (arg_name, _) in method.args(type_id) {
+            let call_arg = match call
+                .args
+                .iter()
+                .find(|call_arg| call_arg.arg_name == arg_name)
+            {
+                Some(call_arg) => call_arg,
+                None => bail!("missing argument {}", arg_name),
+            };
+            args.push(self.compile_value(module, scope, &call_arg.value)?);
+        }
+        let value: BasicValueEnum<'ctx> = match method {
+            ValueMethod::OptionNone => self.build_empty_value(type_id)?.into(),
+            ValueMethod::OptionSome | ValueMethod::MaybeOk => {
+                let value = self.build_empty_value(type_id)?;
+                let value = self.insert_field(value, self.flag(true).into(), FLAG_INDEX)?;
+                self.insert_field(value, args[0], VALUE_INDEX)?.into()
+            }
+            ValueMethod::MaybeErr => {
+                let value = self.build_empty_value(type_id)?;
+                self.insert_field(value, args[0], ERROR_INDEX)?.into()
+            }
+            ValueMethod::OptionIsSome | ValueMethod::MaybeIsOk => {
+                self.extract_field(args[0].into_struct_value(), FLAG_INDEX)?
+            }
+            ValueMethod::OptionIsNone | ValueMethod::MaybeIsErr => {
+                let flag = self
+                    .extract_field(args[0].into_struct_value(), FLAG_INDEX)?
+                    .into_int_value();
+                self.builder.build_not(flag, "not").into()
+            }
+            ValueMethod::OptionUnwrap | ValueMethod::MaybeUnwrap => {
+                let this = args[0].into_struct_value();
+                let flag = self.extract_field(this, FLAG_INDEX)?.into_int_value();
+                let is_empty = self.builder.build_not(flag, "is_empty");
+                let message = if method == ValueMethod::OptionUnwrap {
+                    "called Option:unwrap on a None value"
+                } else {
+                    "called Maybe:unwrap on an error value"
+                };
+                self.build_panic_if(module, scope, is_empty, message, span);
+                self.extract_field(this, VALUE_INDEX)?
+            }
+            ValueMethod::OptionUnwrapOr | ValueMethod::MaybeUnwrapOr => {
+                let this = args[0].into_struct_value();
+                let flag = self.extract_field(this, FLAG_INDEX)?.into_int_value();
+                let value = self.extract_field(this, VALUE_INDEX)?;
+                self.builder.build_select(flag, value, args[1], "unwrap_or")
+            }
+        };
+        Ok(value)
+    }
+}
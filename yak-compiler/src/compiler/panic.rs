@@ -0,0 +1,294 @@
+use super::block::FunctionScope;
+use super::Compiler;
+use crate::builtin::{assert_value_kind, BuiltinFunc};
+use crate::hir::CallValue;
+use anyhow::{bail, Result};
+use inkwell::module::Module;
+use inkwell::types::BasicMetadataTypeEnum;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, IntValue, PointerValue};
+use inkwell::IntPredicate;
+use yak_ast::{ArithOp, Op};
+use yak_lexer::Span;
+
+// yak-std panic runtime
+const PANIC_SYMBOL: &str = "yak_panic";
+const FRAME_PUSH_SYMBOL: &str = "yak_frame_push";
+const FRAME_POP_SYMBOL: &str = "yak_frame_pop";
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Calls the runtime panic (which never returns) with
+    // the message and source location
+    pub(crate) fn build_panic(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        message: PointerValue<'ctx>,
+        span: &Span,
+    ) {
+        let i64_type = self.context.i64_type();
+        let i8_ptr_type = self.i8_ptr_type();
+        let func_type = self.context.void_type().fn_type(
+            &[
+                i8_ptr_type.into(),
+                i8_ptr_type.into(),
+                i64_type.into(),
+                i64_type.into(),
+            ],
+            false,
+        );
+        let func_value = self.get_or_declare_extern(module, PANIC_SYMBOL, func_type);
+        let file = self
+            .builder
+            .build_global_string_ptr(&scope.file, "panic_file")
+            .as_pointer_value();
+        let span = if span.ln == 0 { &scope.span } else { span };
+        let line = i64_type.const_int(span.ln as u64, false);
+        let col = i64_type.const_int(span.start as u64, false);
+        self.builder.build_call(
+            func_value,
+            &[message.into(), file.into(), line.into(), col.into()],
+            "panic",
+        );
+        self.builder.build_unreachable();
+    }
+
+    // Assertions pass their values to the runtime as words (with the
+    // source location) which panics with both values on a mismatch
+    pub(crate) fn build_assert(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        call: &CallValue,
+        func: BuiltinFunc,
+        span: &Span,
+    ) -> Result<()> {
+        let i64_type = self.context.i64_type();
+        let mut args: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        if func == BuiltinFunc::AssertEq {
+            let type_id = match call.args.first().and_then(|arg| arg.value.type_id.as_ref()) {
+                Some(type_id) => type_id,
+                None => bail!(":assert_eq values must be type checked"),
+            };
+            let kind = match assert_value_kind(type_id) {
+                Some(kind) => kind,
+                None => bail!(
+                    ":assert_eq can't compare values of type {}",
+                    &type_id.type_name
+                ),
+            };
+            args.push(i64_type.const_int(kind, false).into());
+        }
+        for (arg_name, arg_type) in func.args() {
+            let call_arg = match call
+                .args
+                .iter()
+                .find(|call_arg| call_arg.arg_name == arg_name)
+            {
+                Some(call_arg) => call_arg,
+                None => bail!("missing argument {}", arg_name),
+            };
+            let value = self.compile_value(module, scope, &call_arg.value)?;
+            let type_id = call_arg.value.type_id.as_ref().unwrap_or(&arg_type);
+            args.push(self.box_word(value, type_id)?.into());
+        }
+        // the words are followed by the source location
+        let mut param_types: Vec<BasicMetadataTypeEnum<'ctx>> = vec![i64_type.into(); args.len()];
+        param_types.push(self.i8_ptr_type().into());
+        param_types.push(i64_type.into());
+        param_types.push(i64_type.into());
+        let file = self
+            .builder
+            .build_global_string_ptr(&scope.file, "assert_file")
+            .as_pointer_value();
+        args.push(file.into());
+        args.push(i64_type.const_int(span.ln as u64, false).into());
+        args.push(i64_type.const_int(span.start as u64, false).into());
+        let func_type = self.context.void_type().fn_type(&param_types, false);
+        let func_value = self.get_or_declare_extern(module, func.symbol(), func_type);
+        self.builder.build_call(func_value, &args, "");
+        Ok(())
+    }
+
+    // Panics if `cond` is true and continues in a new block otherwise
+    pub(crate) fn build_panic_if(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        cond: IntValue<'ctx>,
+        message: &str,
+        span: &Span,
+    ) {
+        let panic_block = self.context.append_basic_block(scope.func_value, "panic");
+        let ok_block = self
+            .context
+            .append_basic_block(scope.func_value, "no_panic");
+        self.builder
+            .build_conditional_branch(cond, panic_block, ok_block);
+        self.builder.position_at_end(panic_block);
+        let message = self
+            .builder
+            .build_global_string_ptr(message, "panic_message")
+            .as_pointer_value();
+        self.build_panic(module, scope, message, span);
+        self.builder.position_at_end(ok_block);
+    }
+
+    // Integer division and remainder always check for a zero divisor
+    // and signed overflow (`MIN / -1`)
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn build_division_checks(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        lhs: IntValue<'ctx>,
+        op: &Op,
+        rhs: IntValue<'ctx>,
+        signed: bool,
+        span: &Span,
+    ) {
+        let is_rem = matches!(op, Op::Arith(ArithOp::Mod));
+        let int_type = rhs.get_type();
+        let zero = int_type.const_zero();
+        let is_zero = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, rhs, zero, "is_zero");
+        let message = if is_rem {
+            "attempt to calculate the remainder with a divisor of zero"
+        } else {
+            "attempt to divide by zero"
+        };
+        self.build_panic_if(module, scope, is_zero, message, span);
+        if signed {
+            let width = int_type.get_bit_width();
+            let min = int_type.const_int(1u64 << (width - 1), false);
+            let is_min = self
+                .builder
+                .build_int_compare(IntPredicate::EQ, lhs, min, "is_min");
+            let is_neg_one = self.builder.build_int_compare(
+                IntPredicate::EQ,
+                rhs,
+                int_type.const_all_ones(),
+                "is_neg_one",
+            );
+            let overflow = self.builder.build_and(is_min, is_neg_one, "overflow");
+            let message = if is_rem {
+                "attempt to calculate the remainder with overflow"
+            } else {
+                "attempt to divide with overflow"
+            };
+            self.build_panic_if(module, scope, overflow, message, span);
+        }
+    }
+
+    // Debug builds panic on `-MIN` (and negating any unsigned value except zero)
+    pub(crate) fn build_neg_checks(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        value: IntValue<'ctx>,
+        signed: bool,
+        span: &Span,
+    ) {
+        let int_type = value.get_type();
+        let overflow = if signed {
+            let min = int_type.const_int(1u64 << (int_type.get_bit_width() - 1), false);
+            self.builder
+                .build_int_compare(IntPredicate::EQ, value, min, "is_min")
+        } else {
+            self.builder.build_int_compare(
+                IntPredicate::NE,
+                value,
+                int_type.const_zero(),
+                "is_nonzero",
+            )
+        };
+        self.build_panic_if(
+            module,
+            scope,
+            overflow,
+            "attempt to negate with overflow",
+            span,
+        );
+    }
+
+    // Debug builds use the llvm overflow intrinsics for `+ - *`
+    // (i.e. `llvm.sadd.with.overflow.i32`) and panic on overflow
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn build_checked_arith(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        lhs: IntValue<'ctx>,
+        op: &Op,
+        rhs: IntValue<'ctx>,
+        signed: bool,
+        span: &Span,
+    ) -> Result<IntValue<'ctx>> {
+        let (name, verb) = match op {
+            Op::Arith(ArithOp::Add) => ("add", "add"),
+            Op::Arith(ArithOp::Sub) => ("sub", "subtract"),
+            Op::Arith(ArithOp::Mul) => ("mul", "multiply"),
+            _ => bail!("operator {:?} can't be overflow checked", op),
+        };
+        let int_type = lhs.get_type();
+        let intrinsic = format!(
+            "llvm.{}{}.with.overflow.i{}",
+            if signed { "s" } else { "u" },
+            name,
+            int_type.get_bit_width()
+        );
+        let result_type = self
+            .context
+            .struct_type(&[int_type.into(), self.context.bool_type().into()], false);
+        let func_type = result_type.fn_type(&[int_type.into(), int_type.into()], false);
+        let func_value = self.get_or_declare_extern(module, &intrinsic, func_type);
+        let result = match self
+            .builder
+            .build_call(func_value, &[lhs.into(), rhs.into()], name)
+            .try_as_basic_value()
+            .left()
+        {
+            Some(result) => result.into_struct_value(),
+            None => bail!("{} didn't return a value", &intrinsic),
+        };
+        let (value, overflow) = match (
+            self.builder.build_extract_value(result, 0, "value"),
+            self.builder.build_extract_value(result, 1, "overflow"),
+        ) {
+            (Some(value), Some(overflow)) => (value.into_int_value(), overflow.into_int_value()),
+            _ => bail!("unable to extract {} results", &intrinsic),
+        };
+        let message = format!("attempt to {} with overflow", verb);
+        self.build_panic_if(module, scope, overflow, &message, span);
+        Ok(value)
+    }
+
+    // Pushes the function name on the runtime backtrace
+    pub(crate) fn build_frame_push(&self, module: &Module<'ctx>, func_name: &str) {
+        let func_type = self
+            .context
+            .void_type()
+            .fn_type(&[self.i8_ptr_type().into()], false);
+        let func_value = self.get_or_declare_extern(module, FRAME_PUSH_SYMBOL, func_type);
+        let name = self
+            .builder
+            .build_global_string_ptr(func_name, "frame")
+            .as_pointer_value();
+        self.builder.build_call(func_value, &[name.into()], "");
+    }
+
+    // Pops the current function frame and returns
+    pub(crate) fn build_function_return(
+        &self,
+        module: &Module<'ctx>,
+        value: Option<BasicValueEnum<'ctx>>,
+    ) {
+        let func_type = self.context.void_type().fn_type(&[], false);
+        let func_value = self.get_or_declare_extern(module, FRAME_POP_SYMBOL, func_type);
+        self.builder.build_call(func_value, &[], "");
+        match value {
+            Some(value) => self.builder.build_return(Some(&value)),
+            None => self.builder.build_return(None),
+        };
+    }
+}
@@ -0,0 +1,631 @@
+use super::block::FunctionScope;
+use super::types::{int_width, is_signed};
+use super::Compiler;
+use crate::builtin::{is_collection, BuiltinMethod, HashMethod};
+use crate::check::{is_float, is_int};
+use crate::hir::CallValue;
+use anyhow::{bail, Result};
+use inkwell::module::{Linkage, Module};
+use inkwell::types::{BasicMetadataTypeEnum, BasicType};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue, PointerValue,
+};
+use inkwell::AddressSpace;
+use yak_core::types::function::FunctionId;
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+use yak_lexer::Span;
+
+// llvm label for function start
+const THUNK_ENTRY: &str = "enter";
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Converts a value into a 64-bit runtime word.
+    // Structs are copied to the heap and passed by pointer.
+    pub(crate) fn box_word(
+        &self,
+        value: BasicValueEnum<'ctx>,
+        type_id: &TypeId,
+    ) -> Result<IntValue<'ctx>> {
+        let i64_type = self.context.i64_type();
+        let word = if let Some(width) = int_width(type_id) {
+            let int_value = value.into_int_value();
+            if width == 64 {
+                int_value
+            } else if is_signed(type_id) {
+                self.builder.build_int_s_extend(int_value, i64_type, "word")
+            } else {
+                self.builder.build_int_z_extend(int_value, i64_type, "word")
+            }
+        } else if is_float(type_id) {
+            let f64_type = self.context.f64_type();
+            let float_value = value.into_float_value();
+            let float_value = if type_id.is("float32") {
+                self.builder.build_float_ext(float_value, f64_type, "fext")
+            } else {
+                float_value
+            };
+            self.builder
+                .build_bitcast(float_value, i64_type, "word")
+                .into_int_value()
+        } else if value.is_pointer_value() {
+            self.builder
+                .build_ptr_to_int(value.into_pointer_value(), i64_type, "word")
+        } else if value.is_struct_value() {
+            let ptr = match self.builder.build_malloc(value.get_type(), "boxed") {
+                Ok(ptr) => ptr,
+                Err(err) => bail!("unable to box {}: {}", type_id.name(), err),
+            };
+            self.builder.build_store(ptr, value);
+            self.builder.build_ptr_to_int(ptr, i64_type, "word")
+        } else {
+            bail!("type {} can't be stored in a Map or Set", type_id.name())
+        };
+        Ok(word)
+    }
+
+    // Converts a 64-bit runtime word back into a value
+    pub(crate) fn unbox_word(
+        &self,
+        word: IntValue<'ctx>,
+        type_id: &TypeId,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let ty = self.basic_type(type_id)?;
+        let value: BasicValueEnum<'ctx> = if let Some(width) = int_width(type_id) {
+            if width == 64 {
+                word.into()
+            } else {
+                self.builder
+                    .build_int_truncate(word, ty.into_int_type(), "unword")
+                    .into()
+            }
+        } else if is_float(type_id) {
+            let f64_type = self.context.f64_type();
+            let float_value = self
+                .builder
+                .build_bitcast(word, f64_type, "unword")
+                .into_float_value();
+            if type_id.is("float32") {
+                self.builder
+                    .build_float_trunc(float_value, ty.into_float_type(), "ftrunc")
+                    .into()
+            } else {
+                float_value.into()
+            }
+        } else if ty.is_pointer_type() {
+            self.builder
+                .build_int_to_ptr(word, ty.into_pointer_type(), "unword")
+                .into()
+        } else if ty.is_struct_type() {
+            let ptr_type = ty.ptr_type(AddressSpace::default());
+            let ptr = self.builder.build_int_to_ptr(word, ptr_type, "unboxed");
+            self.builder.build_load(ptr, "unboxed")
+        } else {
+            bail!("type {} can't be loaded from a Map or Set", type_id.name())
+        };
+        Ok(value)
+    }
+
+    // Returns true if `box_word` copies values of the type to the heap
+    fn is_boxed(&self, type_id: &TypeId) -> Result<bool> {
+        Ok(self.basic_type(type_id)?.is_struct_type())
+    }
+
+    // Frees a word which was boxed by `box_word`
+    pub(crate) fn build_free_word(
+        &self,
+        module: &Module<'ctx>,
+        word: IntValue<'ctx>,
+        type_id: &TypeId,
+    ) -> Result<()> {
+        if self.is_boxed(type_id)? {
+            let free_fn = self.free_word_function(module, "yak_free_word");
+            self.builder.build_call(free_fn, &[word.into()], "");
+        }
+        Ok(())
+    }
+
+    fn free_word_function(&self, module: &Module<'ctx>, symbol: &str) -> FunctionValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let func_type = self.context.void_type().fn_type(&[i64_type.into()], false);
+        self.get_or_declare_extern(module, symbol, func_type)
+    }
+
+    // The runtime drop function for words of the type (null for unboxed words)
+    fn drop_function(&self, module: &Module<'ctx>, type_id: &TypeId) -> Result<PointerValue<'ctx>> {
+        let ptr_type = self.i8_ptr_type();
+        if !self.is_boxed(type_id)? {
+            return Ok(ptr_type.const_null());
+        }
+        // the runtime owns trait objects so it also frees their boxed value
+        let symbol = if type_id.is_trait() {
+            "yak_free_object"
+        } else {
+            "yak_free_word"
+        };
+        let free_fn = self.free_word_function(module, symbol);
+        Ok(self.builder.build_pointer_cast(
+            free_fn.as_global_value().as_pointer_value(),
+            ptr_type,
+            "drop_fn",
+        ))
+    }
+
+    // Returns the runtime hash and eq functions for a key type
+    fn hash_eq_functions(
+        &self,
+        module: &Module<'ctx>,
+        key_type: &TypeId,
+    ) -> Result<(FunctionValue<'ctx>, FunctionValue<'ctx>)> {
+        let i64_type = self.context.i64_type();
+        let bool_type = self.context.bool_type();
+        let hash_type = i64_type.fn_type(&[i64_type.into()], false);
+        let eq_type = bool_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+        let (hash_name, eq_name) = if is_int(key_type) || key_type.is("bool") {
+            ("yak_hash_int", "yak_eq_int")
+        } else if is_float(key_type) {
+            ("yak_hash_float", "yak_eq_float")
+        } else if key_type.is("String") || key_type.is("str") {
+            ("yak_hash_str", "yak_eq_str")
+        } else {
+            return self.struct_hash_eq_thunks(module, key_type);
+        };
+        Ok((
+            self.get_or_declare_extern(module, hash_name, hash_type),
+            self.get_or_declare_extern(module, eq_name, eq_type),
+        ))
+    }
+
+    // Calls the runtime hash or eq function of a primitive or String
+    pub(crate) fn compile_hash_method(
+        &self,
+        module: &Module<'ctx>,
+        scope: &mut FunctionScope<'ctx>,
+        call: &CallValue,
+        method: HashMethod,
+        type_id: &TypeId,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let mut words: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        for (arg_name, arg_type) in method.args(type_id) {
+            let call_arg = match call
+                .args
+                .iter()
+                .find(|call_arg| call_arg.arg_name == arg_name)
+            {
+                Some(call_arg) => call_arg,
+                None => bail!("missing argument {}", arg_name),
+            };
+            let value = self.compile_value(module, scope, &call_arg.value)?;
+            words.push(self.box_word(value, &arg_type)?.into());
+        }
+        let (hash_fn, eq_fn) = self.hash_eq_functions(module, type_id)?;
+        let func = match method {
+            HashMethod::Hash => hash_fn,
+            HashMethod::Eq => eq_fn,
+        };
+        match self
+            .builder
+            .build_call(func, &words, "call")
+            .try_as_basic_value()
+            .left()
+        {
+            Some(value) => Ok(value),
+            None => bail!("{} didn't return a value", type_id.type_name),
+        }
+    }
+
+    // Generates `u64 (u64)` and `bool (u64, u64)` thunks which unbox
+    // struct keys and call the `^Hash` and `^Eq` implementations
+    fn struct_hash_eq_thunks(
+        &self,
+        module: &Module<'ctx>,
+        key_type: &TypeId,
+    ) -> Result<(FunctionValue<'ctx>, FunctionValue<'ctx>)> {
+        let struct_def = match self.hir.find_struct(key_type) {
+            Some(struct_def) => struct_def,
+            None => bail!("unknown key type {}", key_type.name()),
+        };
+        let struct_name = struct_def.type_id.type_name.clone();
+        let pkg_name = struct_def.type_id.pkg_name.clone();
+        // generic struct instances use the matching impl instances
+        let generics = struct_def.type_id.generics.clone();
+        let hash_id = FunctionId::new(pkg_name.clone(), Some(struct_name.clone()), ":hash".into())
+            .with_generics(generics.clone());
+        let eq_id =
+            FunctionId::new(pkg_name, Some(struct_name), ":eq".into()).with_generics(generics);
+        let (hash_def, eq_def) = match (
+            self.hir.find_function(&hash_id),
+            self.hir.find_function(&eq_id),
+        ) {
+            (Some(hash_def), Some(eq_def)) => (hash_def, eq_def),
+            _ => bail!(
+                "type {} must implement ^Hash and ^Eq to be used as a key",
+                key_type.name()
+            ),
+        };
+
+        let i64_type = self.context.i64_type();
+        let bool_type = self.context.bool_type();
+        let saved_block = self.builder.get_insert_block();
+        let saved_location = self.suspend_debug_location();
+
+        // hash thunk
+        let hash_thunk_name = format!("{}$hash", struct_def.type_id.name());
+        let hash_thunk = match module.get_function(&hash_thunk_name) {
+            Some(thunk) => thunk,
+            None => {
+                let thunk_type = i64_type.fn_type(&[i64_type.into()], false);
+                let thunk =
+                    module.add_function(&hash_thunk_name, thunk_type, Some(Linkage::Private));
+                let entry = self.context.append_basic_block(thunk, THUNK_ENTRY);
+                self.builder.position_at_end(entry);
+                let word = thunk.get_nth_param(0).unwrap().into_int_value();
+                let key = self.unbox_word(word, key_type)?;
+                let hash_fn = self.get_or_declare_function(module, hash_def)?;
+                let hash = self
+                    .builder
+                    .build_call(hash_fn, &[key.into()], "hash")
+                    .try_as_basic_value()
+                    .left();
+                match hash {
+                    Some(hash) => self.builder.build_return(Some(&hash)),
+                    None => bail!("{} :hash must return uint64", key_type.name()),
+                };
+                thunk
+            }
+        };
+
+        // eq thunk
+        let eq_thunk_name = format!("{}$eq", struct_def.type_id.name());
+        let eq_thunk = match module.get_function(&eq_thunk_name) {
+            Some(thunk) => thunk,
+            None => {
+                let thunk_type = bool_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+                let thunk = module.add_function(&eq_thunk_name, thunk_type, Some(Linkage::Private));
+                let entry = self.context.append_basic_block(thunk, THUNK_ENTRY);
+                self.builder.position_at_end(entry);
+                let a =
+                    self.unbox_word(thunk.get_nth_param(0).unwrap().into_int_value(), key_type)?;
+                let b =
+                    self.unbox_word(thunk.get_nth_param(1).unwrap().into_int_value(), key_type)?;
+                let eq_fn = self.get_or_declare_function(module, eq_def)?;
+                let eq = self
+                    .builder
+                    .build_call(eq_fn, &[a.into(), b.into()], "eq")
+                    .try_as_basic_value()
+                    .left();
+                match eq {
+                    Some(eq) => self.builder.build_return(Some(&eq)),
+                    None => bail!("{} :eq must return bool", key_type.name()),
+                };
+                thunk
+            }
+        };
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        self.restore_debug_location(saved_location);
+        Ok((hash_thunk, eq_thunk))
+    }
+
+    // Creates a new runtime Map, Set or List handle which owns its boxed words
+    pub(crate) fn build_runtime_new(
+        &self,
+        module: &Module<'ctx>,
+        type_id: &TypeId,
+    ) -> Result<PointerValue<'ctx>> {
+        let ptr_type = self.i8_ptr_type();
+        let mut new_args: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        let symbol = format!("{}_new", runtime_prefix(type_id));
+        // lists don't hash or compare their items
+        if !type_id.is("List") {
+            let (hash_fn, eq_fn) = self.hash_eq_functions(module, &type_id.generics[0])?;
+            let hash_ptr = self.builder.build_pointer_cast(
+                hash_fn.as_global_value().as_pointer_value(),
+                ptr_type,
+                "hash_fn",
+            );
+            let eq_ptr = self.builder.build_pointer_cast(
+                eq_fn.as_global_value().as_pointer_value(),
+                ptr_type,
+                "eq_fn",
+            );
+            new_args.push(hash_ptr.into());
+            new_args.push(eq_ptr.into());
+        }
+        for generic in &type_id.generics {
+            new_args.push(self.drop_function(module, generic)?.into());
+        }
+        let new_type = ptr_type.fn_type(&vec![ptr_type.into(); new_args.len()], false);
+        let new_fn = self.get_or_declare_extern(module, &symbol, new_type);
+        let handle = self
+            .builder
+            .build_call(new_fn, &new_args, "new")
+            .try_as_basic_value()
+            .left();
+        match handle {
+            Some(handle) => Ok(handle.into_pointer_value()),
+            None => bail!("{} should return a handle", symbol),
+        }
+    }
+
+    // Calls a runtime Map, Set or List function with a handle and word arguments
+    pub(crate) fn build_runtime_call(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        method: BuiltinMethod,
+        type_id: &TypeId,
+        args: &[BasicMetadataValueEnum<'ctx>],
+        span: &Span,
+    ) -> Result<Option<BasicValueEnum<'ctx>>> {
+        let i64_type = self.context.i64_type();
+        let bool_type = self.context.bool_type();
+        let ptr_type = self.i8_ptr_type();
+        let symbol = method.symbol();
+        let value = match method {
+            BuiltinMethod::MapNew | BuiltinMethod::SetNew | BuiltinMethod::ListNew => {
+                bail!("use build_runtime_new to create {}", symbol)
+            }
+            BuiltinMethod::MapInsert => {
+                let func_type =
+                    bool_type.fn_type(&[ptr_type.into(), i64_type.into(), i64_type.into()], false);
+                let func = self.get_or_declare_extern(module, symbol, func_type);
+                self.builder
+                    .build_call(func, args, "insert")
+                    .try_as_basic_value()
+                    .left()
+            }
+            BuiltinMethod::SetInsert
+            | BuiltinMethod::SetRemove
+            | BuiltinMethod::SetContains
+            | BuiltinMethod::MapContains => {
+                let func_type = bool_type.fn_type(&[ptr_type.into(), i64_type.into()], false);
+                let func = self.get_or_declare_extern(module, symbol, func_type);
+                self.builder
+                    .build_call(func, args, "call")
+                    .try_as_basic_value()
+                    .left()
+            }
+            BuiltinMethod::MapLen | BuiltinMethod::SetLen | BuiltinMethod::ListLen => {
+                let func_type = i64_type.fn_type(&[ptr_type.into()], false);
+                let func = self.get_or_declare_extern(module, symbol, func_type);
+                self.builder
+                    .build_call(func, args, "len")
+                    .try_as_basic_value()
+                    .left()
+            }
+            // returns Option[V]
+            BuiltinMethod::MapGet | BuiltinMethod::MapRemove => {
+                let (found, out) = self.build_runtime_out_call(module, scope, symbol, args)?;
+                let value_type = &type_id.generics[1];
+                // removed values are owned by the caller
+                let owned = method == BuiltinMethod::MapRemove;
+                Some(self.build_option_from_word(module, found, out, value_type, owned)?)
+            }
+            // returns Option[T] and the popped item is owned by the caller
+            BuiltinMethod::ListPop => {
+                let (found, out) = self.build_runtime_out_call(module, scope, symbol, args)?;
+                let item_type = &type_id.generics[0];
+                Some(self.build_option_from_word(module, found, out, item_type, true)?)
+            }
+            BuiltinMethod::MapIndex => {
+                let (found, out) = self.build_runtime_out_call(module, scope, symbol, args)?;
+                let missing = self.builder.build_not(found, "missing");
+                self.build_panic_if(module, scope, missing, "key not found", span);
+                let word = self.builder.build_load(out, "word").into_int_value();
+                Some(self.unbox_word(word, &type_id.generics[1])?)
+            }
+            BuiltinMethod::ListGet => {
+                let (found, out) = self.build_runtime_out_call(module, scope, symbol, args)?;
+                let out_of_bounds = self.builder.build_not(found, "out_of_bounds");
+                self.build_panic_if(module, scope, out_of_bounds, "index out of bounds", span);
+                let word = self.builder.build_load(out, "word").into_int_value();
+                Some(self.unbox_word(word, &type_id.generics[0])?)
+            }
+            BuiltinMethod::ListSet => {
+                let func_type =
+                    bool_type.fn_type(&[ptr_type.into(), i64_type.into(), i64_type.into()], false);
+                let func = self.get_or_declare_extern(module, symbol, func_type);
+                let found = self
+                    .builder
+                    .build_call(func, args, "set")
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap()
+                    .into_int_value();
+                let out_of_bounds = self.builder.build_not(found, "out_of_bounds");
+                self.build_panic_if(module, scope, out_of_bounds, "index out of bounds", span);
+                None
+            }
+            BuiltinMethod::ListPush => {
+                let func_type = self
+                    .context
+                    .void_type()
+                    .fn_type(&[ptr_type.into(), i64_type.into()], false);
+                let func = self.get_or_declare_extern(module, symbol, func_type);
+                self.builder.build_call(func, args, "");
+                None
+            }
+        };
+        Ok(value)
+    }
+
+    // Calls a runtime function which writes a word to an `out` slot
+    // (appended to the args) and returns true if it wrote one
+    fn build_runtime_out_call(
+        &self,
+        module: &Module<'ctx>,
+        scope: &FunctionScope<'ctx>,
+        symbol: &str,
+        args: &[BasicMetadataValueEnum<'ctx>],
+    ) -> Result<(IntValue<'ctx>, PointerValue<'ctx>)> {
+        let i64_type = self.context.i64_type();
+        let i64_ptr_type = i64_type.ptr_type(AddressSpace::default());
+        // the handle and words followed by the out slot
+        let mut param_types: Vec<BasicMetadataTypeEnum<'ctx>> = vec![i64_type.into(); args.len()];
+        param_types[0] = self.i8_ptr_type().into();
+        param_types.push(i64_ptr_type.into());
+        let func_type = self.context.bool_type().fn_type(&param_types, false);
+        let func = self.get_or_declare_extern(module, symbol, func_type);
+        let out = self.build_entry_alloca(scope, i64_type.into(), "out")?;
+        self.builder.build_store(out, i64_type.const_zero());
+        let mut call_args = args.to_vec();
+        call_args.push(out.into());
+        let found = self
+            .builder
+            .build_call(func, &call_args, "found")
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        Ok((found, out))
+    }
+
+    // Builds `{ i1, T }` where T is only unboxed if the value was found
+    // (and freed after unboxing if the word is `owned`)
+    fn build_option_from_word(
+        &self,
+        module: &Module<'ctx>,
+        found: IntValue<'ctx>,
+        out: PointerValue<'ctx>,
+        value_type: &TypeId,
+        owned: bool,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let option_type = TypeId::builtin("Option").with_generics(vec![value_type.clone()]);
+        let option_llvm_type = self.basic_type(&option_type)?.into_struct_type();
+        let value_llvm_type = self.basic_type(value_type)?;
+
+        let current = match self.builder.get_insert_block() {
+            Some(current) => current,
+            None => bail!("builder isn't positioned"),
+        };
+        let func_value = match current.get_parent() {
+            Some(func_value) => func_value,
+            None => bail!("builder block has no parent function"),
+        };
+        let some_block = self.context.append_basic_block(func_value, "opt_some");
+        let merge_block = self.context.append_basic_block(func_value, "opt_end");
+        self.builder
+            .build_conditional_branch(found, some_block, merge_block);
+
+        self.builder.position_at_end(some_block);
+        let word = self.builder.build_load(out, "word").into_int_value();
+        let some_value = self.unbox_word(word, value_type)?;
+        if owned {
+            self.build_free_word(module, word, value_type)?;
+        }
+        let some_block_end = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(merge_block);
+
+        self.builder.position_at_end(merge_block);
+        let phi = self.builder.build_phi(value_llvm_type, "opt_value");
+        let none_value = value_llvm_type.const_zero();
+        phi.add_incoming(&[(&some_value, some_block_end), (&none_value, current)]);
+
+        let option = option_llvm_type.get_undef();
+        let option = self
+            .builder
+            .build_insert_value(option, found, 0, "opt_is_some")
+            .unwrap()
+            .into_struct_value();
+        let option = self
+            .builder
+            .build_insert_value(option, phi.as_basic_value(), 1, "opt")
+            .unwrap()
+            .into_struct_value();
+        Ok(option.into())
+    }
+
+    pub(crate) fn build_runtime_free(
+        &self,
+        module: &Module<'ctx>,
+        collection: PointerValue<'ctx>,
+        type_id: &TypeId,
+    ) {
+        let ptr_type = self.i8_ptr_type();
+        let func_type = self.context.void_type().fn_type(&[ptr_type.into()], false);
+        let symbol = format!("{}_free", runtime_prefix(type_id));
+        let func = self.get_or_declare_extern(module, &symbol, func_type);
+        self.builder.build_call(func, &[collection.into()], "");
+    }
+
+    pub(crate) fn build_runtime_iter(
+        &self,
+        module: &Module<'ctx>,
+        collection: PointerValue<'ctx>,
+        type_id: &TypeId,
+    ) -> PointerValue<'ctx> {
+        let ptr_type = self.i8_ptr_type();
+        let func_type = ptr_type.fn_type(&[ptr_type.into()], false);
+        let symbol = format!("{}_iter", runtime_prefix(type_id));
+        let func = self.get_or_declare_extern(module, &symbol, func_type);
+        self.builder
+            .build_call(func, &[collection.into()], "iter")
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value()
+    }
+
+    pub(crate) fn build_runtime_iter_next(
+        &self,
+        module: &Module<'ctx>,
+        iter: PointerValue<'ctx>,
+        key_ptr: PointerValue<'ctx>,
+        value_ptr: PointerValue<'ctx>,
+        type_id: &TypeId,
+    ) -> IntValue<'ctx> {
+        let ptr_type = self.i8_ptr_type();
+        let i64_ptr_type = self.context.i64_type().ptr_type(AddressSpace::default());
+        let bool_type = self.context.bool_type();
+        let symbol = format!("{}_iter_next", runtime_prefix(type_id));
+        let call_site = if type_id.is("Map") {
+            let func_type = bool_type.fn_type(
+                &[ptr_type.into(), i64_ptr_type.into(), i64_ptr_type.into()],
+                false,
+            );
+            let func = self.get_or_declare_extern(module, &symbol, func_type);
+            self.builder.build_call(
+                func,
+                &[iter.into(), key_ptr.into(), value_ptr.into()],
+                "next",
+            )
+        } else {
+            let func_type = bool_type.fn_type(&[ptr_type.into(), i64_ptr_type.into()], false);
+            let func = self.get_or_declare_extern(module, &symbol, func_type);
+            self.builder
+                .build_call(func, &[iter.into(), key_ptr.into()], "next")
+        };
+        call_site
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value()
+    }
+
+    pub(crate) fn build_runtime_iter_free(
+        &self,
+        module: &Module<'ctx>,
+        iter: PointerValue<'ctx>,
+        type_id: &TypeId,
+    ) {
+        let ptr_type = self.i8_ptr_type();
+        let func_type = self.context.void_type().fn_type(&[ptr_type.into()], false);
+        let symbol = format!("{}_iter_free", runtime_prefix(type_id));
+        let func = self.get_or_declare_extern(module, &symbol, func_type);
+        self.builder.build_call(func, &[iter.into()], "");
+    }
+}
+
+// The yak-std symbol prefix for a collection type
+fn runtime_prefix(type_id: &TypeId) -> &'static str {
+    if type_id.is("Map") {
+        "yak_map"
+    } else if type_id.is("Set") {
+        "yak_set"
+    } else {
+        "yak_list"
+    }
+}
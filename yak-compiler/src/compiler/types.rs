@@ -0,0 +1,176 @@
+use super::Compiler;
+use crate::builtin::is_collection;
+use crate::hir::{FunctionDef, Hir};
+use anyhow::{bail, Result};
+use inkwell::module::Module;
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType, StructType};
+use inkwell::values::FunctionValue;
+use inkwell::AddressSpace;
+use std::collections::HashMap;
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+
+// Returns the bit width for integer primitives
+pub(crate) fn int_width(type_id: &TypeId) -> Option<u32> {
+    if !type_id.is_primitive() {
+        return None;
+    }
+    let width = match type_id.type_name.as_str() {
+        "bool" => 1,
+        "byte" | "int8" | "uint8" => 8,
+        "int16" | "uint16" => 16,
+        "char" | "int" | "uint" | "int32" | "uint32" => 32,
+        "int64" | "uint64" => 64,
+        _ => return None,
+    };
+    Some(width)
+}
+
+pub(crate) fn is_signed(type_id: &TypeId) -> bool {
+    type_id.is_primitive() && type_id.type_name.starts_with("int")
+}
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    // Create named llvm struct types for every struct in the hir.
+    // This runs before compiling modules so structs can reference each other.
+    // Generic structs only have types for their instances.
+    pub(crate) fn create_struct_types(&mut self, hir: &Hir) -> Result<()> {
+        let mut struct_types: HashMap<String, StructType<'ctx>> = HashMap::new();
+        let struct_defs: Vec<_> = hir
+            .modules
+            .iter()
+            .flat_map(|module| module.struct_defs.iter())
+            .filter(|struct_def| struct_def.generics.is_empty())
+            .collect();
+        for struct_def in &struct_defs {
+            let name = struct_def.type_id.name();
+            struct_types.insert(name.clone(), self.context.opaque_struct_type(&name));
+        }
+        self.struct_types = struct_types;
+        for struct_def in &struct_defs {
+            let mut field_types = vec![];
+            for field in &struct_def.fields {
+                field_types.push(self.basic_type(&field.type_id)?);
+            }
+            self.struct_types[&struct_def.type_id.name()].set_body(&field_types, false);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn i8_ptr_type(&self) -> inkwell::types::PointerType<'ctx> {
+        self.context.i8_type().ptr_type(AddressSpace::default())
+    }
+
+    pub(crate) fn struct_type(&self, type_id: &TypeId) -> Option<StructType<'ctx>> {
+        if type_id.is_tuple() {
+            return self.tuple_type(type_id).ok();
+        }
+        let struct_def = self.hir.find_struct(type_id)?;
+        self.struct_types.get(&struct_def.type_id.name()).copied()
+    }
+
+    // Anonymous tuples are literal structs of their element types
+    fn tuple_type(&self, type_id: &TypeId) -> Result<StructType<'ctx>> {
+        let mut element_types = vec![];
+        for element in &type_id.generics {
+            element_types.push(self.basic_type(element)?);
+        }
+        Ok(self.context.struct_type(&element_types, false))
+    }
+
+    // Maps a yak type to an llvm type
+    pub(crate) fn basic_type(&self, type_id: &TypeId) -> Result<BasicTypeEnum<'ctx>> {
+        if let Some(width) = int_width(type_id) {
+            return Ok(self.context.custom_width_int_type(width).into());
+        }
+        if type_id.is("float32") {
+            return Ok(self.context.f32_type().into());
+        }
+        if type_id.is("float") || type_id.is("float64") {
+            return Ok(self.context.f64_type().into());
+        }
+        // runtime handles
+        if type_id.is("String") || type_id.is("str") || is_collection(type_id) {
+            return Ok(self.i8_ptr_type().into());
+        }
+        // Option[T] is `{ i1, T }`
+        if type_id.is("Option") {
+            let inner = match type_id.generics.first() {
+                Some(inner) => self.basic_type(inner)?,
+                None => bail!("Option requires a type argument"),
+            };
+            let is_some = self.context.bool_type().into();
+            return Ok(self.context.struct_type(&[is_some, inner], false).into());
+        }
+        // Maybe[T E] is `{ i1, T, E }`
+        if type_id.is("Maybe") {
+            if type_id.generics.len() != 2 {
+                bail!("Maybe requires two type arguments");
+            }
+            let is_ok = self.context.bool_type().into();
+            let value = self.basic_type(&type_id.generics[0])?;
+            let error = self.basic_type(&type_id.generics[1])?;
+            return Ok(self
+                .context
+                .struct_type(&[is_ok, value, error], false)
+                .into());
+        }
+        if type_id.is_trait() {
+            return Ok(self.object_type().into());
+        }
+        if type_id.is_function() {
+            return Ok(self.function_value_type().into());
+        }
+        if type_id.is_tuple() {
+            return Ok(self.tuple_type(type_id)?.into());
+        }
+        if let Some(struct_type) = self.struct_type(type_id) {
+            return Ok(struct_type.into());
+        }
+        if type_id.is_primitive() || type_id.is_builtin() {
+            bail!("type {} isn't supported yet", &type_id.type_name);
+        }
+        // the checker rejects types which aren't defined
+        unreachable!("unknown type {}", type_id.name())
+    }
+
+    pub(crate) fn function_type(&self, func_def: &FunctionDef) -> Result<FunctionType<'ctx>> {
+        let mut arg_types: Vec<BasicMetadataTypeEnum<'ctx>> = vec![];
+        for arg in &func_def.args {
+            arg_types.push(self.basic_type(&arg.type_id)?.into());
+        }
+        let func_type = match &func_def.return_type {
+            Some(return_type) => self.basic_type(return_type)?.fn_type(&arg_types, false),
+            None => self.context.void_type().fn_type(&arg_types, false),
+        };
+        Ok(func_type)
+    }
+
+    // Returns the module function for `func_def`
+    // and declares it if it's defined by another module
+    pub(crate) fn get_or_declare_function(
+        &self,
+        module: &Module<'ctx>,
+        func_def: &FunctionDef,
+    ) -> Result<FunctionValue<'ctx>> {
+        let func_name = func_def.function_id.name();
+        if let Some(func_value) = module.get_function(&func_name) {
+            return Ok(func_value);
+        }
+        let func_type = self.function_type(func_def)?;
+        Ok(module.add_function(&func_name, func_type, None))
+    }
+
+    // Returns an external runtime function
+    pub(crate) fn get_or_declare_extern(
+        &self,
+        module: &Module<'ctx>,
+        name: &str,
+        func_type: FunctionType<'ctx>,
+    ) -> FunctionValue<'ctx> {
+        match module.get_function(name) {
+            Some(func_value) => func_value,
+            None => module.add_function(name, func_type, None),
+        }
+    }
+}
@@ -1,11 +1,23 @@
+use crate::builtin::{is_collection, INDEX_FUNC, TRAIT_EQ, TRAIT_HASH};
+use crate::check::type_eq;
 use anyhow::{bail, Context, Error, Result};
-use yak_ast::{Ast, ConstStmt, FuncInputArgTypeStmt, FuncInputTypeStmt, FuncStmt, StructStmt};
+use std::path::PathBuf;
+use yak_ast::{
+    ArithOp, AssignOp, Ast, BitwiseOp, Block as BlockStmt, BlockStmt as BlockGroupStmt,
+    ClosureValueStmt, CollectionValueStmt, ConstStmt, Expr, ExprStmt, ForInStmt,
+    FuncInputArgTypeStmt, FuncInputTypeStmt, FuncStmt, FuncValueStmt, IfStmt, ImplTraitStmt,
+    NewTypeStmt, Op, PackageSymbol, PostfixExprStmt, PostfixOp, StructStmt, StructValueStmt,
+    TestCaseStmt, TestStmt, TraitStmt, TupleTypeStmt, TupleValueStmt, TypeStmt, UnaryOp, Value,
+    ValueStmt,
+};
 use yak_core::types::constant::ConstantId;
 use yak_core::types::field::FieldId;
 use yak_core::types::function::{FunctionArgId, FunctionId};
 use yak_core::types::module::ModuleId;
-use yak_core::types::types::TypeId;
+use yak_core::types::types::{TypeId, TUPLE_TYPE};
 use yak_core::utils::clean_quotes;
+use yak_lexer::token::TokenType as Ty;
+use yak_lexer::Span;
 
 trait Lower<Stmt> {
     fn lower(stmt: &Stmt, opts: Opts) -> Result<Self>
@@ -18,6 +30,12 @@ struct Opts {
     pkg_id: Option<String>,
     // required for FunctionDef
     struct_name: Option<String>,
+    // impl type parameters (i.e. `T` in `impl Box[T]`)
+    struct_generics: Vec<String>,
+    // all type parameters in scope
+    generics: Vec<String>,
+    // trait bounds of the type parameters in scope
+    bounds: Vec<TraitBound>,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -27,6 +45,11 @@ pub struct ModuleDef {
     pub struct_defs: Vec<StructDef>,
     pub function_defs: Vec<FunctionDef>,
     pub constant_defs: Vec<ConstantDef>,
+    pub trait_defs: Vec<TraitDef>,
+    pub impl_trait_defs: Vec<ImplTraitDef>,
+    pub alias_defs: Vec<AliasDef>,
+    pub test_defs: Vec<TestDef>,
+    pub imports: Vec<ImportDef>,
 }
 
 impl ModuleDef {
@@ -35,15 +58,744 @@ impl ModuleDef {
             .iter()
             .find(|func_def| func_def.function_id.is_main)
     }
+
+    pub fn is_test(&self, function_id: &FunctionId) -> bool {
+        self.test_defs
+            .iter()
+            .any(|test_def| &test_def.function_id == function_id)
+    }
+}
+
+// Converts a TypeStmt into a TypeId.
+// Primitives and builtins live in their own packages
+// and everything else is assumed to be defined by `pkg_id`.
+pub fn lower_type(stmt: &TypeStmt, pkg_id: &str) -> TypeId {
+    lower_generic_type(stmt, pkg_id, &[])
+}
+
+// Same as lower_type but names in `params` are type parameters
+fn lower_generic_type(stmt: &TypeStmt, pkg_id: &str, params: &[String]) -> TypeId {
+    if stmt.generics.is_none() && params.contains(&stmt.type_name) {
+        return TypeId::generic(&stmt.type_name);
+    }
+    if let Some(func_type) = &stmt.func_type {
+        let args = func_type
+            .input_type
+            .as_ref()
+            .map(|input_type| {
+                input_type
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        (
+                            arg.arg_name.clone(),
+                            lower_generic_type(&arg.arg_type, pkg_id, params),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let return_type = func_type
+            .output_type
+            .as_ref()
+            .map(|output| lower_generic_type(&output.output_type, pkg_id, params));
+        return TypeId::function(args, return_type);
+    }
+    let generics = stmt
+        .generics
+        .as_ref()
+        .map(|generics| {
+            generics
+                .iter()
+                .map(|generic| lower_generic_type(generic, pkg_id, params))
+                .collect()
+        })
+        .unwrap_or_default();
+    let type_name = stmt.type_name.as_str();
+    let is_primitive = Ty::primitives().into_iter().any(|ty| {
+        let name: String = ty.into();
+        name == type_name
+    });
+    let is_builtin = Ty::builtins().into_iter().any(|ty| {
+        let name: String = ty.into();
+        name == type_name
+    });
+    let type_id = if is_primitive {
+        TypeId::primitive(type_name)
+    } else if type_name == TUPLE_TYPE {
+        TypeId::tuple(vec![])
+    } else if is_builtin {
+        TypeId::builtin(type_name)
+    } else if type_name.starts_with('^') {
+        lower_trait_id(type_name, pkg_id)
+    } else {
+        TypeId::new(pkg_id.into(), type_name.into())
+    };
+    type_id.with_generics(generics)
+}
+
+//
+// Function body blocks
+//
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Let(LetDef),
+    Assign(AssignDef),
+    Expr(ExprValue),
+    If(If),
+    ForIn(ForInDef),
+    Return(Option<ExprValue>),
+}
+
+impl Block {
+    // The statement location used by debug info
+    // (the default span if the statement doesn't have one)
+    pub fn span(&self) -> Span {
+        match self {
+            Block::Let(let_def) => let_def.span,
+            Block::Assign(assign_def) => assign_def.span,
+            Block::Expr(expr) => expr.span,
+            Block::If(if_def) => if_def.span,
+            Block::ForIn(for_def) => for_def.span,
+            Block::Return(Some(value)) => value.span,
+            Block::Return(None) => Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct If {
+    // if + elif conditions
+    pub conditions: Vec<Condition>,
+    pub else_blocks: Option<Vec<Block>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub expr: ExprValue,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForInDef {
+    // `for k v in` binds two vars for maps
+    pub vars: Vec<String>,
+    pub iter: ExprValue,
+    pub blocks: Vec<Block>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetDef {
+    pub var_name: String,
+    pub type_id: Option<TypeId>,
+    pub value: ExprValue,
+    pub mutable: bool,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignDef {
+    pub var_name: String,
+    // struct field path (i.e. `p.x.y = 1` has the fields x and y)
+    pub fields: Vec<String>,
+    pub value: ExprValue,
+    pub span: Span,
+}
+
+impl AssignDef {
+    // The assigned variable or field as a value
+    pub fn target(&self) -> ExprValue {
+        let mut value = ExprValue::new(ExprKind::Var(self.var_name.clone()));
+        for field in &self.fields {
+            value = ExprValue::new(ExprKind::Field(Box::new(value), field.clone()));
+        }
+        value
+    }
+}
+
+// Expressions are annotated with a type_id by the checker
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprValue {
+    pub kind: ExprKind,
+    pub type_id: Option<TypeId>,
+    // source location used by runtime panics
+    pub span: Span,
+}
+
+impl ExprValue {
+    pub fn new(kind: ExprKind) -> Self {
+        ExprValue {
+            kind,
+            type_id: None,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    None,
+    Bool(bool),
+    Int(IntValue),
+    Float(FloatValue),
+    String(StringValue),
+    Var(String),
+    Field(Box<ExprValue>, String),
+    Call(CallValue),
+    Struct(StructValue),
+    Collection(CollectionValue),
+    Unary(UnaryOp, Box<ExprValue>),
+    Binary(Box<ExprValue>, Op, Box<ExprValue>),
+    // the `None` literal (an empty Option)
+    OptionNone,
+    // `value?` returns early if value is None or an error
+    Try(Box<ExprValue>),
+    // a concrete value used as a trait object (added by the checker)
+    Object(Box<ExprValue>),
+    Closure(ClosureValue),
+    // `value as Type` for numeric and bool values
+    Cast(Box<ExprValue>, TypeId),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntValue {
+    pub value: u64,
+    pub signed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatValue {
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringValue {
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallValue {
+    // `Type:func {}` calls
+    pub func_type: Option<TypeId>,
+    pub func_name: String,
+    pub args: Vec<CallArg>,
+    // resolved by the checker
+    pub function_id: Option<FunctionId>,
+    // `:f {}` calls of a local function value (resolved by the checker)
+    pub value_type: Option<TypeId>,
+    // `value:func {}` calls (the value is the `self` arg
+    // and the checker resolves `func_type` from its type)
+    pub receiver: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallArg {
+    pub arg_name: String,
+    pub value: ExprValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    pub type_id: TypeId,
+    pub fields: Vec<StructFieldValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructFieldValue {
+    pub field_name: String,
+    pub value: ExprValue,
+}
+
+// Anonymous function (i.e. `fn { x: int } int => x + 1`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosureValue {
+    pub args: Vec<FunctionArg>,
+    pub return_type: Option<TypeId>,
+    pub body: Box<ExprValue>,
+    // outer variables used by the body (added by the checker)
+    pub captures: Vec<Capture>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    pub var_name: String,
+    pub type_id: TypeId,
+    // `let` variables are captured by reference and `const` by value
+    pub by_ref: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionValue {
+    pub type_id: TypeId,
+    // map items alternate between keys and values
+    pub items: Vec<ExprValue>,
+}
+
+impl Lower<ExprStmt> for ExprValue {
+    fn lower(stmt: &ExprStmt, opts: Opts) -> Result<Self> {
+        lower_expr(&stmt.expr, &opts)
+    }
+}
+
+fn lower_expr(expr: &Expr, opts: &Opts) -> Result<ExprValue> {
+    let pkg_id = opts.pkg_id.clone().unwrap_or_default();
+    let kind = match expr {
+        Expr::None => ExprKind::None,
+        Expr::Unary(unary) => {
+            ExprKind::Unary(unary.op.clone(), Box::new(lower_expr(&unary.rhs, opts)?))
+        }
+        Expr::Binary(binary) if matches!(binary.op, Op::Assign(_)) => {
+            bail!("assignments can't be used as values (package {})", pkg_id)
+        }
+        Expr::Binary(binary) => ExprKind::Binary(
+            Box::new(lower_expr(&binary.lhs, opts)?),
+            binary.op.clone(),
+            Box::new(lower_expr(&binary.rhs, opts)?),
+        ),
+        Expr::Postfix(postfix) => match &postfix.op {
+            PostfixOp::Try => ExprKind::Try(Box::new(lower_expr(&postfix.lhs, opts)?)),
+            PostfixOp::Field(field) => {
+                ExprKind::Field(Box::new(lower_expr(&postfix.lhs, opts)?), field.clone())
+            }
+            // `value:func {}` passes the value as `self`
+            PostfixOp::Method(func_stmt) => {
+                let mut call = lower_call(func_stmt, opts)?;
+                call.args.insert(
+                    0,
+                    CallArg {
+                        arg_name: "self".into(),
+                        value: lower_expr(&postfix.lhs, opts)?,
+                    },
+                );
+                call.receiver = true;
+                ExprKind::Call(call)
+            }
+            // `value[key]` is `value:get { key }`
+            PostfixOp::Index(index) => ExprKind::Call(receiver_call(
+                lower_expr(&postfix.lhs, opts)?,
+                ":get",
+                vec![CallArg {
+                    arg_name: "key".into(),
+                    value: lower_expr(&index.expr, opts)?,
+                }],
+            )),
+            PostfixOp::Cast(type_stmt) => ExprKind::Cast(
+                Box::new(lower_expr(&postfix.lhs, opts)?),
+                lower_self_type(type_stmt, &pkg_id, opts),
+            ),
+            PostfixOp::None => bail!("unknown postfix operator (package {})", pkg_id),
+        },
+        Expr::Value(value_stmt) => match &value_stmt.value {
+            Value::None => ExprKind::OptionNone,
+            Value::Bool(value) => ExprKind::Bool(*value),
+            Value::Int(value) => ExprKind::Int(IntValue {
+                value: *value as u64,
+                signed: true,
+            }),
+            Value::UInt(value) => ExprKind::Int(IntValue {
+                value: *value as u64,
+                signed: false,
+            }),
+            Value::Float(value) => ExprKind::Float(FloatValue { value: *value }),
+            Value::String(value) => ExprKind::String(StringValue {
+                value: value.clone(),
+            }),
+            Value::Var(name) => ExprKind::Var(name.clone()),
+            // `a.b.c` is field access on the variable `a`
+            Value::Package(path) => {
+                let mut parts = path.split('.');
+                let var = parts.next().unwrap_or_default();
+                let mut value = ExprValue::new(ExprKind::Var(var.into()));
+                for field in parts {
+                    value = ExprValue::new(ExprKind::Field(Box::new(value), field.into()));
+                }
+                return Ok(value);
+            }
+            Value::Func(func_stmt) => ExprKind::Call(lower_call(func_stmt, opts)?),
+            Value::Struct(struct_stmt) => ExprKind::Struct(lower_struct_value(struct_stmt, opts)?),
+            Value::Collection(collection_stmt) => {
+                ExprKind::Collection(lower_collection_value(collection_stmt, opts)?)
+            }
+            Value::Enum(_) => bail!("enum values aren't supported yet (package {})", pkg_id),
+            Value::Tuple(tuple_stmt) => ExprKind::Struct(lower_tuple_value(tuple_stmt, opts)?),
+            Value::Closure(closure_stmt) => ExprKind::Closure(lower_closure(closure_stmt, opts)?),
+        },
+    };
+    let mut value = ExprValue::new(kind);
+    value.span = match expr {
+        Expr::Binary(binary) => binary.span,
+        Expr::Value(ValueStmt {
+            value: Value::Func(func_stmt),
+        }) => func_stmt.span,
+        Expr::Postfix(PostfixExprStmt {
+            op: PostfixOp::Method(func_stmt),
+            ..
+        }) => func_stmt.span,
+        _ => Span::default(),
+    };
+    Ok(value)
+}
+
+fn lower_call(stmt: &FuncValueStmt, opts: &Opts) -> Result<CallValue> {
+    let pkg_id = opts.pkg_id.clone().unwrap_or_default();
+    let mut args = vec![];
+    for arg in &stmt.args {
+        args.push(CallArg {
+            arg_name: arg.arg_name.clone(),
+            value: lower_expr(&arg.arg_value.expr, opts)?,
+        });
+    }
+    Ok(CallValue {
+        func_type: stmt
+            .func_type
+            .as_ref()
+            .map(|func_type| lower_self_type(func_type, &pkg_id, opts)),
+        func_name: stmt.func_name.clone(),
+        args,
+        function_id: None,
+        value_type: None,
+        receiver: false,
+    })
+}
+
+// A `value:func {}` call built by lowering
+fn receiver_call(receiver: ExprValue, func_name: &str, mut args: Vec<CallArg>) -> CallValue {
+    args.insert(
+        0,
+        CallArg {
+            arg_name: "self".into(),
+            value: receiver,
+        },
+    );
+    CallValue {
+        func_type: None,
+        func_name: func_name.into(),
+        args,
+        function_id: None,
+        value_type: None,
+        receiver: true,
+    }
+}
+
+fn lower_closure(stmt: &ClosureValueStmt, opts: &Opts) -> Result<ClosureValue> {
+    let pkg_id = opts.pkg_id.clone().unwrap_or_default();
+    let mut args = vec![];
+    if let Some(input_type) = &stmt.func_type.input_type {
+        for (arg_num, input_arg) in input_type.args.iter().enumerate() {
+            args.push(FunctionArg {
+                arg_id: FunctionArgId::new(input_arg.arg_name.clone(), arg_num),
+                type_id: lower_self_type(&input_arg.arg_type, &pkg_id, opts),
+            });
+        }
+    }
+    Ok(ClosureValue {
+        args,
+        return_type: stmt
+            .func_type
+            .output_type
+            .as_ref()
+            .map(|output| lower_self_type(&output.output_type, &pkg_id, opts)),
+        body: Box::new(lower_expr(&stmt.body.expr, opts)?),
+        captures: vec![],
+    })
+}
+
+fn lower_struct_value(stmt: &StructValueStmt, opts: &Opts) -> Result<StructValue> {
+    let pkg_id = opts.pkg_id.clone().unwrap_or_default();
+    let mut fields = vec![];
+    for field in &stmt.fields {
+        fields.push(StructFieldValue {
+            field_name: field.field_name.clone(),
+            value: lower_expr(&field.field_value.expr, opts)?,
+        });
+    }
+    Ok(StructValue {
+        type_id: lower_self_type(&stmt.struct_type, &pkg_id, opts),
+        fields,
+    })
+}
+
+fn lower_tuple_value(stmt: &TupleValueStmt, opts: &Opts) -> Result<StructValue> {
+    let pkg_id = opts.pkg_id.clone().unwrap_or_default();
+    let mut fields = vec![];
+    for (field_num, field) in stmt.fields.iter().enumerate() {
+        fields.push(StructFieldValue {
+            field_name: field_num.to_string(),
+            value: lower_expr(&field.field_value.expr, opts)?,
+        });
+    }
+    Ok(StructValue {
+        type_id: lower_self_type(&stmt.tuple_type, &pkg_id, opts),
+        fields,
+    })
+}
+
+fn lower_collection_value(stmt: &CollectionValueStmt, opts: &Opts) -> Result<CollectionValue> {
+    let pkg_id = opts.pkg_id.clone().unwrap_or_default();
+    let type_id = lower_self_type(&stmt.collection_type, &pkg_id, opts);
+    // user types might be aliases (checked once they're resolved)
+    let is_user_type = !type_id.is_primitive() && !type_id.is_builtin();
+    if !is_user_type && !is_collection(&type_id) {
+        bail!(
+            ":from isn't supported for type {}",
+            &stmt.collection_type.type_name
+        );
+    }
+    let mut items = vec![];
+    for item in &stmt.items {
+        items.push(lower_expr(&item.expr, opts)?);
+    }
+    Ok(CollectionValue { type_id, items })
+}
+
+// Flattens nested block statements into a list of blocks
+fn lower_blocks(stmts: &[BlockGroupStmt], opts: &Opts) -> Result<Vec<Block>> {
+    let mut blocks = vec![];
+    for stmt in stmts {
+        for block in &stmt.blocks {
+            lower_block(block, stmt.span, opts, &mut blocks)?;
+        }
+    }
+    Ok(blocks)
+}
+
+// `span` is the location of the statement
+fn lower_block(stmt: &BlockStmt, span: Span, opts: &Opts, blocks: &mut Vec<Block>) -> Result<()> {
+    let pkg_id = opts.pkg_id.clone().unwrap_or_default();
+    match stmt {
+        BlockStmt::None => {}
+        BlockStmt::Block(inner) => {
+            for block in &inner.blocks {
+                lower_block(block, inner.span, opts, blocks)?;
+            }
+        }
+        BlockStmt::Const(const_stmt) => {
+            let assign = &const_stmt.assign;
+            blocks.push(Block::Let(LetDef {
+                var_name: assign.var_type.var_name.clone(),
+                type_id: assign
+                    .var_type
+                    .var_type
+                    .as_ref()
+                    .map(|var_type| lower_self_type(var_type, &pkg_id, opts)),
+                value: ExprValue::lower(&assign.expr, opts.clone())?,
+                mutable: false,
+                span,
+            }));
+        }
+        BlockStmt::Let(let_stmt) => {
+            let assign = &let_stmt.assign;
+            blocks.push(Block::Let(LetDef {
+                var_name: assign.var_type.var_name.clone(),
+                type_id: assign
+                    .var_type
+                    .var_type
+                    .as_ref()
+                    .map(|var_type| lower_self_type(var_type, &pkg_id, opts)),
+                value: ExprValue::lower(&assign.expr, opts.clone())?,
+                mutable: true,
+                span,
+            }));
+        }
+        BlockStmt::Assign(assign) => {
+            if assign.var_type.var_type.is_some() {
+                bail!(
+                    "assignment to {} can't declare a type (use let)",
+                    &assign.var_type.var_name
+                );
+            }
+            let mut assign_def = AssignDef {
+                var_name: assign.var_type.var_name.clone(),
+                fields: vec![],
+                value: ExprValue::lower(&assign.expr, opts.clone())?,
+                span,
+            };
+            assign_def.value =
+                lower_assign_value(&assign.op, assign_def.target(), assign_def.value, span)?;
+            blocks.push(Block::Assign(assign_def));
+        }
+        // `p.x = 1` and `m[key] = value`
+        BlockStmt::Expr(ExprStmt {
+            expr: Expr::Binary(binary),
+        }) if matches!(binary.op, Op::Assign(_)) => {
+            let value = lower_expr(&binary.rhs, opts)?;
+            if let Expr::Postfix(PostfixExprStmt {
+                lhs,
+                op: PostfixOp::Index(index),
+            }) = binary.lhs.as_ref()
+            {
+                let receiver = lower_expr(lhs, opts)?;
+                let key = lower_expr(&index.expr, opts)?;
+                // `m[key] += v` is `m[key] = m[key] + v` (which panics if the key is missing)
+                let value = if binary.op == Op::Assign(AssignOp::Eq) {
+                    value
+                } else {
+                    let mut target = ExprValue::new(ExprKind::Call(receiver_call(
+                        receiver.clone(),
+                        INDEX_FUNC,
+                        vec![CallArg {
+                            arg_name: "key".into(),
+                            value: key.clone(),
+                        }],
+                    )));
+                    target.span = binary.span;
+                    lower_assign_value(&binary.op, target, value, binary.span)?
+                };
+                // `m[key] = value` is `m:insert { key value }`
+                let call = receiver_call(
+                    receiver,
+                    ":insert",
+                    vec![
+                        CallArg {
+                            arg_name: "key".into(),
+                            value: key,
+                        },
+                        CallArg {
+                            arg_name: "value".into(),
+                            value,
+                        },
+                    ],
+                );
+                let mut expr = ExprValue::new(ExprKind::Call(call));
+                expr.span = binary.span;
+                blocks.push(Block::Expr(expr));
+                return Ok(());
+            }
+            let (var_name, fields) = assign_target(&binary.lhs)?;
+            let mut assign_def = AssignDef {
+                var_name,
+                fields,
+                value,
+                span,
+            };
+            assign_def.value = lower_assign_value(
+                &binary.op,
+                assign_def.target(),
+                assign_def.value,
+                binary.span,
+            )?;
+            blocks.push(Block::Assign(assign_def));
+        }
+        BlockStmt::Expr(expr_stmt) => {
+            let value = ExprValue::lower(expr_stmt, opts.clone())?;
+            blocks.push(Block::Expr(with_stmt_span(value, span)));
+        }
+        BlockStmt::Return(ret_stmt) => {
+            let value = ExprValue::lower(&ret_stmt.expr, opts.clone())?;
+            if value.kind == ExprKind::None {
+                blocks.push(Block::Return(None));
+            } else {
+                blocks.push(Block::Return(Some(with_stmt_span(value, span))));
+            }
+        }
+        BlockStmt::If(if_stmt) => blocks.push(Block::If(lower_if(if_stmt, span, opts)?)),
+        BlockStmt::ForIn(for_stmt) => {
+            blocks.push(Block::ForIn(lower_for_in(for_stmt, span, opts)?))
+        }
+        _ => {
+            bail!("block {:?} isn't supported yet", stmt)
+        }
+    }
+    Ok(())
+}
+
+// Statement values without a location (i.e. `return x`)
+// use the statement location
+fn with_stmt_span(mut value: ExprValue, span: Span) -> ExprValue {
+    if value.span.ln == 0 {
+        value.span = span;
+    }
+    value
+}
+
+// Splits an assignment target into the variable and field path
+fn assign_target(expr: &Expr) -> Result<(String, Vec<String>)> {
+    match expr {
+        Expr::Value(ValueStmt {
+            value: Value::Var(name),
+        }) => Ok((name.clone(), vec![])),
+        Expr::Value(ValueStmt {
+            value: Value::Package(path),
+        }) => {
+            let mut parts = path.split('.').map(String::from);
+            let var_name = parts.next().unwrap_or_default();
+            Ok((var_name, parts.collect()))
+        }
+        Expr::Postfix(PostfixExprStmt {
+            lhs,
+            op: PostfixOp::Field(field),
+        }) => {
+            let (var_name, mut fields) = assign_target(lhs)?;
+            fields.push(field.clone());
+            Ok((var_name, fields))
+        }
+        _ => bail!("invalid assignment target {:?}", expr),
+    }
+}
+
+// Compound assignments are desugared (i.e. `x += 1` is `x = x + 1`)
+fn lower_assign_value(
+    op: &Op,
+    target: ExprValue,
+    value: ExprValue,
+    span: Span,
+) -> Result<ExprValue> {
+    let op = match op {
+        Op::Assign(AssignOp::Eq) => return Ok(value),
+        Op::Assign(AssignOp::Add) => Op::Arith(ArithOp::Add),
+        Op::Assign(AssignOp::Sub) => Op::Arith(ArithOp::Sub),
+        Op::Assign(AssignOp::Mul) => Op::Arith(ArithOp::Mul),
+        Op::Assign(AssignOp::Div) => Op::Arith(ArithOp::Div),
+        Op::Assign(AssignOp::FloorDiv) => Op::Arith(ArithOp::FloorDiv),
+        Op::Assign(AssignOp::Mod) => Op::Arith(ArithOp::Mod),
+        Op::Assign(AssignOp::Pow) => Op::Arith(ArithOp::Pow),
+        Op::Assign(AssignOp::BitwiseAnd) => Op::Bitwise(BitwiseOp::And),
+        Op::Assign(AssignOp::BitwiseOr) => Op::Bitwise(BitwiseOp::Or),
+        Op::Assign(AssignOp::BitwiseXOr) => Op::Bitwise(BitwiseOp::XOr),
+        Op::Assign(AssignOp::BitwiseShiftL) => Op::Bitwise(BitwiseOp::ShiftL),
+        Op::Assign(AssignOp::BitwiseShiftR) => Op::Bitwise(BitwiseOp::ShiftR),
+        _ => bail!("expected an assignment operator but found {:?}", op),
+    };
+    let mut value = ExprValue::new(ExprKind::Binary(Box::new(target), op, Box::new(value)));
+    value.span = span;
+    Ok(value)
 }
 
-struct Block {}
-struct If {}
-struct Condition {}
-struct ExprValue {}
-struct IntValue {}
-struct FloatValue {}
-struct StringValue {}
+fn lower_if(stmt: &IfStmt, span: Span, opts: &Opts) -> Result<If> {
+    let mut conditions = vec![Condition {
+        expr: ExprValue::lower(&stmt.if_cond.condition.expr, opts.clone())?,
+        blocks: lower_blocks(&stmt.if_cond.blocks, opts)?,
+    }];
+    for elif in &stmt.elif_cond {
+        conditions.push(Condition {
+            expr: ExprValue::lower(&elif.condition.expr, opts.clone())?,
+            blocks: lower_blocks(&elif.blocks, opts)?,
+        });
+    }
+    let else_blocks = match &stmt.else_cond {
+        Some(else_cond) => Some(lower_blocks(&else_cond.blocks, opts)?),
+        None => None,
+    };
+    Ok(If {
+        conditions,
+        else_blocks,
+        span,
+    })
+}
+
+fn lower_for_in(stmt: &ForInStmt, span: Span, opts: &Opts) -> Result<ForInDef> {
+    if stmt.vars.len() > 2 {
+        bail!("for statements bind at most two variables");
+    }
+    Ok(ForInDef {
+        vars: stmt.vars.clone(),
+        iter: ExprValue::lower(&stmt.iter, opts.clone())?,
+        blocks: lower_blocks(&stmt.blocks, opts)?,
+        span,
+    })
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConstantDef {
@@ -65,13 +817,23 @@ impl Lower<ConstStmt> for ConstantDef {
     }
 }
 
-pub struct LetDef {}
 pub struct EnumDef {}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionDef {
     pub function_id: FunctionId,
+    // type parameters (impl type parameters first)
+    pub generics: Vec<String>,
+    pub bounds: Vec<TraitBound>,
     pub args: Vec<FunctionArg>,
+    pub return_type: Option<TypeId>,
+    // instance functions receive `self` as the first arg
+    pub is_self: bool,
+    pub blocks: Vec<Block>,
+    // the source file used by runtime panics
+    pub file: Option<PathBuf>,
+    // location of the function name used by debug info
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -85,24 +847,56 @@ impl Lower<FuncStmt> for FunctionDef {
         if opts.pkg_id.is_none() {
             bail!("expected pkg_id value for FunctionDef")
         }
-        let pkg_id = opts.pkg_id.unwrap();
+        let pkg_id = opts.pkg_id.clone().unwrap();
         let func_name = stmt.func_name.clone();
+        let (generics, bounds) = lower_type_params(&stmt.generics, &pkg_id)?;
+        let opts = Opts {
+            generics: [opts.generics.clone(), generics].concat(),
+            bounds: [opts.bounds.clone(), bounds].concat(),
+            ..opts
+        };
         // function def
         let mut def = FunctionDef {
-            function_id: FunctionId::new(pkg_id.clone(), opts.struct_name, func_name),
+            function_id: FunctionId::new(pkg_id.clone(), opts.struct_name.clone(), func_name),
+            generics: opts.generics.clone(),
+            bounds: opts.bounds.clone(),
             args: vec![],
+            return_type: stmt
+                .func_type
+                .output_type
+                .as_ref()
+                .map(|output| lower_self_type(&output.output_type, &pkg_id, &opts)),
+            is_self: stmt.func_type.is_self,
+            blocks: lower_blocks(&stmt.func_body.blocks, &opts)
+                .with_context(|| format!("failed to lower function {}", &stmt.func_name))?,
+            file: stmt.file.clone(),
+            span: stmt.span,
         };
+        // self arg
+        if def.is_self {
+            let type_id = match self_type(&pkg_id, &opts) {
+                Some(type_id) => type_id,
+                None => bail!(
+                    "function {} defines self outside of an impl",
+                    &stmt.func_name
+                ),
+            };
+            def.args.push(FunctionArg {
+                arg_id: FunctionArgId::new("self".into(), 0),
+                type_id,
+            });
+        }
         // function args
         if let Some(input_type) = &stmt.func_type.input_type {
+            let offset = def.args.len();
             input_type.args.iter().enumerate().try_fold(
                 &mut def.args,
                 |acc, (input_num, input_arg)| -> Result<&mut Vec<FunctionArg>> {
                     let arg_name = input_arg.arg_name.clone();
-                    let arg_num = input_num;
-                    let arg_type = input_arg.arg_type.type_name.clone();
+                    let arg_num = offset + input_num;
                     let func_arg = FunctionArg {
                         arg_id: FunctionArgId::new(arg_name, arg_num),
-                        type_id: TypeId::new(pkg_id.clone(), arg_type),
+                        type_id: lower_self_type(&input_arg.arg_type, &pkg_id, &opts),
                     };
                     acc.push(func_arg);
                     Ok(acc)
@@ -113,9 +907,202 @@ impl Lower<FuncStmt> for FunctionDef {
     }
 }
 
+// Tests are functions without args which are
+// only compiled into the test harness
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestDef {
+    pub test_name: String,
+    pub function_id: FunctionId,
+}
+
+// Lowers a test into its def and the function which runs it
+// (named `:test#0`, `:test#1`, etc. so they can't be called)
+fn lower_test(stmt: &TestStmt, test_num: usize, opts: Opts) -> Result<(TestDef, FunctionDef)> {
+    if opts.pkg_id.is_none() {
+        bail!("expected pkg_id value for TestStmt")
+    }
+    let pkg_id = opts.pkg_id.clone().unwrap();
+    let function_id = FunctionId::new(pkg_id, None, format!(":test#{}", test_num));
+    let func_def = FunctionDef {
+        function_id: function_id.clone(),
+        generics: vec![],
+        bounds: vec![],
+        args: vec![],
+        return_type: None,
+        is_self: false,
+        blocks: lower_blocks(&stmt.test_body.blocks, &opts)
+            .with_context(|| format!("failed to lower test {:?}", &stmt.test_name))?,
+        file: stmt.file.clone(),
+        span: stmt.span,
+    };
+    let def = TestDef {
+        test_name: stmt.test_name.clone(),
+        function_id,
+    };
+    Ok((def, func_def))
+}
+
+// Lowers each test case row into a test (named `test/row`)
+// which binds the row values before running the body
+fn lower_test_case(
+    stmt: &TestCaseStmt,
+    first_num: usize,
+    opts: Opts,
+) -> Result<Vec<(TestDef, FunctionDef)>> {
+    if opts.pkg_id.is_none() {
+        bail!("expected pkg_id value for TestCaseStmt")
+    }
+    let pkg_id = opts.pkg_id.clone().unwrap();
+    let body = lower_blocks(&stmt.test_body.blocks, &opts)
+        .with_context(|| format!("failed to lower testcase {:?}", &stmt.test_name))?;
+    let mut tests = vec![];
+    for (row_num, row) in stmt.rows.iter().enumerate() {
+        let test_name = format!("{}/{}", &stmt.test_name, &row.row_name);
+        if let Some(arg) = row.args.iter().find(|arg| {
+            !stmt
+                .input_type
+                .args
+                .iter()
+                .any(|input_arg| input_arg.arg_name == arg.arg_name)
+        }) {
+            bail!(
+                "testcase {:?} has unknown input {}",
+                &test_name,
+                &arg.arg_name
+            );
+        }
+        let mut blocks = vec![];
+        for input_arg in &stmt.input_type.args {
+            let mut values = row
+                .args
+                .iter()
+                .filter(|arg| arg.arg_name == input_arg.arg_name);
+            let arg = match (values.next(), values.next()) {
+                (Some(arg), None) => arg,
+                (None, _) => bail!(
+                    "testcase {:?} is missing input {}",
+                    &test_name,
+                    &input_arg.arg_name
+                ),
+                (Some(_), Some(_)) => bail!(
+                    "testcase {:?} has duplicate input {}",
+                    &test_name,
+                    &input_arg.arg_name
+                ),
+            };
+            blocks.push(Block::Let(LetDef {
+                var_name: input_arg.arg_name.clone(),
+                type_id: Some(lower_self_type(&input_arg.arg_type, &pkg_id, &opts)),
+                value: ExprValue::lower(&arg.arg_value, opts.clone())
+                    .with_context(|| format!("failed to lower testcase {:?}", &test_name))?,
+                mutable: false,
+                span: stmt.span,
+            }));
+        }
+        blocks.extend(body.iter().cloned());
+        let function_id = FunctionId::new(
+            pkg_id.clone(),
+            None,
+            format!(":test#{}", first_num + row_num),
+        );
+        let func_def = FunctionDef {
+            function_id: function_id.clone(),
+            generics: vec![],
+            bounds: vec![],
+            args: vec![],
+            return_type: None,
+            is_self: false,
+            blocks,
+            file: stmt.file.clone(),
+            span: stmt.span,
+        };
+        tests.push((
+            TestDef {
+                test_name,
+                function_id,
+            },
+            func_def,
+        ));
+    }
+    Ok(tests)
+}
+
+// The impl (or trait) type (i.e. `Box[T]` for `impl Box[T]`)
+fn self_type(pkg_id: &str, opts: &Opts) -> Option<TypeId> {
+    let struct_name = opts.struct_name.as_ref()?;
+    let generics = opts
+        .struct_generics
+        .iter()
+        .map(|generic| TypeId::generic(generic))
+        .collect();
+    Some(TypeId::new(pkg_id.into(), struct_name.clone()).with_generics(generics))
+}
+
+// Same as lower_type but `Self` resolves to the impl (or trait) type
+// and type parameters in scope resolve to generics
+fn lower_self_type(stmt: &TypeStmt, pkg_id: &str, opts: &Opts) -> TypeId {
+    match self_type(pkg_id, opts) {
+        Some(type_id) if stmt.type_name == "Self" => type_id,
+        _ => lower_generic_type(stmt, pkg_id, &opts.generics),
+    }
+}
+
+// Traits belong to their package except the builtin `^Hash` and `^Eq`
+fn lower_trait_id(trait_name: &str, pkg_id: &str) -> TypeId {
+    if trait_name == TRAIT_HASH || trait_name == TRAIT_EQ {
+        TypeId::builtin(trait_name)
+    } else {
+        TypeId::new(pkg_id.into(), trait_name.into())
+    }
+}
+
+// Splits type parameters into names and trait bounds
+// (i.e. `[K ^Hash ^Eq V]` has the params K and V and two bounds on K)
+fn lower_type_params(stmts: &[TypeStmt], pkg_id: &str) -> Result<(Vec<String>, Vec<TraitBound>)> {
+    let mut params: Vec<String> = vec![];
+    let mut bounds = vec![];
+    for stmt in stmts {
+        if !stmt.type_name.starts_with('^') {
+            params.push(stmt.type_name.clone());
+            continue;
+        }
+        let param = match params.last() {
+            Some(param) => param.clone(),
+            None => bail!(
+                "trait bound {} must follow a type parameter",
+                &stmt.type_name
+            ),
+        };
+        bounds.push(TraitBound {
+            param,
+            trait_id: lower_trait_id(&stmt.type_name, pkg_id),
+        });
+    }
+    Ok((params, bounds))
+}
+
+// Type parameters of a type statement (i.e. `struct Pair[K V]`)
+fn type_stmt_params(stmt: &TypeStmt, pkg_id: &str) -> Result<(Vec<String>, Vec<TraitBound>)> {
+    match &stmt.generics {
+        Some(generics) => lower_type_params(generics, pkg_id),
+        None => Ok((vec![], vec![])),
+    }
+}
+
+// A trait bound on a type parameter (i.e. `T ^Display`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitBound {
+    pub param: String,
+    pub trait_id: TypeId,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructDef {
+    // instances of generic structs have concrete type arguments
     pub type_id: TypeId,
+    // type parameters (empty for instances)
+    pub generics: Vec<String>,
+    pub bounds: Vec<TraitBound>,
     pub fields: Vec<StructField>,
 }
 
@@ -133,8 +1120,11 @@ impl Lower<StructStmt> for StructDef {
         // struct def
         let pkg_id = opts.pkg_id.unwrap();
         let struct_name = stmt.struct_type.type_name.clone();
+        let (generics, bounds) = type_stmt_params(&stmt.struct_type, &pkg_id)?;
         let mut def = StructDef {
             type_id: TypeId::new(pkg_id.clone(), struct_name),
+            generics: generics.clone(),
+            bounds,
             fields: vec![],
         };
         // struct fields
@@ -142,10 +1132,9 @@ impl Lower<StructStmt> for StructDef {
             &mut def.fields,
             |acc, (field_num, field)| -> Result<&mut Vec<StructField>> {
                 let field_name = field.field_name.clone();
-                let field_type = field.field_type.type_name.clone();
                 let struct_field = StructField {
                     field_id: FieldId::new(field_name, field_num),
-                    type_id: TypeId::new(pkg_id.clone(), field_type),
+                    type_id: lower_generic_type(&field.field_type, &pkg_id, &generics),
                 };
                 acc.push(struct_field);
                 Ok(acc)
@@ -156,9 +1145,157 @@ impl Lower<StructStmt> for StructDef {
     }
 }
 
-pub struct TraitDef {}
+// tuples are structs with positional fields ("0", "1", ...)
+impl Lower<TupleTypeStmt> for StructDef {
+    fn lower(stmt: &TupleTypeStmt, opts: Opts) -> Result<Self> {
+        if opts.pkg_id.is_none() {
+            bail!("expected pkg_id value for TupleTypeStmt")
+        }
+        let pkg_id = opts.pkg_id.unwrap();
+        let (generics, bounds) = lower_type_params(&stmt.generics, &pkg_id)?;
+        let fields = stmt
+            .tuple
+            .types
+            .iter()
+            .enumerate()
+            .map(|(field_num, field_type)| StructField {
+                field_id: FieldId::new(field_num.to_string(), field_num),
+                type_id: lower_generic_type(field_type, &pkg_id, &generics),
+            })
+            .collect();
+        Ok(StructDef {
+            type_id: TypeId::new(pkg_id, stmt.tuple_name.clone()),
+            generics,
+            bounds,
+            fields,
+        })
+    }
+}
+
+// newtypes are tuples with a single field (i.e. `UserId { 1 }` and `id.0`)
+impl Lower<NewTypeStmt> for StructDef {
+    fn lower(stmt: &NewTypeStmt, opts: Opts) -> Result<Self> {
+        if opts.pkg_id.is_none() {
+            bail!("expected pkg_id value for NewTypeStmt")
+        }
+        let pkg_id = opts.pkg_id.unwrap();
+        Ok(StructDef {
+            type_id: TypeId::new(pkg_id.clone(), stmt.type_name.clone()),
+            generics: vec![],
+            bounds: vec![],
+            fields: vec![StructField {
+                field_id: FieldId::new("0".into(), 0),
+                type_id: lower_type(&stmt.new_type, &pkg_id),
+            }],
+        })
+    }
+}
 
-pub struct ImplTraitDef {}
+// A type or trait imported from a dependency
+// (i.e. `import { pkg.name2 { Type2 as SomeType } }`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDef {
+    // the dependency package
+    pub pkg_name: String,
+    pub type_name: String,
+    // the name used by the importing package
+    pub as_name: String,
+}
+
+// Type aliases are replaced by their type before checking
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasDef {
+    pub type_id: TypeId,
+    pub alias_type: TypeId,
+}
+
+impl Lower<NewTypeStmt> for AliasDef {
+    fn lower(stmt: &NewTypeStmt, opts: Opts) -> Result<Self> {
+        if opts.pkg_id.is_none() {
+            bail!("expected pkg_id value for NewTypeStmt")
+        }
+        let pkg_id = opts.pkg_id.unwrap();
+        Ok(AliasDef {
+            type_id: TypeId::new(pkg_id.clone(), stmt.type_name.clone()),
+            alias_type: lower_type(&stmt.new_type, &pkg_id),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitDef {
+    pub type_id: TypeId,
+    // function signatures (and default implementations)
+    pub function_defs: Vec<FunctionDef>,
+}
+
+impl Lower<TraitStmt> for TraitDef {
+    fn lower(stmt: &TraitStmt, opts: Opts) -> Result<Self> {
+        if opts.pkg_id.is_none() {
+            bail!("expected pkg_id value for TraitDef")
+        }
+        let pkg_id = opts.pkg_id.clone().unwrap();
+        let trait_name = stmt.trait_type.type_name.clone();
+        let mut def = TraitDef {
+            type_id: TypeId::new(pkg_id, trait_name.clone()),
+            function_defs: vec![],
+        };
+        for func in &stmt.funcs {
+            let opts = Opts {
+                struct_name: Some(trait_name.clone()),
+                ..opts.clone()
+            };
+            def.function_defs.push(FunctionDef::lower(func, opts)?);
+        }
+        Ok(def)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplTraitDef {
+    pub type_id: TypeId,
+    // None for inherent impls
+    pub trait_id: Option<TypeId>,
+    // impl functions are compiled with the module functions
+    pub function_ids: Vec<FunctionId>,
+}
+
+// Lowers an impl block into its def and the functions it implements
+fn lower_impl_trait(stmt: &ImplTraitStmt, opts: Opts) -> Result<(ImplTraitDef, Vec<FunctionDef>)> {
+    if opts.pkg_id.is_none() {
+        bail!("expected pkg_id value for ImplTraitDef")
+    }
+    let pkg_id = opts.pkg_id.clone().unwrap();
+    let type_name = stmt.impl_type.type_name.clone();
+    let (generics, bounds) = type_stmt_params(&stmt.impl_type, &pkg_id)?;
+    let opts = Opts {
+        struct_name: Some(type_name.clone()),
+        struct_generics: generics.clone(),
+        generics,
+        bounds,
+        ..opts
+    };
+    let mut def = ImplTraitDef {
+        type_id: TypeId::new(pkg_id.clone(), type_name.clone()).with_generics(
+            opts.struct_generics
+                .iter()
+                .map(|generic| TypeId::generic(generic))
+                .collect(),
+        ),
+        trait_id: stmt
+            .impl_trait
+            .as_ref()
+            .map(|impl_trait| lower_trait_id(&impl_trait.type_name, &pkg_id)),
+        function_ids: vec![],
+    };
+    let mut function_defs = vec![];
+    for func in &stmt.funcs {
+        let func_def = FunctionDef::lower(func, opts.clone())?;
+        def.function_ids.push(func_def.function_id.clone());
+        function_defs.push(func_def);
+    }
+    Ok((def, function_defs))
+}
 
 // High-level IR
 // Converts ast -> IR
@@ -183,6 +1320,26 @@ impl Hir {
             module_id: ModuleId::new(pkg_id.clone(), as_pkg_name.clone()),
             ..Default::default()
         };
+        // imported types and traits
+        for import in &pkg.imports {
+            for symbol in &import.symbols {
+                let type_name = match &symbol.symbol {
+                    PackageSymbol::Type(name) | PackageSymbol::Trait(name) => name.clone(),
+                    _ => continue,
+                };
+                let as_name = match &symbol.as_symbol {
+                    Some(PackageSymbol::Type(name)) | Some(PackageSymbol::Trait(name)) => {
+                        name.clone()
+                    }
+                    _ => type_name.clone(),
+                };
+                module.imports.push(ImportDef {
+                    pkg_name: clean_quotes(import.package_id.clone()),
+                    type_name,
+                    as_name,
+                });
+            }
+        }
         // struct defs
         ast.parsed.structs.iter().try_fold(
             &mut module.struct_defs,
@@ -196,6 +1353,26 @@ impl Hir {
                 Ok(acc)
             },
         )?;
+        // tuple defs
+        for stmt in &ast.parsed.tuples {
+            let opts = Opts {
+                pkg_id: as_pkg_name.clone(),
+                ..Default::default()
+            };
+            module.struct_defs.push(StructDef::lower(stmt, opts)?);
+        }
+        // type defs
+        for stmt in &ast.parsed.types {
+            let opts = Opts {
+                pkg_id: as_pkg_name.clone(),
+                ..Default::default()
+            };
+            if stmt.is_alias {
+                module.alias_defs.push(AliasDef::lower(stmt, opts)?);
+            } else {
+                module.struct_defs.push(StructDef::lower(stmt, opts)?);
+            }
+        }
         // function defs
         ast.parsed.funcs.iter().try_fold(
             &mut module.function_defs,
@@ -210,6 +1387,64 @@ impl Hir {
                 Ok(acc)
             },
         )?;
+        // trait defs
+        ast.parsed.traits.iter().try_fold(
+            &mut module.trait_defs,
+            |acc, stmt| -> Result<&mut Vec<TraitDef>> {
+                let opts = Opts {
+                    pkg_id: as_pkg_name.clone(),
+                    ..Default::default()
+                };
+                let def = TraitDef::lower(stmt, opts)?;
+                acc.push(def);
+                Ok(acc)
+            },
+        )?;
+        // impl defs
+        for stmt in ast.parsed.impl_traits.iter() {
+            let opts = Opts {
+                pkg_id: as_pkg_name.clone(),
+                ..Default::default()
+            };
+            let (def, mut function_defs) = lower_impl_trait(stmt, opts)?;
+            module.impl_trait_defs.push(def);
+            module.function_defs.append(&mut function_defs);
+        }
+        // test defs
+        for (test_num, stmt) in ast.parsed.tests.iter().enumerate() {
+            if module
+                .test_defs
+                .iter()
+                .any(|test_def| test_def.test_name == stmt.test_name)
+            {
+                bail!("duplicate test {:?}", &stmt.test_name);
+            }
+            let opts = Opts {
+                pkg_id: as_pkg_name.clone(),
+                ..Default::default()
+            };
+            let (def, func_def) = lower_test(stmt, test_num, opts)?;
+            module.test_defs.push(def);
+            module.function_defs.push(func_def);
+        }
+        // test case defs (one test per row)
+        for stmt in &ast.parsed.test_cases {
+            let opts = Opts {
+                pkg_id: as_pkg_name.clone(),
+                ..Default::default()
+            };
+            for (def, func_def) in lower_test_case(stmt, module.test_defs.len(), opts)? {
+                if module
+                    .test_defs
+                    .iter()
+                    .any(|test_def| test_def.test_name == def.test_name)
+                {
+                    bail!("duplicate test {:?}", &def.test_name);
+                }
+                module.test_defs.push(def);
+                module.function_defs.push(func_def);
+            }
+        }
         // constant defs
         ast.parsed.consts.iter().try_fold(
             &mut module.constant_defs,
@@ -232,4 +1467,119 @@ impl Hir {
     pub fn merge_modules(&mut self, hir: &Hir) {
         self.modules.append(&mut hir.modules.clone());
     }
+
+    // Generic structs resolve to the instance with the same
+    // type arguments (after monomorphization) or the generic definition
+    pub fn find_struct(&self, type_id: &TypeId) -> Option<&StructDef> {
+        let structs = self
+            .modules
+            .iter()
+            .flat_map(|module| module.struct_defs.iter());
+        let is_instance_id = !type_id.generics.is_empty() && !type_id.has_generic();
+        let mut found = None;
+        let mut generic_def = None;
+        for struct_def in structs {
+            if struct_def.type_id.type_name != type_id.type_name {
+                continue;
+            }
+            // the generic definition is listed before its instances
+            if is_instance_id && !struct_def.generics.is_empty() {
+                if generic_def.is_none() || struct_def.type_id.pkg_name == type_id.pkg_name {
+                    generic_def = Some(struct_def);
+                }
+                continue;
+            }
+            if !struct_def.type_id.generics.is_empty() {
+                let is_instance = struct_def.type_id.generics.len() == type_id.generics.len()
+                    && struct_def
+                        .type_id
+                        .generics
+                        .iter()
+                        .zip(type_id.generics.iter())
+                        .all(|(a, b)| type_eq(a, b));
+                if is_instance {
+                    return Some(struct_def);
+                }
+                continue;
+            }
+            // prefer an exact package match
+            if struct_def.type_id.pkg_name == type_id.pkg_name {
+                return Some(struct_def);
+            }
+            found = found.or(Some(struct_def));
+        }
+        found.or(generic_def)
+    }
+
+    pub fn find_trait(&self, trait_id: &TypeId) -> Option<&TraitDef> {
+        self.modules
+            .iter()
+            .flat_map(|module| module.trait_defs.iter())
+            .find(|trait_def| &trait_def.type_id == trait_id)
+    }
+
+    pub fn find_function(&self, function_id: &FunctionId) -> Option<&FunctionDef> {
+        self.modules
+            .iter()
+            .flat_map(|module| module.function_defs.iter())
+            .find(|func_def| &func_def.function_id == function_id)
+    }
+
+    // Returns the impl of a trait for a concrete type
+    pub fn find_impl(&self, type_id: &TypeId, trait_id: &TypeId) -> Option<&ImplTraitDef> {
+        self.modules
+            .iter()
+            .flat_map(|module| module.impl_trait_defs.iter())
+            .find(|impl_def| {
+                impl_def.trait_id.as_ref() == Some(trait_id)
+                    && impl_matches(&impl_def.type_id, type_id, &mut vec![])
+            })
+    }
+
+    // Returns the impl function of a trait function for a concrete type
+    // (i.e. `:display` of `Point ^Display` is `pkg#Point:display`)
+    pub fn find_impl_function(
+        &self,
+        type_id: &TypeId,
+        trait_id: &TypeId,
+        func_name: &str,
+    ) -> Option<FunctionId> {
+        self.find_impl(type_id, trait_id)?
+            .function_ids
+            .iter()
+            .find(|function_id| function_id.func_name == func_name)
+            .map(|function_id| function_id.clone().with_generics(type_id.generics.clone()))
+    }
+
+    // Returns true if `type_id` implements the trait
+    pub fn implements(&self, type_id: &TypeId, trait_id: &TypeId) -> bool {
+        self.find_impl(type_id, trait_id).is_some()
+    }
+}
+
+// Returns true if an impl type (i.e. `Box[T]` of `impl Box[T] ^Display`)
+// is `type_id` once its type parameters are bound
+fn impl_matches(
+    impl_type: &TypeId,
+    type_id: &TypeId,
+    bindings: &mut Vec<(String, TypeId)>,
+) -> bool {
+    if impl_type.is_generic() {
+        if let Some((_, bound)) = bindings
+            .iter()
+            .find(|(param, _)| param == &impl_type.type_name)
+        {
+            return bound == type_id;
+        }
+        bindings.push((impl_type.type_name.clone(), type_id.clone()));
+        return true;
+    }
+    impl_type.pkg_name == type_id.pkg_name
+        && impl_type.type_name == type_id.type_name
+        && impl_type.generics.len() == type_id.generics.len()
+        && impl_type
+            .generics
+            .iter()
+            .zip(type_id.generics.iter())
+            .all(|(impl_type, type_id)| impl_matches(impl_type, type_id, bindings))
 }
@@ -1,4 +1,8 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
+pub mod builtin;
+pub mod check;
 pub mod compiler;
 pub mod hir;
+pub mod mono;
+mod test;
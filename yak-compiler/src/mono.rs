@@ -0,0 +1,400 @@
+use crate::builtin::HashMethod;
+use crate::hir::{Block, ExprKind, ExprValue, FunctionDef, Hir, StructDef, StructField};
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashSet;
+use yak_core::types::function::FunctionId;
+use yak_core::types::name::Name;
+use yak_core::types::types::TypeId;
+
+// Guards against generic types which grow forever (i.e. `:f[T]` calling `:f[Box[T]]`)
+const MAX_INSTANCES: usize = 10_000;
+
+// A generic function or struct with concrete type arguments
+#[derive(Debug, Clone)]
+enum Instance {
+    Function(FunctionId),
+    Struct(TypeId),
+}
+
+// Monomorphization
+// Copies generic functions and structs for each set of type arguments
+// used by non-generic code. Instances are named by their mangled type
+// arguments (i.e. `pkg:id[yak.primitive#int32]`) and are added once to
+// the module which defines the generic, so packages share instances.
+pub fn monomorphize(hir: &mut Hir) -> Result<()> {
+    let templates = hir.clone();
+    let mut mono = Mono {
+        hir: &templates,
+        queue: vec![],
+        seen: HashSet::new(),
+    };
+    for module in hir.modules.iter_mut() {
+        for func_def in module.function_defs.iter_mut() {
+            if func_def.generics.is_empty() {
+                mono.visit_function(func_def)?;
+            }
+        }
+        for struct_def in module.struct_defs.iter() {
+            if struct_def.generics.is_empty() {
+                for field in &struct_def.fields {
+                    mono.visit_type(&mono.canonical(&field.type_id));
+                }
+            }
+        }
+    }
+    while let Some(instance) = mono.queue.pop() {
+        if mono.seen.len() > MAX_INSTANCES {
+            bail!("too many generic instances (is a generic type recursive?)");
+        }
+        match instance {
+            Instance::Function(function_id) => {
+                let (index, func_def) = mono.instantiate_function(&function_id)?;
+                hir.modules[index].function_defs.push(func_def);
+            }
+            Instance::Struct(type_id) => {
+                let (index, struct_def) = mono.instantiate_struct(&type_id)?;
+                hir.modules[index].struct_defs.push(struct_def);
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Mono<'a> {
+    // the hir before monomorphization
+    hir: &'a Hir,
+    queue: Vec<Instance>,
+    // mangled instance names
+    seen: HashSet<String>,
+}
+
+impl<'a> Mono<'a> {
+    fn enqueue(&mut self, name: String, instance: Instance) {
+        if self.seen.insert(name) {
+            self.queue.push(instance);
+        }
+    }
+
+    // Resolves user types to the package which defines them
+    // since imported types are lowered with the importing package
+    fn canonical(&self, type_id: &TypeId) -> TypeId {
+        let mut type_id = type_id.clone();
+        type_id.generics = type_id
+            .generics
+            .iter()
+            .map(|generic| self.canonical(generic))
+            .collect();
+        if !type_id.is_primitive() && !type_id.is_builtin() && !type_id.is_generic() {
+            if let Some(struct_def) = self.hir.find_struct(&type_id) {
+                type_id.pkg_name = struct_def.type_id.pkg_name.clone();
+            }
+        }
+        type_id
+    }
+
+    // Queues generic struct instances referenced by `type_id`
+    fn visit_type(&mut self, type_id: &TypeId) {
+        for generic in &type_id.generics {
+            self.visit_type(generic);
+        }
+        if type_id.generics.is_empty() || type_id.has_generic() {
+            return;
+        }
+        if type_id.is_primitive() || type_id.is_builtin() {
+            return;
+        }
+        if let Some(struct_def) = self.hir.find_struct(type_id) {
+            if !struct_def.generics.is_empty() {
+                self.enqueue(type_id.name(), Instance::Struct(type_id.clone()));
+            }
+        }
+    }
+
+    // Canonicalizes every type in a (non-generic) function,
+    // resolves trait function calls and queues the instances it uses
+    fn visit_function(&mut self, func_def: &mut FunctionDef) -> Result<()> {
+        map_function_types(func_def, &|type_id| self.canonical(type_id));
+        let mut types = vec![];
+        let mut function_ids = vec![];
+        let mut errors = vec![];
+        for arg in &func_def.args {
+            types.push(arg.type_id.clone());
+        }
+        types.extend(func_def.return_type.clone());
+        visit_blocks(&mut func_def.blocks, &mut |expr| {
+            types.extend(expr.type_id.clone());
+            if let ExprKind::Call(call) = &mut expr.kind {
+                let is_trait_call = call
+                    .function_id
+                    .as_ref()
+                    .and_then(|function_id| function_id.struct_name.as_ref())
+                    .map(|struct_name| struct_name.starts_with('^'))
+                    .unwrap_or(false);
+                if let (true, Some(func_type), Some(function_id)) =
+                    (is_trait_call, &call.func_type, &call.function_id)
+                {
+                    // resolve the impl function of the concrete type
+                    let trait_name = function_id.struct_name.clone().unwrap_or_default();
+                    let trait_id = TypeId::new(function_id.pkg_name.clone(), trait_name.clone());
+                    let is_builtin_impl =
+                        HashMethod::lookup_trait(func_type, &trait_name, &function_id.func_name)
+                            .is_some();
+                    if func_type.is_trait() {
+                        // trait objects are dispatched at runtime
+                    } else if is_builtin_impl {
+                        // primitives and Strings use the builtin `:hash` and `:eq`
+                        call.function_id = None;
+                    } else {
+                        match self.hir.find_impl_function(
+                            func_type,
+                            &trait_id,
+                            &function_id.func_name,
+                        ) {
+                            Some(impl_id) => call.function_id = Some(impl_id),
+                            None => errors.push(anyhow!(
+                                "type {} doesn't implement {}{}",
+                                func_type.name(),
+                                &trait_name,
+                                &function_id.func_name
+                            )),
+                        }
+                    }
+                }
+                if let Some(function_id) = &call.function_id {
+                    if !function_id.generics.is_empty() {
+                        function_ids.push(function_id.clone());
+                    }
+                }
+            }
+        });
+        for type_id in types {
+            self.visit_type(&type_id);
+        }
+        if let Some(err) = errors.into_iter().next() {
+            return Err(err);
+        }
+        for function_id in function_ids {
+            self.enqueue(function_id.name(), Instance::Function(function_id));
+        }
+        Ok(())
+    }
+
+    // Returns the module index which defines the generic function
+    fn find_function(&self, function_id: &FunctionId) -> Option<(usize, &'a FunctionDef)> {
+        let generic_id = function_id.clone().with_generics(vec![]);
+        self.hir
+            .modules
+            .iter()
+            .enumerate()
+            .find_map(|(index, module)| {
+                module
+                    .function_defs
+                    .iter()
+                    .find(|func_def| func_def.function_id == generic_id)
+                    .map(|func_def| (index, func_def))
+            })
+    }
+
+    fn instantiate_function(&mut self, function_id: &FunctionId) -> Result<(usize, FunctionDef)> {
+        let (index, generic_def) = match self.find_function(function_id) {
+            Some(found) => found,
+            None => bail!("unknown generic function {}", function_id.name()),
+        };
+        if generic_def.generics.len() != function_id.generics.len() {
+            bail!(
+                "function {} expects {} type arguments",
+                generic_def.function_id.name(),
+                generic_def.generics.len()
+            );
+        }
+        let mut func_def = generic_def.clone();
+        map_function_types(&mut func_def, &|type_id| {
+            type_id.substitute(&generic_def.generics, &function_id.generics)
+        });
+        func_def.function_id = function_id.clone();
+        func_def.generics = vec![];
+        func_def.bounds = vec![];
+        self.visit_function(&mut func_def)?;
+        Ok((index, func_def))
+    }
+
+    fn instantiate_struct(&mut self, type_id: &TypeId) -> Result<(usize, StructDef)> {
+        let found = self
+            .hir
+            .modules
+            .iter()
+            .enumerate()
+            .find_map(|(index, module)| {
+                module
+                    .struct_defs
+                    .iter()
+                    .find(|struct_def| {
+                        struct_def.type_id.pkg_name == type_id.pkg_name
+                            && struct_def.type_id.type_name == type_id.type_name
+                    })
+                    .map(|struct_def| (index, struct_def))
+            });
+        let (index, generic_def) = match found {
+            Some(found) => found,
+            None => bail!("unknown generic struct {}", type_id.name()),
+        };
+        let mut struct_def = StructDef {
+            type_id: generic_def
+                .type_id
+                .clone()
+                .with_generics(type_id.generics.clone()),
+            generics: vec![],
+            bounds: vec![],
+            fields: vec![],
+        };
+        for field in &generic_def.fields {
+            let field_type = self.canonical(
+                &field
+                    .type_id
+                    .substitute(&generic_def.generics, &type_id.generics),
+            );
+            self.visit_type(&field_type);
+            struct_def.fields.push(StructField {
+                field_id: field.field_id.clone(),
+                type_id: field_type,
+            });
+        }
+        // impl functions (i.e. ^Hash and ^Eq) are instantiated with the struct
+        for func_def in self
+            .hir
+            .modules
+            .iter()
+            .flat_map(|module| module.function_defs.iter())
+        {
+            let function_id = &func_def.function_id;
+            if function_id.pkg_name == generic_def.type_id.pkg_name
+                && function_id.struct_name.as_ref() == Some(&generic_def.type_id.type_name)
+                && func_def.generics.len() == type_id.generics.len()
+            {
+                let function_id = function_id.clone().with_generics(type_id.generics.clone());
+                self.enqueue(function_id.name(), Instance::Function(function_id));
+            }
+        }
+        Ok((index, struct_def))
+    }
+}
+
+// Rewrites every type in a function signature and body
+pub(crate) fn map_function_types(func_def: &mut FunctionDef, f: &dyn Fn(&TypeId) -> TypeId) {
+    for arg in func_def.args.iter_mut() {
+        arg.type_id = f(&arg.type_id);
+    }
+    func_def.return_type = func_def.return_type.as_ref().map(f);
+    map_block_types(&mut func_def.blocks, f);
+    visit_blocks(&mut func_def.blocks, &mut |expr| {
+        expr.type_id = expr.type_id.as_ref().map(f);
+        match &mut expr.kind {
+            ExprKind::Call(call) => {
+                call.func_type = call.func_type.as_ref().map(f);
+                call.value_type = call.value_type.as_ref().map(f);
+                if let Some(function_id) = call.function_id.as_mut() {
+                    function_id.generics = function_id.generics.iter().map(f).collect();
+                }
+            }
+            ExprKind::Struct(struct_value) => struct_value.type_id = f(&struct_value.type_id),
+            ExprKind::Collection(collection) => collection.type_id = f(&collection.type_id),
+            ExprKind::Cast(_, type_id) => *type_id = f(type_id),
+            ExprKind::Closure(closure) => {
+                for arg in closure.args.iter_mut() {
+                    arg.type_id = f(&arg.type_id);
+                }
+                closure.return_type = closure.return_type.as_ref().map(f);
+                for capture in closure.captures.iter_mut() {
+                    capture.type_id = f(&capture.type_id);
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+// Rewrites `let` types (expression types are rewritten by visit_blocks)
+fn map_block_types(blocks: &mut [Block], f: &dyn Fn(&TypeId) -> TypeId) {
+    for block in blocks.iter_mut() {
+        match block {
+            Block::Let(let_def) => let_def.type_id = let_def.type_id.as_ref().map(f),
+            Block::If(if_def) => {
+                for condition in if_def.conditions.iter_mut() {
+                    map_block_types(&mut condition.blocks, f);
+                }
+                if let Some(else_blocks) = if_def.else_blocks.as_mut() {
+                    map_block_types(else_blocks, f);
+                }
+            }
+            Block::ForIn(for_def) => map_block_types(&mut for_def.blocks, f),
+            Block::Assign(_) | Block::Expr(_) | Block::Return(_) => {}
+        }
+    }
+}
+
+// Calls `f` for every expression in `blocks`
+fn visit_blocks(blocks: &mut [Block], f: &mut dyn FnMut(&mut ExprValue)) {
+    for block in blocks.iter_mut() {
+        match block {
+            Block::Let(let_def) => visit_expr(&mut let_def.value, f),
+            Block::Assign(assign_def) => visit_expr(&mut assign_def.value, f),
+            Block::Expr(expr) => visit_expr(expr, f),
+            Block::If(if_def) => {
+                for condition in if_def.conditions.iter_mut() {
+                    visit_expr(&mut condition.expr, f);
+                    visit_blocks(&mut condition.blocks, f);
+                }
+                if let Some(else_blocks) = if_def.else_blocks.as_mut() {
+                    visit_blocks(else_blocks, f);
+                }
+            }
+            Block::ForIn(for_def) => {
+                visit_expr(&mut for_def.iter, f);
+                visit_blocks(&mut for_def.blocks, f);
+            }
+            Block::Return(value) => {
+                if let Some(value) = value {
+                    visit_expr(value, f);
+                }
+            }
+        }
+    }
+}
+
+fn visit_expr(expr: &mut ExprValue, f: &mut dyn FnMut(&mut ExprValue)) {
+    f(expr);
+    match &mut expr.kind {
+        ExprKind::Field(inner, _)
+        | ExprKind::Unary(_, inner)
+        | ExprKind::Try(inner)
+        | ExprKind::Object(inner)
+        | ExprKind::Cast(inner, _) => visit_expr(inner, f),
+        ExprKind::Binary(lhs, _, rhs) => {
+            visit_expr(lhs, f);
+            visit_expr(rhs, f);
+        }
+        ExprKind::Call(call) => {
+            for arg in call.args.iter_mut() {
+                visit_expr(&mut arg.value, f);
+            }
+        }
+        ExprKind::Struct(struct_value) => {
+            for field in struct_value.fields.iter_mut() {
+                visit_expr(&mut field.value, f);
+            }
+        }
+        ExprKind::Collection(collection) => {
+            for item in collection.items.iter_mut() {
+                visit_expr(item, f);
+            }
+        }
+        ExprKind::Closure(closure) => visit_expr(&mut closure.body, f),
+        ExprKind::None
+        | ExprKind::Bool(_)
+        | ExprKind::Int(_)
+        | ExprKind::Float(_)
+        | ExprKind::String(_)
+        | ExprKind::Var(_)
+        | ExprKind::OptionNone => {}
+    }
+}
@@ -0,0 +1,1069 @@
+#[cfg(test)]
+use crate::check::check_all;
+#[cfg(test)]
+use crate::compiler::link::{cross_gcc, version_script};
+#[cfg(test)]
+use crate::compiler::{
+    Compiler, CompilerOpts, Emit, LibKind, LinkError, Linker, OptLevel, TargetOpts,
+};
+#[cfg(test)]
+use crate::hir::{Block, ExprKind, Hir};
+#[cfg(test)]
+use crate::mono::monomorphize;
+#[cfg(test)]
+use inkwell::context::Context;
+#[cfg(test)]
+use inkwell::execution_engine::ExecutionEngine;
+#[cfg(test)]
+use inkwell::memory_buffer::MemoryBuffer;
+#[cfg(test)]
+use inkwell::OptimizationLevel;
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::PathBuf;
+#[cfg(test)]
+use std::process::Command;
+#[cfg(test)]
+use yak_ast::Ast;
+#[cfg(test)]
+use yak_core::models::yak_home::YakHome;
+#[cfg(test)]
+use yak_core::types::name::Name;
+
+// Lowers a single file package (`test.pkg`)
+#[cfg(test)]
+fn lower(src: &str) -> Hir {
+    let mut ast = Ast::from_source(src);
+    ast.parse().unwrap();
+    ast.parsed.package.package_id = "\"test.pkg\"".into();
+    let mut hir = Hir::default();
+    hir.from_ast(true, None, &ast).unwrap();
+    hir
+}
+
+// Lowers a package (`test.pkg`) which imports from a dependency (`test.dep`)
+#[cfg(test)]
+fn lower_with_dep(dep_src: &str, import: &str, src: &str) -> Hir {
+    let parse = |pkg: String, src: &str| {
+        let mut ast = Ast::from_source(&pkg);
+        ast.parse_package().unwrap();
+        ast.parse_source(PathBuf::from("src/main.yak"), src)
+            .unwrap();
+        ast
+    };
+    let dep_pkg = "package \"test.dep\"\nfiles {\n}\nexport {\n  :make\n}\n".to_string();
+    let pkg = format!(
+        "package \"test.pkg\"\nfiles {{\n}}\nimport {{\n  test.dep {{\n    {}\n  }}\n}}\n",
+        import
+    );
+    let mut hir = Hir::default();
+    hir.from_ast(true, None, &parse(pkg, src)).unwrap();
+    let mut dep_hir = Hir::default();
+    dep_hir
+        .from_ast(false, Some("test.dep".into()), &parse(dep_pkg, dep_src))
+        .unwrap();
+    hir.merge_modules(&dep_hir);
+    hir
+}
+
+// The type errors of a package
+#[cfg(test)]
+fn check_errors(src: &str) -> Vec<String> {
+    errors(lower(src))
+}
+
+#[cfg(test)]
+fn errors(mut hir: Hir) -> Vec<String> {
+    check_all(&mut hir)
+        .into_iter()
+        .map(|check_error| format!("{:#}", check_error.error))
+        .collect()
+}
+
+// Type checks and monomorphizes a package
+#[cfg(test)]
+fn mono(src: &str) -> Hir {
+    let mut hir = lower(src);
+    let errors: Vec<String> = check_all(&mut hir)
+        .into_iter()
+        .map(|check_error| format!("{:#}", check_error.error))
+        .collect();
+    assert_eq!(errors, Vec::<String>::new());
+    monomorphize(&mut hir).unwrap();
+    hir
+}
+
+// The names of the non-generic functions of a package
+#[cfg(test)]
+fn function_names(hir: &Hir) -> Vec<String> {
+    hir.modules[0]
+        .function_defs
+        .iter()
+        .filter(|func_def| func_def.generics.is_empty())
+        .map(|func_def| func_def.function_id.name())
+        .collect()
+}
+
+// The resolved function of the call returned by a function
+#[cfg(test)]
+fn returned_call(hir: &Hir, name: &str) -> Option<String> {
+    let func_def = hir.modules[0]
+        .function_defs
+        .iter()
+        .find(|func_def| func_def.function_id.name() == name)
+        .unwrap();
+    match func_def.blocks.last() {
+        Some(Block::Return(Some(value))) => match &value.kind {
+            ExprKind::Call(call) => call.function_id.as_ref().map(|id| id.name()),
+            kind => panic!("expected a call but found {:?}", kind),
+        },
+        block => panic!("expected a return but found {:?}", block),
+    }
+}
+
+// The options for compiling `test.pkg` to a temp directory
+#[cfg(test)]
+fn test_opts(name: &str, emit: Vec<Emit>, target: TargetOpts) -> CompilerOpts {
+    let output_dir =
+        std::env::temp_dir().join(format!("yak-compiler-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&output_dir);
+    CompilerOpts {
+        pkg_id: "test.pkg".into(),
+        pkg_local_path: ".".into(),
+        output_dir: output_dir.display().to_string(),
+        overflow_checks: false,
+        debug_assertions: false,
+        debug_info: false,
+        opt_level: OptLevel::O0,
+        linker: Linker::default(),
+        lib_kind: LibKind::default(),
+        exports: vec![],
+        test: false,
+        emit,
+        target,
+    }
+}
+
+// Runs `f` with a compiler for the options
+// (and the target machine, context and builder it borrows)
+#[cfg(test)]
+fn with_compiler<T>(opts: CompilerOpts, hir: Hir, f: impl FnOnce(&mut Compiler) -> T) -> T {
+    let machine = opts.target.target_machine(opts.opt_level).unwrap();
+    let context = Context::create();
+    let builder = context.create_builder();
+    let mut compiler = Compiler::new(opts, hir, &context, &builder, machine);
+    f(&mut compiler)
+}
+
+// Type checks, monomorphizes and compiles a package (without linking)
+// to a temp directory
+#[cfg(test)]
+fn compile_package(name: &str, src: &str, emit: Vec<Emit>, target: TargetOpts) -> PathBuf {
+    let opts = test_opts(name, emit, target);
+    let output_dir = PathBuf::from(&opts.output_dir);
+    with_compiler(opts, mono(src), |compiler| compiler.compile().unwrap());
+    output_dir
+}
+
+// Compiles a package to llvm ir
+#[cfg(test)]
+fn compile_ir(name: &str, src: &str) -> String {
+    let output_dir = compile_package(name, src, vec![Emit::LlvmIr], TargetOpts::default());
+    let ir = fs::read_to_string(output_dir.join("module/test.pkg.ll")).unwrap();
+    let _ = fs::remove_dir_all(&output_dir);
+    ir
+}
+
+#[test]
+fn check_anonymous_tuples() {
+    let src = "
+fn :swap { pair: (int, String) } (String, int) =>
+  return (pair.1, pair.0)
+
+fn :main {} =>
+  let pair = (1, \"a\")
+  pair.0 = 2
+  const swapped = :swap { pair: pair }
+  const nested = ((1, 2.5), true)
+  const half = nested.0.1
+";
+    assert_eq!(check_errors(src), Vec::<String>::new());
+
+    let errors = check_errors(
+        "
+fn :swap { pair: (int, String) } (String, int) =>
+  return (pair.1, pair.0)
+
+fn :main {} =>
+  const a = :swap { pair: (1, 2) }
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("expected type String but found int32"));
+
+    let errors = check_errors(
+        "
+fn :swap { pair: (int, String) } (String, int) =>
+  return (pair.1, pair.0)
+
+fn :main {} =>
+  const a = :swap { pair: (1, \"a\", 3) }
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("expected type (int32, String) but found (int32, String, int32)"));
+
+    let errors = check_errors(
+        "
+fn :main {} =>
+  const a = (1, 2).2
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("type (int32, int32) has no field 2"));
+}
+
+#[test]
+fn check_generic_tuples() {
+    let src = "
+tuple Entry[K V] { K List[V] }
+
+fn :key[K V] { entry: Entry[K V] } K =>
+  return entry.0
+
+fn :main {} =>
+  const inferred = Entry { \"a\" List[int]:from { 1 2 } }
+  const explicit = Entry[int bool] { 7 List[bool]:from { true } }
+  const key = :key { entry: explicit }
+  const sum = key + 1
+";
+    assert_eq!(check_errors(src), Vec::<String>::new());
+
+    let errors = check_errors(
+        "
+tuple Entry[K V] { K List[V] }
+
+fn :main {} =>
+  const entry = Entry[int bool] { \"a\" List[bool]:from { true } }
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("expected type int32 but found String"));
+}
+
+#[test]
+fn compile_tuples() {
+    let ir = compile_ir(
+        "tuples",
+        "
+tuple Entry[K V] { K V }
+
+fn :swap { pair: (int, String) } (String, int) =>
+  return (pair.1, pair.0)
+
+fn :main {} =>
+  const swapped = :swap { pair: (1, \"a\") }
+  const entry = Entry { 1 true }
+",
+    );
+    // anonymous tuples are literal structs
+    assert!(ir.contains("{ i8*, i32 } @\"test.pkg:swap\"({ i32, i8* }"));
+    // generic tuples are named instances
+    assert!(ir.contains(
+        "%\"test.pkg#Entry[yak.primitive#int32,yak.primitive#bool]\" = type { i32, i1 }"
+    ));
+}
+
+#[test]
+fn check_generics() {
+    let src = "
+struct Box[T]
+  value: T
+
+fn :id[T] { value: T } T =>
+  return value
+
+fn :main {} =>
+  const explicit = Box[String] { value: \"a\" }
+  const inferred = Box { value: 1 }
+  const boxed = :id { value: inferred }
+  const sum = boxed.value + 1
+";
+    assert_eq!(check_errors(src), Vec::<String>::new());
+
+    let errors = check_errors(
+        "
+struct Box[T]
+  value: T
+
+fn :main {} =>
+  const a = Box[int String] { value: 1 }
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("expects 1 type arguments"));
+
+    let errors = check_errors(
+        "
+fn :id[T] { value: T } T =>
+  return value
+
+fn :main {} =>
+  const a = :id { value: 1 } + \"a\"
+",
+    );
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn check_bounds() {
+    let src = "
+trait ^Show
+  fn :show self {} String
+
+struct Point
+  x: int
+
+impl Point ^Show
+  fn :show self {} String =>
+    return \"point\"
+
+fn :show_value[T ^Show] { value: T } String =>
+  return T:show { self: value }
+
+fn :key[K ^Hash ^Eq] { key: K } uint64 =>
+  return K:hash { self: key }
+
+fn :main {} =>
+  const shown = :show_value { value: Point { x: 1 } }
+  const int_key = :key { key: 1 }
+  const string_key = :key { key: \"a\" }
+";
+    assert_eq!(check_errors(src), Vec::<String>::new());
+
+    // user structs don't implement the builtin traits
+    let errors = check_errors(
+        "
+struct Point
+  x: int
+
+fn :key[K ^Hash ^Eq] { key: K } uint64 =>
+  return K:hash { self: key }
+
+fn :main {} =>
+  const a = :key { key: Point { x: 1 } }
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("type Point doesn't implement ^Hash"));
+
+    // primitives don't implement user traits
+    let errors = check_errors(
+        "
+trait ^Show
+  fn :show self {} String
+
+fn :show_value[T ^Show] { value: T } String =>
+  return T:show { self: value }
+
+fn :main {} =>
+  const a = :show_value { value: 1 }
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("type int32 doesn't implement ^Show"));
+
+    let errors = check_errors(
+        "
+fn :show_value[T ^Show] { value: T } T =>
+  return value
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("unknown trait ^Show"));
+}
+
+#[test]
+fn check_builtin_impl_signatures() {
+    let impl_src = |func: &str| {
+        format!(
+            "
+struct Point
+  x: int
+
+impl Point ^Hash
+{}
+",
+            func
+        )
+    };
+    let src = impl_src("  fn :hash self {} uint64 =>\n    return :hash_int { value: self.x }");
+    assert_eq!(check_errors(&src), Vec::<String>::new());
+
+    let src = impl_src("  fn :hash self {} int =>\n    return self.x");
+    let errors = check_errors(&src);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("impl Point ^Hash function :hash expected signature"));
+
+    let src = impl_src("  fn :hash self { seed: uint64 } uint64 =>\n    return seed");
+    let errors = check_errors(&src);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("expected signature"));
+
+    let errors = check_errors(
+        "
+struct Point
+  x: int
+
+impl Point ^Eq
+  fn :equals self { other: Point } bool =>
+    return true
+",
+    );
+    assert!(!errors.is_empty());
+    assert!(errors[0].contains("is missing function :eq"));
+}
+
+#[test]
+fn mono_instances() {
+    let hir = mono(
+        "
+struct Box[T]
+  value: T
+
+fn :id[T] { value: T } T =>
+  return value
+
+fn :main {} =>
+  const a = :id { value: 1 }
+  const b = :id { value: 2 }
+  const c = :id { value: \"a\" }
+  const d = Box { value: true }
+",
+    );
+    let names = function_names(&hir);
+    // one instance per set of type arguments
+    let count = |name: &str| names.iter().filter(|other| *other == name).count();
+    assert_eq!(count("test.pkg:id[yak.primitive#int32]"), 1);
+    assert_eq!(count("test.pkg:id[yak.builtin#String]"), 1);
+    assert!(hir.modules[0]
+        .struct_defs
+        .iter()
+        .any(
+            |struct_def| struct_def.type_id.name() == "test.pkg#Box[yak.primitive#bool]"
+                && struct_def.generics.is_empty()
+        ));
+}
+
+#[test]
+fn mono_bound_calls() {
+    let hir = mono(
+        "
+trait ^Show
+  fn :show self {} String
+
+struct Point
+  x: int
+
+impl Point ^Show
+  fn :show self {} String =>
+    return \"point\"
+
+fn :show_value[T ^Show] { value: T } String =>
+  return T:show { self: value }
+
+fn :key[K ^Hash] { key: K } uint64 =>
+  return K:hash { self: key }
+
+fn :main {} =>
+  const shown = :show_value { value: Point { x: 1 } }
+  const key = :key { key: 1 }
+",
+    );
+    // calls through a bound resolve to the impl function
+    assert_eq!(
+        returned_call(&hir, "test.pkg:show_value[test.pkg#Point]"),
+        Some("test.pkg#Point:show".into())
+    );
+    // builtin impls are generated by the compiler
+    assert_eq!(
+        returned_call(&hir, "test.pkg:key[yak.primitive#int32]"),
+        None
+    );
+}
+
+#[test]
+fn check_feature_examples() {
+    // the example package files share one namespace
+    let src = [
+        include_str!("../../examples/yak-features/src/aliases.yak"),
+        include_str!("../../examples/yak-features/src/bounds.yak"),
+        include_str!("../../examples/yak-features/src/casts.yak"),
+        include_str!("../../examples/yak-features/src/checker.yak"),
+        include_str!("../../examples/yak-features/src/closures.yak"),
+        include_str!("../../examples/yak-features/src/generics.yak"),
+        include_str!("../../examples/yak-features/src/tuples.yak"),
+        include_str!("../../examples/yak-features/src/vtables.yak"),
+    ]
+    .join("\n");
+    assert_eq!(check_errors(&src), Vec::<String>::new());
+    mono(&src);
+}
+
+#[test]
+fn compile_aarch64_object() {
+    let target = TargetOpts {
+        triple: Some("aarch64-unknown-linux-gnu".into()),
+        cpu: None,
+        features: None,
+    };
+    let src = "
+fn :main {} =>
+  const a = 1 + 2
+";
+    let output_dir = compile_package("aarch64", src, vec![Emit::Obj], target);
+    let object = fs::read(output_dir.join("obj/test.pkg.o")).unwrap();
+    let _ = fs::remove_dir_all(&output_dir);
+    // a little endian ELF (`EI_DATA` is 1) for EM_AARCH64
+    assert_eq!(&object[..4], b"\x7fELF");
+    assert_eq!(object[5], 1);
+    assert_eq!(u16::from_le_bytes([object[18], object[19]]), 183);
+}
+
+#[test]
+fn link_cross_commands() {
+    assert_eq!(
+        cross_gcc("aarch64-unknown-linux-gnu"),
+        "aarch64-linux-gnu-gcc"
+    );
+    assert_eq!(cross_gcc("x86_64-pc-linux-gnu"), "x86_64-linux-gnu-gcc");
+    assert_eq!(cross_gcc("aarch64-linux-gnu"), "aarch64-linux-gnu-gcc");
+    assert_eq!(cross_gcc("riscv64gc-unknown-linux"), "riscv64gc-linux-gcc");
+
+    let triple = Some("aarch64-unknown-linux-gnu");
+    let args = |cmd: &Command| -> Vec<String> {
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    };
+    // cc uses the gcc cross toolchain or falls back to clang
+    let cmd = Linker::Cc.command(triple);
+    if cmd.get_program() == "clang" {
+        assert_eq!(args(&cmd), vec!["--target=aarch64-unknown-linux-gnu"]);
+    } else {
+        assert_eq!(cmd.get_program(), "aarch64-linux-gnu-gcc");
+        assert!(args(&cmd).is_empty());
+    }
+    let cmd = Linker::Clang.command(triple);
+    assert_eq!(cmd.get_program(), "clang");
+    assert_eq!(args(&cmd), vec!["--target=aarch64-unknown-linux-gnu"]);
+    let cmd = Linker::LdLld.command(triple);
+    assert_eq!(cmd.get_program(), "clang");
+    assert_eq!(
+        args(&cmd),
+        vec!["--target=aarch64-unknown-linux-gnu", "-fuse-ld=lld"]
+    );
+    // the host links with cc
+    assert_eq!(Linker::Cc.command(None).get_program(), "cc");
+    assert_eq!(args(&Linker::LdLld.command(None)), vec!["-fuse-ld=lld"]);
+}
+
+#[test]
+fn link_exported_symbols() {
+    let hir = mono(
+        "
+struct Point
+  x: int
+
+impl Point
+  fn :new { x: int } Point =>
+    return Point { x: x }
+
+fn :add { a: int b: int } int =>
+  return a + b
+
+fn :id[T] { value: T } T =>
+  return value
+
+fn :main {} =>
+  const a = :id { value: 1 }
+",
+    );
+    let opts = test_opts("exports", vec![], TargetOpts::default());
+    with_compiler(opts, hir, |compiler| {
+        // functions export themselves and types export their functions
+        compiler.opts.exports = vec![":add".into(), "Point".into()];
+        assert_eq!(
+            compiler.exported_symbols().unwrap(),
+            vec!["test.pkg:add", "test.pkg#Point:new"]
+        );
+        // generic functions (and their instances) aren't exported
+        compiler.opts.exports = vec![":id".into()];
+        let err = compiler.exported_symbols().unwrap_err();
+        assert_eq!(err.to_string(), "exported function :id isn't defined");
+        compiler.opts.exports = vec![];
+        assert!(compiler.exported_symbols().unwrap().is_empty());
+    });
+}
+
+#[test]
+fn link_version_script() {
+    let symbols = vec!["test.pkg:add".to_string(), "test.pkg#Point:new".to_string()];
+    assert_eq!(
+        version_script(&symbols),
+        "{\n  global:\n    \"test.pkg:add\";\n    \"test.pkg#Point:new\";\n  local: *;\n};\n"
+    );
+    // libraries without exports hide everything
+    assert_eq!(version_script(&[]), "{\n  local: *;\n};\n");
+}
+
+#[test]
+fn link_errors() {
+    let opts = test_opts("link-errors", vec![], TargetOpts::default());
+    let output_file = format!("{}/bin/test.pkg", &opts.output_dir);
+    let output_dir = opts.output_dir.clone();
+    with_compiler(opts, Hir::default(), |compiler| {
+        // linkers which aren't installed
+        let err = compiler
+            .run_linker(Command::new("yak-missing-linker"), &output_file)
+            .unwrap_err();
+        let link_error = err.downcast_ref::<LinkError>().unwrap();
+        assert_eq!(link_error.linker, "yak-missing-linker");
+        assert_eq!(link_error.output_file, output_file);
+        assert!(link_error
+            .stderr
+            .starts_with("failed to run yak-missing-linker: "));
+
+        // failed links keep what the linker printed
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo \"undefined symbol: main\" >&2; exit 1"]);
+        let err = compiler.run_linker(cmd, &output_file).unwrap_err();
+        let link_error = err.downcast_ref::<LinkError>().unwrap();
+        assert_eq!(link_error.linker, "sh");
+        assert_eq!(link_error.stderr, "undefined symbol: main\n");
+        assert_eq!(
+            err.to_string(),
+            format!("sh failed to link {}: undefined symbol: main", &output_file)
+        );
+
+        assert!(compiler
+            .run_linker(Command::new("true"), &output_file)
+            .is_ok());
+    });
+    let _ = fs::remove_dir_all(&output_dir);
+}
+
+#[test]
+fn link_missing_runtime() {
+    let triple = "aarch64-unknown-linux-gnu";
+    let lib_dir = YakHome::default().get_home_version_target_lib_dir(triple);
+    if lib_dir.join("libyak_std.a").exists() {
+        return;
+    }
+    let target = TargetOpts {
+        triple: Some(triple.into()),
+        ..TargetOpts::default()
+    };
+    let opts = test_opts("link-runtime", vec![], target);
+    let mut cmd = Command::new("true");
+    let err = with_compiler(opts, Hir::default(), |compiler| {
+        compiler.link_runtime(&mut cmd).unwrap_err()
+    });
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "yak runtime isn't installed in {} (run `make std-install-target TARGET={}`)",
+            lib_dir.display(),
+            triple
+        )
+    );
+    assert_eq!(cmd.get_args().count(), 0);
+}
+
+#[test]
+fn emit_names() {
+    let names = ["tokens", "ast", "hir", "llvm-ir", "asm", "obj"];
+    for name in names {
+        let emit: Emit = name.parse().unwrap();
+        assert_eq!(emit.name(), name);
+    }
+    let err = "bitcode".parse::<Emit>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown emit type: bitcode (expected tokens, ast, hir, llvm-ir, asm or obj)"
+    );
+    // llvm-ir modules are written to `module`
+    assert_eq!(Emit::LlvmIr.dir("target"), "target/module");
+    assert_eq!(Emit::Tokens.dir("target"), "target/tokens");
+    assert_eq!(Emit::Obj.dir("target"), "target/obj");
+}
+
+#[test]
+fn opt_levels() {
+    let levels = [
+        ("0", OptLevel::O0, OptimizationLevel::None, 0, None),
+        ("1", OptLevel::O1, OptimizationLevel::Less, 0, None),
+        ("2", OptLevel::O2, OptimizationLevel::Default, 0, Some(225)),
+        (
+            "3",
+            OptLevel::O3,
+            OptimizationLevel::Aggressive,
+            0,
+            Some(250),
+        ),
+        ("s", OptLevel::Os, OptimizationLevel::Default, 1, Some(75)),
+        ("z", OptLevel::Oz, OptimizationLevel::Default, 2, Some(25)),
+    ];
+    for (name, opt_level, llvm_level, size_level, inline_threshold) in levels {
+        assert_eq!(name.parse::<OptLevel>().unwrap(), opt_level);
+        assert_eq!(opt_level.name(), name);
+        // the target machine and pass manager settings
+        assert_eq!(opt_level.llvm_level(), llvm_level);
+        assert_eq!(opt_level.size_level(), size_level);
+        assert_eq!(opt_level.inline_threshold(), inline_threshold);
+        assert!(TargetOpts::default().target_machine(opt_level).is_ok());
+    }
+    let err = "fast".parse::<OptLevel>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown opt level: fast (expected 0, 1, 2, 3, s or z)"
+    );
+}
+
+#[test]
+fn compile_opt_levels() {
+    let src = "
+fn :add { a: int b: int } int =>
+  return a + b
+
+fn :scale { a: int } int =>
+  let x = a
+  x = x * 3 + 1
+  x = x * 5 + 2
+  x = x * 7 + 3
+  x = x * 11 + 4
+  x = x * 13 + 5
+  x = x * 17 + 6
+  x = x * 19 + 7
+  x = x * 23 + 8
+  x = x * 29 + 9
+  x = x * 31 + 10
+  return x
+
+fn :main {} =>
+  const sum = :add { a: 1 b: 2 }
+  const scaled = :scale { a: sum }
+";
+    let ir = |opt_level: OptLevel| {
+        let mut opts = test_opts("opt-levels", vec![Emit::LlvmIr], TargetOpts::default());
+        opts.opt_level = opt_level;
+        let output_dir = PathBuf::from(&opts.output_dir);
+        with_compiler(opts, mono(src), |compiler| compiler.compile().unwrap());
+        let ir = fs::read_to_string(output_dir.join("module/test.pkg.ll")).unwrap();
+        let _ = fs::remove_dir_all(&output_dir);
+        ir
+    };
+    let main_calls_pkg_main = "call void @\"test.pkg:main\"()";
+
+    // -O0 doesn't run the passes
+    let ir_o0 = ir(OptLevel::O0);
+    assert!(ir_o0.contains("alloca"));
+    assert!(ir_o0.contains("call i32 @\"test.pkg:add\""));
+    assert!(ir_o0.contains(main_calls_pkg_main));
+
+    // -O2 promotes the locals and inlines the calls
+    let ir_o2 = ir(OptLevel::O2);
+    assert!(!ir_o2.contains("alloca"));
+    assert!(!ir_o2.contains("call i32 @\"test.pkg:add\""));
+    assert!(!ir_o2.contains(main_calls_pkg_main));
+
+    // -Os inlines less
+    let ir_os = ir(OptLevel::Os);
+    assert!(!ir_os.contains("alloca"));
+    assert!(!ir_os.contains("call i32 @\"test.pkg:add\""));
+    assert!(ir_os.contains(main_calls_pkg_main));
+}
+
+#[test]
+fn compile_debug_info() {
+    let src = "
+fn :add { a: int b: int } int =>
+  const sum = a + b
+  return sum
+";
+    // functions only get subprograms when they're from a source file
+    let mut ast = Ast::from_source("package \"test.pkg\"\nfiles {\n}\n");
+    ast.parse_package().unwrap();
+    ast.parse_source(PathBuf::from("src/main.yak"), src)
+        .unwrap();
+    let mut hir = Hir::default();
+    hir.from_ast(true, None, &ast).unwrap();
+    assert!(check_all(&mut hir).is_empty());
+    monomorphize(&mut hir).unwrap();
+
+    let mut opts = test_opts("debug-info", vec![Emit::LlvmIr], TargetOpts::default());
+    opts.debug_info = true;
+    let output_dir = PathBuf::from(&opts.output_dir);
+    with_compiler(opts, hir, |compiler| compiler.compile().unwrap());
+    let ir = fs::read_to_string(output_dir.join("module/test.pkg.ll")).unwrap();
+    assert!(ir.contains("!DICompileUnit("), "{}", ir);
+    assert!(
+        ir.contains("!DISubprogram(name: \"test.pkg:add\""),
+        "{}",
+        ir
+    );
+    assert!(ir.contains("!DILocalVariable(name: \"sum\""), "{}", ir);
+    assert!(ir.contains("\"Dwarf Version\""), "{}", ir);
+    assert!(ir.contains("\"Debug Info Version\""), "{}", ir);
+    let _ = fs::remove_dir_all(&output_dir);
+}
+
+#[test]
+fn compile_objects() {
+    let src = "
+fn :add { a: int b: int } int =>
+  return a + b
+";
+    // objects are always written (to be linked) but are only
+    // artifacts when they're emitted
+    for emit in [vec![], vec![Emit::Obj]] {
+        let reported = emit.contains(&Emit::Obj);
+        let opts = test_opts("objects", emit, TargetOpts::default());
+        let output_dir = opts.output_dir.clone();
+        let (object_files, kinds) = with_compiler(opts, mono(src), |compiler| {
+            compiler.compile().unwrap();
+            let kinds: Vec<&str> = compiler
+                .artifacts
+                .iter()
+                .map(|artifact| artifact.kind)
+                .collect();
+            (compiler.object_files.clone(), kinds)
+        });
+
+        let object_file = format!("{}/obj/test.pkg.o", &output_dir);
+        assert_eq!(object_files, vec![object_file.clone()]);
+        assert_eq!(kinds, if reported { vec!["obj"] } else { vec![] });
+        // a host object with the function symbol
+        let object = fs::read(&object_file).unwrap();
+        if cfg!(target_os = "linux") {
+            assert_eq!(&object[..4], b"\x7fELF");
+        }
+        assert!(object
+            .windows("test.pkg:add".len())
+            .any(|bytes| bytes == b"test.pkg:add"));
+        let _ = fs::remove_dir_all(&output_dir);
+    }
+}
+
+#[test]
+fn check_newtypes_by_package() {
+    let dep_src = "
+type UserId int64
+
+fn :make {} UserId =>
+  return UserId { 1 }
+";
+    let hir = lower_with_dep(
+        dep_src,
+        ":make",
+        "
+fn :main {} =>
+  const id = :make {}
+  const raw: int64 = id.0
+",
+    );
+    assert_eq!(errors(hir), Vec::<String>::new());
+
+    // a new type with the same name in another package is a different type
+    let hir = lower_with_dep(
+        dep_src,
+        ":make",
+        "
+type UserId int64
+
+fn :main {} =>
+  const id: UserId = :make {}
+",
+    );
+    let errors = errors(hir);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("expected type UserId but found UserId"));
+}
+
+#[test]
+fn check_unknown_types() {
+    let errors = check_errors(
+        "
+struct Point
+  x: int
+
+fn :x { point: Pont } int =>
+  return 1
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("unknown type Pont"));
+
+    let errors = check_errors(
+        "
+fn :main {} =>
+  const points: List[Pont] = List:new {}
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("unknown type Pont"));
+
+    let errors = check_errors(
+        "
+struct Line
+  start: Pont
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("type error in struct Line field start: unknown type Pont"));
+}
+
+#[test]
+fn check_const_assign() {
+    let src = "
+struct Point
+  x: int
+
+fn :main {} =>
+  let count = 1
+  count = 2
+  count += 1
+  let point = Point { x: 1 }
+  point.x += 1
+";
+    assert_eq!(check_errors(src), Vec::<String>::new());
+
+    let errors = check_errors(
+        "
+fn :main {} =>
+  const count = 1
+  count = 2
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("cannot assign twice to const count"));
+
+    // compound assignments assign to the const too
+    let errors = check_errors(
+        "
+fn :main {} =>
+  const count = 1
+  count += 1
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("cannot assign twice to const count"));
+
+    let errors = check_errors(
+        "
+struct Point
+  x: int
+
+fn :main {} =>
+  const point = Point { x: 1 }
+  point.x *= 2
+",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("cannot assign to field x of const point"));
+}
+
+#[test]
+fn check_casts() {
+    let src = "
+fn :main {} =>
+  const big: int64 = 300
+  const small = big as int8
+  const flag = true as int
+  const ratio = big as float32
+  const whole = 7.9 as uint16
+";
+    assert_eq!(check_errors(src), Vec::<String>::new());
+
+    let cases = [
+        ("Point { x: 1 } as int", "cannot cast Point as int32"),
+        ("1 as Point", "cannot cast int32 as Point"),
+        ("1 as bool", "cannot cast int32 as bool"),
+        ("2.5 as bool", "cannot cast float64 as bool"),
+        ("true as float64", "cannot cast bool as float64"),
+        ("\"1\" as int", "cannot cast String as int32"),
+    ];
+    for (cast, expected) in cases {
+        let errors = check_errors(&format!(
+            "
+struct Point
+  x: int
+
+fn :main {{}} =>
+  const value = {}
+",
+            cast
+        ));
+        assert_eq!(errors.len(), 1, "{}", cast);
+        assert!(errors[0].contains(expected), "{}: {}", cast, errors[0]);
+    }
+}
+
+#[test]
+fn compile_float_casts() {
+    let ir = compile_ir(
+        "float-casts",
+        "
+fn :to_int8 { x: float64 } int8 =>
+  return x as int8
+
+fn :to_uint8 { x: float64 } uint8 =>
+  return x as uint8
+",
+    );
+    // out of range values are poison for `fptosi` and `fptoui`
+    assert!(ir.contains("call i8 @llvm.fptosi.sat.i8.f64(double"));
+    assert!(ir.contains("call i8 @llvm.fptoui.sat.i8.f64(double"));
+    assert!(!ir.contains(" = fptosi ") && !ir.contains(" = fptoui "));
+
+    // saturate out of range values and convert NaN to 0
+    ExecutionEngine::link_in_mc_jit();
+    let context = Context::create();
+    let buffer = MemoryBuffer::create_from_memory_range_copy(ir.as_bytes(), "float-casts");
+    let module = context.create_module_from_ir(buffer).unwrap();
+    let engine = module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .unwrap();
+    // functions push frames for backtraces (the runtime isn't linked)
+    extern "C" fn frame_push(_name: *const i8) {}
+    extern "C" fn frame_pop() {}
+    let push = module.get_function("yak_frame_push").unwrap();
+    engine.add_global_mapping(&push, frame_push as *const () as usize);
+    let pop = module.get_function("yak_frame_pop").unwrap();
+    engine.add_global_mapping(&pop, frame_pop as *const () as usize);
+    let to_int8 = unsafe {
+        engine
+            .get_function::<unsafe extern "C" fn(f64) -> i8>("test.pkg:to_int8")
+            .unwrap()
+    };
+    let to_uint8 = unsafe {
+        engine
+            .get_function::<unsafe extern "C" fn(f64) -> u8>("test.pkg:to_uint8")
+            .unwrap()
+    };
+    let cases = [
+        (7.9, 7, 7),
+        (-7.9, -7, 0),
+        (1000.0, 127, 255),
+        (-1000.0, -128, 0),
+    ];
+    for (value, int8, uint8) in cases {
+        assert_eq!(unsafe { to_int8.call(value) }, int8, "{} as int8", value);
+        assert_eq!(unsafe { to_uint8.call(value) }, uint8, "{} as uint8", value);
+    }
+    assert_eq!(unsafe { to_int8.call(f64::NAN) }, 0);
+    assert_eq!(unsafe { to_uint8.call(f64::NAN) }, 0);
+    assert_eq!(unsafe { to_int8.call(f64::INFINITY) }, 127);
+    assert_eq!(unsafe { to_uint8.call(f64::NEG_INFINITY) }, 0);
+}
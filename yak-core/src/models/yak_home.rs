@@ -31,6 +31,14 @@ impl YakHome {
     pub fn get_home_version_bin_dir(&self) -> PathBuf {
         PathBuf::from_iter([&self.env.yak_home, "yak", &self.version_path_part(), "bin"].iter())
     }
+    // ~/.yak/yak/v0.0.0/lib
+    pub fn get_home_version_lib_dir(&self) -> PathBuf {
+        PathBuf::from_iter([&self.env.yak_home, "yak", &self.version_path_part(), "lib"].iter())
+    }
+    // ~/.yak/yak/v0.0.0/lib/aarch64-unknown-linux-gnu
+    pub fn get_home_version_target_lib_dir(&self, triple: &str) -> PathBuf {
+        self.get_home_version_lib_dir().join(triple)
+    }
     // ~/.yak/yak/v0.0.0/pkg
     pub fn get_home_version_pkg_dir(&self) -> PathBuf {
         PathBuf::from_iter([&self.env.yak_home, "yak", &self.version_path_part(), "pkg"].iter())
@@ -52,6 +60,7 @@ impl YakHome {
             paths.append(&mut vec![
                 self.get_home_version_dir(),
                 self.get_home_version_bin_dir(),
+                self.get_home_version_lib_dir(),
                 self.get_home_version_pkg_dir(),
                 self.get_home_version_src_dir(),
             ]);
@@ -77,6 +86,7 @@ pub enum YakPath {
     Root(PathBuf),
     RootVersion(PathBuf),
     RootVersionBin(PathBuf),
+    RootVersionLib(PathBuf),
     RootVersionPkg(PathBuf),
     RootVersionSrc(PathBuf),
 }
@@ -107,4 +117,15 @@ mod tests {
             "expected to create yak home dir"
         );
     }
+
+    #[test]
+    fn yak_target_lib_dir() {
+        let yak_home = YakHome::default();
+        let lib_dir = yak_home.get_home_version_target_lib_dir("aarch64-unknown-linux-gnu");
+        assert_eq!(
+            lib_dir.parent(),
+            Some(yak_home.get_home_version_lib_dir().as_path())
+        );
+        assert!(lib_dir.ends_with("lib/aarch64-unknown-linux-gnu"));
+    }
 }
@@ -21,11 +21,21 @@ pub struct YakPackage {
     pub pkg_dependencies: Vec<YakDependency>,
     pub pkg_imports: Vec<YakImport>,
     pub pkg_exports: YakExport,
+    // build settings by profile name (debug or release)
+    pub pkg_profiles: Vec<YakProfile>,
+    // linker settings
+    pub pkg_link: YakLink,
     // TODO: yak version semver requirement
     pub yak_version: YakVersion,
 }
 
 impl YakPackage {
+    pub fn get_profile(&self, name: &str) -> Option<&YakProfile> {
+        self.pkg_profiles
+            .iter()
+            .find(|profile| profile.name == name)
+    }
+
     // returns a list of all remote dependency urls
     // - remote path + relative dep.path
     // - remote dep.path
@@ -157,6 +167,28 @@ pub struct YakFeature {
     pub dependencies: Vec<YakDependency>,
 }
 
+// Settings which override the profile defaults
+// (i.e. `profile release { opt_level s }`)
+#[derive(Debug, Default, Clone)]
+pub struct YakProfile {
+    pub name: String,
+    // 0, 1, 2, 3, s or z
+    pub opt_level: Option<String>,
+    pub overflow_checks: Option<bool>,
+    pub debug_assertions: Option<bool>,
+    // generate DWARF debug info
+    pub debug_info: Option<bool>,
+}
+
+// How the package is linked (i.e. `link { linker ld.lld lib static }`)
+#[derive(Debug, Default, Clone)]
+pub struct YakLink {
+    // cc, clang or ld.lld
+    pub linker: Option<String>,
+    // static or shared (libraries only)
+    pub lib: Option<String>,
+}
+
 #[derive(Debug, Default)]
 pub struct YakImport {
     pub pkg_id: String,
@@ -2,8 +2,8 @@ use super::name::Name;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FieldId {
-    field_name: String,
-    field_num: usize,
+    pub field_name: String,
+    pub field_num: usize,
 }
 
 impl FieldId {
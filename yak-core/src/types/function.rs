@@ -1,4 +1,5 @@
 use super::name::Name;
+use super::types::{mangle_generics, TypeId};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionId {
@@ -6,6 +7,8 @@ pub struct FunctionId {
     pub struct_name: Option<String>,
     pub func_name: String,
     pub is_main: bool,
+    // concrete type arguments for generic function instances
+    pub generics: Vec<TypeId>,
 }
 
 impl FunctionId {
@@ -16,25 +19,42 @@ impl FunctionId {
             struct_name,
             func_name,
             is_main,
+            generics: vec![],
         }
     }
+
+    pub fn with_generics(mut self, generics: Vec<TypeId>) -> Self {
+        self.generics = generics;
+        self
+    }
 }
 
 impl Name for FunctionId {
     fn name(&self) -> String {
         if self.struct_name.is_some() {
             let struct_name = self.struct_name.clone().unwrap();
-            format!("{}#{}{}", &self.pkg_name, &struct_name, &self.func_name)
+            format!(
+                "{}#{}{}{}",
+                &self.pkg_name,
+                &struct_name,
+                &self.func_name,
+                mangle_generics(&self.generics)
+            )
         } else {
-            format!("{}{}", &self.pkg_name, &self.func_name)
+            format!(
+                "{}{}{}",
+                &self.pkg_name,
+                &self.func_name,
+                mangle_generics(&self.generics)
+            )
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionArgId {
-    arg_name: String,
-    arg_num: usize,
+    pub arg_name: String,
+    pub arg_num: usize,
 }
 
 impl FunctionArgId {
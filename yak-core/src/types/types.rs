@@ -1,9 +1,23 @@
 use super::name::Name;
 
+// Package name used for primitive types (int32, bool, str, etc.)
+pub const PRIMITIVE_PKG: &str = "yak.primitive";
+
+// Package name used for builtin types (Map, Set, Option, etc.)
+pub const BUILTIN_PKG: &str = "yak.builtin";
+
+// Package name used for type parameters (i.e. `T` in `struct Box[T]`)
+pub const GENERIC_PKG: &str = "yak.generic";
+
+// Type name of anonymous tuples (i.e. `(int, String)`)
+pub const TUPLE_TYPE: &str = "()";
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypeId {
-    pkg_name: String,
-    type_name: String,
+    pub pkg_name: String,
+    pub type_name: String,
+    // concrete type arguments (i.e. Map[String int])
+    pub generics: Vec<TypeId>,
 }
 
 impl TypeId {
@@ -11,12 +25,216 @@ impl TypeId {
         TypeId {
             pkg_name,
             type_name,
+            generics: vec![],
+        }
+    }
+
+    pub fn primitive(type_name: &str) -> Self {
+        TypeId::new(PRIMITIVE_PKG.into(), type_name.into())
+    }
+
+    pub fn builtin(type_name: &str) -> Self {
+        TypeId::new(BUILTIN_PKG.into(), type_name.into())
+    }
+
+    pub fn generic(type_name: &str) -> Self {
+        TypeId::new(GENERIC_PKG.into(), type_name.into())
+    }
+
+    // Function types are builtins named by their args
+    // (i.e. `fn { x: int } int` is `fn{x}[int int]`). The generics
+    // are the arg types followed by the return type (if any).
+    pub fn function(args: Vec<(String, TypeId)>, return_type: Option<TypeId>) -> Self {
+        let names: Vec<String> = args.iter().map(|(name, _)| name.clone()).collect();
+        let mut generics: Vec<TypeId> = args.into_iter().map(|(_, type_id)| type_id).collect();
+        generics.extend(return_type);
+        TypeId::builtin(&format!("fn{{{}}}", names.join(" "))).with_generics(generics)
+    }
+
+    // Anonymous tuples are builtins with their
+    // element types as generics (i.e. `()[int String]`)
+    pub fn tuple(elements: Vec<TypeId>) -> Self {
+        TypeId::builtin(TUPLE_TYPE).with_generics(elements)
+    }
+
+    pub fn with_generics(mut self, generics: Vec<TypeId>) -> Self {
+        self.generics = generics;
+        self
+    }
+
+    pub fn is_primitive(&self) -> bool {
+        self.pkg_name == PRIMITIVE_PKG
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        self.pkg_name == BUILTIN_PKG
+    }
+
+    // Returns true if this is a type parameter
+    pub fn is_generic(&self) -> bool {
+        self.pkg_name == GENERIC_PKG
+    }
+
+    // Returns true if this is a trait object (i.e. `^Shape` used as a type)
+    pub fn is_trait(&self) -> bool {
+        self.type_name.starts_with('^') && !self.is_generic()
+    }
+
+    // Returns true if this is a function type (i.e. `fn { x: int } int`)
+    pub fn is_function(&self) -> bool {
+        self.is_builtin() && self.type_name.starts_with("fn{")
+    }
+
+    // Returns true if this is an anonymous tuple type (i.e. `(int, String)`)
+    pub fn is_tuple(&self) -> bool {
+        self.is_builtin() && self.type_name == TUPLE_TYPE
+    }
+
+    // Returns the named args of a function type
+    pub fn function_args(&self) -> Vec<(String, TypeId)> {
+        let names = self
+            .type_name
+            .trim_start_matches("fn{")
+            .trim_end_matches('}')
+            .split_whitespace();
+        names
+            .zip(self.generics.iter())
+            .map(|(name, type_id)| (name.to_string(), type_id.clone()))
+            .collect()
+    }
+
+    // Returns the return type of a function type
+    pub fn function_return(&self) -> Option<TypeId> {
+        self.generics.get(self.function_args().len()).cloned()
+    }
+
+    // Returns true if this type (or any type argument)
+    // still references a type parameter
+    pub fn has_generic(&self) -> bool {
+        self.is_generic() || self.generics.iter().any(|generic| generic.has_generic())
+    }
+
+    // Replaces the type parameters `params` with the concrete types `args`
+    // (i.e. `Option[T]` with `T = int` is `Option[int]`)
+    pub fn substitute(&self, params: &[String], args: &[TypeId]) -> TypeId {
+        if self.is_generic() {
+            if let Some(index) = params.iter().position(|param| param == &self.type_name) {
+                if let Some(arg) = args.get(index) {
+                    return arg.clone();
+                }
+            }
         }
+        let mut type_id = self.clone();
+        type_id.generics = self
+            .generics
+            .iter()
+            .map(|generic| generic.substitute(params, args))
+            .collect();
+        type_id
+    }
+
+    // Returns true if this is the builtin or primitive `type_name`
+    pub fn is(&self, type_name: &str) -> bool {
+        (self.is_primitive() || self.is_builtin()) && self.type_name == type_name
     }
 }
 
+// Mangles type arguments into a name suffix (i.e. `[yak.primitive#int]`)
+pub fn mangle_generics(generics: &[TypeId]) -> String {
+    if generics.is_empty() {
+        return "".into();
+    }
+    let names: Vec<String> = generics.iter().map(|generic| generic.name()).collect();
+    format!("[{}]", names.join(","))
+}
+
 impl Name for TypeId {
     fn name(&self) -> String {
-        format!("{}#{}", &self.pkg_name, &self.type_name)
+        format!(
+            "{}#{}{}",
+            &self.pkg_name,
+            &self.type_name,
+            mangle_generics(&self.generics)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_id_mangle_generics() {
+        let type_id = TypeId::new("my.pkg".into(), "Box".into()).with_generics(vec![
+            TypeId::primitive("int"),
+            TypeId::builtin("Option").with_generics(vec![TypeId::builtin("String")]),
+        ]);
+        assert_eq!(
+            type_id.name(),
+            "my.pkg#Box[yak.primitive#int,yak.builtin#Option[yak.builtin#String]]"
+        );
+        assert_eq!(TypeId::primitive("int").name(), "yak.primitive#int");
+    }
+
+    #[test]
+    fn type_id_is_trait() {
+        assert!(TypeId::new("my.pkg".into(), "^Shape".into()).is_trait());
+        assert!(!TypeId::new("my.pkg".into(), "Shape".into()).is_trait());
+        assert!(!TypeId::generic("T").is_trait());
+    }
+
+    #[test]
+    fn type_id_function() {
+        let type_id = TypeId::function(
+            vec![
+                ("x".into(), TypeId::primitive("int32")),
+                ("y".into(), TypeId::generic("T")),
+            ],
+            Some(TypeId::primitive("bool")),
+        );
+        assert!(type_id.is_function());
+        assert!(type_id.has_generic());
+        assert_eq!(
+            type_id.function_args(),
+            vec![
+                ("x".to_string(), TypeId::primitive("int32")),
+                ("y".to_string(), TypeId::generic("T")),
+            ]
+        );
+        assert_eq!(type_id.function_return(), Some(TypeId::primitive("bool")));
+        let void = TypeId::function(vec![], None);
+        assert!(void.is_function());
+        assert!(void.function_args().is_empty());
+        assert_eq!(void.function_return(), None);
+        assert!(!TypeId::builtin("Map").is_function());
+    }
+
+    #[test]
+    fn type_id_tuple() {
+        let type_id = TypeId::tuple(vec![TypeId::primitive("int32"), TypeId::builtin("String")]);
+        assert!(type_id.is_tuple());
+        assert_eq!(
+            type_id.name(),
+            "yak.builtin#()[yak.primitive#int32,yak.builtin#String]"
+        );
+        assert!(!TypeId::new("my.pkg".into(), TUPLE_TYPE.into()).is_tuple());
+        assert!(!TypeId::builtin("List").is_tuple());
+    }
+
+    #[test]
+    fn type_id_substitute() {
+        let params = vec!["K".to_string(), "V".to_string()];
+        let args = vec![TypeId::builtin("String"), TypeId::primitive("int")];
+        let type_id =
+            TypeId::builtin("Map").with_generics(vec![TypeId::generic("K"), TypeId::generic("V")]);
+        assert!(type_id.has_generic());
+        let found = type_id.substitute(&params, &args);
+        assert_eq!(found, TypeId::builtin("Map").with_generics(args));
+        assert!(!found.has_generic());
+        // unknown params are left alone
+        assert_eq!(
+            TypeId::generic("T").substitute(&params, &[]),
+            TypeId::generic("T")
+        );
     }
 }
@@ -0,0 +1,303 @@
+// Source formatter
+// Re-emits `.yak` and `yak.pkg` files from the token stream
+// so comments, blank lines and token spellings (i.e. `int`) are kept
+use crate::token::TokenType;
+use crate::{Lexer, Token};
+use std::cmp::Reverse;
+
+// Spaces per indentation level
+pub const INDENT_WIDTH: usize = 2;
+// Lines wider than this have their arg lists split one per line
+pub const MAX_WIDTH: usize = 100;
+
+// A token and its source text
+#[derive(Debug, Clone)]
+struct Word {
+    ty: TokenType,
+    text: String,
+    // separated from the previous word by whitespace
+    spaced: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    depth: usize,
+    words: Vec<Word>,
+}
+
+// Formats source code (formatting is idempotent)
+pub fn format_source(source: &str) -> String {
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let mut lines = vec![];
+    for line in indent_lines(source_lines(source, &lexer.tokens)) {
+        match line {
+            Some(line) => split_long_line(line, &mut lines),
+            None => lines.push(None),
+        }
+    }
+    align_headers(&mut lines);
+    render(&lines)
+}
+
+// Splits the tokens into lines of words with their source indentation
+fn source_lines(source: &str, tokens: &[Token]) -> Vec<(usize, Vec<Word>)> {
+    let mut lines = vec![];
+    let mut indent = 0;
+    let mut words = vec![];
+    let mut spaced = false;
+    // byte position of the next token in the source
+    let mut cursor = 0;
+    for token in tokens {
+        match &token.ty {
+            TokenType::NL => {
+                lines.push((indent, std::mem::take(&mut words)));
+                indent = 0;
+                spaced = false;
+                cursor = source[cursor..]
+                    .find('\n')
+                    .map_or(source.len(), |at| cursor + at + 1);
+            }
+            TokenType::Indent(size) => indent = *size,
+            TokenType::Sp => spaced = true,
+            ty => {
+                let rest = &source[cursor..];
+                cursor += rest.len() - rest.trim_start_matches(' ').len();
+                let text = spelling(ty, &source[cursor..]);
+                if source[cursor..].starts_with(&text) {
+                    cursor += text.len();
+                }
+                words.push(Word {
+                    ty: ty.clone(),
+                    text,
+                    spaced,
+                });
+                spaced = false;
+            }
+        }
+    }
+    lines.push((indent, words));
+    lines
+}
+
+// The source spelling of a token
+// (the lexer maps `int` to int32, `uint` to uint32 and `float` to float32)
+fn spelling(ty: &TokenType, rest: &str) -> String {
+    let text: String = ty.clone().into();
+    let alias = match ty {
+        TokenType::PrInt32 => "int",
+        TokenType::PrUInt32 => "uint",
+        TokenType::PrFloat32 => "float",
+        _ => return text,
+    };
+    if !rest.starts_with(&text) && rest.starts_with(alias) {
+        return alias.into();
+    }
+    text
+}
+
+// Maps source indentation to nesting depth so any consistent indentation
+// width is normalized (blank lines are None and comments don't open a level)
+fn indent_lines(source_lines: Vec<(usize, Vec<Word>)>) -> Vec<Option<Line>> {
+    let mut levels = vec![0];
+    source_lines
+        .into_iter()
+        .map(|(indent, words)| {
+            if words.is_empty() {
+                return None;
+            }
+            let is_comment = words.len() == 1 && matches!(words[0].ty, TokenType::Comment(_));
+            let depth = if is_comment {
+                let at = levels.iter().filter(|level| **level <= indent).count() - 1;
+                if levels[at] == indent {
+                    at
+                } else {
+                    at + 1
+                }
+            } else {
+                while levels.last().is_some_and(|level| *level > indent) {
+                    levels.pop();
+                }
+                if levels.last() != Some(&indent) {
+                    levels.push(indent);
+                }
+                levels.len() - 1
+            };
+            Some(Line { depth, words })
+        })
+        .collect()
+}
+
+// Splits a long line at its arg list (i.e. `{ a: int b: int }`)
+// so each arg is on its own line
+fn split_long_line(line: Line, lines: &mut Vec<Option<Line>>) {
+    if width(&line) <= MAX_WIDTH {
+        lines.push(Some(line));
+        return;
+    }
+    let (open, close) = match find_args_group(&line.words) {
+        Some(group) => group,
+        None => {
+            lines.push(Some(line));
+            return;
+        }
+    };
+    let head = Line {
+        depth: line.depth,
+        words: line.words[..=open].to_vec(),
+    };
+    split_long_line(head, lines);
+    for arg in split_args(&line.words[open + 1..close]) {
+        let arg = Line {
+            depth: line.depth + 1,
+            words: arg,
+        };
+        split_long_line(arg, lines);
+    }
+    let tail = Line {
+        depth: line.depth,
+        words: line.words[close..].to_vec(),
+    };
+    split_long_line(tail, lines);
+}
+
+// The outermost (then widest) `{ name: value }` group closed on the line
+fn find_args_group(words: &[Word]) -> Option<(usize, usize)> {
+    let mut opens = vec![];
+    let mut groups = vec![];
+    for (at, word) in words.iter().enumerate() {
+        match word.ty {
+            TokenType::PunctBraceL => opens.push(at),
+            TokenType::PunctBraceR => {
+                if let Some(open) = opens.pop() {
+                    if is_arg_start(&words[open + 1..]) {
+                        groups.push((opens.len(), open, at));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    groups
+        .into_iter()
+        .min_by_key(|(nested, open, close)| (*nested, Reverse(close - open)))
+        .map(|(_, open, close)| (open, close))
+}
+
+// Does an arg start here? (i.e. `name:`)
+fn is_arg_start(words: &[Word]) -> bool {
+    match words {
+        [name, colon, ..] => {
+            matches!(name.ty, TokenType::IdVar(_) | TokenType::KwSelf)
+                && colon.ty == TokenType::PunctColon
+        }
+        _ => false,
+    }
+}
+
+// Splits the words of an arg list into its args
+fn split_args(words: &[Word]) -> Vec<Vec<Word>> {
+    let mut args: Vec<Vec<Word>> = vec![];
+    let mut nested = 0usize;
+    for (at, word) in words.iter().enumerate() {
+        match word.ty {
+            TokenType::PunctBraceL | TokenType::PunctBracketL | TokenType::PunctParenL => {
+                nested += 1
+            }
+            TokenType::PunctBraceR | TokenType::PunctBracketR | TokenType::PunctParenR => {
+                nested = nested.saturating_sub(1)
+            }
+            _ => {}
+        }
+        let starts_arg = nested == 0 && is_arg_start(&words[at..]);
+        match args.last_mut() {
+            Some(arg) if !starts_arg => arg.push(word.clone()),
+            _ => args.push(vec![word.clone()]),
+        }
+    }
+    args
+}
+
+// Aligns the package header values (i.e. `package     "my.pkg"`)
+fn align_headers(lines: &mut [Option<Line>]) {
+    let is_header = |line: &Option<Line>| match line {
+        Some(Line { depth: 0, words }) => {
+            words.len() == 2
+                && matches!(
+                    words[0].ty,
+                    TokenType::KwPackage | TokenType::KwDescription | TokenType::KwVersion
+                )
+                && matches!(words[1].ty, TokenType::LitString(_))
+        }
+        _ => false,
+    };
+    let mut start = 0;
+    while start < lines.len() {
+        let end = start
+            + lines[start..]
+                .iter()
+                .take_while(|line| is_header(line))
+                .count();
+        let headers = &mut lines[start..end];
+        let key_width = headers
+            .iter()
+            .flatten()
+            .map(|line| line.words[0].text.len())
+            .max()
+            .unwrap_or(0);
+        for line in headers.iter_mut().flatten() {
+            let key = &mut line.words[0].text;
+            *key = format!("{:width$}", key, width = key_width);
+            line.words[1].spaced = true;
+        }
+        start = end + 1;
+    }
+}
+
+fn width(line: &Line) -> usize {
+    line.depth * INDENT_WIDTH + render_words(&line.words).chars().count()
+}
+
+fn render_words(words: &[Word]) -> String {
+    let mut out = String::new();
+    for (at, word) in words.iter().enumerate() {
+        if at > 0 {
+            let space = match (&words[at - 1].ty, &word.ty) {
+                (TokenType::PunctBraceL, TokenType::PunctBraceR) => false,
+                (TokenType::PunctBraceL, _) | (_, TokenType::PunctBraceR) => true,
+                (_, TokenType::Comment(_)) => true,
+                _ => word.spaced,
+            };
+            if space {
+                out.push(' ');
+            }
+        }
+        match word.ty {
+            TokenType::Comment(_) => out.push_str(word.text.trim_end()),
+            _ => out.push_str(&word.text),
+        }
+    }
+    out
+}
+
+// Renders the lines with at most one blank line between them
+// and a single trailing newline
+fn render(lines: &[Option<Line>]) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    for line in lines {
+        match line {
+            Some(line) => {
+                if blank && !out.is_empty() {
+                    out.push('\n');
+                }
+                blank = false;
+                out.push_str(&" ".repeat(line.depth * INDENT_WIDTH));
+                out.push_str(&render_words(&line.words));
+                out.push('\n');
+            }
+            None => blank = true,
+        }
+    }
+    out
+}
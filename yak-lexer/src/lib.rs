@@ -3,32 +3,41 @@
 
 mod test;
 
+pub mod fmt;
 pub mod token;
 use regex::Regex;
 use token::TokenType;
 
 #[cfg(not(test))]
-use log::{debug, info};
+use log::debug;
 #[cfg(test)]
-use std::{println as debug, println as info};
+use std::println as debug;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
-    // TODO: disable Span (need to rework this)
-    // pub span: Span,
     pub ty: TokenType,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+impl Token {
+    pub fn new(ty: TokenType) -> Self {
+        Token {
+            ty,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Span {
     // line number
-    ln: usize,
+    pub ln: usize,
     // char pos in the raw src
-    pos: usize,
+    pub pos: usize,
     // start column
-    start: usize,
+    pub start: usize,
     // end column
-    end: usize,
+    pub end: usize,
 }
 
 // literal
@@ -225,7 +234,7 @@ impl<'a> Lexer<'a> {
         // the line number of the char
         let mut ln = 1usize;
         // the column of the char
-        let mut col = 1usize;
+        let mut col = 0usize;
 
         while let Some(this) = self.stack.pop() {
             pos += 1; // this prob needs to be after we do any work?
@@ -243,9 +252,7 @@ impl<'a> Lexer<'a> {
                         buf.push(this);
                     } else {
                         self.buf_to_token(&mut buf, true, pos, ln, col);
-                        pos += 1;
-                        col += 1;
-                        self.push_token(TokenType::Sp, pos, ln, col);
+                        self.push_token(TokenType::Sp, pos, ln, col + 1);
                     }
                 }
                 '\n' => {
@@ -257,7 +264,7 @@ impl<'a> Lexer<'a> {
                         // check if the buf matches anything... should flush
                         // the buf only if not in the middle of a quote
                         self.buf_to_token(&mut buf, !quote_on, pos, ln, col);
-                        self.push_token(TokenType::NL, pos, ln, col);
+                        self.push_token(TokenType::NL, pos, ln, col + 1);
                         col = 0;
                     }
                     ln += 1;
@@ -277,7 +284,7 @@ impl<'a> Lexer<'a> {
                             quote_on = false;
                             buf.push(this);
                             let token_type = TokenType::LitString(String::from_iter(buf.clone()));
-                            self.push_token(token_type, pos, ln, col);
+                            self.push_token(token_type, pos, ln, col + 1);
                             buf.clear();
                         }
                     }
@@ -304,7 +311,7 @@ impl<'a> Lexer<'a> {
                         prev = next;
                     }
 
-                    self.buf_to_token(&mut buf, true, pos, ln, col);
+                    self.buf_to_token(&mut buf, true, pos, ln, col + 1);
                     continue;
                 }
                 '=' => {
@@ -329,23 +336,23 @@ impl<'a> Lexer<'a> {
                             "==" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpEqEq, pos, ln, col);
+                                self.push_token(TokenType::OpEqEq, pos, ln, col + 1);
                                 prev = next;
                             }
                             "=>" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::PunctFatArrow, pos, ln, col);
+                                self.push_token(TokenType::PunctFatArrow, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 // put next back on the stack
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpAssignEq, pos, ln, col);
+                                self.push_token(TokenType::OpAssignEq, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpAssignEq, pos, ln, col);
+                        self.push_token(TokenType::OpAssignEq, pos, ln, col + 1);
                     }
                 }
                 '!' => {
@@ -371,26 +378,26 @@ impl<'a> Lexer<'a> {
                             "!=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpNotEq, pos, ln, col);
+                                self.push_token(TokenType::OpNotEq, pos, ln, col + 1);
                                 prev = next;
                             }
                             "!(" => {
                                 // unary expression
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpUnaryNot, pos, ln, col);
+                                self.push_token(TokenType::OpUnaryNot, pos, ln, col + 1);
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::PunctParenL, pos, ln, col);
+                                self.push_token(TokenType::PunctParenL, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::PunctExclamation, pos, ln, col);
+                                self.push_token(TokenType::PunctExclamation, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::PunctExclamation, pos, ln, col);
+                        self.push_token(TokenType::PunctExclamation, pos, ln, col + 1);
                     }
                 }
                 '>' => {
@@ -417,10 +424,12 @@ impl<'a> Lexer<'a> {
                             ">=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpGte, pos, ln, col);
+                                self.push_token(TokenType::OpGte, pos, ln, col + 1);
                                 prev = next;
                             }
                             ">>" => {
+                                pos += 1;
+                                col += 1;
                                 // >>=
                                 if self.stack.last().map_or(false, |next| next.eq(&'=')) {
                                     if let Some(next) = self.stack.pop() {
@@ -430,22 +439,22 @@ impl<'a> Lexer<'a> {
                                             TokenType::OpAssignBitwiseShiftR,
                                             pos,
                                             ln,
-                                            col,
+                                            col + 1,
                                         );
                                         prev = next;
                                     }
                                 } else {
-                                    self.push_token(TokenType::OpBitwiseShiftR, pos, ln, col);
+                                    self.push_token(TokenType::OpBitwiseShiftR, pos, ln, col + 1);
                                     prev = next;
                                 }
                             }
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpGt, pos, ln, col);
+                                self.push_token(TokenType::OpGt, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpGt, pos, ln, col);
+                        self.push_token(TokenType::OpGt, pos, ln, col + 1);
                     }
                 }
                 '<' => {
@@ -471,11 +480,13 @@ impl<'a> Lexer<'a> {
                             "<=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpLte, pos, ln, col);
+                                self.push_token(TokenType::OpLte, pos, ln, col + 1);
                                 prev = next;
                             }
                             "<<" => {
-                                // >>=
+                                pos += 1;
+                                col += 1;
+                                // <<=
                                 if self.stack.last().map_or(false, |next| next.eq(&'=')) {
                                     if let Some(next) = self.stack.pop() {
                                         pos += 1;
@@ -484,22 +495,22 @@ impl<'a> Lexer<'a> {
                                             TokenType::OpAssignBitwiseShiftL,
                                             pos,
                                             ln,
-                                            col,
+                                            col + 1,
                                         );
                                         prev = next;
                                     }
                                 } else {
-                                    self.push_token(TokenType::OpBitwiseShiftL, pos, ln, col);
+                                    self.push_token(TokenType::OpBitwiseShiftL, pos, ln, col + 1);
                                     prev = next;
                                 }
                             }
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpLt, pos, ln, col);
+                                self.push_token(TokenType::OpLt, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpLt, pos, ln, col);
+                        self.push_token(TokenType::OpLt, pos, ln, col + 1);
                     }
                 }
                 '+' => {
@@ -525,24 +536,24 @@ impl<'a> Lexer<'a> {
                             "+=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignAdd, pos, ln, col);
+                                self.push_token(TokenType::OpAssignAdd, pos, ln, col + 1);
                                 prev = next;
                             }
                             "+(" => {
                                 // unary expression
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpUnaryPlus, pos, ln, col);
+                                self.push_token(TokenType::OpUnaryPlus, pos, ln, col + 1);
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::PunctParenL, pos, ln, col);
+                                self.push_token(TokenType::PunctParenL, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 // check if "+" followed by a digit or decimal
                                 if !ASCII_DIGIT.contains(&next) && next != '.' {
                                     self.stack.push(next);
-                                    self.push_token(TokenType::OpAdd, pos, ln, col);
+                                    self.push_token(TokenType::OpAdd, pos, ln, col + 1);
                                 } else {
                                     pos += 1;
                                     col += 1;
@@ -553,7 +564,7 @@ impl<'a> Lexer<'a> {
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpAdd, pos, ln, col);
+                        self.push_token(TokenType::OpAdd, pos, ln, col + 1);
                     }
                 }
                 '-' => {
@@ -579,24 +590,24 @@ impl<'a> Lexer<'a> {
                             "-=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignSub, pos, ln, col);
+                                self.push_token(TokenType::OpAssignSub, pos, ln, col + 1);
                                 prev = next;
                             }
                             "-(" => {
                                 // unary expression
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpUnaryMinus, pos, ln, col);
+                                self.push_token(TokenType::OpUnaryMinus, pos, ln, col + 1);
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::PunctParenL, pos, ln, col);
+                                self.push_token(TokenType::PunctParenL, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 // check if "-" followed by a digit or decimal
                                 if !ASCII_DIGIT.contains(&next) && next != '.' {
                                     self.stack.push(next);
-                                    self.push_token(TokenType::OpSub, pos, ln, col);
+                                    self.push_token(TokenType::OpSub, pos, ln, col + 1);
                                 } else {
                                     pos += 1;
                                     col += 1;
@@ -607,7 +618,7 @@ impl<'a> Lexer<'a> {
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpSub, pos, ln, col);
+                        self.push_token(TokenType::OpSub, pos, ln, col + 1);
                     }
                 }
                 '/' => {
@@ -633,30 +644,37 @@ impl<'a> Lexer<'a> {
                             "/=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignDiv, pos, ln, col);
+                                self.push_token(TokenType::OpAssignDiv, pos, ln, col + 1);
                                 prev = next;
                             }
                             "//" => {
+                                pos += 1;
+                                col += 1;
                                 // //=
                                 if self.stack.last().map_or(false, |next| next.eq(&'=')) {
                                     if let Some(next) = self.stack.pop() {
                                         pos += 1;
                                         col += 1;
-                                        self.push_token(TokenType::OpAssignFloorDiv, pos, ln, col);
+                                        self.push_token(
+                                            TokenType::OpAssignFloorDiv,
+                                            pos,
+                                            ln,
+                                            col + 1,
+                                        );
                                         prev = next;
                                     }
                                 } else {
-                                    self.push_token(TokenType::OpFloorDiv, pos, ln, col);
+                                    self.push_token(TokenType::OpFloorDiv, pos, ln, col + 1);
                                     prev = next;
                                 }
                             }
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpDiv, pos, ln, col);
+                                self.push_token(TokenType::OpDiv, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpDiv, pos, ln, col);
+                        self.push_token(TokenType::OpDiv, pos, ln, col + 1);
                     }
                 }
                 '*' => {
@@ -682,7 +700,7 @@ impl<'a> Lexer<'a> {
                             "*=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignMul, pos, ln, col);
+                                self.push_token(TokenType::OpAssignMul, pos, ln, col + 1);
                                 prev = next;
                             }
                             "**" => {
@@ -694,22 +712,22 @@ impl<'a> Lexer<'a> {
                                     if let Some(next) = self.stack.pop() {
                                         pos += 1;
                                         col += 1;
-                                        self.push_token(TokenType::OpAssignPow, pos, ln, col);
+                                        self.push_token(TokenType::OpAssignPow, pos, ln, col + 1);
                                         prev = next;
                                     }
                                 } else {
-                                    self.push_token(TokenType::OpPow, pos, ln, col);
+                                    self.push_token(TokenType::OpPow, pos, ln, col + 1);
                                     prev = next;
                                 }
                             }
 
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpMul, pos, ln, col);
+                                self.push_token(TokenType::OpMul, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpMul, pos, ln, col);
+                        self.push_token(TokenType::OpMul, pos, ln, col + 1);
                     }
                 }
                 '%' => {
@@ -735,16 +753,16 @@ impl<'a> Lexer<'a> {
                             "%=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignMod, pos, ln, col);
+                                self.push_token(TokenType::OpAssignMod, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpMod, pos, ln, col);
+                                self.push_token(TokenType::OpMod, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpMod, pos, ln, col);
+                        self.push_token(TokenType::OpMod, pos, ln, col + 1);
                     }
                 }
                 '&' => {
@@ -770,22 +788,22 @@ impl<'a> Lexer<'a> {
                             "&&" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpLogicalAnd, pos, ln, col);
+                                self.push_token(TokenType::OpLogicalAnd, pos, ln, col + 1);
                                 prev = next;
                             }
                             "&=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignBitwiseAnd, pos, ln, col);
+                                self.push_token(TokenType::OpAssignBitwiseAnd, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpBitwiseAnd, pos, ln, col);
+                                self.push_token(TokenType::OpBitwiseAnd, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpBitwiseAnd, pos, ln, col);
+                        self.push_token(TokenType::OpBitwiseAnd, pos, ln, col + 1);
                     }
                 }
                 '|' => {
@@ -811,22 +829,22 @@ impl<'a> Lexer<'a> {
                             "||" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpLogicalOr, pos, ln, col);
+                                self.push_token(TokenType::OpLogicalOr, pos, ln, col + 1);
                                 prev = next;
                             }
                             "|=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignBitwiseOr, pos, ln, col);
+                                self.push_token(TokenType::OpAssignBitwiseOr, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpBitwiseOr, pos, ln, col);
+                                self.push_token(TokenType::OpBitwiseOr, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpBitwiseOr, pos, ln, col);
+                        self.push_token(TokenType::OpBitwiseOr, pos, ln, col + 1);
                     }
                 }
                 ':' => {
@@ -855,14 +873,14 @@ impl<'a> Lexer<'a> {
                             "::" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::PunctDoubleColon, pos, ln, col);
+                                self.push_token(TokenType::PunctDoubleColon, pos, ln, col + 1);
                                 prev = next;
                             }
                             _ => {
                                 if !ASCII_LOWER.contains(&next) {
                                     // :
                                     self.stack.push(next);
-                                    self.push_token(TokenType::PunctColon, pos, ln, col);
+                                    self.push_token(TokenType::PunctColon, pos, ln, col + 1);
                                 } else {
                                     // sniff :func here
                                     // if the next char is a-z
@@ -873,7 +891,7 @@ impl<'a> Lexer<'a> {
                                     buf.push(next);
                                     prev = next;
                                     while let Some(next) = self.stack.pop() {
-                                        if next == '\n' || next == ' ' {
+                                        if next == '\n' || next == ' ' || next == '[' {
                                             self.stack.push(next);
                                             break;
                                         }
@@ -882,13 +900,13 @@ impl<'a> Lexer<'a> {
                                         buf.push(next);
                                         prev = next;
                                     }
-                                    self.buf_to_token(&mut buf, true, pos, ln, col);
+                                    self.buf_to_token(&mut buf, true, pos, ln, col + 1);
                                     continue;
                                 }
                             }
                         }
                     } else {
-                        self.push_token(TokenType::PunctColon, pos, ln, col);
+                        self.push_token(TokenType::PunctColon, pos, ln, col + 1);
                     }
                 }
                 '^' => {
@@ -915,18 +933,25 @@ impl<'a> Lexer<'a> {
                             "^=" => {
                                 pos += 1;
                                 col += 1;
-                                self.push_token(TokenType::OpAssignBitwiseXOr, pos, ln, col);
+                                self.push_token(TokenType::OpAssignBitwiseXOr, pos, ln, col + 1);
                                 prev = next;
                             }
                             // ^Trait
                             "^A" | "^B" | "^C" | "^D" | "^E" | "^F" | "^G" | "^H" | "^I" | "^J"
                             | "^K" | "^L" | "^M" | "^N" | "^O" | "^P" | "^Q" | "^R" | "^S"
                             | "^T" | "^U" | "^V" | "^W" | "^X" | "^Y" | "^Z" => {
+                                pos += 1;
+                                col += 1;
                                 buf.push(this);
                                 buf.push(next);
                                 prev = next;
                                 while let Some(next) = self.stack.pop() {
-                                    if next == '\n' || next == ' ' {
+                                    if next == '\n'
+                                        || next == ' '
+                                        || next == '['
+                                        || next == ']'
+                                        || next == ':'
+                                    {
                                         self.stack.push(next);
                                         break;
                                     }
@@ -935,16 +960,16 @@ impl<'a> Lexer<'a> {
                                     buf.push(next);
                                     prev = next;
                                 }
-                                self.buf_to_token(&mut buf, true, pos, ln, col);
+                                self.buf_to_token(&mut buf, true, pos, ln, col + 1);
                             }
                             // ^
                             _ => {
                                 self.stack.push(next);
-                                self.push_token(TokenType::OpBitwiseXOr, pos, ln, col);
+                                self.push_token(TokenType::OpBitwiseXOr, pos, ln, col + 1);
                             }
                         }
                     } else {
-                        self.push_token(TokenType::OpBitwiseXOr, pos, ln, col);
+                        self.push_token(TokenType::OpBitwiseXOr, pos, ln, col + 1);
                     }
                 }
                 '{' => {
@@ -961,7 +986,7 @@ impl<'a> Lexer<'a> {
                     }
                     // flush buffer
                     self.buf_to_token(&mut buf, true, pos, ln, col);
-                    self.push_token(TokenType::PunctBraceL, pos, ln, col);
+                    self.push_token(TokenType::PunctBraceL, pos, ln, col + 1);
                 }
                 '}' => {
                     // inside quote
@@ -977,7 +1002,7 @@ impl<'a> Lexer<'a> {
                     }
                     // flush buffer
                     self.buf_to_token(&mut buf, true, pos, ln, col);
-                    self.push_token(TokenType::PunctBraceR, pos, ln, col);
+                    self.push_token(TokenType::PunctBraceR, pos, ln, col + 1);
                 }
                 '[' => {
                     // inside quote
@@ -993,7 +1018,7 @@ impl<'a> Lexer<'a> {
                     }
                     // flush buffer
                     self.buf_to_token(&mut buf, true, pos, ln, col);
-                    self.push_token(TokenType::PunctBracketL, pos, ln, col);
+                    self.push_token(TokenType::PunctBracketL, pos, ln, col + 1);
                 }
                 ']' => {
                     // inside quote
@@ -1009,7 +1034,7 @@ impl<'a> Lexer<'a> {
                     }
                     // flush buffer
                     self.buf_to_token(&mut buf, true, pos, ln, col);
-                    self.push_token(TokenType::PunctBracketR, pos, ln, col);
+                    self.push_token(TokenType::PunctBracketR, pos, ln, col + 1);
                 }
                 '(' => {
                     // inside quote
@@ -1025,7 +1050,7 @@ impl<'a> Lexer<'a> {
                     }
                     // flush buffer
                     self.buf_to_token(&mut buf, true, pos, ln, col);
-                    self.push_token(TokenType::PunctParenL, pos, ln, col);
+                    self.push_token(TokenType::PunctParenL, pos, ln, col + 1);
                 }
                 ')' => {
                     // inside quote
@@ -1041,7 +1066,40 @@ impl<'a> Lexer<'a> {
                     }
                     // flush buffer
                     self.buf_to_token(&mut buf, true, pos, ln, col);
-                    self.push_token(TokenType::PunctParenR, pos, ln, col);
+                    self.push_token(TokenType::PunctParenR, pos, ln, col + 1);
+                }
+                '?' => {
+                    // inside quote
+                    if quote_on {
+                        buf.push(this);
+                        prev = this;
+                        continue;
+                    }
+                    // clear indent
+                    if indent_on {
+                        self.push_token(TokenType::Indent(indent), pos, ln, col);
+                        indent_on = false;
+                    }
+                    // flush buffer
+                    self.buf_to_token(&mut buf, true, pos, ln, col);
+                    self.push_token(TokenType::PunctQuestion, pos, ln, col + 1);
+                }
+                // separates anonymous tuple elements (i.e. `(1, "a")`)
+                ',' => {
+                    // inside quote
+                    if quote_on {
+                        buf.push(this);
+                        prev = this;
+                        continue;
+                    }
+                    // clear indent
+                    if indent_on {
+                        self.push_token(TokenType::Indent(indent), pos, ln, col);
+                        indent_on = false;
+                    }
+                    // flush buffer
+                    self.buf_to_token(&mut buf, true, pos, ln, col);
+                    self.push_token(TokenType::PunctComma, pos, ln, col + 1);
                 }
                 _ => {
                     if indent_on {
@@ -1056,17 +1114,14 @@ impl<'a> Lexer<'a> {
         }
 
         if buf.len() > 0 {
-            self.buf_to_token(&mut buf, true, pos, ln, col);
+            self.buf_to_token(&mut buf, true, pos, ln, col + 1);
             // println!("buf: {:?}", &buf);
         }
-        info!("======TOK======");
-        info!("{:#?}", self.tokens);
-        info!("===============");
     }
 
     #[allow(unused_assignments)]
-    fn push_token(&mut self, token_type: TokenType, _pos: usize, _line: usize, col: usize) {
-        let mut _start = 0usize;
+    fn push_token(&mut self, token_type: TokenType, pos: usize, line: usize, col: usize) {
+        let mut start = 0usize;
         match &token_type {
             // len=1
             TokenType::Sp
@@ -1079,6 +1134,9 @@ impl<'a> Lexer<'a> {
             | TokenType::PunctParenR
             | TokenType::PunctColon
             | TokenType::PunctExclamation
+            | TokenType::PunctQuestion
+            | TokenType::PunctComma
+            | TokenType::PunctDot
             | TokenType::OpAssignEq
             | TokenType::OpGt
             | TokenType::OpLt
@@ -1090,8 +1148,10 @@ impl<'a> Lexer<'a> {
             | TokenType::OpUnaryMinus
             | TokenType::OpUnaryPlus
             | TokenType::OpUnaryNot
+            | TokenType::OpBitwiseAnd
+            | TokenType::OpBitwiseOr
             | TokenType::OpBitwiseXOr
-            | TokenType::OpLogicalNot => _start = col - 1,
+            | TokenType::OpLogicalNot => start = col - 1,
 
             // len=2
             TokenType::PunctDoubleColon
@@ -1112,81 +1172,82 @@ impl<'a> Lexer<'a> {
             | TokenType::OpAssignBitwiseXOr
             | TokenType::OpLogicalAnd
             | TokenType::OpLogicalOr
-            | TokenType::OpBitwiseAnd
-            | TokenType::OpBitwiseOr
             | TokenType::OpBitwiseShiftL
-            | TokenType::OpBitwiseShiftR => _start = col - 2,
+            | TokenType::OpBitwiseShiftR => start = col - 2,
 
             // len=3
             TokenType::OpAssignPow
             | TokenType::OpAssignFloorDiv
             | TokenType::OpAssignBitwiseShiftL
-            | TokenType::OpAssignBitwiseShiftR => _start = col - 3,
+            | TokenType::OpAssignBitwiseShiftR => start = col - 3,
 
             // len=fixed
-            TokenType::PrBool => _start = col - 4,
-            TokenType::PrByte => _start = col - 4,
-            TokenType::PrChar => _start = col - 4,
-            TokenType::PrFloat => _start = col - 5,
-            TokenType::PrFloat32 => _start = col - 7,
-            TokenType::PrFloat64 => _start = col - 7,
-            TokenType::PrInt => _start = col - 3,
-            TokenType::PrInt8 => _start = col - 4,
-            TokenType::PrInt16 => _start = col - 5,
-            TokenType::PrInt32 => _start = col - 5,
-            TokenType::PrInt64 => _start = col - 5,
-            TokenType::PrStr => _start = col - 3,
-            TokenType::PrUInt => _start = col - 4,
-            TokenType::PrUInt8 => _start = col - 5,
-            TokenType::PrUInt16 => _start = col - 6,
-            TokenType::PrUInt32 => _start = col - 6,
-            TokenType::PrUInt64 => _start = col - 6,
-
-            TokenType::KwAs => _start = col - 2,
-            TokenType::KwBreak => _start = col - 5,
-            TokenType::KwCase => _start = col - 4,
-            TokenType::KwConst => _start = col - 5,
-            TokenType::KwContinue => _start = col - 8,
-            TokenType::KwDependencies => _start = col - 12,
-            TokenType::KwDescription => _start = col - 11,
-            TokenType::KwElse => _start = col - 4,
-            TokenType::KwElseIf => _start = col - 4, // elif
-            TokenType::KwEnum => _start = col - 4,
-            TokenType::KwExport => _start = col - 6,
-            TokenType::KwFiles => _start = col - 5,
-            TokenType::KwFn => _start = col - 2,
-            TokenType::KwFor => _start = col - 3,
-            TokenType::KwIf => _start = col - 2,
-            TokenType::KwImpl => _start = col - 4,
-            TokenType::KwImport => _start = col - 6,
-            TokenType::KwIn => _start = col - 2,
-            TokenType::KwLazy => _start = col - 4,
-            TokenType::KwLet => _start = col - 3,
-            TokenType::KwMatch => _start = col - 5,
-            TokenType::KwPackage => _start = col - 7,
-            TokenType::KwPrimitive => _start = col - 8,
-            TokenType::KwReturn => _start = col - 6,
-            TokenType::KwSelf => _start = col - 4,
-            TokenType::KwStruct => _start = col - 6,
-            TokenType::KwTest => _start = col - 4,
-            TokenType::KwTestCase => _start = col - 8,
-            TokenType::KwThen => _start = col - 4,
-            TokenType::KwTrait => _start = col - 5,
-            TokenType::KwType => _start = col - 4,
-            TokenType::KwVersion => _start = col - 7,
-            TokenType::KwWhile => _start = col - 5,
+            TokenType::PrBool => start = col - 4,
+            TokenType::PrByte => start = col - 4,
+            TokenType::PrChar => start = col - 4,
+            TokenType::PrFloat => start = col - 5,
+            TokenType::PrFloat32 => start = col - 7,
+            TokenType::PrFloat64 => start = col - 7,
+            TokenType::PrInt => start = col - 3,
+            TokenType::PrInt8 => start = col - 4,
+            TokenType::PrInt16 => start = col - 5,
+            TokenType::PrInt32 => start = col - 5,
+            TokenType::PrInt64 => start = col - 5,
+            TokenType::PrStr => start = col - 3,
+            TokenType::PrUInt => start = col - 4,
+            TokenType::PrUInt8 => start = col - 5,
+            TokenType::PrUInt16 => start = col - 6,
+            TokenType::PrUInt32 => start = col - 6,
+            TokenType::PrUInt64 => start = col - 6,
+
+            TokenType::KwAs => start = col - 2,
+            TokenType::KwBreak => start = col - 5,
+            TokenType::KwCase => start = col - 4,
+            TokenType::KwConst => start = col - 5,
+            TokenType::KwContinue => start = col - 8,
+            TokenType::KwDependencies => start = col - 12,
+            TokenType::KwDescription => start = col - 11,
+            TokenType::KwElse => start = col - 4,
+            TokenType::KwElseIf => start = col - 4, // elif
+            TokenType::KwEnum => start = col - 4,
+            TokenType::KwExport => start = col - 6,
+            TokenType::KwFiles => start = col - 5,
+            TokenType::KwFn => start = col - 2,
+            TokenType::KwFor => start = col - 3,
+            TokenType::KwIf => start = col - 2,
+            TokenType::KwImpl => start = col - 4,
+            TokenType::KwImport => start = col - 6,
+            TokenType::KwIn => start = col - 2,
+            TokenType::KwLazy => start = col - 4,
+            TokenType::KwLet => start = col - 3,
+            TokenType::KwLink => start = col - 4,
+            TokenType::KwMatch => start = col - 5,
+            TokenType::KwPackage => start = col - 7,
+            TokenType::KwPrimitive => start = col - 8,
+            TokenType::KwProfile => start = col - 7,
+            TokenType::KwReturn => start = col - 6,
+            TokenType::KwSelf => start = col - 4,
+            TokenType::KwStruct => start = col - 6,
+            TokenType::KwTest => start = col - 4,
+            TokenType::KwTestCase => start = col - 8,
+            TokenType::KwThen => start = col - 4,
+            TokenType::KwTrait => start = col - 5,
+            TokenType::KwTuple => start = col - 5,
+            TokenType::KwType => start = col - 4,
+            TokenType::KwVersion => start = col - 7,
+            TokenType::KwWhile => start = col - 5,
 
             // Builtin Types, Fn, const, etc
-            TokenType::BuiltinTypeList => _start = col - 4,
-            TokenType::BuiltinTypeMap => _start = col - 3,
-            TokenType::BuiltinTypeMaybe => _start = col - 5,
-            TokenType::BuiltinTypeNone => _start = col - 4,
-            TokenType::BuiltinTypeOption => _start = col - 6,
-            TokenType::BuiltinTypeSet => _start = col - 3,
-            TokenType::BuiltinTypeString => _start = col - 6,
+            TokenType::BuiltinTypeList => start = col - 4,
+            TokenType::BuiltinTypeMap => start = col - 3,
+            TokenType::BuiltinTypeMaybe => start = col - 5,
+            TokenType::BuiltinTypeNone => start = col - 4,
+            TokenType::BuiltinTypeOption => start = col - 6,
+            TokenType::BuiltinTypeSet => start = col - 3,
+            TokenType::BuiltinTypeString => start = col - 6,
 
             // Special
-            TokenType::SpecialTypeSelf => _start = col - 4,
+            TokenType::SpecialTypeSelf => start = col - 4,
 
             // len=unknown
             TokenType::Comment(text)
@@ -1197,20 +1258,20 @@ impl<'a> Lexer<'a> {
             | TokenType::IdType(text)
             | TokenType::IdVar(text)
             | TokenType::IdFunc(text)
-            | TokenType::IdTrait(text) => _start = col - text.len(),
+            | TokenType::IdTrait(text) => start = col - text.len(),
 
             // indent
-            TokenType::Indent(size) => _start = col - size,
+            TokenType::Indent(size) => start = col - size,
         }
         // push token
         let token = Token {
-            // span: Span {
-            //     ln: line,
-            //     start: start,
-            //     end: col,
-            //     pos: pos,
-            // },
             ty: token_type,
+            span: Span {
+                ln: line,
+                pos,
+                start,
+                end: col,
+            },
         };
         self.tokens.push(token);
     }
@@ -1253,6 +1314,7 @@ impl<'a> Lexer<'a> {
             "testcase" => self.push_token(TokenType::KwTestCase, pos, line, col),
             "then" => self.push_token(TokenType::KwThen, pos, line, col),
             "trait" => self.push_token(TokenType::KwTrait, pos, line, col),
+            "tuple" => self.push_token(TokenType::KwTuple, pos, line, col),
             "type" => self.push_token(TokenType::KwType, pos, line, col),
             "while" => self.push_token(TokenType::KwWhile, pos, line, col),
 
@@ -1264,6 +1326,8 @@ impl<'a> Lexer<'a> {
             "export" => self.push_token(TokenType::KwExport, pos, line, col),
             "import" => self.push_token(TokenType::KwImport, pos, line, col),
             "files" => self.push_token(TokenType::KwFiles, pos, line, col),
+            "profile" => self.push_token(TokenType::KwProfile, pos, line, col),
+            "link" => self.push_token(TokenType::KwLink, pos, line, col),
 
             // Literals
             "true" | "false" => {
@@ -1307,6 +1371,31 @@ impl<'a> Lexer<'a> {
                     return;
                 }
                 // debug!("buf_to_token this: {}", &this);
+                // field access after a group
+                // example: :func {}.field or (value).field
+                // tuple fields are positional: (pair).0
+                if let Some(path) = this.strip_prefix('.') {
+                    let fields: Vec<&str> = path.split('.').collect();
+                    let after_group = self.tokens.last().is_some_and(|tok| {
+                        tok.ty == TokenType::PunctBraceR || tok.ty == TokenType::PunctParenR
+                    });
+                    if fields.iter().all(|field| {
+                        self.matcher.var_id.is_match(field)
+                            || (after_group
+                                && !field.is_empty()
+                                && field.chars().all(|c| c.is_ascii_digit()))
+                    }) {
+                        let mut end = col - this.len();
+                        for field in fields {
+                            end += 1;
+                            self.push_token(TokenType::PunctDot, pos, line, end);
+                            end += field.len();
+                            self.push_token(TokenType::IdVar(field.into()), pos, line, end);
+                        }
+                        buf.clear();
+                        return;
+                    }
+                }
                 if let Some(token_type) = self.matcher.resolve(this) {
                     self.push_token(token_type, pos, line, col)
                 } else {
@@ -1,5 +1,26 @@
 #[cfg(test)]
-use crate::{Lexer, Token, TokenType::*};
+use crate::fmt::format_source;
+#[cfg(test)]
+use crate::{Lexer, TokenType, TokenType::*};
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::{Path, PathBuf};
+
+// Expected tokens only compare the token type
+// (spans are checked by the span tests)
+#[cfg(test)]
+#[derive(Debug)]
+struct Token {
+    ty: TokenType,
+}
+
+#[cfg(test)]
+impl PartialEq<Token> for crate::Token {
+    fn eq(&self, other: &Token) -> bool {
+        self.ty == other.ty
+    }
+}
 
 #[test]
 fn special_punctuation() {
@@ -38,6 +59,70 @@ fn special_colon() {
     assert_eq!(lexer.tokens, expected);
 }
 
+#[test]
+fn func_type_params() {
+    let source = ":id[T] {}";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token {
+            ty: IdFunc(":id".into()),
+        },
+        Token { ty: PunctBracketL },
+        Token {
+            ty: IdType("T".into()),
+        },
+        Token { ty: PunctBracketR },
+        Token { ty: Sp },
+        Token { ty: PunctBraceL },
+        Token { ty: PunctBraceR },
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
+#[test]
+fn trait_bounds() {
+    let source = "[T ^Display]";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token { ty: PunctBracketL },
+        Token {
+            ty: IdType("T".into()),
+        },
+        Token { ty: Sp },
+        Token {
+            ty: IdTrait("^Display".into()),
+        },
+        Token { ty: PunctBracketR },
+    ];
+    assert_eq!(lexer.tokens, expected);
+    let span = lexer.tokens[4].span;
+    assert_eq!((span.ln, span.start, span.end), (1, 4, 12));
+}
+
+#[test]
+fn trait_object_call() {
+    let source = "^Shape:area {}";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token {
+            ty: IdTrait("^Shape".into()),
+        },
+        Token {
+            ty: IdFunc(":area".into()),
+        },
+        Token { ty: Sp },
+        Token { ty: PunctBraceL },
+        Token { ty: PunctBraceR },
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
 #[test]
 fn special_pipe() {
     let source = "| |= ||";
@@ -242,6 +327,124 @@ fn special_exclamation() {
     assert_eq!(lexer.tokens, expected);
 }
 
+#[test]
+fn special_question() {
+    let source = "x? :func {}?";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token {
+            ty: IdVar("x".into()),
+        },
+        Token { ty: PunctQuestion },
+        Token { ty: Sp },
+        Token {
+            ty: IdFunc(":func".into()),
+        },
+        Token { ty: Sp },
+        Token { ty: PunctBraceL },
+        Token { ty: PunctBraceR },
+        Token { ty: PunctQuestion },
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
+#[test]
+fn special_dot() {
+    let source = "(p).x :func {}.x.y";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token { ty: PunctParenL },
+        Token {
+            ty: IdVar("p".into()),
+        },
+        Token { ty: PunctParenR },
+        Token { ty: PunctDot },
+        Token {
+            ty: IdVar("x".into()),
+        },
+        Token { ty: Sp },
+        Token {
+            ty: IdFunc(":func".into()),
+        },
+        Token { ty: Sp },
+        Token { ty: PunctBraceL },
+        Token { ty: PunctBraceR },
+        Token { ty: PunctDot },
+        Token {
+            ty: IdVar("x".into()),
+        },
+        Token { ty: PunctDot },
+        Token {
+            ty: IdVar("y".into()),
+        },
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
+#[test]
+fn special_dot_tuple() {
+    let source = "(p).0 :func {}.1.x .5";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token { ty: PunctParenL },
+        Token {
+            ty: IdVar("p".into()),
+        },
+        Token { ty: PunctParenR },
+        Token { ty: PunctDot },
+        Token {
+            ty: IdVar("0".into()),
+        },
+        Token { ty: Sp },
+        Token {
+            ty: IdFunc(":func".into()),
+        },
+        Token { ty: Sp },
+        Token { ty: PunctBraceL },
+        Token { ty: PunctBraceR },
+        Token { ty: PunctDot },
+        Token {
+            ty: IdVar("1".into()),
+        },
+        Token { ty: PunctDot },
+        Token {
+            ty: IdVar("x".into()),
+        },
+        Token { ty: Sp },
+        Token {
+            ty: LitNumber(".5".into()),
+        },
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
+#[test]
+fn special_comma() {
+    let source = "(1, \"a,b\")";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token { ty: PunctParenL },
+        Token {
+            ty: LitNumber("1".into()),
+        },
+        Token { ty: PunctComma },
+        Token { ty: Sp },
+        Token {
+            ty: LitString("\"a,b\"".into()),
+        },
+        Token { ty: PunctParenR },
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
 #[test]
 fn special_asterisk() {
     let source = "* *= **=";
@@ -499,6 +702,22 @@ fn keywords() {
     assert_eq!(lexer.tokens, expected);
 }
 
+#[test]
+fn keyword_tuple() {
+    let source = "tuple Pair";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let expected = vec![
+        Token { ty: Indent(0) },
+        Token { ty: KwTuple },
+        Token { ty: Sp },
+        Token {
+            ty: IdType("Pair".into()),
+        },
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
 #[test]
 fn primitives() {
     let source = "bool byte float float32 float64 int int8 int16 int32 int64 uint uint8 uint16 uint32 uint64";
@@ -1270,6 +1489,169 @@ export {
     assert_eq!(lexer.tokens, expected);
 }
 
+#[test]
+fn span_lines_and_columns() {
+    let source = "const x = 1\n  :f { a: \"s t\" } // 2 & y";
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    let spans: Vec<(usize, usize, usize)> = lexer
+        .tokens
+        .iter()
+        .filter(|token| !matches!(token.ty, Sp | NL | Indent(_)))
+        .map(|token| (token.span.ln, token.span.start, token.span.end))
+        .collect();
+    // (line, start column, end column)
+    let expected = vec![
+        (1, 1, 6),
+        (1, 7, 8),
+        (1, 9, 10),
+        (1, 11, 12),
+        (2, 3, 5),
+        (2, 6, 7),
+        (2, 8, 9),
+        (2, 9, 10),
+        (2, 11, 16),
+        (2, 17, 18),
+        (2, 19, 21),
+        (2, 22, 23),
+        (2, 24, 25),
+        (2, 26, 27),
+    ];
+    assert_eq!(spans, expected);
+}
+
+#[test]
+fn fmt_indent_and_spacing() {
+    let source = "\n\n# add\nfn :add {a: int b: int} int =>\n    # sum\n    const x = a + b   # trailing\n\n\n\n    return x\nfn :noop { } =>\n    let y = 1";
+    let expected = "# add\nfn :add { a: int b: int } int =>\n  # sum\n  const x = a + b # trailing\n\n  return x\nfn :noop {} =>\n  let y = 1\n";
+    assert_eq!(format_source(source), expected);
+}
+
+#[test]
+fn fmt_keeps_spellings() {
+    let source = "fn :f { a: int b: uint c: float d: int32 } str =>\n  return \"a  { b }\"\n";
+    assert_eq!(format_source(source), source);
+}
+
+#[test]
+fn fmt_long_args() {
+    let source = format!(
+        "fn :f {{ first: int second: int third: fn {{ x: int }} int }} int =>\n  return :g {{ value: first name: \"{}\" }}\n",
+        "x".repeat(80)
+    );
+    let expected = format!(
+        "fn :f {{ first: int second: int third: fn {{ x: int }} int }} int =>\n  return :g {{\n    value: first\n    name: \"{}\"\n  }}\n",
+        "x".repeat(80)
+    );
+    assert_eq!(format_source(&source), expected);
+
+    let source = format!(
+        "fn :f {{ first: int second: int third: fn {{ x: int }} int {} }} int =>\n  return first\n",
+        "fourth: int ".repeat(6)
+    );
+    let expected = format!(
+        "fn :f {{\n  first: int\n  second: int\n  third: fn {{ x: int }} int\n{}}} int =>\n  return first\n",
+        "  fourth: int\n".repeat(6)
+    );
+    assert_eq!(format_source(&source), expected);
+    assert_eq!(format_source(&expected), expected);
+}
+
+#[test]
+fn fmt_package_headers() {
+    let source = "package \"my.pkg\"\nversion   \"0.0.1\"\nfiles {\n    \"./src/lib.yak\"\n}";
+    let expected = "package \"my.pkg\"\nversion \"0.0.1\"\nfiles {\n  \"./src/lib.yak\"\n}\n";
+    assert_eq!(format_source(source), expected);
+}
+
+// Formatting the example and builtin packages is
+// idempotent and only changes whitespace
+#[test]
+fn fmt_round_trip() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut files = vec![];
+    package_files(&root.join("examples"), &mut files);
+    package_files(&root.join("pkg"), &mut files);
+    assert!(files.len() > 10);
+    for file in files {
+        let source = fs::read_to_string(&file).unwrap();
+        let formatted = format_source(&source);
+        assert_eq!(format_source(&formatted), formatted, "{}", file.display());
+        assert_eq!(
+            code_tokens(&formatted),
+            code_tokens(&source),
+            "{}",
+            file.display()
+        );
+    }
+}
+
+#[test]
+fn package_profile() {
+    let source = "profile release {\n  opt_level s\n  overflow_checks false\n}";
+    let tokens = code_tokens(source);
+    let expected = vec![
+        KwProfile,
+        IdVar("release".into()),
+        PunctBraceL,
+        IdVar("opt_level".into()),
+        IdVar("s".into()),
+        IdVar("overflow_checks".into()),
+        LitBoolean("false".into()),
+        PunctBraceR,
+    ];
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn package_link() {
+    let source = "link {\n  linker ld.lld\n  lib static\n}";
+    let tokens = code_tokens(source);
+    let expected = vec![
+        KwLink,
+        PunctBraceL,
+        IdVar("linker".into()),
+        IdPackage("ld.lld".into()),
+        IdVar("lib".into()),
+        IdVar("static".into()),
+        PunctBraceR,
+    ];
+    assert_eq!(tokens, expected);
+}
+
+// The package files and the source files they list
+#[cfg(test)]
+fn package_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let pkg_dir = entry.unwrap().path();
+        let pkg_file = pkg_dir.join("yak.pkg");
+        let source = fs::read_to_string(&pkg_file).unwrap();
+        let tokens = code_tokens(&source);
+        let listed = tokens
+            .iter()
+            .skip_while(|ty| **ty != KwFiles)
+            .take_while(|ty| **ty != PunctBraceR)
+            .filter_map(|ty| match ty {
+                LitString(path) => Some(pkg_dir.join(path.trim_matches('"'))),
+                _ => None,
+            });
+        files.extend(listed);
+        files.push(pkg_file);
+    }
+}
+
+#[cfg(test)]
+fn code_tokens(source: &str) -> Vec<TokenType> {
+    let mut lexer = Lexer::from_source(source);
+    lexer.parse();
+    lexer
+        .tokens
+        .into_iter()
+        .map(|token| token.ty)
+        .filter(|ty| !matches!(ty, Sp | NL | Indent(_)))
+        .collect()
+}
+
 // Test Template
 // #[test]
 // fn test_() {
@@ -27,6 +27,12 @@ pub enum TokenType {
     PunctFatArrow,
     // !
     PunctExclamation,
+    // ?
+    PunctQuestion,
+    // .
+    PunctDot,
+    // ,
+    PunctComma,
 
     // Literals
     LitString(String),
@@ -84,6 +90,7 @@ pub enum TokenType {
     KwTestCase,
     KwThen,
     KwTrait,
+    KwTuple,
     KwType,
     KwWhile,
 
@@ -95,6 +102,8 @@ pub enum TokenType {
     KwExport,
     KwImport,
     KwFiles,
+    KwProfile,
+    KwLink,
 
     // BuiltIns
     BuiltinTypeOption,
@@ -175,6 +184,9 @@ impl Into<String> for TokenType {
             TokenType::PunctDoubleColon => "::".into(),
             TokenType::PunctFatArrow => "=>".into(),
             TokenType::PunctExclamation => "!".into(),
+            TokenType::PunctQuestion => "?".into(),
+            TokenType::PunctDot => ".".into(),
+            TokenType::PunctComma => ",".into(),
             TokenType::LitString(s) => s,
             TokenType::LitBoolean(s) => s,
             TokenType::LitNumber(s) => s,
@@ -220,9 +232,11 @@ impl Into<String> for TokenType {
             TokenType::KwIn => "in".into(),
             TokenType::KwLazy => "lazy".into(),
             TokenType::KwLet => "let".into(),
+            TokenType::KwLink => "link".into(),
             TokenType::KwMatch => "match".into(),
             TokenType::KwPackage => "package".into(),
             TokenType::KwPrimitive => "primitive".into(),
+            TokenType::KwProfile => "profile".into(),
             TokenType::KwReturn => "return".into(),
             TokenType::KwSelf => "self".into(),
             TokenType::KwStruct => "struct".into(),
@@ -230,6 +244,7 @@ impl Into<String> for TokenType {
             TokenType::KwTestCase => "testcase".into(),
             TokenType::KwThen => "then".into(),
             TokenType::KwTrait => "trait".into(),
+            TokenType::KwTuple => "tuple".into(),
             TokenType::KwType => "type".into(),
             TokenType::KwVersion => "version".into(),
             TokenType::KwWhile => "while".into(),
@@ -328,6 +343,24 @@ impl TokenType {
         ]
     }
 
+    pub fn assign_operators() -> Vec<Self> {
+        vec![
+            Self::OpAssignEq,
+            Self::OpAssignAdd,
+            Self::OpAssignSub,
+            Self::OpAssignDiv,
+            Self::OpAssignMul,
+            Self::OpAssignPow,
+            Self::OpAssignMod,
+            Self::OpAssignFloorDiv,
+            Self::OpAssignBitwiseAnd,
+            Self::OpAssignBitwiseOr,
+            Self::OpAssignBitwiseXOr,
+            Self::OpAssignBitwiseShiftL,
+            Self::OpAssignBitwiseShiftR,
+        ]
+    }
+
     pub fn operators() -> Vec<Self> {
         vec![
             Self::OpEqEq,
@@ -0,0 +1,498 @@
+use crate::hover::{display_function, find_function, find_let};
+use crate::symbols::{Receiver, SymbolKind};
+use crate::workspace::{statements, Range, Workspace};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use yak_compiler::check::display_type;
+use yak_core::types::types::TypeId;
+
+// LSP CompletionItemKind values
+const KIND_METHOD: u32 = 2;
+const KIND_FUNCTION: u32 = 3;
+const KIND_FIELD: u32 = 5;
+const KIND_VARIABLE: u32 = 6;
+const KIND_INTERFACE: u32 = 8;
+const KIND_ENUM: u32 = 13;
+const KIND_STRUCT: u32 = 22;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: u32,
+    pub detail: Option<String>,
+    // the text inserted over the typed prefix
+    pub insert: String,
+}
+
+impl Completion {
+    fn new(label: String, kind: u32, detail: Option<String>) -> Self {
+        Completion {
+            insert: label.clone(),
+            label,
+            kind,
+            detail,
+        }
+    }
+
+    pub fn to_json(&self, range: Range) -> Value {
+        let mut item = json!({
+            "label": self.label,
+            "kind": self.kind,
+            "textEdit": { "range": range.to_json(), "newText": self.insert },
+        });
+        if let Some(detail) = &self.detail {
+            item["detail"] = json!(detail);
+        }
+        item
+    }
+}
+
+// What the cursor is completing
+#[derive(Debug, PartialEq)]
+enum Context {
+    // `:name` (called on a type, value or nothing)
+    Function(String),
+    // `value.field`
+    Field(String),
+    // `^Name`
+    Trait,
+    // `Name`
+    Type,
+    // `name:` inside a call (or struct value) braces
+    Arg,
+}
+
+impl Workspace {
+    // Loads a workspace for completion
+    // (edited documents rarely parse at the cursor so the line being edited,
+    // then its whole statement, is blanked until the package parses)
+    pub fn load_completing(path: &Path, documents: &HashMap<PathBuf, String>, line: usize) -> Self {
+        let text = match documents.get(path) {
+            Some(text) => text.clone(),
+            None => fs::read_to_string(path).unwrap_or_default(),
+        };
+        let line_count = text.lines().count();
+        let statement = statements(&text)
+            .iter()
+            .map(|(start, _)| *start)
+            .collect::<Vec<usize>>();
+        let start = statement
+            .iter()
+            .rev()
+            .find(|start| **start <= line)
+            .copied()
+            .unwrap_or(line);
+        let end = statement
+            .iter()
+            .find(|start| **start > line)
+            .copied()
+            .unwrap_or(line_count);
+        let mut first = None;
+        // nothing, the line then the statement
+        for blank in [line..line, line..line + 1, start..end] {
+            let mut documents = documents.clone();
+            documents.insert(path.to_path_buf(), blank_lines(&text, blank));
+            let ws = Workspace::load(path, &documents);
+            if ws.hir.is_some() {
+                return ws;
+            }
+            first.get_or_insert(ws);
+        }
+        first.unwrap_or_default()
+    }
+
+    // The completions at a zero-based position of a document
+    // and the range of the typed prefix they replace
+    pub fn completions(
+        &self,
+        path: &Path,
+        text: &str,
+        line: usize,
+        character: usize,
+    ) -> (Vec<Completion>, Range) {
+        let source = text.lines().nth(line).unwrap_or_default();
+        let before: String = source.chars().take(character).collect();
+        let word_start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map_or(before.len(), |(at, _)| at);
+        let word = &before[word_start..];
+        let head = &before[..word_start];
+        let (context, prefix_start) = match head.chars().last() {
+            Some(':') if !head.ends_with("::") => {
+                let receiver = callee(&head[..head.len() - 1]);
+                (Context::Function(receiver.to_string()), word_start - 1)
+            }
+            Some('.') => {
+                let value = head[..head.len() - 1]
+                    .rsplit(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                    .next()
+                    .unwrap_or_default();
+                (Context::Field(value.to_string()), word_start)
+            }
+            Some('^') => (Context::Trait, word_start - 1),
+            _ if word.starts_with(char::is_uppercase) => (Context::Type, word_start),
+            _ => (Context::Arg, word_start),
+        };
+        let range = Range {
+            start: (line, head[..prefix_start].chars().count()),
+            end: (line, before.chars().count()),
+        };
+        let prefix = &before[prefix_start..];
+        let file = self.file_index(path);
+        let mut completions = match (&context, file) {
+            (Context::Function(receiver), Some(file)) => {
+                self.function_completions(file, line, receiver)
+            }
+            (Context::Field(value), Some(file)) => self.field_completions(file, line, value),
+            (Context::Trait, _) => self.trait_completions(),
+            (Context::Type, _) => self.type_completions(),
+            (Context::Arg, Some(file)) => {
+                let offset = text
+                    .lines()
+                    .take(line)
+                    .map(|line| line.len() + 1)
+                    .sum::<usize>()
+                    + before.len();
+                self.arg_completions(file, line, &text[..offset.min(text.len())])
+            }
+            _ => vec![],
+        };
+        completions.retain(|completion| completion.label.starts_with(prefix));
+        completions.sort_by(|a, b| a.label.cmp(&b.label));
+        completions.dedup_by(|a, b| a.label == b.label);
+        (completions, range)
+    }
+
+    // The type named by a receiver (i.e. `Point`, `Self`, `Map[String int]` or a variable)
+    // (None for values of an unknown type)
+    fn receiver(&self, file: usize, line: usize, receiver: &str) -> Option<Receiver> {
+        let receiver = receiver.split('[').next().unwrap_or_default();
+        let name = receiver.rsplit('.').next().unwrap_or_default();
+        if name.is_empty() {
+            return Some(Receiver::None);
+        }
+        if name == "Self" {
+            return match self.self_type_at(file, line) {
+                Some(container) => Some(Receiver::Type(container)),
+                None => Some(Receiver::Any),
+            };
+        }
+        if name.starts_with(char::is_uppercase) {
+            return Some(Receiver::Type(name.to_string()));
+        }
+        match self.value_type(file, line, receiver) {
+            Some(type_id) => Some(Receiver::Type(type_id.type_name)),
+            // package functions (i.e. `my.pkg:func`)
+            None if receiver.contains('.') => Some(Receiver::None),
+            None => None,
+        }
+    }
+
+    // The type of a variable (or its fields) in the function at a line
+    // (i.e. `p` or `self.origin`)
+    fn value_type(&self, file: usize, line: usize, value: &str) -> Option<TypeId> {
+        let hir = self.hir.as_ref()?;
+        // the line being edited may be blank (so after the function's lines)
+        let scope = self.scope_at(file, line).or_else(|| {
+            self.symbols.iter().rposition(|symbol| {
+                symbol.file == file
+                    && matches!(
+                        symbol.kind,
+                        SymbolKind::Function | SymbolKind::Method | SymbolKind::Test
+                    )
+                    && symbol.lines.start <= line
+            })
+        })?;
+        let func_def = self.scope_function(hir, scope)?;
+        let mut names = value.split('.');
+        let name = names.next()?;
+        let mut type_id = match func_def.args.iter().find(|arg| arg.arg_id.arg_name == name) {
+            Some(arg) => arg.type_id.clone(),
+            None => find_let(&func_def.blocks, name)?.type_id.clone()?,
+        };
+        for name in names {
+            let struct_def = hir.find_struct(&type_id)?;
+            type_id = struct_def
+                .fields
+                .iter()
+                .find(|field| field.field_id.field_name == name)?
+                .type_id
+                .clone();
+        }
+        Some(type_id)
+    }
+
+    fn function_completions(&self, file: usize, line: usize, receiver: &str) -> Vec<Completion> {
+        let receiver = match self.receiver(file, line, receiver) {
+            Some(receiver) => receiver,
+            None => return vec![],
+        };
+        let mut completions = vec![];
+        if let Some(hir) = &self.hir {
+            for module in &hir.modules {
+                for func_def in &module.function_defs {
+                    let function_id = &func_def.function_id;
+                    let matches = match &receiver {
+                        Receiver::None => function_id.struct_name.is_none(),
+                        Receiver::Type(type_name) => {
+                            function_id.struct_name.as_ref() == Some(type_name)
+                        }
+                        Receiver::Any => function_id.struct_name.is_some(),
+                    };
+                    if !matches || module.is_test(function_id) {
+                        continue;
+                    }
+                    let kind = match function_id.struct_name {
+                        Some(_) => KIND_METHOD,
+                        None => KIND_FUNCTION,
+                    };
+                    completions.push(Completion::new(
+                        function_id.func_name.clone(),
+                        kind,
+                        Some(display_function(func_def)),
+                    ));
+                }
+            }
+        }
+        // functions which haven't been lowered (i.e. the package doesn't parse)
+        for symbol in &self.symbols {
+            let matches = match (&receiver, &symbol.container) {
+                (Receiver::None, None) => symbol.kind == SymbolKind::Function,
+                (Receiver::Type(type_name), Some(container)) => type_name == container,
+                (Receiver::Any, Some(_)) => true,
+                _ => false,
+            };
+            if matches && matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method) {
+                let kind = match symbol.kind {
+                    SymbolKind::Method => KIND_METHOD,
+                    _ => KIND_FUNCTION,
+                };
+                completions.push(Completion::new(symbol.name.clone(), kind, None));
+            }
+        }
+        completions
+    }
+
+    fn field_completions(&self, file: usize, line: usize, value: &str) -> Vec<Completion> {
+        let hir = match &self.hir {
+            Some(hir) => hir,
+            None => return vec![],
+        };
+        let struct_def = match self
+            .value_type(file, line, value)
+            .and_then(|type_id| hir.find_struct(&type_id))
+        {
+            Some(struct_def) => struct_def,
+            None => return vec![],
+        };
+        struct_def
+            .fields
+            .iter()
+            .map(|field| {
+                Completion::new(
+                    field.field_id.field_name.clone(),
+                    KIND_FIELD,
+                    Some(display_type(&field.type_id)),
+                )
+            })
+            .collect()
+    }
+
+    fn trait_completions(&self) -> Vec<Completion> {
+        let mut completions: Vec<Completion> = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Trait)
+            .map(|symbol| Completion::new(symbol.name.clone(), KIND_INTERFACE, None))
+            .collect();
+        if let Some(hir) = &self.hir {
+            for module in &hir.modules {
+                for trait_def in &module.trait_defs {
+                    let name = trait_def.type_id.type_name.clone();
+                    completions.push(Completion::new(name, KIND_INTERFACE, None));
+                }
+            }
+        }
+        completions
+    }
+
+    fn type_completions(&self) -> Vec<Completion> {
+        let mut completions: Vec<Completion> = self
+            .symbols
+            .iter()
+            .filter_map(|symbol| match symbol.kind {
+                SymbolKind::Struct | SymbolKind::Type => Some((symbol, KIND_STRUCT)),
+                SymbolKind::Enum => Some((symbol, KIND_ENUM)),
+                _ => None,
+            })
+            .map(|(symbol, kind)| Completion::new(symbol.name.clone(), kind, None))
+            .collect();
+        if let Some(hir) = &self.hir {
+            for module in &hir.modules {
+                let struct_names = module
+                    .struct_defs
+                    .iter()
+                    .map(|struct_def| &struct_def.type_id);
+                let alias_names = module.alias_defs.iter().map(|alias_def| &alias_def.type_id);
+                for type_id in struct_names.chain(alias_names) {
+                    let name = type_id.type_name.clone();
+                    completions.push(Completion::new(name, KIND_STRUCT, None));
+                }
+            }
+        }
+        completions
+    }
+
+    // The args of the call (or fields of the struct value) whose braces
+    // contain the cursor which haven't been given yet
+    fn arg_completions(&self, file: usize, line: usize, text: &str) -> Vec<Completion> {
+        let hir = match &self.hir {
+            Some(hir) => hir,
+            None => return vec![],
+        };
+        // args are named so values (i.e. after `name: `) aren't completed
+        if text.trim_end().ends_with(':') {
+            return vec![];
+        }
+        let open = match open_brace(text) {
+            Some(open) => open,
+            None => return vec![],
+        };
+        let callee = callee(&text[..open]);
+        let given = given_names(&text[open + 1..]);
+        let args: Vec<(String, TypeId)> = match callee.rsplit_once(':') {
+            Some((receiver, name)) if !name.is_empty() => {
+                let func_def = self.receiver(file, line, receiver).and_then(|receiver| {
+                    find_function(hir, &format!(":{}", name), &receiver, None)
+                });
+                match func_def {
+                    Some(func_def) => func_def
+                        .args
+                        .iter()
+                        .map(|arg| (arg.arg_id.arg_name.clone(), arg.type_id.clone()))
+                        .collect(),
+                    None => vec![],
+                }
+            }
+            // struct values (i.e. `Point { x: 1 }`)
+            _ => {
+                let name = callee.split('[').next().unwrap_or_default();
+                let name = name.rsplit('.').next().unwrap_or_default();
+                hir.modules
+                    .iter()
+                    .flat_map(|module| module.struct_defs.iter())
+                    .find(|struct_def| struct_def.type_id.type_name == name)
+                    .map(|struct_def| {
+                        struct_def
+                            .fields
+                            .iter()
+                            .map(|field| (field.field_id.field_name.clone(), field.type_id.clone()))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }
+        };
+        args.into_iter()
+            .filter(|(name, _)| !given.contains(name))
+            .map(|(name, type_id)| Completion {
+                insert: format!("{}: ", name),
+                label: name,
+                kind: KIND_VARIABLE,
+                detail: Some(display_type(&type_id)),
+            })
+            .collect()
+    }
+}
+
+// Replaces lines with empty ones (so line numbers don't change)
+fn blank_lines(text: &str, lines: std::ops::Range<usize>) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(at, line)| if lines.contains(&at) { "" } else { line })
+        .collect::<Vec<&str>>()
+        .join("\n")
+        + "\n"
+}
+
+// Calls the function for each char outside of strings and comments
+fn each_code_char(text: &str, mut f: impl FnMut(usize, char)) {
+    let mut in_string = false;
+    let mut in_comment = false;
+    let mut escaped = false;
+    for (at, c) in text.char_indices() {
+        if in_comment {
+            in_comment = c != '\n';
+        } else if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == '#' {
+            in_comment = true;
+        } else {
+            f(at, c);
+        }
+    }
+}
+
+// The byte offset of the innermost unclosed `{`
+fn open_brace(text: &str) -> Option<usize> {
+    let mut opens = vec![];
+    each_code_char(text, |at, c| match c {
+        '{' => opens.push(at),
+        '}' => {
+            opens.pop();
+        }
+        _ => {}
+    });
+    opens.pop()
+}
+
+// The function (or type) before a brace (i.e. `Point:sum` or `Map[String int]:new`)
+fn callee(text: &str) -> &str {
+    let text = text.trim_end();
+    let mut depth = 0usize;
+    for (at, c) in text.char_indices().rev() {
+        match c {
+            ']' => depth += 1,
+            '[' => depth = depth.saturating_sub(1),
+            c if c.is_whitespace() && depth == 0 => return &text[at + 1..],
+            '{' | '(' if depth == 0 => return &text[at + 1..],
+            _ => {}
+        }
+    }
+    text
+}
+
+// The names given in braces so far (i.e. `x` and `y` in `x: 1 y: 2`)
+fn given_names(text: &str) -> Vec<String> {
+    let mut names = vec![];
+    let mut depth = 0usize;
+    let mut word = String::new();
+    each_code_char(text, |_, c| match c {
+        '{' | '[' | '(' => {
+            depth += 1;
+            word.clear();
+        }
+        '}' | ']' | ')' => {
+            depth = depth.saturating_sub(1);
+            word.clear();
+        }
+        ':' if depth == 0 && !word.is_empty() => {
+            names.push(std::mem::take(&mut word));
+        }
+        c if c.is_alphanumeric() || c == '_' => word.push(c),
+        _ => word.clear(),
+    });
+    names
+}
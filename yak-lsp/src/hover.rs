@@ -0,0 +1,204 @@
+use crate::symbols::{Receiver, SymbolKind, Target};
+use crate::workspace::Workspace;
+use yak_compiler::check::{display_signature, display_type};
+use yak_compiler::hir::{Block, FunctionDef, Hir, LetDef};
+
+impl Workspace {
+    // The hover text for a target (its type or signature)
+    // falling back to the source line of its definition
+    pub fn hover(&self, target: &Target) -> Option<String> {
+        let definition = self.definitions(target).into_iter().next();
+        // definitions in other packages have the same names
+        let pkg_name = definition.map(|(file, _)| self.files[file].pkg_name.as_str());
+        let hir_hover = self
+            .hir
+            .as_ref()
+            .and_then(|hir| self.hir_hover(hir, target, pkg_name));
+        hir_hover.or_else(|| {
+            let (file, span) = definition?;
+            let line = self.files[file]
+                .text
+                .lines()
+                .nth(span.ln.saturating_sub(1))?;
+            Some(line.trim().to_string())
+        })
+    }
+
+    fn hir_hover(&self, hir: &Hir, target: &Target, pkg_name: Option<&str>) -> Option<String> {
+        let in_pkg = |name: &str| pkg_name.map_or(true, |pkg_name| pkg_name == name);
+        match target {
+            Target::Func { name, receiver } => {
+                let func_def = find_function(hir, name, receiver, pkg_name)?;
+                Some(display_function(func_def))
+            }
+            Target::Type(name) => {
+                let types = hir.modules.iter().filter(|module| {
+                    in_pkg(&module.module_id.as_pkg_name.clone().unwrap_or_default())
+                });
+                for module in types {
+                    if let Some(struct_def) = module
+                        .struct_defs
+                        .iter()
+                        .find(|struct_def| &struct_def.type_id.type_name == name)
+                    {
+                        let mut text = format!("struct {}", name);
+                        if !struct_def.generics.is_empty() {
+                            text.push_str(&format!("[{}]", struct_def.generics.join(" ")));
+                        }
+                        for field in &struct_def.fields {
+                            text.push_str(&format!(
+                                "\n  {}: {}",
+                                field.field_id.field_name,
+                                display_type(&field.type_id)
+                            ));
+                        }
+                        return Some(text);
+                    }
+                    if let Some(alias_def) = module
+                        .alias_defs
+                        .iter()
+                        .find(|alias_def| &alias_def.type_id.type_name == name)
+                    {
+                        return Some(format!(
+                            "type {} = {}",
+                            name,
+                            display_type(&alias_def.alias_type)
+                        ));
+                    }
+                }
+                None
+            }
+            Target::Trait(name) => {
+                let trait_def = hir
+                    .modules
+                    .iter()
+                    .flat_map(|module| module.trait_defs.iter())
+                    .filter(|trait_def| in_pkg(&trait_def.type_id.pkg_name))
+                    .find(|trait_def| &trait_def.type_id.type_name == name)?;
+                let mut text = format!("trait {}", name);
+                for func_def in &trait_def.function_defs {
+                    text.push_str(&format!(
+                        "\n  fn {} {}",
+                        func_def.function_id.func_name,
+                        display_signature(func_def, &|type_id| type_id.clone())
+                    ));
+                }
+                Some(text)
+            }
+            Target::Var {
+                name,
+                scope: Some(scope),
+            } => {
+                let func_def = self.scope_function(hir, *scope)?;
+                if let Some(arg) = func_def
+                    .args
+                    .iter()
+                    .find(|arg| &arg.arg_id.arg_name == name)
+                {
+                    return Some(format!("{}: {}", name, display_type(&arg.type_id)));
+                }
+                let let_def = find_let(&func_def.blocks, name)?;
+                let keyword = if let_def.mutable { "let" } else { "const" };
+                match &let_def.type_id {
+                    Some(type_id) => {
+                        Some(format!("{} {}: {}", keyword, name, display_type(type_id)))
+                    }
+                    None => Some(format!("{} {}", keyword, name)),
+                }
+            }
+            Target::Var { scope: None, .. } => None,
+        }
+    }
+
+    // The function def of a function, method or test symbol
+    pub fn scope_function<'h>(&self, hir: &'h Hir, scope: usize) -> Option<&'h FunctionDef> {
+        let symbol = &self.symbols[scope];
+        let pkg_name = &self.files[symbol.file].pkg_name;
+        let function_id = match symbol.kind {
+            SymbolKind::Test => hir
+                .modules
+                .iter()
+                .flat_map(|module| module.test_defs.iter())
+                .find(|test_def| {
+                    &test_def.function_id.pkg_name == pkg_name
+                        && (test_def.test_name == symbol.name
+                            || test_def.test_name.starts_with(&format!("{}/", symbol.name)))
+                })
+                .map(|test_def| test_def.function_id.clone())?,
+            _ => {
+                return hir
+                    .modules
+                    .iter()
+                    .flat_map(|module| module.function_defs.iter())
+                    .find(|func_def| {
+                        let function_id = &func_def.function_id;
+                        &function_id.pkg_name == pkg_name
+                            && function_id.func_name == symbol.name
+                            && function_id.struct_name == symbol.container
+                    })
+            }
+        };
+        hir.find_function(&function_id)
+    }
+}
+
+// The function (or method) a call refers to
+pub fn find_function<'h>(
+    hir: &'h Hir,
+    name: &str,
+    receiver: &Receiver,
+    pkg_name: Option<&str>,
+) -> Option<&'h FunctionDef> {
+    hir.modules
+        .iter()
+        .flat_map(|module| {
+            let traits = module.trait_defs.iter();
+            module
+                .function_defs
+                .iter()
+                .chain(traits.flat_map(|trait_def| trait_def.function_defs.iter()))
+        })
+        .filter(|func_def| {
+            pkg_name.map_or(true, |pkg_name| pkg_name == func_def.function_id.pkg_name)
+        })
+        .find(|func_def| {
+            let function_id = &func_def.function_id;
+            function_id.func_name == name
+                && match receiver {
+                    Receiver::None => function_id.struct_name.is_none(),
+                    Receiver::Type(type_name) => {
+                        function_id.struct_name.as_ref() == Some(type_name)
+                    }
+                    Receiver::Any => function_id.struct_name.is_some(),
+                }
+        })
+}
+
+pub fn display_function(func_def: &FunctionDef) -> String {
+    let function_id = &func_def.function_id;
+    format!(
+        "fn {}{} {}",
+        function_id.struct_name.clone().unwrap_or_default(),
+        function_id.func_name,
+        display_signature(func_def, &|type_id| type_id.clone())
+    )
+}
+
+// The first let (or const) of a variable in the blocks
+pub fn find_let<'b>(blocks: &'b [Block], name: &str) -> Option<&'b LetDef> {
+    blocks.iter().find_map(|block| match block {
+        Block::Let(let_def) if let_def.var_name == name => Some(let_def),
+        Block::If(if_def) => if_def
+            .conditions
+            .iter()
+            .find_map(|condition| find_let(&condition.blocks, name))
+            .or_else(|| {
+                if_def
+                    .else_blocks
+                    .as_ref()
+                    .and_then(|blocks| find_let(blocks, name))
+            }),
+        Block::ForIn(for_in) => find_let(&for_in.blocks, name),
+        _ => None,
+    })
+}
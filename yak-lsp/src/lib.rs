@@ -0,0 +1,314 @@
+// Yak language server
+// Speaks LSP (JSON-RPC) over stdio and answers requests
+// by loading the document's package with the lexer, parser and HIR
+mod completion;
+mod hover;
+mod rpc;
+mod symbols;
+mod test;
+mod workspace;
+
+use anyhow::{bail, Context, Result};
+use log::{error, info};
+use rpc::{error_response, notification, response};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use url::Url;
+use workspace::Workspace;
+
+pub use rpc::{read_message, write_message};
+pub use workspace::{Diagnostic, Range, Severity};
+
+// Type checks a package and its resolved dependencies without codegen
+// and returns the diagnostics of every file (the ones the server publishes)
+pub fn check_package(pkg_dir: &Path, deps: &[(String, PathBuf)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Workspace::load_package_deps(pkg_dir, deps, &HashMap::new()).diagnostics;
+    diagnostics.sort_by(|a, b| (&a.path, a.range.start).cmp(&(&b.path, b.range.start)));
+    diagnostics
+}
+
+// Runs the server until the client exits
+pub fn run_stdio() -> Result<()> {
+    // the parser panics on some bad input (which is reported as a diagnostic)
+    std::panic::set_hook(Box::new(|panic| error!("{}", panic)));
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    Server::default().serve(&mut stdin.lock(), &mut stdout.lock())
+}
+
+#[derive(Debug, Default)]
+pub struct Server {
+    // open documents by path
+    documents: HashMap<PathBuf, String>,
+    initialized: bool,
+    shutdown: bool,
+}
+
+impl Server {
+    // Handles messages until `exit` (or the end of the input)
+    pub fn serve(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+        while let Some(message) = read_message(reader)? {
+            if message["method"] == "exit" {
+                if !self.shutdown {
+                    bail!("exit before shutdown");
+                }
+                return Ok(());
+            }
+            for reply in self.handle(&message) {
+                write_message(writer, &reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the response (for requests) and any notifications
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        info!("lsp message: {}", method);
+        let id = match message.get("id") {
+            Some(id) => id,
+            None => return self.notify(method, params),
+        };
+        if !self.initialized && method != "initialize" {
+            return vec![error_response(
+                id,
+                rpc::SERVER_NOT_INITIALIZED,
+                "server not initialized",
+            )];
+        }
+        let result = match method {
+            "initialize" => {
+                self.initialized = true;
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "completionProvider": { "triggerCharacters": [":", ".", "^", "{"] },
+                        "hoverProvider": true,
+                        "definitionProvider": true,
+                        "referencesProvider": true,
+                        "documentSymbolProvider": true,
+                    },
+                    "serverInfo": { "name": "yak-lsp", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/completion" => self.completion(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            _ => {
+                let message = format!("unknown method: {}", method);
+                return vec![error_response(id, rpc::METHOD_NOT_FOUND, &message)];
+            }
+        };
+        match result {
+            Ok(result) => vec![response(id, result)],
+            Err(err) => vec![error_response(
+                id,
+                rpc::INVALID_PARAMS,
+                &format!("{:#}", err),
+            )],
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let document = &params["textDocument"];
+        let path = match document_path(params) {
+            Ok(path) => path,
+            // i.e. `initialized`
+            Err(_) => return vec![],
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = document["text"].as_str().unwrap_or_default();
+                self.documents.insert(path.clone(), text.into());
+            }
+            "textDocument/didChange" => {
+                // full sync sends the whole text
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let Some(text) = text {
+                    self.documents.insert(path.clone(), text.into());
+                }
+            }
+            "textDocument/didSave" => {}
+            "textDocument/didClose" => {
+                self.documents.remove(&path);
+                return vec![];
+            }
+            _ => return vec![],
+        }
+        self.publish_diagnostics(&path)
+    }
+
+    // Publishes the diagnostics of every file in the document's package
+    // (files without errors get an empty list to clear old ones)
+    fn publish_diagnostics(&self, path: &Path) -> Vec<Value> {
+        let ws = Workspace::load(path, &self.documents);
+        ws.root_files
+            .iter()
+            .filter_map(|file| {
+                let diagnostics: Vec<Value> = ws
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| &diagnostic.path == file)
+                    .map(|diagnostic| diagnostic.to_json())
+                    .collect();
+                let uri = Url::from_file_path(file).ok()?;
+                Some(notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri.as_str(), "diagnostics": diagnostics }),
+                ))
+            })
+            .collect()
+    }
+
+    fn completion(&self, params: &Value) -> Result<Value> {
+        let path = document_path(params)?;
+        let (line, character) = position(params)?;
+        let text = match self.documents.get(&path) {
+            Some(text) => text.clone(),
+            None => fs::read_to_string(&path).unwrap_or_default(),
+        };
+        let ws = Workspace::load_completing(&path, &self.documents, line);
+        let (completions, range) = ws.completions(&path, &text, line, character);
+        Ok(Value::Array(
+            completions
+                .iter()
+                .map(|completion| completion.to_json(range))
+                .collect(),
+        ))
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value> {
+        let (ws, file, at) = match self.token(params)? {
+            Some(token) => token,
+            None => return Ok(Value::Null),
+        };
+        let text = match ws.resolve(file, at).and_then(|target| ws.hover(&target)) {
+            Some(text) => text,
+            None => return Ok(Value::Null),
+        };
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": format!("```yak\n{}\n```", text) },
+            "range": Range::of(&ws.files[file].code[at].span).to_json(),
+        }))
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value> {
+        let (ws, file, at) = match self.token(params)? {
+            Some(token) => token,
+            None => return Ok(Value::Null),
+        };
+        let locations = match ws.resolve(file, at) {
+            Some(target) => ws.definitions(&target),
+            None => vec![],
+        };
+        Ok(Value::Array(
+            locations
+                .into_iter()
+                .filter_map(|(file, span)| location(&ws, file, Range::of(&span)))
+                .collect(),
+        ))
+    }
+
+    fn references(&self, params: &Value) -> Result<Value> {
+        let (ws, file, at) = match self.token(params)? {
+            Some(token) => token,
+            None => return Ok(Value::Null),
+        };
+        let target = match ws.resolve(file, at) {
+            Some(target) => target,
+            None => return Ok(json!([])),
+        };
+        let include_declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+        let definitions = ws.definitions(&target);
+        let is_definition = |file: usize, span: &yak_lexer::Span| {
+            definitions
+                .iter()
+                .any(|(def_file, def_span)| *def_file == file && symbols::same_span(def_span, span))
+        };
+        Ok(Value::Array(
+            ws.references(&target)
+                .into_iter()
+                .filter(|(file, span)| include_declaration || !is_definition(*file, span))
+                .filter_map(|(file, span)| location(&ws, file, Range::of(&span)))
+                .collect(),
+        ))
+    }
+
+    fn document_symbols(&self, params: &Value) -> Result<Value> {
+        let path = document_path(params)?;
+        let ws = Workspace::load(&path, &self.documents);
+        let file = match ws.file_index(&path) {
+            Some(file) => file,
+            None => return Ok(json!([])),
+        };
+        Ok(Value::Array(
+            ws.symbols
+                .iter()
+                .filter(|symbol| symbol.file == file)
+                .filter_map(|symbol| {
+                    let mut info = json!({
+                        "name": symbol.name,
+                        "kind": symbol.kind.lsp_kind(),
+                        "location": location(&ws, file, Range::of(&symbol.span))?,
+                    });
+                    if let Some(container) = &symbol.container {
+                        info["containerName"] = json!(container);
+                    }
+                    Some(info)
+                })
+                .collect(),
+        ))
+    }
+
+    // Loads the workspace of a text document position
+    // and finds the code token at the position
+    fn token(&self, params: &Value) -> Result<Option<(Workspace, usize, usize)>> {
+        let path = document_path(params)?;
+        let (line, character) = position(params)?;
+        let ws = Workspace::load(&path, &self.documents);
+        let token = ws
+            .file_index(&path)
+            .and_then(|file| Some((file, ws.token_at(file, line, character)?)));
+        Ok(token.map(|(file, at)| (ws, file, at)))
+    }
+}
+
+fn document_path(params: &Value) -> Result<PathBuf> {
+    let uri = params["textDocument"]["uri"]
+        .as_str()
+        .context("expected a text document uri")?;
+    let url = Url::parse(uri).with_context(|| format!("invalid uri: {}", uri))?;
+    match url.to_file_path() {
+        Ok(path) => Ok(path),
+        Err(_) => bail!("expected a file uri: {}", uri),
+    }
+}
+
+// The zero-based (line, character) of a text document position
+fn position(params: &Value) -> Result<(usize, usize)> {
+    let position = &params["position"];
+    match (position["line"].as_u64(), position["character"].as_u64()) {
+        (Some(line), Some(character)) => Ok((line as usize, character as usize)),
+        _ => bail!("expected a position"),
+    }
+}
+
+fn location(ws: &Workspace, file: usize, range: Range) -> Option<Value> {
+    let uri = Url::from_file_path(&ws.files[file].path).ok()?;
+    Some(json!({ "uri": uri.as_str(), "range": range.to_json() }))
+}
@@ -0,0 +1,506 @@
+use crate::workspace::Workspace;
+use std::ops::Range;
+use yak_core::utils::clean_quotes;
+use yak_lexer::token::TokenType as Ty;
+use yak_lexer::{Span, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Type,
+    Constant,
+    Test,
+}
+
+impl SymbolKind {
+    // LSP SymbolKind values
+    pub fn lsp_kind(self) -> u32 {
+        match self {
+            SymbolKind::Method => 6,
+            SymbolKind::Enum => 10,
+            SymbolKind::Trait => 11,
+            SymbolKind::Function | SymbolKind::Test => 12,
+            SymbolKind::Constant => 14,
+            SymbolKind::Struct => 23,
+            SymbolKind::Type => 26,
+        }
+    }
+}
+
+// A top-level definition (or a method in an impl or trait)
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // the impl type (or trait) of a method
+    pub container: Option<String>,
+    pub file: usize,
+    // the span of the name token
+    pub span: Span,
+    // the zero-based lines of the definition
+    pub lines: Range<usize>,
+}
+
+// What a token refers to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Func { name: String, receiver: Receiver },
+    Type(String),
+    Trait(String),
+    // a local variable (or arg) of the function symbol
+    // or a constant if there's no scope
+    Var { name: String, scope: Option<usize> },
+}
+
+// The type a function is called on
+#[derive(Debug, Clone, PartialEq)]
+pub enum Receiver {
+    None,
+    Type(String),
+    // a method called on a value (i.e. `p:area {}`)
+    Any,
+}
+
+impl Target {
+    // Do two targets refer to the same definition?
+    fn matches(&self, other: &Target) -> bool {
+        match (self, other) {
+            (
+                Target::Func {
+                    name: a,
+                    receiver: ra,
+                },
+                Target::Func {
+                    name: b,
+                    receiver: rb,
+                },
+            ) => {
+                a == b
+                    && match (ra, rb) {
+                        (Receiver::None, Receiver::None) => true,
+                        (Receiver::Type(a), Receiver::Type(b)) => a == b,
+                        (Receiver::Any, Receiver::Type(_) | Receiver::Any)
+                        | (Receiver::Type(_), Receiver::Any) => true,
+                        _ => false,
+                    }
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+pub fn same_span(a: &Span, b: &Span) -> bool {
+    a.ln == b.ln && a.start == b.start && a.end == b.end
+}
+
+// A source line of tokens (without spaces or comments)
+struct Line<'t> {
+    line: usize,
+    indent: usize,
+    // inside a `{ }` group started on a previous line
+    continued: bool,
+    tokens: Vec<&'t Token>,
+}
+
+fn lines(tokens: &[Token]) -> Vec<Line<'_>> {
+    let mut lines = vec![];
+    let mut line = Line {
+        line: 0,
+        indent: 0,
+        continued: false,
+        tokens: vec![],
+    };
+    let mut braces = 0usize;
+    for token in tokens {
+        match &token.ty {
+            Ty::NL => {
+                let next = Line {
+                    line: 0,
+                    indent: 0,
+                    continued: braces > 0,
+                    tokens: vec![],
+                };
+                lines.push(std::mem::replace(&mut line, next));
+            }
+            Ty::Indent(size) => line.indent = *size,
+            Ty::Sp | Ty::Comment(_) => {}
+            ty => {
+                match ty {
+                    Ty::PunctBraceL => braces += 1,
+                    Ty::PunctBraceR => braces = braces.saturating_sub(1),
+                    _ => {}
+                }
+                line.tokens.push(token);
+            }
+        }
+    }
+    lines.push(line);
+    lines.retain(|line| !line.tokens.is_empty());
+    for line in lines.iter_mut() {
+        line.line = line.tokens[0].span.ln.saturating_sub(1);
+    }
+    lines
+}
+
+// The name of an identity without its package prefix
+// (i.e. `Point` for `my.pkg.Point`)
+pub fn ident(ty: &Ty) -> Option<String> {
+    match ty {
+        Ty::IdFunc(name) | Ty::IdTrait(name) => Some(name.clone()),
+        Ty::IdType(name) | Ty::IdVar(name) => name.rsplit('.').next().map(String::from),
+        _ => None,
+    }
+}
+
+// Indexes the definitions in the tokens of a file
+pub fn index_symbols(file: usize, tokens: &[Token]) -> Vec<Symbol> {
+    let lines = lines(tokens);
+    let mut symbols = vec![];
+    let mut container: Option<String> = None;
+    for (at, line) in lines.iter().enumerate() {
+        if line.continued {
+            continue;
+        }
+        let top_level = line.indent == 0;
+        if top_level {
+            container = None;
+        }
+        let (first, second) = match line.tokens.as_slice() {
+            [first, second, ..] => (&first.ty, *second),
+            _ => continue,
+        };
+        let kind = match first {
+            Ty::KwFn if top_level => SymbolKind::Function,
+            Ty::KwFn if container.is_some() => SymbolKind::Method,
+            Ty::KwStruct if top_level => SymbolKind::Struct,
+            Ty::KwEnum if top_level => SymbolKind::Enum,
+            Ty::KwTrait if top_level => SymbolKind::Trait,
+            Ty::KwType | Ty::KwTuple if top_level => SymbolKind::Type,
+            Ty::KwConst if top_level => SymbolKind::Constant,
+            Ty::KwTest | Ty::KwTestCase if top_level => SymbolKind::Test,
+            Ty::KwImpl if top_level => {
+                container = ident(&second.ty);
+                continue;
+            }
+            _ => continue,
+        };
+        let name = match (&kind, &second.ty) {
+            (SymbolKind::Test, Ty::LitString(name)) => clean_quotes(name.clone()),
+            (SymbolKind::Test, _) => continue,
+            (_, ty) => match ident(ty) {
+                Some(name) => name,
+                None => continue,
+            },
+        };
+        if kind == SymbolKind::Trait {
+            container = Some(name.clone());
+        }
+        // the definition ends at the next line with the same (or less) indentation
+        let end = lines[at + 1..]
+            .iter()
+            .find(|next| !next.continued && next.indent <= line.indent)
+            .map_or(lines.last().map_or(0, |last| last.line) + 1, |next| {
+                next.line
+            });
+        symbols.push(Symbol {
+            name,
+            kind,
+            container: match kind {
+                SymbolKind::Method => container.clone(),
+                _ => None,
+            },
+            file,
+            span: second.span,
+            lines: line.line..end,
+        });
+    }
+    symbols
+}
+
+impl Workspace {
+    // The code token at a zero-based position
+    // (or the one ending at it when the cursor is after a word)
+    pub fn token_at(&self, file: usize, line: usize, character: usize) -> Option<usize> {
+        let code = &self.files[file].code;
+        let on_line = |token: &Token| token.span.ln == line + 1;
+        code.iter()
+            .position(|token| {
+                on_line(token)
+                    && token.span.start <= character + 1
+                    && character + 1 < token.span.end
+            })
+            .or_else(|| {
+                code.iter()
+                    .position(|token| on_line(token) && token.span.end == character + 1)
+            })
+    }
+
+    // The function (or test) whose lines contain a token
+    fn scope(&self, file: usize, at: usize) -> Option<usize> {
+        let line = self.files[file].code[at].span.ln.saturating_sub(1);
+        self.scope_at(file, line)
+    }
+
+    // The function (or test) whose lines contain a zero-based line
+    pub fn scope_at(&self, file: usize, line: usize) -> Option<usize> {
+        self.symbols.iter().position(|symbol| {
+            symbol.file == file
+                && matches!(
+                    symbol.kind,
+                    SymbolKind::Function | SymbolKind::Method | SymbolKind::Test
+                )
+                && symbol.lines.contains(&line)
+        })
+    }
+
+    // Is the token between a function name and its `=>`?
+    fn in_header(&self, scope: usize, at: usize) -> bool {
+        let symbol = &self.symbols[scope];
+        let code = &self.files[symbol.file].code;
+        let start = match code
+            .iter()
+            .position(|token| same_span(&token.span, &symbol.span))
+        {
+            Some(start) => start,
+            None => return false,
+        };
+        let end = code[start..]
+            .iter()
+            .position(|token| token.ty == Ty::PunctFatArrow)
+            .map_or(code.len(), |end| start + end);
+        start < at && at < end
+    }
+
+    // Does the var token define a variable (an arg, let, const or for var)?
+    fn is_var_definition(&self, file: usize, scope: usize, at: usize) -> bool {
+        let code = &self.files[file].code;
+        let prev = at.checked_sub(1).map(|prev| &code[prev].ty);
+        let next = code.get(at + 1).map(|next| &next.ty);
+        if matches!(prev, Some(Ty::KwLet | Ty::KwConst)) {
+            return true;
+        }
+        if next == Some(&Ty::PunctColon) && self.in_header(scope, at) {
+            return true;
+        }
+        // for vars come before `in`
+        let ln = code[at].span.ln;
+        let line_start = code[..at]
+            .iter()
+            .rposition(|token| token.span.ln != ln)
+            .map_or(0, |prev| prev + 1);
+        code[line_start].ty == Ty::KwFor
+            && code[at..]
+                .iter()
+                .take_while(|token| token.span.ln == ln)
+                .any(|token| token.ty == Ty::KwIn)
+    }
+
+    // The first definition of a variable in a function
+    fn local_definition(&self, scope: usize, name: &str) -> Option<usize> {
+        let symbol = &self.symbols[scope];
+        let file = symbol.file;
+        self.files[file]
+            .code
+            .iter()
+            .enumerate()
+            .position(|(at, token)| {
+                symbol.lines.contains(&token.span.ln.saturating_sub(1))
+                    && matches!(&token.ty, Ty::IdVar(_))
+                    && ident(&token.ty).as_deref() == Some(name)
+                    && self.is_var_definition(file, scope, at)
+            })
+    }
+
+    // The container of the method (or impl) a token is in
+    fn self_type(&self, file: usize, at: usize) -> Option<String> {
+        let line = self.files[file].code[at].span.ln.saturating_sub(1);
+        self.self_type_at(file, line)
+    }
+
+    // The container of the method (or impl) at a zero-based line
+    pub fn self_type_at(&self, file: usize, line: usize) -> Option<String> {
+        self.symbols
+            .iter()
+            .filter(|symbol| symbol.file == file && symbol.lines.contains(&line))
+            .find_map(|symbol| symbol.container.clone())
+    }
+
+    // What a code token refers to
+    pub fn resolve(&self, file: usize, at: usize) -> Option<Target> {
+        let code = &self.files[file].code;
+        let token = &code[at];
+        // the previous token without a space between them
+        let attached = at
+            .checked_sub(1)
+            .map(|prev| &code[prev])
+            .filter(|prev| prev.span.ln == token.span.ln && prev.span.end == token.span.start);
+        match &token.ty {
+            Ty::IdFunc(name) => {
+                let definition = self
+                    .symbols
+                    .iter()
+                    .find(|symbol| symbol.file == file && same_span(&symbol.span, &token.span));
+                let receiver = match definition {
+                    Some(symbol) => match &symbol.container {
+                        Some(container) => Receiver::Type(container.clone()),
+                        None => Receiver::None,
+                    },
+                    None => match attached.map(|prev| &prev.ty) {
+                        Some(ty @ Ty::IdType(_)) => Receiver::Type(ident(ty).unwrap_or_default()),
+                        Some(Ty::SpecialTypeSelf) => match self.self_type(file, at) {
+                            Some(container) => Receiver::Type(container),
+                            None => Receiver::Any,
+                        },
+                        // generic types (i.e. `Box[int]:new`)
+                        Some(Ty::PunctBracketR) => self.generic_receiver(file, at - 1),
+                        Some(Ty::IdVar(_) | Ty::KwSelf | Ty::PunctParenR | Ty::PunctBraceR) => {
+                            Receiver::Any
+                        }
+                        _ => Receiver::None,
+                    },
+                };
+                Some(Target::Func {
+                    name: name.clone(),
+                    receiver,
+                })
+            }
+            Ty::IdType(_) => ident(&token.ty).map(Target::Type),
+            Ty::SpecialTypeSelf => self.self_type(file, at).map(Target::Type),
+            Ty::IdTrait(name) => Some(Target::Trait(name.clone())),
+            Ty::IdVar(_) => {
+                // fields
+                if attached.is_some_and(|prev| prev.ty == Ty::PunctDot) {
+                    return None;
+                }
+                let name = ident(&token.ty)?;
+                let scope = self.scope(file, at);
+                let prev = at.checked_sub(1).map(|prev| &code[prev].ty);
+                let named = code
+                    .get(at + 1)
+                    .is_some_and(|next| next.ty == Ty::PunctColon);
+                // arg and field names (i.e. `{ x: 1 }`)
+                if named
+                    && !matches!(prev, Some(Ty::KwLet | Ty::KwConst))
+                    && !scope.is_some_and(|scope| self.in_header(scope, at))
+                {
+                    return None;
+                }
+                let scope = scope.filter(|scope| self.local_definition(*scope, &name).is_some());
+                Some(Target::Var { name, scope })
+            }
+            _ => None,
+        }
+    }
+
+    // The type of a `Type[...]` receiver ending at the `]` token
+    fn generic_receiver(&self, file: usize, close: usize) -> Receiver {
+        let code = &self.files[file].code;
+        let mut depth = 0usize;
+        for at in (0..=close).rev() {
+            match code[at].ty {
+                Ty::PunctBracketR => depth += 1,
+                Ty::PunctBracketL => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return match at.checked_sub(1).map(|prev| &code[prev].ty) {
+                            Some(ty @ Ty::IdType(_)) => {
+                                Receiver::Type(ident(ty).unwrap_or_default())
+                            }
+                            _ => Receiver::Any,
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+        Receiver::Any
+    }
+
+    // The (file, span) locations a target is defined at
+    pub fn definitions(&self, target: &Target) -> Vec<(usize, Span)> {
+        let symbols: Vec<&Symbol> = match target {
+            Target::Func { name, receiver } => {
+                let functions: Vec<&Symbol> = self
+                    .symbols
+                    .iter()
+                    .filter(|symbol| {
+                        matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method)
+                            && &symbol.name == name
+                    })
+                    .collect();
+                let found: Vec<&Symbol> = functions
+                    .iter()
+                    .copied()
+                    .filter(|symbol| match receiver {
+                        Receiver::None => symbol.container.is_none(),
+                        Receiver::Type(container) => symbol.container.as_ref() == Some(container),
+                        Receiver::Any => symbol.container.is_some(),
+                    })
+                    .collect();
+                // i.e. trait methods called on a type
+                if found.is_empty() {
+                    functions
+                } else {
+                    found
+                }
+            }
+            Target::Type(name) => self
+                .symbols
+                .iter()
+                .filter(|symbol| {
+                    matches!(
+                        symbol.kind,
+                        SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Type
+                    ) && &symbol.name == name
+                })
+                .collect(),
+            Target::Trait(name) => self
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.kind == SymbolKind::Trait && &symbol.name == name)
+                .collect(),
+            Target::Var {
+                name,
+                scope: Some(scope),
+            } => {
+                let file = self.symbols[*scope].file;
+                return self
+                    .local_definition(*scope, name)
+                    .map(|at| vec![(file, self.files[file].code[at].span)])
+                    .unwrap_or_default();
+            }
+            Target::Var { name, scope: None } => self
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.kind == SymbolKind::Constant && &symbol.name == name)
+                .collect(),
+        };
+        symbols
+            .into_iter()
+            .map(|symbol| (symbol.file, symbol.span))
+            .collect()
+    }
+
+    // Every token in the workspace which refers to the target
+    pub fn references(&self, target: &Target) -> Vec<(usize, Span)> {
+        let mut references = vec![];
+        for (file, source) in self.files.iter().enumerate() {
+            for (at, token) in source.code.iter().enumerate() {
+                if ident(&token.ty).is_none() && token.ty != Ty::SpecialTypeSelf {
+                    continue;
+                }
+                if self
+                    .resolve(file, at)
+                    .is_some_and(|resolved| resolved.matches(target))
+                {
+                    references.push((file, token.span));
+                }
+            }
+        }
+        references
+    }
+}
@@ -0,0 +1,470 @@
+#[cfg(test)]
+use crate::{read_message, write_message, Server};
+#[cfg(test)]
+use serde_json::{json, Value};
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use url::Url;
+
+#[cfg(test)]
+const APP_PKG: &str = r#"package     "my.app"
+description "An app"
+files {
+  "./src/main.yak"
+  "./src/shapes.yak"
+}
+dependencies {
+  my.lib "../lib"
+}
+import {
+  my.lib {
+    :greet
+  }
+}
+"#;
+
+#[cfg(test)]
+const MAIN_YAK: &str = r#"fn :main {} =>
+  let p = Point { x: 1 y: 2 }
+  let total = Point:sum { self: p }
+  const hello = :greet { name: "yak" }
+"#;
+
+#[cfg(test)]
+const SHAPES_YAK: &str = r#"struct Point
+  x: int
+  y: int
+
+impl Point
+  fn :sum self {} int =>
+    return self.x + self.y
+"#;
+
+#[cfg(test)]
+const LIB_PKG: &str = r#"package     "my.lib"
+description "A lib"
+files {
+  "./src/lib.yak"
+}
+"#;
+
+#[cfg(test)]
+const LIB_YAK: &str = r#"fn :greet { name: String } String =>
+  return name
+"#;
+
+// Writes the app and lib packages into a temp dir
+// and returns the app package dir
+#[cfg(test)]
+fn fixture(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("yak-lsp-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let files = [
+        ("app/yak.pkg", APP_PKG),
+        ("app/src/main.yak", MAIN_YAK),
+        ("app/src/shapes.yak", SHAPES_YAK),
+        ("lib/yak.pkg", LIB_PKG),
+        ("lib/src/lib.yak", LIB_YAK),
+    ];
+    for (path, text) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    root.join("app")
+}
+
+#[cfg(test)]
+fn uri(path: &Path) -> String {
+    Url::from_file_path(path).unwrap().to_string()
+}
+
+#[cfg(test)]
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+#[cfg(test)]
+fn notify(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+#[cfg(test)]
+fn position(path: &Path, line: u64, character: u64) -> Value {
+    json!({
+        "textDocument": { "uri": uri(path) },
+        "position": { "line": line, "character": character },
+    })
+}
+
+// Runs a scripted session (initialize, the messages, shutdown and exit)
+// and returns the messages written by the server
+#[cfg(test)]
+fn transcript(messages: Vec<Value>) -> Vec<Value> {
+    let mut input = vec![];
+    write_message(&mut input, &request(0, "initialize", json!({}))).unwrap();
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+    write_message(&mut input, &request(999, "shutdown", json!(null))).unwrap();
+    write_message(&mut input, &notify("exit", json!(null))).unwrap();
+    let mut output = vec![];
+    Server::default()
+        .serve(&mut input.as_slice(), &mut output)
+        .unwrap();
+    let mut reader = output.as_slice();
+    let mut replies = vec![];
+    while let Some(reply) = read_message(&mut reader).unwrap() {
+        replies.push(reply);
+    }
+    replies
+}
+
+#[cfg(test)]
+fn reply(replies: &[Value], id: u64) -> &Value {
+    replies
+        .iter()
+        .find(|reply| reply["id"] == id)
+        .unwrap_or_else(|| panic!("no reply for request {}", id))
+}
+
+// The diagnostics of the last publish for a file
+#[cfg(test)]
+fn diagnostics(replies: &[Value], path: &Path) -> Vec<Value> {
+    let publishes: Vec<&Value> = replies
+        .iter()
+        .filter(|reply| {
+            reply["method"] == "textDocument/publishDiagnostics"
+                && reply["params"]["uri"] == uri(path)
+        })
+        .collect();
+    let publish = publishes.last().expect("no diagnostics published");
+    publish["params"]["diagnostics"].as_array().unwrap().clone()
+}
+
+#[cfg(test)]
+fn open(path: &Path, text: &str) -> Value {
+    notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": uri(path), "languageId": "yak", "version": 1, "text": text },
+        }),
+    )
+}
+
+#[cfg(test)]
+fn change(path: &Path, version: u64, text: &str) -> Value {
+    notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri(path), "version": version },
+            "contentChanges": [{ "text": text }],
+        }),
+    )
+}
+
+#[test]
+fn lsp_initialize_and_shutdown() {
+    let replies = transcript(vec![request(1, "textDocument/formatting", json!({}))]);
+    let capabilities = &reply(&replies, 0)["result"]["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(capabilities["referencesProvider"], true);
+    assert_eq!(capabilities["documentSymbolProvider"], true);
+    assert_eq!(reply(&replies, 1)["error"]["code"], -32601);
+    assert_eq!(reply(&replies, 999)["result"], Value::Null);
+
+    // requests before initialize are rejected
+    let mut server = Server::default();
+    let replies = server.handle(&request(1, "textDocument/hover", json!({})));
+    assert_eq!(replies[0]["error"]["code"], -32002);
+
+    // exit without shutdown is an error
+    let mut input = vec![];
+    write_message(&mut input, &notify("exit", json!(null))).unwrap();
+    let mut output = vec![];
+    assert!(Server::default()
+        .serve(&mut input.as_slice(), &mut output)
+        .is_err());
+}
+
+#[test]
+fn lsp_diagnostics() {
+    let app = fixture("diagnostics");
+    let main = app.join("src/main.yak");
+    let type_error = MAIN_YAK.replace("let total = Point", "let total: String = Point");
+    let parse_error = MAIN_YAK.replace("Point { x: 1 y: 2 }", "Point { x: 1 y: 2");
+
+    // type errors are reported at the function
+    let replies = transcript(vec![open(&main, MAIN_YAK), change(&main, 2, &type_error)]);
+    let found = diagnostics(&replies, &main);
+    assert_eq!(found.len(), 1, "{:#?}", found);
+    assert_eq!(
+        found[0]["range"]["start"],
+        json!({ "line": 0, "character": 3 })
+    );
+    let message = found[0]["message"].as_str().unwrap();
+    assert!(message.contains("type error in my.app:main"), "{}", message);
+    assert!(diagnostics(&replies, &app.join("src/shapes.yak")).is_empty());
+
+    // parse errors are reported at the statement
+    let replies = transcript(vec![open(&main, &parse_error)]);
+    let found = diagnostics(&replies, &main);
+    assert!(!found.is_empty());
+    assert_eq!(found[0]["range"]["start"]["line"], 0);
+
+    // fixing the errors clears them
+    let replies = transcript(vec![
+        open(&main, &parse_error),
+        change(&main, 2, &type_error),
+        change(&main, 3, MAIN_YAK),
+    ]);
+    assert!(diagnostics(&replies, &main).is_empty());
+    assert!(diagnostics(&replies, &app.join("yak.pkg")).is_empty());
+}
+
+#[test]
+fn lsp_hover() {
+    let app = fixture("hover");
+    let main = app.join("src/main.yak");
+    let replies = transcript(vec![
+        open(&main, MAIN_YAK),
+        // `p` in `{ self: p }`
+        request(1, "textDocument/hover", position(&main, 2, 32)),
+        // `:sum`
+        request(2, "textDocument/hover", position(&main, 2, 20)),
+        // `Point`
+        request(3, "textDocument/hover", position(&main, 1, 12)),
+        // `:greet`
+        request(4, "textDocument/hover", position(&main, 3, 18)),
+        // whitespace
+        request(5, "textDocument/hover", position(&main, 1, 1)),
+    ]);
+    let hover = |id| reply(&replies, id)["result"]["contents"]["value"].clone();
+    assert_eq!(hover(1), "```yak\nlet p: Point\n```");
+    assert_eq!(hover(2), "```yak\nfn Point:sum self {} int32\n```");
+    assert_eq!(
+        hover(3),
+        "```yak\nstruct Point\n  x: int32\n  y: int32\n```"
+    );
+    assert_eq!(hover(4), "```yak\nfn :greet { name: String } String\n```");
+    assert_eq!(reply(&replies, 5)["result"], Value::Null);
+    assert_eq!(
+        reply(&replies, 1)["result"]["range"],
+        json!({
+            "start": { "line": 2, "character": 32 },
+            "end": { "line": 2, "character": 33 },
+        })
+    );
+}
+
+#[test]
+fn lsp_definition() {
+    let app = fixture("definition");
+    let main = app.join("src/main.yak");
+    let replies = transcript(vec![
+        // across files
+        request(1, "textDocument/definition", position(&main, 1, 12)),
+        request(2, "textDocument/definition", position(&main, 2, 20)),
+        // across packages
+        request(3, "textDocument/definition", position(&main, 3, 18)),
+        // locals
+        request(4, "textDocument/definition", position(&main, 2, 32)),
+    ]);
+    let location = |id, path: PathBuf, line, start, end| {
+        assert_eq!(
+            reply(&replies, id)["result"],
+            json!([{
+                "uri": uri(&path),
+                "range": {
+                    "start": { "line": line, "character": start },
+                    "end": { "line": line, "character": end },
+                },
+            }])
+        );
+    };
+    location(1, app.join("src/shapes.yak"), 0, 7, 12);
+    location(2, app.join("src/shapes.yak"), 5, 5, 9);
+    location(3, app.parent().unwrap().join("lib/src/lib.yak"), 0, 3, 9);
+    location(4, main, 1, 6, 7);
+}
+
+#[test]
+fn lsp_document_symbols() {
+    let app = fixture("symbols");
+    let shapes = app.join("src/shapes.yak");
+    let params = json!({ "textDocument": { "uri": uri(&shapes) } });
+    let replies = transcript(vec![request(1, "textDocument/documentSymbol", params)]);
+    let symbols: Vec<(Value, Value, Value)> = reply(&replies, 1)["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].clone(),
+                symbol["kind"].clone(),
+                symbol["containerName"].clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        symbols,
+        vec![
+            (json!("Point"), json!(23), Value::Null),
+            (json!(":sum"), json!(6), json!("Point")),
+        ]
+    );
+}
+
+#[test]
+fn lsp_references() {
+    let app = fixture("references");
+    let main = app.join("src/main.yak");
+    let shapes = app.join("src/shapes.yak");
+    let references = |include_declaration| {
+        let mut params = position(&shapes, 0, 8);
+        params["context"] = json!({ "includeDeclaration": include_declaration });
+        let replies = transcript(vec![request(1, "textDocument/references", params)]);
+        let mut found: Vec<(String, u64)> = reply(&replies, 1)["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|location| {
+                (
+                    location["uri"].as_str().unwrap().to_string(),
+                    location["range"]["start"]["line"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        found.sort();
+        found
+    };
+    assert_eq!(
+        references(false),
+        vec![(uri(&main), 1), (uri(&main), 2), (uri(&shapes), 4)]
+    );
+    assert_eq!(references(true).len(), 4);
+}
+
+// Completes at the end of a line added to the end of :main
+#[cfg(test)]
+fn complete(app: &Path, line: &str) -> Vec<Value> {
+    let main = app.join("src/main.yak");
+    let text = format!("{}{}\n", MAIN_YAK, line);
+    let at = json!({
+        "textDocument": { "uri": uri(&main) },
+        "position": { "line": 4, "character": line.len() },
+    });
+    let replies = transcript(vec![
+        open(&main, &text),
+        request(1, "textDocument/completion", at),
+    ]);
+    reply(&replies, 1)["result"].as_array().unwrap().clone()
+}
+
+#[cfg(test)]
+fn labels(items: &[Value]) -> Vec<&str> {
+    items
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn lsp_completion() {
+    let app = fixture("completion");
+
+    // functions after `:`
+    let items = complete(&app, "  :gr");
+    assert_eq!(labels(&items), vec![":greet"]);
+    assert_eq!(items[0]["kind"], 3);
+    assert_eq!(items[0]["detail"], "fn :greet { name: String } String");
+    assert_eq!(
+        items[0]["textEdit"],
+        json!({
+            "range": {
+                "start": { "line": 4, "character": 2 },
+                "end": { "line": 4, "character": 5 },
+            },
+            "newText": ":greet",
+        })
+    );
+    assert_eq!(labels(&complete(&app, "  Point:")), vec![":sum"]);
+    assert_eq!(labels(&complete(&app, "  p:")), vec![":sum"]);
+
+    // types and traits by prefix
+    assert_eq!(labels(&complete(&app, "  let q = Po")), vec!["Point"]);
+    let main = app.join("src/main.yak");
+    let text = format!(
+        "trait ^Named\n  fn :name self {{}} String\n\n{}impl Point ^N\n",
+        MAIN_YAK
+    );
+    let at = json!({
+        "textDocument": { "uri": uri(&main) },
+        "position": { "line": 7, "character": 13 },
+    });
+    let replies = transcript(vec![
+        open(&main, &text),
+        request(1, "textDocument/completion", at),
+    ]);
+    let items = reply(&replies, 1)["result"].as_array().unwrap().clone();
+    assert_eq!(labels(&items), vec!["^Named"]);
+    assert_eq!(items[0]["kind"], 8);
+
+    // fields after `.`
+    let items = complete(&app, "  const x = p.");
+    assert_eq!(labels(&items), vec!["x", "y"]);
+    assert_eq!(items[0]["detail"], "int32");
+
+    // the remaining named args
+    let items = complete(&app, "  const hi = :greet { ");
+    assert_eq!(labels(&items), vec!["name"]);
+    assert_eq!(items[0]["textEdit"]["newText"], "name: ");
+    assert!(complete(&app, r#"  const hi = :greet { name: "a" "#).is_empty());
+    assert_eq!(
+        labels(&complete(&app, "  const q = Point { x: 1 ")),
+        vec!["y"]
+    );
+    assert_eq!(
+        labels(&complete(&app, "  const q = Point:sum { ")),
+        vec!["self"]
+    );
+    // values aren't completed
+    assert!(complete(&app, "  const q = Point { x: ").is_empty());
+}
+
+#[test]
+fn lsp_check_package() {
+    let app = fixture("check");
+    assert!(crate::check_package(&app, &[]).is_empty());
+    // resolved dependencies are only loaded once
+    let deps = vec![("my.lib".to_string(), app.parent().unwrap().join("lib"))];
+    assert!(crate::check_package(&app, &deps).is_empty());
+
+    // errors in dependencies are reported too
+    let lib_yak = app.parent().unwrap().join("lib/src/lib.yak");
+    fs::write(&lib_yak, LIB_YAK.replace("return name", "return 1")).unwrap();
+    let pkg = APP_PKG.replace(
+        "dependencies {",
+        "dependencies {\n  my.remote \"https://example.com/remote\"",
+    );
+    fs::write(app.join("yak.pkg"), pkg).unwrap();
+    let found = crate::check_package(&app, &deps);
+    assert_eq!(found.len(), 2, "{:#?}", found);
+    assert_eq!(found[0].path, app.join("yak.pkg"));
+    assert_eq!(found[0].severity, crate::Severity::Warning);
+    assert_eq!(found[0].range.start, (7, 0));
+    assert_eq!(found[0].code, "missing-dependency");
+    assert!(found[0]
+        .message
+        .starts_with("dependency my.remote not found"));
+    assert_eq!(found[0].suggestions.len(), 1);
+    assert_eq!(found[1].path, lib_yak);
+    assert_eq!(found[1].severity, crate::Severity::Error);
+    assert_eq!(found[1].range.start, (0, 3));
+    assert_eq!(found[1].code, "type-error");
+}
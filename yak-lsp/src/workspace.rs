@@ -0,0 +1,538 @@
+use crate::symbols::{index_symbols, Symbol, SymbolKind};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use yak_ast::Ast;
+use yak_compiler::check::check_all;
+use yak_compiler::hir::Hir;
+use yak_compiler::mono::monomorphize;
+use yak_core::models::yak_package::YakPackage;
+use yak_core::types::function::FunctionId;
+use yak_lexer::token::TokenType as Ty;
+use yak_lexer::{Lexer, Span, Token};
+
+// A zero-based LSP range of (line, character) positions
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Range {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl Range {
+    // Token spans have 1-based lines and columns
+    pub fn of(span: &Span) -> Self {
+        let line = span.ln.saturating_sub(1);
+        Range {
+            start: (line, span.start.saturating_sub(1)),
+            end: (line, span.end.saturating_sub(1)),
+        }
+    }
+
+    pub fn line(line: usize, text: &str) -> Self {
+        let width = text
+            .lines()
+            .nth(line)
+            .map_or(0, |text| text.chars().count());
+        Range {
+            start: (line, 0),
+            end: (line, width),
+        }
+    }
+
+    pub fn to_json(self) -> Value {
+        json!({
+            "start": { "line": self.start.0, "character": self.start.1 },
+            "end": { "line": self.end.0, "character": self.end.1 },
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub range: Range,
+    pub severity: Severity,
+    // the kind of diagnostic (i.e. "type-error")
+    pub code: &'static str,
+    pub message: String,
+    // fixes to show with the message
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn error(path: &Path, range: Range, code: &'static str, message: String) -> Self {
+        Diagnostic {
+            path: path.to_path_buf(),
+            range,
+            severity: Severity::Error,
+            code,
+            message,
+            suggestions: vec![],
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        // LSP DiagnosticSeverity values
+        let severity = match self.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        };
+        json!({
+            "range": self.range.to_json(),
+            "severity": severity,
+            "code": self.code,
+            "source": "yak",
+            "message": self.message,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub text: String,
+    // tokens without spaces, newlines, indents or comments
+    pub code: Vec<Token>,
+    // the package name (or import name) used in the HIR
+    pub pkg_name: String,
+}
+
+// A parsed package waiting to be lowered
+struct Package {
+    ast: Ast,
+    pkg_root: bool,
+    as_pkg_id: Option<String>,
+    pkg_file: PathBuf,
+}
+
+// The package of a document with its local dependencies
+#[derive(Debug, Default)]
+pub struct Workspace {
+    pub files: Vec<SourceFile>,
+    pub symbols: Vec<Symbol>,
+    // None if any package failed to parse or lower
+    pub hir: Option<Hir>,
+    pub diagnostics: Vec<Diagnostic>,
+    // files of the document's package (diagnostics are published for these)
+    pub root_files: Vec<PathBuf>,
+}
+
+impl Workspace {
+    // Loads the package of a file using the text of open documents
+    // over the files on disk (files outside a package are loaded on their own)
+    pub fn load(path: &Path, documents: &HashMap<PathBuf, String>) -> Self {
+        let pkg_dir = path
+            .parent()
+            .and_then(|dir| dir.ancestors().find(|dir| dir.join("yak.pkg").is_file()));
+        let mut ws = match pkg_dir {
+            Some(pkg_dir) => Workspace::load_package_dir(pkg_dir, documents),
+            None => {
+                let mut ws = Workspace::default();
+                ws.load_file(path, documents);
+                ws
+            }
+        };
+        if !ws.root_files.iter().any(|file| file == path) {
+            ws.root_files.push(path.to_path_buf());
+        }
+        ws
+    }
+
+    // Loads a package directory and its local dependencies
+    pub fn load_package_dir(pkg_dir: &Path, documents: &HashMap<PathBuf, String>) -> Self {
+        Workspace::load_package_deps(pkg_dir, &[], documents)
+    }
+
+    // Loads a package directory with the dependencies it was resolved to
+    // (dependencies which weren't resolved are looked up from the package files)
+    pub fn load_package_deps(
+        pkg_dir: &Path,
+        deps: &[(String, PathBuf)],
+        documents: &HashMap<PathBuf, String>,
+    ) -> Self {
+        let mut ws = Workspace::default();
+        let mut packages = vec![];
+        let mut visited = HashSet::new();
+        let mut ok = true;
+        for (dep_as_pkg_id, dep_dir) in deps {
+            let as_pkg_id = Some(dep_as_pkg_id.clone());
+            ok &= ws.load_package(dep_dir, as_pkg_id, documents, &mut packages, &mut visited);
+        }
+        // the package is loaded last so it's checked as the root package
+        ok &= ws.load_package(pkg_dir, None, documents, &mut packages, &mut visited);
+        ws.root_files = ws
+            .files
+            .iter()
+            .filter(|file| file.path.starts_with(pkg_dir))
+            .map(|file| file.path.clone())
+            .collect();
+        if ok {
+            ws.check(packages);
+        }
+        ws
+    }
+
+    pub fn file_index(&self, path: &Path) -> Option<usize> {
+        self.files.iter().position(|file| file.path == path)
+    }
+
+    // Returns false if the package (or a dependency) has errors
+    fn load_package(
+        &mut self,
+        pkg_dir: &Path,
+        as_pkg_id: Option<String>,
+        documents: &HashMap<PathBuf, String>,
+        packages: &mut Vec<Package>,
+        visited: &mut HashSet<PathBuf>,
+    ) -> bool {
+        if !visited.insert(pkg_dir.to_path_buf()) {
+            return true;
+        }
+        let pkg_root = as_pkg_id.is_none();
+        let pkg_file = pkg_dir.join("yak.pkg");
+        let text = match read_source(&pkg_file, documents) {
+            Some(text) => text,
+            None => return false,
+        };
+        let parsed = guard(|| {
+            let mut ast = Ast::from_source(&text);
+            let result = ast.parse_package();
+            (ast, result)
+        });
+        let mut ast = match parsed {
+            Ok((ast, Ok(()))) => ast,
+            Ok((ast, Err(err))) => {
+                let errors = parse_errors(&ast, err);
+                self.add_file(&pkg_file, text, String::new());
+                self.add_errors(&pkg_file, 0, "parse-error", errors);
+                return false;
+            }
+            Err(panic) => {
+                self.add_file(&pkg_file, text, String::new());
+                self.add_errors(&pkg_file, 0, "parse-error", vec![panic]);
+                return false;
+            }
+        };
+        let pkg_local_path = pkg_dir.display().to_string();
+        let mut yak_pkg: YakPackage = match ast.parsed.package.clone().into_yak_package(
+            pkg_root,
+            as_pkg_id.clone(),
+            pkg_local_path,
+            None,
+        ) {
+            Ok(yak_pkg) => yak_pkg,
+            Err(err) => {
+                self.add_file(&pkg_file, text, String::new());
+                self.add_errors(&pkg_file, 0, "package-error", vec![format!("{:#}", err)]);
+                return false;
+            }
+        };
+        let pkg_name = as_pkg_id.clone().unwrap_or(yak_pkg.pkg_id.clone());
+        let pkg_file_index = self.files.len();
+        self.add_file(&pkg_file, text, pkg_name.clone());
+
+        let mut ok = true;
+        for file in yak_pkg.get_local_file_paths().unwrap_or_default() {
+            let text = match read_source(&file, documents) {
+                Some(text) => text,
+                None => {
+                    let message = format!("unable to read file: {}", file.display());
+                    self.add_errors(&pkg_file, 0, "package-error", vec![message]);
+                    ok = false;
+                    continue;
+                }
+            };
+            match guard(|| ast.parse_source(file.clone(), &text)) {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    ok = false;
+                    self.statement_errors(&file, &text, &err);
+                    // errors are kept by the ast so the next file
+                    // would fail with them
+                    ast.parsed.errors.clear();
+                }
+                Err(panic) => {
+                    ok = false;
+                    self.statement_errors(&file, &text, &anyhow::anyhow!(panic));
+                }
+            }
+            self.add_file(&file, text, pkg_name.clone());
+        }
+
+        for (dep_as_pkg_id, dep_dir) in yak_pkg.get_local_dep_paths().unwrap_or_default() {
+            // remote dependencies may not be downloaded yet
+            if !dep_dir.join("yak.pkg").is_file() {
+                let pkg_text = &self.files[pkg_file_index].text;
+                let line = pkg_text
+                    .lines()
+                    .position(|line| line.trim_start().starts_with(&dep_as_pkg_id))
+                    .unwrap_or(0);
+                self.diagnostics.push(Diagnostic {
+                    path: pkg_file.clone(),
+                    range: Range::line(line, pkg_text),
+                    severity: Severity::Warning,
+                    code: "missing-dependency",
+                    message: format!(
+                        "dependency {} not found: {}",
+                        dep_as_pkg_id,
+                        dep_dir.display()
+                    ),
+                    suggestions: vec!["run yak-cli get to download it".into()],
+                });
+                continue;
+            }
+            ok &= self.load_package(&dep_dir, Some(dep_as_pkg_id), documents, packages, visited);
+        }
+        packages.push(Package {
+            ast,
+            pkg_root,
+            as_pkg_id,
+            pkg_file,
+        });
+        ok
+    }
+
+    // Loads a file which isn't part of a package as the "main" package
+    fn load_file(&mut self, path: &Path, documents: &HashMap<PathBuf, String>) {
+        let text = read_source(path, documents).unwrap_or_default();
+        let parsed = guard(|| {
+            let mut ast = Ast::from_source(&text);
+            let result = ast.parse();
+            (ast, result)
+        });
+        self.add_file(path, text.clone(), "main".into());
+        let mut ast = match parsed {
+            Ok((ast, Ok(()))) => ast,
+            Ok((_, Err(err))) => return self.statement_errors(path, &text, &err),
+            Err(panic) => return self.statement_errors(path, &text, &anyhow::anyhow!(panic)),
+        };
+        ast.parsed.package.package_id = "\"main\"".into();
+        self.check(vec![Package {
+            ast,
+            pkg_root: true,
+            as_pkg_id: None,
+            pkg_file: path.to_path_buf(),
+        }]);
+    }
+
+    // Lowers and type checks the packages (the root package is last)
+    fn check(&mut self, packages: Vec<Package>) {
+        let mut hir = Hir::default();
+        for package in packages.iter().rev() {
+            if let Err(err) =
+                hir.from_ast(package.pkg_root, package.as_pkg_id.clone(), &package.ast)
+            {
+                let message = format!("{:#}", err);
+                self.add_errors(&package.pkg_file, 0, "lower-error", vec![message]);
+                return;
+            }
+        }
+        let pkg_file = match packages.last() {
+            Some(package) => package.pkg_file.clone(),
+            None => return,
+        };
+        for check_error in check_all(&mut hir) {
+            let message = format!("{:#}", check_error.error);
+            let location = check_error
+                .function_id
+                .as_ref()
+                .and_then(|function_id| self.function_location(&hir, function_id));
+            match location {
+                Some((path, range)) => {
+                    self.diagnostics
+                        .push(Diagnostic::error(&path, range, "type-error", message))
+                }
+                None => self.add_errors(&pkg_file, 0, "type-error", vec![message]),
+            }
+        }
+        // generic instances are only checked once the package type checks
+        if !self.has_errors() {
+            if let Err(err) = monomorphize(&mut hir.clone()) {
+                let message = format!("{:#}", err);
+                self.add_errors(&pkg_file, 0, "generic-error", vec![message]);
+            }
+        }
+        self.hir = Some(hir);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    // The name of the function (or test) a function id was lowered from
+    fn function_location(&self, hir: &Hir, function_id: &FunctionId) -> Option<(PathBuf, Range)> {
+        let test_name = hir
+            .modules
+            .iter()
+            .flat_map(|module| module.test_defs.iter())
+            .find(|test_def| &test_def.function_id == function_id)
+            .map(|test_def| test_def.test_name.clone());
+        self.symbols
+            .iter()
+            .find(|symbol| {
+                self.files[symbol.file].pkg_name == function_id.pkg_name
+                    && match &test_name {
+                        // testcase rows are named "test/row"
+                        Some(test_name) => {
+                            symbol.kind == SymbolKind::Test
+                                && (test_name == &symbol.name
+                                    || test_name.starts_with(&format!("{}/", symbol.name)))
+                        }
+                        None => {
+                            matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method)
+                                && symbol.name == function_id.func_name
+                                && symbol.container == function_id.struct_name
+                        }
+                    }
+            })
+            .map(|symbol| {
+                (
+                    self.files[symbol.file].path.clone(),
+                    Range::of(&symbol.span),
+                )
+            })
+    }
+
+    fn add_file(&mut self, path: &Path, text: String, pkg_name: String) {
+        let tokens = guard(|| {
+            let mut lexer = Lexer::from_source(&text);
+            lexer.parse();
+            lexer.tokens
+        })
+        .unwrap_or_default();
+        let file = self.files.len();
+        self.symbols.append(&mut index_symbols(file, &tokens));
+        let code = tokens
+            .into_iter()
+            .filter(|token| !matches!(token.ty, Ty::Sp | Ty::NL | Ty::Indent(_) | Ty::Comment(_)))
+            .collect();
+        self.files.push(SourceFile {
+            path: path.to_path_buf(),
+            text,
+            code,
+            pkg_name,
+        });
+    }
+
+    fn add_errors(&mut self, path: &Path, line: usize, code: &'static str, messages: Vec<String>) {
+        let text = read_source(path, &HashMap::new()).unwrap_or_default();
+        let text = self
+            .files
+            .iter()
+            .find(|file| file.path == path)
+            .map_or(text, |file| file.text.clone());
+        for message in messages {
+            self.diagnostics.push(Diagnostic::error(
+                path,
+                Range::line(line, &text),
+                code,
+                message,
+            ));
+        }
+    }
+
+    // Parser errors have no locations so each top-level statement
+    // is parsed on its own to find the statements with errors
+    fn statement_errors(&mut self, path: &Path, text: &str, err: &anyhow::Error) {
+        let count = self.diagnostics.len();
+        for (line, statement) in statements(text) {
+            let parsed = guard(|| {
+                let mut ast = Ast::from_source(&statement);
+                let result = ast.parse();
+                (ast, result)
+            });
+            let errors = match parsed {
+                Ok((_, Ok(()))) => continue,
+                Ok((ast, Err(err))) => parse_errors(&ast, err),
+                Err(panic) => vec![panic],
+            };
+            for message in errors {
+                self.diagnostics.push(Diagnostic::error(
+                    path,
+                    Range::line(line, text),
+                    "parse-error",
+                    message,
+                ));
+            }
+        }
+        if self.diagnostics.len() == count {
+            let message = format!("{:#}", err);
+            self.diagnostics.push(Diagnostic::error(
+                path,
+                Range::line(0, text),
+                "parse-error",
+                message,
+            ));
+        }
+    }
+}
+
+fn read_source(path: &Path, documents: &HashMap<PathBuf, String>) -> Option<String> {
+    match documents.get(path) {
+        Some(text) => Some(text.clone()),
+        None => fs::read_to_string(path).ok(),
+    }
+}
+
+// The errors collected by the parser (or the error it bailed with)
+fn parse_errors(ast: &Ast, err: anyhow::Error) -> Vec<String> {
+    if ast.parsed.errors.is_empty() {
+        vec![format!("{:#}", err)]
+    } else {
+        ast.parsed
+            .errors
+            .iter()
+            .map(|err| format!("{:#}", err))
+            .collect()
+    }
+}
+
+// Splits source into top-level statements with their first line
+// (a statement starts at a line without indentation)
+pub fn statements(text: &str) -> Vec<(usize, String)> {
+    let mut statements: Vec<(usize, String)> = vec![];
+    for (line, source) in text.lines().enumerate() {
+        let starts = !source.is_empty()
+            && !source.starts_with([' ', '\t', '#', '}'])
+            && !source.trim().is_empty();
+        match statements.last_mut() {
+            Some((_, statement)) if !starts => {
+                statement.push_str(source);
+                statement.push('\n');
+            }
+            _ if source.trim().is_empty() || source.starts_with('#') => {}
+            _ => statements.push((line, format!("{}\n", source))),
+        }
+    }
+    statements
+}
+
+// Runs the lexer or parser (which panic on some bad input)
+pub fn guard<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|panic| {
+        panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "syntax error".into())
+    })
+}
@@ -1,15 +1,21 @@
 use anyhow::{bail, Context, Result};
 use log::info;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use yak_ast::Ast;
-use yak_compiler::compiler::{Compiler, CompilerOpts};
+use yak_compiler::compiler::{
+    Artifact, Compiler, CompilerOpts, Emit, LibKind, Linker, OptLevel, TargetOpts,
+};
 use yak_compiler::hir::Hir;
 use yak_core::models::yak_env::YakEnv;
 use yak_core::models::yak_home::YakHome;
-use yak_core::models::yak_package::YakPackage;
+use yak_core::models::yak_package::{Symbol, YakPackage};
 use yak_core::utils::download_file;
+use yak_lexer::fmt::format_source;
+use yak_lexer::Lexer;
+
+mod test;
 
 #[derive(Debug, Default)]
 pub struct YakPkg {
@@ -17,6 +23,16 @@ pub struct YakPkg {
     deps: HashMap<String, Self>,
     ast: Option<Ast>,
     hir: Option<Hir>,
+    // the linked binary (pkg root only)
+    bin_file: Option<String>,
+    // files written by the build (pkg root only)
+    artifacts: Vec<Artifact>,
+}
+
+impl YakPkg {
+    pub fn artifacts(&self) -> &[Artifact] {
+        &self.artifacts
+    }
 }
 
 #[derive(Debug, Default)]
@@ -102,10 +118,6 @@ pub fn get(args: &mut GetArgs) -> Result<YakPkg> {
         pkg_ast.parse_file(PathBuf::from(&pkg_file.local_path))?;
     }
 
-    info!("=============");
-    info!("{:#?}", &pkg_ast);
-    info!("=============");
-
     // store
     pkg.pkg = yak_pkg;
     pkg.ast = Some(pkg_ast);
@@ -119,6 +131,19 @@ pub struct BuildArgs {
     pub pkg_root: bool,
     pub pkg_as_pkg_id: Option<String>,
     pub path: String,
+    // build with the release profile instead of debug
+    pub release: bool,
+    // overrides the profile opt level
+    pub opt_level: Option<OptLevel>,
+    // build the test harness instead of :main
+    pub test: bool,
+    // intermediate artifacts to write (pkg root only)
+    pub emit: Vec<Emit>,
+    // the machine code target (pkg root only)
+    pub target: TargetOpts,
+    // override the package `link` settings (pkg root only)
+    pub linker: Option<Linker>,
+    pub lib_kind: Option<LibKind>,
 }
 
 pub fn build(args: BuildArgs) -> Result<YakPkg> {
@@ -131,21 +156,7 @@ pub fn build(args: BuildArgs) -> Result<YakPkg> {
     let cwd = yak_env.cwd()?;
     info!("current directory: {}", &cwd.display());
 
-    // package directory
-    let abs_path = fs::canonicalize(&args.path)
-        .with_context(|| format!("Failed to canonicalize build args path: {}", &args.path))?;
-    info!("checking abs path directory: {:?}", &abs_path);
-
-    // append yak.pkg to end of path
-    let pkgfile = if !abs_path.ends_with("/yak.pkg") {
-        PathBuf::from_iter([&abs_path, &PathBuf::from("yak.pkg")].iter())
-    } else {
-        abs_path.clone()
-    };
-
-    // check if yak.pkg file exists
-    let pkgfile = fs::canonicalize(&pkgfile)
-        .with_context(|| format!("Failed to find  package file path: {}", &pkgfile.display()))?;
+    let (pkgfile, pkg_local_path) = find_pkg_file(&args.path)?;
     info!("building package file: {:?}", &pkgfile);
 
     // parse the package file
@@ -154,11 +165,6 @@ pub fn build(args: BuildArgs) -> Result<YakPkg> {
     let parsed_pkg = pkg_ast.parsed.package.clone();
 
     // convert AST => yak_package
-    let mut pkg_local_path = abs_path.into_os_string().into_string().unwrap();
-    if pkg_local_path.ends_with("/yak.pkg") {
-        pkg_local_path = pkg_local_path.replace("/yak.pkg", "");
-    }
-
     // we only build local packages
     let mut yak_pkg = parsed_pkg.into_yak_package(
         args.pkg_root,
@@ -166,71 +172,84 @@ pub fn build(args: BuildArgs) -> Result<YakPkg> {
         pkg_local_path,
         None,
     )?;
-    info!("======PKG======");
-    info!("{:#?}", yak_pkg);
-    info!("===============");
+    info!("building package: {}", &yak_pkg.pkg_id);
 
     // create YakPkg
     let mut pkg = YakPkg::default();
 
-    // cache dependencies here
-    let remote_dep_urls = yak_pkg.get_remote_dep_urls()?;
-    info!("get remote dependencies: {:#?}", &remote_dep_urls);
-    for dep in remote_dep_urls {
-        // should get all remote deps
-        let mut get_args = GetArgs {
-            path: dep.1.into(),
-            pkg_root: false,
-        };
-        let _pkg = get(&mut get_args)?;
-    }
-
     // should build all dependencies
     // and skip the compiler phase...
-    let build_dep_paths = yak_pkg.get_local_dep_paths()?;
+    let build_dep_paths = dep_paths(&mut yak_pkg)?;
     info!("build dependency paths: {:#?}", &build_dep_paths);
     for dep in build_dep_paths {
         let build_args = BuildArgs {
             pkg_as_pkg_id: Some(dep.0.clone()),
             pkg_root: false,
             path: dep.1.into_os_string().into_string().unwrap(),
+            release: args.release,
+            opt_level: args.opt_level,
+            test: false,
+            emit: vec![],
+            target: TargetOpts::default(),
+            linker: None,
+            lib_kind: None,
         };
         let _pkg = build(build_args)?;
         pkg.deps.insert(dep.0, _pkg);
     }
 
     // build src code
+    // other targets build in `target/{triple}`
+    let output_dir = match &args.target.triple {
+        Some(triple) => format!("{}/target/{}", &yak_pkg.pkg_local_path, triple),
+        None => format!("{}/target", &yak_pkg.pkg_local_path),
+    };
     let pkg_files = yak_pkg.get_local_file_paths()?;
     for pkg_file in pkg_files.into_iter() {
+        if args.emit.contains(&Emit::Tokens) {
+            let artifact = emit_tokens(&output_dir, &yak_pkg.pkg_local_path, &pkg_file)?;
+            pkg.artifacts.push(artifact);
+        }
         pkg_ast.parse_file(pkg_file)?;
     }
-    info!("======AST======");
-    info!("{:#?}", &pkg_ast);
-    info!("===============");
+    if args.emit.contains(&Emit::Ast) {
+        let file_name = format!("{}.ast", &yak_pkg.pkg_id);
+        let contents = format!("{:#?}\n", &pkg_ast.parsed);
+        pkg.artifacts
+            .push(Emit::Ast.write(&output_dir, &file_name, &contents)?);
+    }
 
     let mut hir = Hir::default();
 
     // Parse pkg ast
     hir.from_ast(args.pkg_root, args.pkg_as_pkg_id, &pkg_ast)?;
 
-    info!("======HIR======");
-    info!("{:#?}", &hir);
-    info!("===============");
-
     // We only compile the pkg root
     if args.pkg_root {
         // Iterate all pkg.deps and merge modules
         merge_dep_hir_modules(&pkg, &mut hir);
         // Compiler options
-        let pkg_local_path = yak_pkg.pkg_local_path.clone();
-        let output_dir = format!("{}/target", &pkg_local_path);
+        let profile = build_profile(&yak_pkg, args.release, args.opt_level)?;
+        let (linker, lib_kind) = link_settings(&yak_pkg, args.linker, args.lib_kind)?;
         let compiler_opts = CompilerOpts {
             pkg_id: yak_pkg.pkg_id.clone(),
             pkg_local_path: yak_pkg.pkg_local_path.clone(),
             output_dir: output_dir,
+            overflow_checks: profile.overflow_checks,
+            debug_assertions: profile.debug_assertions,
+            debug_info: profile.debug_info,
+            opt_level: profile.opt_level,
+            linker,
+            lib_kind,
+            exports: export_names(&yak_pkg),
+            test: args.test,
+            emit: args.emit,
+            target: args.target,
         };
+        pkg.bin_file = Some(compiler_opts.bin_file());
         // Build package
-        Compiler::build(compiler_opts, hir.clone())?;
+        let mut artifacts = Compiler::build(compiler_opts, hir.clone())?;
+        pkg.artifacts.append(&mut artifacts);
     }
 
     // Pack pkg
@@ -241,6 +260,281 @@ pub fn build(args: BuildArgs) -> Result<YakPkg> {
     Ok(pkg)
 }
 
+// Build settings chosen by the profile
+#[derive(Debug)]
+struct BuildProfile {
+    opt_level: OptLevel,
+    overflow_checks: bool,
+    debug_assertions: bool,
+    debug_info: bool,
+}
+
+// The debug (or release) profile defaults with the package
+// `profile` settings and the opt level arg applied on top
+fn build_profile(
+    yak_pkg: &YakPackage,
+    release: bool,
+    opt_level: Option<OptLevel>,
+) -> Result<BuildProfile> {
+    let (name, mut profile) = if release {
+        let profile = BuildProfile {
+            opt_level: OptLevel::O3,
+            overflow_checks: false,
+            debug_assertions: false,
+            debug_info: false,
+        };
+        ("release", profile)
+    } else {
+        let profile = BuildProfile {
+            opt_level: OptLevel::O0,
+            overflow_checks: true,
+            debug_assertions: true,
+            debug_info: true,
+        };
+        ("debug", profile)
+    };
+    if let Some(pkg_profile) = yak_pkg.get_profile(name) {
+        if let Some(opt_level) = &pkg_profile.opt_level {
+            profile.opt_level = opt_level.parse()?;
+        }
+        if let Some(overflow_checks) = pkg_profile.overflow_checks {
+            profile.overflow_checks = overflow_checks;
+        }
+        if let Some(debug_assertions) = pkg_profile.debug_assertions {
+            profile.debug_assertions = debug_assertions;
+        }
+        if let Some(debug_info) = pkg_profile.debug_info {
+            profile.debug_info = debug_info;
+        }
+    }
+    if let Some(opt_level) = opt_level {
+        profile.opt_level = opt_level;
+    }
+    info!("build profile {}: {:?}", name, &profile);
+    Ok(profile)
+}
+
+// The package `link` settings with the linker and lib kind args applied on top
+fn link_settings(
+    yak_pkg: &YakPackage,
+    linker: Option<Linker>,
+    lib_kind: Option<LibKind>,
+) -> Result<(Linker, LibKind)> {
+    let linker = match (linker, &yak_pkg.pkg_link.linker) {
+        (Some(linker), _) => linker,
+        (None, Some(linker)) => linker.parse()?,
+        (None, None) => Linker::default(),
+    };
+    let lib_kind = match (lib_kind, &yak_pkg.pkg_link.lib) {
+        (Some(lib_kind), _) => lib_kind,
+        (None, Some(lib_kind)) => lib_kind.parse()?,
+        (None, None) => LibKind::default(),
+    };
+    info!("link with {} ({} lib)", linker.name(), lib_kind.name());
+    Ok((linker, lib_kind))
+}
+
+// The exported function and type names (constants and traits
+// don't have symbols to export)
+fn export_names(yak_pkg: &YakPackage) -> Vec<String> {
+    yak_pkg
+        .pkg_exports
+        .symbols
+        .iter()
+        .filter_map(|sym| match &sym.symbol {
+            Symbol::Func(name) | Symbol::Type(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Writes the tokens of a source file one per line
+// (i.e. `3:5-9 IdFunc(":main")`) to `target/tokens/{file}.tokens`
+fn emit_tokens(output_dir: &str, pkg_local_path: &str, pkg_file: &Path) -> Result<Artifact> {
+    let src = fs::read_to_string(pkg_file)
+        .with_context(|| format!("unable to read file: {}", &pkg_file.display()))?;
+    let mut lexer = Lexer::from_source(&src);
+    lexer.parse();
+    let mut contents = String::new();
+    for token in &lexer.tokens {
+        let span = &token.span;
+        contents.push_str(&format!(
+            "{}:{}-{} {:?}\n",
+            span.ln, span.start, span.end, token.ty
+        ));
+    }
+    // keep the source file directories (i.e. src/main.yak.tokens)
+    let file_name = pkg_file
+        .strip_prefix(pkg_local_path)
+        .unwrap_or(pkg_file)
+        .display()
+        .to_string();
+    let file_name = format!("{}.tokens", file_name.trim_start_matches('/'));
+    Emit::Tokens.write(output_dir, &file_name, &contents)
+}
+
+// Gets the remote dependencies of a package (so they're cached locally)
+// and returns the local paths of its dependencies by package id
+fn dep_paths(yak_pkg: &mut YakPackage) -> Result<Vec<(String, PathBuf)>> {
+    let remote_dep_urls = yak_pkg.get_remote_dep_urls()?;
+    info!("get remote dependencies: {:#?}", &remote_dep_urls);
+    for dep in remote_dep_urls {
+        // should get all remote deps
+        let mut get_args = GetArgs {
+            path: dep.1.into(),
+            pkg_root: false,
+        };
+        let _pkg = get(&mut get_args)?;
+    }
+    yak_pkg.get_local_dep_paths()
+}
+
+// Resolves the dependencies of a package (and theirs) like a build does
+// without compiling them. Dependencies which aren't found are skipped
+// so checks can report them with a location.
+pub fn resolve_deps(path: &str) -> Result<Vec<(String, PathBuf)>> {
+    let mut deps = vec![];
+    let mut visited = HashSet::new();
+    resolve_pkg_deps(path, None, &mut deps, &mut visited)?;
+    Ok(deps)
+}
+
+fn resolve_pkg_deps(
+    path: &str,
+    as_pkg_id: Option<String>,
+    deps: &mut Vec<(String, PathBuf)>,
+    visited: &mut HashSet<PathBuf>,
+) -> Result<()> {
+    let (pkgfile, pkg_local_path) = find_pkg_file(path)?;
+    if !visited.insert(pkgfile.clone()) {
+        return Ok(());
+    }
+    let mut pkg_ast = Ast::from_file(pkgfile)?;
+    pkg_ast.parse_package()?;
+    let pkg_root = as_pkg_id.is_none();
+    let mut yak_pkg = pkg_ast.parsed.package.clone().into_yak_package(
+        pkg_root,
+        as_pkg_id,
+        pkg_local_path,
+        None,
+    )?;
+    for (dep_pkg_id, dep_path) in dep_paths(&mut yak_pkg)? {
+        if !dep_path.join("yak.pkg").is_file() {
+            info!(
+                "dependency {} not found: {}",
+                &dep_pkg_id,
+                dep_path.display()
+            );
+            continue;
+        }
+        // packages may be shared (or cycle) through several dependencies
+        if deps.iter().any(|(_, path)| path == &dep_path) {
+            continue;
+        }
+        let path = dep_path.display().to_string();
+        deps.push((dep_pkg_id.clone(), dep_path));
+        resolve_pkg_deps(&path, Some(dep_pkg_id), deps, visited)?;
+    }
+    Ok(())
+}
+
+// Finds the package file for a package directory (or package file) path
+// and returns it with the package directory
+pub fn find_pkg_file(path: &str) -> Result<(PathBuf, String)> {
+    // package directory
+    let abs_path = fs::canonicalize(path)
+        .with_context(|| format!("Failed to canonicalize package path: {}", path))?;
+    info!("checking abs path directory: {:?}", &abs_path);
+
+    // append yak.pkg to end of path
+    let pkgfile = if !abs_path.ends_with("/yak.pkg") {
+        PathBuf::from_iter([&abs_path, &PathBuf::from("yak.pkg")].iter())
+    } else {
+        abs_path.clone()
+    };
+
+    // check if yak.pkg file exists
+    let pkgfile = fs::canonicalize(&pkgfile)
+        .with_context(|| format!("Failed to find  package file path: {}", &pkgfile.display()))?;
+
+    let mut pkg_local_path = abs_path.into_os_string().into_string().unwrap();
+    if pkg_local_path.ends_with("/yak.pkg") {
+        pkg_local_path = pkg_local_path.replace("/yak.pkg", "");
+    }
+    Ok((pkgfile, pkg_local_path))
+}
+
+// FmtArgs
+#[derive(Debug, Default)]
+pub struct FmtArgs {
+    pub path: String,
+    // report unformatted files without writing them
+    pub check: bool,
+}
+
+// Formats the package file and its source files
+// and returns the files which weren't formatted
+pub fn fmt(args: FmtArgs) -> Result<Vec<PathBuf>> {
+    info!("fmt args: {:?}", args);
+    let (pkgfile, pkg_local_path) = find_pkg_file(&args.path)?;
+
+    // parse the package file for its source files
+    let mut pkg_ast = Ast::from_file(pkgfile.clone())?;
+    pkg_ast.parse_package()?;
+    let parsed_pkg = pkg_ast.parsed.package.clone();
+    let mut yak_pkg = parsed_pkg.into_yak_package(true, None, pkg_local_path, None)?;
+
+    let mut files = vec![pkgfile];
+    files.append(&mut yak_pkg.get_local_file_paths()?);
+    let mut unformatted = vec![];
+    for file in files {
+        let src = fs::read_to_string(&file)
+            .with_context(|| format!("unable to read file: {}", &file.display()))?;
+        let formatted = format_source(&src);
+        if formatted == src {
+            continue;
+        }
+        if !args.check {
+            fs::write(&file, formatted)
+                .with_context(|| format!("unable to write file: {}", &file.display()))?;
+        }
+        unformatted.push(file);
+    }
+    Ok(unformatted)
+}
+
+// A test harness binary and its tests
+// (the harness runs a test by its index)
+#[derive(Debug, Default)]
+pub struct TestPkg {
+    pub bin_file: String,
+    pub tests: Vec<String>,
+}
+
+// Builds the test harness for the package tests
+// (tests in dependencies aren't included)
+pub fn test(args: BuildArgs) -> Result<TestPkg> {
+    let args = BuildArgs {
+        pkg_root: true,
+        test: true,
+        ..args
+    };
+    let pkg = build(args)?;
+    let bin_file = match &pkg.bin_file {
+        Some(bin_file) => bin_file.clone(),
+        None => bail!("package {} has no test binary", &pkg.pkg.pkg_id),
+    };
+    let tests = pkg
+        .hir
+        .iter()
+        .flat_map(|hir| hir.modules.iter())
+        .filter(|module| module.pkg_root)
+        .flat_map(|module| module.test_defs.iter())
+        .map(|test_def| test_def.test_name.clone())
+        .collect();
+    Ok(TestPkg { bin_file, tests })
+}
+
 fn merge_dep_hir_modules(pkg: &YakPkg, hir: &mut Hir) {
     for (_as_pkg_id, pkg_dep) in pkg.deps.iter() {
         if let Some(pkg_dep_hir) = &pkg_dep.hir {
@@ -0,0 +1,151 @@
+#[cfg(test)]
+use crate::{build, resolve_deps, BuildArgs};
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use yak_compiler::compiler::Emit;
+#[cfg(test)]
+use yak_compiler::compiler::LibKind;
+
+#[cfg(test)]
+const PKG: &str = r#"package     "test.emit"
+description "A package to emit"
+files {
+  "./src/lib.yak"
+}
+export {
+  :add
+}
+"#;
+
+#[cfg(test)]
+const LIB: &str = "fn :add { a: int b: int } int =>
+  return a + b
+";
+
+// An empty temp directory for a test
+#[cfg(test)]
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("yak-pkg-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::canonicalize(dir).unwrap()
+}
+
+// Writes the library package to a temp directory
+// (static libraries are archived so the yak runtime isn't needed)
+#[cfg(test)]
+fn write_pkg(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("yak.pkg"), PKG).unwrap();
+    fs::write(dir.join("src/lib.yak"), LIB).unwrap();
+    dir
+}
+
+// The artifacts of a build as (kind, file relative to the package)
+#[cfg(test)]
+fn build_artifacts(dir: &Path, emit: Vec<Emit>) -> Vec<(String, String)> {
+    let args = BuildArgs {
+        pkg_root: true,
+        path: dir.display().to_string(),
+        emit,
+        lib_kind: Some(LibKind::Static),
+        ..BuildArgs::default()
+    };
+    let pkg = build(args).unwrap();
+    pkg.artifacts()
+        .iter()
+        .map(|artifact| {
+            assert!(Path::new(&artifact.file).is_file(), "{}", &artifact.file);
+            let file = Path::new(&artifact.file).strip_prefix(dir).unwrap();
+            (artifact.kind.to_string(), file.display().to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn build_emit_files() {
+    let dir = write_pkg("emit");
+    let emit = "tokens,ast,hir,llvm-ir,asm,obj"
+        .split(',')
+        .map(|name| name.parse().unwrap())
+        .collect();
+    let artifacts = build_artifacts(&dir, emit);
+    let expected = [
+        ("tokens", "target/tokens/src/lib.yak.tokens"),
+        ("ast", "target/ast/test.emit.ast"),
+        ("hir", "target/hir/test.emit.hir"),
+        ("llvm-ir", "target/module/test.emit.ll"),
+        ("asm", "target/asm/test.emit.s"),
+        ("obj", "target/obj/test.emit.o"),
+        ("lib", "target/lib/libtest.emit.a"),
+    ];
+    let expected: Vec<(String, String)> = expected
+        .iter()
+        .map(|(kind, file)| (kind.to_string(), file.to_string()))
+        .collect();
+    assert_eq!(artifacts, expected);
+
+    // one `line:start-end Token` per line
+    let tokens = fs::read_to_string(dir.join("target/tokens/src/lib.yak.tokens")).unwrap();
+    assert_eq!(tokens.lines().next(), Some("1:1-1 Indent(0)"));
+    let ir = fs::read_to_string(dir.join("target/module/test.emit.ll")).unwrap();
+    assert!(ir.contains("@\"test.emit:add\"("));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn build_without_emit() {
+    let dir = write_pkg("no-emit");
+    let artifacts = build_artifacts(&dir, vec![]);
+    assert_eq!(
+        artifacts,
+        vec![("lib".to_string(), "target/lib/libtest.emit.a".to_string())]
+    );
+    // objects are still written to be linked
+    assert!(dir.join("target/obj/test.emit.o").is_file());
+    assert!(!dir.join("target/module").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// Writes a package with dependencies to a temp directory
+#[cfg(test)]
+fn write_dep_pkg(root: &Path, name: &str, deps: &[(&str, &str)]) {
+    let dir = root.join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let deps: String = deps
+        .iter()
+        .map(|(pkg_id, path)| format!("  {} \"{}\"\n", pkg_id, path))
+        .collect();
+    let pkg = format!(
+        "package     \"test.{}\"\ndescription \"A dependency\"\nfiles {{\n}}\ndependencies {{\n{}}}\n",
+        name, deps
+    );
+    fs::write(dir.join("yak.pkg"), pkg).unwrap();
+}
+
+#[test]
+fn resolve_nested_deps() {
+    let root = temp_dir("deps");
+    write_dep_pkg(
+        &root,
+        "app",
+        &[("test.lib", "../lib"), ("test.missing", "../missing")],
+    );
+    write_dep_pkg(&root, "lib", &[("test.util", "../util")]);
+    write_dep_pkg(&root, "util", &[("test.lib", "../lib")]);
+
+    // dependencies of dependencies resolve once (and missing ones are skipped)
+    let deps = resolve_deps(&root.join("app").display().to_string()).unwrap();
+    assert_eq!(
+        deps,
+        vec![
+            ("test.lib".to_string(), root.join("lib")),
+            ("test.util".to_string(), root.join("util")),
+        ]
+    );
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,114 @@
+use crate::panic::{format_panic, frames, to_str};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+// Exit status for failed assertions
+// (so the test runner can tell them apart from panics)
+pub const ASSERT_EXIT_CODE: i32 = 102;
+
+// How `:assert_eq` values are passed as 64-bit words
+// (keep in sync with the yak-compiler builtins)
+const KIND_INT: u64 = 0;
+const KIND_UINT: u64 = 1;
+const KIND_FLOAT: u64 = 2;
+const KIND_BOOL: u64 = 3;
+const KIND_STR: u64 = 4;
+
+unsafe fn format_value(kind: u64, word: u64) -> String {
+    match kind {
+        KIND_INT => (word as i64).to_string(),
+        KIND_UINT => word.to_string(),
+        KIND_FLOAT => format!("{:?}", f64::from_bits(word)),
+        KIND_BOOL => (word != 0).to_string(),
+        KIND_STR if word == 0 => "<null>".into(),
+        KIND_STR => format!(
+            "{:?}",
+            CStr::from_ptr(word as *const c_char).to_string_lossy()
+        ),
+        _ => format!("{:#x}", word),
+    }
+}
+
+unsafe fn values_eq(kind: u64, left: u64, right: u64) -> bool {
+    match kind {
+        KIND_FLOAT => f64::from_bits(left) == f64::from_bits(right),
+        KIND_STR if left == 0 || right == 0 => left == right,
+        KIND_STR => CStr::from_ptr(left as *const c_char) == CStr::from_ptr(right as *const c_char),
+        _ => left == right,
+    }
+}
+
+unsafe fn assert_failed(message: &str, file: *const c_char, line: u64, col: u64) -> ! {
+    eprint!(
+        "{}",
+        format_panic(message, &to_str(file), line, col, &frames())
+    );
+    std::process::exit(ASSERT_EXIT_CODE)
+}
+
+/// # Safety
+/// `file` must be a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn yak_assert(value: u64, file: *const c_char, line: u64, col: u64) {
+    if value == 0 {
+        assert_failed("assertion failed", file, line, col);
+    }
+}
+
+/// # Safety
+/// `file` must be a nul terminated string and
+/// String values must point to nul terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn yak_assert_eq(
+    kind: u64,
+    left: u64,
+    right: u64,
+    file: *const c_char,
+    line: u64,
+    col: u64,
+) {
+    if values_eq(kind, left, right) {
+        return;
+    }
+    let message = format!(
+        "assertion failed: left == right\n  left: {}\n right: {}",
+        format_value(kind, left),
+        format_value(kind, right)
+    );
+    assert_failed(&message, file, line, col);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn assert_eq_values() {
+        let a = CString::new("yak").unwrap();
+        let b = CString::new("yak").unwrap();
+        unsafe {
+            assert!(values_eq(KIND_INT, (-1i64) as u64, u64::MAX));
+            assert!(values_eq(KIND_FLOAT, 1.5f64.to_bits(), 1.5f64.to_bits()));
+            assert!(!values_eq(
+                KIND_FLOAT,
+                f64::NAN.to_bits(),
+                f64::NAN.to_bits()
+            ));
+            assert!(values_eq(KIND_STR, a.as_ptr() as u64, b.as_ptr() as u64));
+            assert!(!values_eq(KIND_STR, a.as_ptr() as u64, 0));
+        }
+    }
+
+    #[test]
+    fn assert_eq_format() {
+        let s = CString::new("a \"b\"").unwrap();
+        unsafe {
+            assert_eq!(format_value(KIND_INT, (-3i64) as u64), "-3");
+            assert_eq!(format_value(KIND_UINT, u64::MAX), "18446744073709551615");
+            assert_eq!(format_value(KIND_FLOAT, 2.0f64.to_bits()), "2.0");
+            assert_eq!(format_value(KIND_BOOL, 1), "true");
+            assert_eq!(format_value(KIND_STR, s.as_ptr() as u64), "\"a \\\"b\\\"\"");
+        }
+    }
+}
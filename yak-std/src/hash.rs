@@ -0,0 +1,127 @@
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+
+// Map and Set keys are passed around as 64-bit words.
+// Primitives are stored by value and everything else
+// (String, structs, etc.) as a pointer to the value.
+pub type HashFn = extern "C" fn(u64) -> u64;
+pub type EqFn = extern "C" fn(u64, u64) -> bool;
+// Frees a boxed word owned by a Map or Set (None for unboxed words)
+pub type DropFn = Option<extern "C" fn(u64)>;
+
+extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+// FNV-1a constants
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// Hash an int, uint, bool or char word
+#[no_mangle]
+pub extern "C" fn yak_hash_int(word: u64) -> u64 {
+    fnv1a(&word.to_le_bytes())
+}
+
+#[no_mangle]
+pub extern "C" fn yak_eq_int(a: u64, b: u64) -> bool {
+    a == b
+}
+
+// Floats are hashed by their bits so `-0.0` and `0.0` hash differently.
+#[no_mangle]
+pub extern "C" fn yak_hash_float(word: u64) -> u64 {
+    fnv1a(&word.to_le_bytes())
+}
+
+#[no_mangle]
+pub extern "C" fn yak_eq_float(a: u64, b: u64) -> bool {
+    f64::from_bits(a) == f64::from_bits(b)
+}
+
+// Hash a nul-terminated String word
+#[no_mangle]
+pub extern "C" fn yak_hash_str(word: u64) -> u64 {
+    if word == 0 {
+        return fnv1a(&[]);
+    }
+    let s = unsafe { CStr::from_ptr(word as *const c_char) };
+    fnv1a(s.to_bytes())
+}
+
+#[no_mangle]
+pub extern "C" fn yak_eq_str(a: u64, b: u64) -> bool {
+    if a == b {
+        return true;
+    }
+    if a == 0 || b == 0 {
+        return false;
+    }
+    let a = unsafe { CStr::from_ptr(a as *const c_char) };
+    let b = unsafe { CStr::from_ptr(b as *const c_char) };
+    a == b
+}
+
+// Frees a struct word boxed by the compiler with malloc
+#[no_mangle]
+pub extern "C" fn yak_free_word(word: u64) {
+    unsafe { free(word as *mut c_void) }
+}
+
+// Frees a boxed trait object word and the value it points to
+// (trait objects are `{ data, vtable }` and the data is boxed with malloc)
+#[no_mangle]
+pub extern "C" fn yak_free_object(word: u64) {
+    unsafe {
+        let object = word as *mut *mut c_void;
+        free(*object);
+        free(object as *mut c_void);
+    }
+}
+
+pub(crate) fn drop_word(drop_fn: DropFn, word: u64) {
+    if let Some(drop_fn) = drop_fn {
+        drop_fn(word);
+    }
+}
+
+// Combine two hashes. This is what user `^Hash` implementations
+// should use when hashing multiple struct fields.
+#[no_mangle]
+pub extern "C" fn yak_hash_combine(seed: u64, hash: u64) -> u64 {
+    seed ^ (hash
+        .wrapping_add(0x9e37_79b9_7f4a_7c15)
+        .wrapping_add(seed << 6)
+        .wrapping_add(seed >> 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn hash_str_matches_equal_strings() {
+        let a = CString::new("key1").unwrap();
+        let b = CString::new("key1").unwrap();
+        let c = CString::new("key2").unwrap();
+        let (a, b, c) = (a.as_ptr() as u64, b.as_ptr() as u64, c.as_ptr() as u64);
+        assert_eq!(yak_hash_str(a), yak_hash_str(b));
+        assert!(yak_eq_str(a, b));
+        assert!(!yak_eq_str(a, c));
+        assert!(!yak_eq_str(a, 0));
+    }
+
+    #[test]
+    fn hash_combine_is_order_dependent() {
+        let x = yak_hash_int(1);
+        let y = yak_hash_int(2);
+        assert_ne!(yak_hash_combine(x, y), yak_hash_combine(y, x));
+    }
+}
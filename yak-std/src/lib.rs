@@ -1,3 +1,10 @@
+pub mod assert;
+pub mod hash;
+pub mod list;
+pub mod map;
+pub mod panic;
+pub mod set;
+
 #[no_mangle]
 pub extern "C" fn print_int(x: i64) -> () {
     println!("printing: {}", x);
@@ -0,0 +1,237 @@
+use crate::hash::{drop_word, DropFn};
+
+// YakList is the runtime for the builtin `List[T]` type.
+// The list owns its items and frees boxed words using the
+// drop function when they're replaced or the list is freed.
+// Indexes past the end return false so the compiler can panic.
+pub struct YakList {
+    items: Vec<u64>,
+    drop_item: DropFn,
+}
+
+impl YakList {
+    pub fn new(drop_item: DropFn) -> Self {
+        YakList {
+            items: vec![],
+            drop_item,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn push(&mut self, item: u64) {
+        self.items.push(item);
+    }
+
+    pub fn get(&self, index: u64) -> Option<u64> {
+        self.items.get(index as usize).copied()
+    }
+
+    // Replaces the item at `index` and drops the old item
+    pub fn set(&mut self, index: u64, item: u64) -> bool {
+        match self.items.get_mut(index as usize) {
+            Some(slot) => {
+                let old = std::mem::replace(slot, item);
+                drop_word(self.drop_item, old);
+                true
+            }
+            None => {
+                drop_word(self.drop_item, item);
+                false
+            }
+        }
+    }
+
+    // The caller owns the popped item
+    pub fn pop(&mut self) -> Option<u64> {
+        self.items.pop()
+    }
+}
+
+impl Drop for YakList {
+    fn drop(&mut self) {
+        for item in self.items.drain(..) {
+            drop_word(self.drop_item, item);
+        }
+    }
+}
+
+// YakListIter iterates list items in order.
+// Mutating the list while iterating isn't supported.
+pub struct YakListIter {
+    list: *const YakList,
+    index: usize,
+}
+
+//
+// C ABI used by the compiler
+//
+
+#[no_mangle]
+pub extern "C" fn yak_list_new(drop_item: DropFn) -> *mut YakList {
+    Box::into_raw(Box::new(YakList::new(drop_item)))
+}
+
+/// # Safety
+/// `list` must be a pointer returned by `yak_list_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_free(list: *mut YakList) {
+    if !list.is_null() {
+        drop(Box::from_raw(list));
+    }
+}
+
+/// # Safety
+/// `list` must be a pointer returned by `yak_list_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_push(list: *mut YakList, item: u64) {
+    (*list).push(item)
+}
+
+// Returns false if the index is out of bounds
+/// # Safety
+/// `list` must be a pointer returned by `yak_list_new`
+/// and `out` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_get(list: *const YakList, index: u64, out: *mut u64) -> bool {
+    match (*list).get(index) {
+        Some(item) => {
+            *out = item;
+            true
+        }
+        None => false,
+    }
+}
+
+// Returns false if the index is out of bounds
+/// # Safety
+/// `list` must be a pointer returned by `yak_list_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_set(list: *mut YakList, index: u64, item: u64) -> bool {
+    (*list).set(index, item)
+}
+
+// Returns false if the list is empty
+/// # Safety
+/// `list` must be a pointer returned by `yak_list_new`
+/// and `out` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_pop(list: *mut YakList, out: *mut u64) -> bool {
+    match (*list).pop() {
+        Some(item) => {
+            *out = item;
+            true
+        }
+        None => false,
+    }
+}
+
+/// # Safety
+/// `list` must be a pointer returned by `yak_list_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_len(list: *const YakList) -> u64 {
+    (*list).len() as u64
+}
+
+#[no_mangle]
+pub extern "C" fn yak_list_iter(list: *const YakList) -> *mut YakListIter {
+    Box::into_raw(Box::new(YakListIter { list, index: 0 }))
+}
+
+/// # Safety
+/// `iter` must be a pointer returned by `yak_list_iter`
+/// and `item_out` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_iter_next(iter: *mut YakListIter, item_out: *mut u64) -> bool {
+    let iter = &mut *iter;
+    match (*iter.list).get(iter.index as u64) {
+        Some(item) => {
+            iter.index += 1;
+            *item_out = item;
+            true
+        }
+        None => false,
+    }
+}
+
+/// # Safety
+/// `iter` must be a pointer returned by `yak_list_iter`
+#[no_mangle]
+pub unsafe extern "C" fn yak_list_iter_free(iter: *mut YakListIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn list_push_get_set_pop() {
+        unsafe {
+            let list = yak_list_new(None);
+            yak_list_push(list, 7);
+            yak_list_push(list, 8);
+            assert_eq!(yak_list_len(list), 2);
+            let mut out = 0u64;
+            assert!(yak_list_get(list, 1, &mut out));
+            assert_eq!(out, 8);
+            assert!(!yak_list_get(list, 2, &mut out));
+            assert!(yak_list_set(list, 0, 9));
+            assert!(!yak_list_set(list, 2, 10));
+            assert!(yak_list_get(list, 0, &mut out));
+            assert_eq!(out, 9);
+
+            let iter = yak_list_iter(list);
+            let mut item = 0u64;
+            assert!(yak_list_iter_next(iter, &mut item));
+            assert_eq!(item, 9);
+            assert!(yak_list_iter_next(iter, &mut item));
+            assert_eq!(item, 8);
+            assert!(!yak_list_iter_next(iter, &mut item));
+            yak_list_iter_free(iter);
+
+            assert!(yak_list_pop(list, &mut out));
+            assert_eq!(out, 8);
+            assert!(yak_list_pop(list, &mut out));
+            assert!(!yak_list_pop(list, &mut out));
+            assert_eq!(yak_list_len(list), 0);
+            yak_list_free(list);
+        }
+    }
+
+    static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+    extern "C" fn count_drop(_word: u64) {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn list_drops_replaced_rejected_and_remaining_items() {
+        unsafe {
+            let list = yak_list_new(Some(count_drop));
+            yak_list_push(list, 1);
+            yak_list_push(list, 2);
+            // replaced
+            assert!(yak_list_set(list, 0, 3));
+            assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+            // out of bounds
+            assert!(!yak_list_set(list, 5, 4));
+            assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+            // popped items are owned by the caller
+            let mut out = 0u64;
+            assert!(yak_list_pop(list, &mut out));
+            assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+            yak_list_free(list);
+            assert_eq!(DROPPED.load(Ordering::SeqCst), 3);
+        }
+    }
+}
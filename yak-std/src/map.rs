@@ -0,0 +1,352 @@
+use crate::hash::{drop_word, DropFn, EqFn, HashFn};
+
+// Initial number of buckets for a new map
+const MAP_INITIAL_BUCKETS: usize = 8;
+
+// Resize once len > buckets * 3/4
+const MAP_LOAD_NUM: usize = 3;
+const MAP_LOAD_DEN: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    hash: u64,
+    key: u64,
+    value: u64,
+}
+
+// YakMap is the runtime for the builtin `Map[K V]` type.
+// This is a separate chaining hash map which calls back into
+// the compiled `^Hash` and `^Eq` implementations of the key type.
+// The map owns its keys and values and frees boxed words using the
+// drop functions when they're replaced, removed, or the map is freed.
+pub struct YakMap {
+    hash: HashFn,
+    eq: EqFn,
+    drop_key: DropFn,
+    drop_value: DropFn,
+    buckets: Vec<Vec<Entry>>,
+    len: usize,
+}
+
+impl YakMap {
+    pub fn new(hash: HashFn, eq: EqFn) -> Self {
+        YakMap::with_drop(hash, eq, None, None)
+    }
+
+    pub fn with_drop(hash: HashFn, eq: EqFn, drop_key: DropFn, drop_value: DropFn) -> Self {
+        YakMap {
+            hash,
+            eq,
+            drop_key,
+            drop_value,
+            buckets: vec![vec![]; MAP_INITIAL_BUCKETS],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn bucket_index(&self, hash: u64) -> usize {
+        (hash % self.buckets.len() as u64) as usize
+    }
+
+    fn find(&self, key: u64) -> (u64, usize, Option<usize>) {
+        let hash = (self.hash)(key);
+        let index = self.bucket_index(hash);
+        let pos = self.buckets[index]
+            .iter()
+            .position(|entry| entry.hash == hash && (self.eq)(entry.key, key));
+        (hash, index, pos)
+    }
+
+    // Returns the previous value if the key already existed.
+    // The existing key is kept so the new key is dropped.
+    pub fn insert(&mut self, key: u64, value: u64) -> Option<u64> {
+        let (hash, index, pos) = self.find(key);
+        if let Some(pos) = pos {
+            let entry = &mut self.buckets[index][pos];
+            let old = entry.value;
+            entry.value = value;
+            drop_word(self.drop_key, key);
+            drop_word(self.drop_value, old);
+            return Some(old);
+        }
+        self.buckets[index].push(Entry { hash, key, value });
+        self.len += 1;
+        if self.len * MAP_LOAD_DEN > self.buckets.len() * MAP_LOAD_NUM {
+            self.grow();
+        }
+        None
+    }
+
+    pub fn get(&self, key: u64) -> Option<u64> {
+        let (_, index, pos) = self.find(key);
+        pos.map(|pos| self.buckets[index][pos].value)
+    }
+
+    // The caller owns the removed value
+    pub fn remove(&mut self, key: u64) -> Option<u64> {
+        let (_, index, pos) = self.find(key);
+        pos.map(|pos| {
+            self.len -= 1;
+            let entry = self.buckets[index].swap_remove(pos);
+            drop_word(self.drop_key, entry.key);
+            entry.value
+        })
+    }
+
+    pub fn contains(&self, key: u64) -> bool {
+        self.find(key).2.is_some()
+    }
+
+    // Returns the entry at the iterator position
+    // and advances the position
+    fn next_entry(&self, bucket: &mut usize, index: &mut usize) -> Option<(u64, u64)> {
+        while *bucket < self.buckets.len() {
+            if let Some(entry) = self.buckets[*bucket].get(*index) {
+                *index += 1;
+                return Some((entry.key, entry.value));
+            }
+            *bucket += 1;
+            *index = 0;
+        }
+        None
+    }
+
+    fn grow(&mut self) {
+        let size = self.buckets.len() * 2;
+        let mut buckets: Vec<Vec<Entry>> = vec![vec![]; size];
+        for entry in self.buckets.drain(..).flatten() {
+            buckets[(entry.hash % size as u64) as usize].push(entry);
+        }
+        self.buckets = buckets;
+    }
+}
+
+impl Drop for YakMap {
+    fn drop(&mut self) {
+        for entry in self.buckets.iter().flatten() {
+            drop_word(self.drop_key, entry.key);
+            drop_word(self.drop_value, entry.value);
+        }
+    }
+}
+
+// YakMapIter iterates map entries in bucket order.
+// Mutating the map while iterating isn't supported.
+pub struct YakMapIter {
+    map: *const YakMap,
+    bucket: usize,
+    index: usize,
+}
+
+//
+// C ABI used by the compiler
+//
+
+#[no_mangle]
+pub extern "C" fn yak_map_new(
+    hash: HashFn,
+    eq: EqFn,
+    drop_key: DropFn,
+    drop_value: DropFn,
+) -> *mut YakMap {
+    Box::into_raw(Box::new(YakMap::with_drop(hash, eq, drop_key, drop_value)))
+}
+
+/// # Safety
+/// `map` must be a pointer returned by `yak_map_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_free(map: *mut YakMap) {
+    if !map.is_null() {
+        drop(Box::from_raw(map));
+    }
+}
+
+/// # Safety
+/// `map` must be a pointer returned by `yak_map_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_insert(map: *mut YakMap, key: u64, value: u64) -> bool {
+    (*map).insert(key, value).is_none()
+}
+
+/// # Safety
+/// `map` must be a pointer returned by `yak_map_new`
+/// and `out` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_get(map: *const YakMap, key: u64, out: *mut u64) -> bool {
+    match (*map).get(key) {
+        Some(value) => {
+            *out = value;
+            true
+        }
+        None => false,
+    }
+}
+
+/// # Safety
+/// `map` must be a pointer returned by `yak_map_new`
+/// and `out` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_remove(map: *mut YakMap, key: u64, out: *mut u64) -> bool {
+    match (*map).remove(key) {
+        Some(value) => {
+            *out = value;
+            true
+        }
+        None => false,
+    }
+}
+
+/// # Safety
+/// `map` must be a pointer returned by `yak_map_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_contains(map: *const YakMap, key: u64) -> bool {
+    (*map).contains(key)
+}
+
+/// # Safety
+/// `map` must be a pointer returned by `yak_map_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_len(map: *const YakMap) -> u64 {
+    (*map).len() as u64
+}
+
+#[no_mangle]
+pub extern "C" fn yak_map_iter(map: *const YakMap) -> *mut YakMapIter {
+    Box::into_raw(Box::new(YakMapIter {
+        map,
+        bucket: 0,
+        index: 0,
+    }))
+}
+
+/// # Safety
+/// `iter` must be a pointer returned by `yak_map_iter`
+/// and `key_out`/`value_out` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_iter_next(
+    iter: *mut YakMapIter,
+    key_out: *mut u64,
+    value_out: *mut u64,
+) -> bool {
+    let iter = &mut *iter;
+    match (*iter.map).next_entry(&mut iter.bucket, &mut iter.index) {
+        Some((key, value)) => {
+            *key_out = key;
+            *value_out = value;
+            true
+        }
+        None => false,
+    }
+}
+
+/// # Safety
+/// `iter` must be a pointer returned by `yak_map_iter`
+#[no_mangle]
+pub unsafe extern "C" fn yak_map_iter_free(iter: *mut YakMapIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{yak_eq_int, yak_eq_str, yak_hash_int, yak_hash_str};
+    use std::ffi::CString;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn map_insert_get_remove() {
+        let mut map = YakMap::new(yak_hash_int, yak_eq_int);
+        assert_eq!(map.insert(1, 10), None);
+        assert_eq!(map.insert(2, 20), None);
+        assert_eq!(map.insert(1, 11), Some(10));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(1), Some(11));
+        assert!(map.contains(2));
+        assert_eq!(map.remove(2), Some(20));
+        assert_eq!(map.remove(2), None);
+        assert!(!map.contains(2));
+        assert_eq!(map.len(), 1);
+    }
+
+    static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+    extern "C" fn count_drop(word: u64) {
+        DROPPED.fetch_add(word, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn map_drops_replaced_removed_and_remaining_words() {
+        let mut map = YakMap::with_drop(yak_hash_int, yak_eq_int, Some(count_drop), None);
+        map.insert(1, 10);
+        map.insert(2, 20);
+        // the duplicate key is dropped and the map keeps the first one
+        map.insert(1, 11);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+        assert_eq!(map.remove(2), Some(20));
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 3);
+        drop(map);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn map_grows_and_keeps_entries() {
+        let mut map = YakMap::new(yak_hash_int, yak_eq_int);
+        for i in 0..1000u64 {
+            map.insert(i, i * 2);
+        }
+        assert_eq!(map.len(), 1000);
+        assert!(map.buckets.len() > MAP_INITIAL_BUCKETS);
+        for i in 0..1000u64 {
+            assert_eq!(map.get(i), Some(i * 2));
+        }
+    }
+
+    #[test]
+    fn map_string_keys() {
+        let key1 = CString::new("key1").unwrap();
+        let key1_copy = CString::new("key1").unwrap();
+        let key2 = CString::new("key2").unwrap();
+        unsafe {
+            let map = yak_map_new(yak_hash_str, yak_eq_str, None, None);
+            assert!(yak_map_insert(map, key1.as_ptr() as u64, 1));
+            assert!(yak_map_insert(map, key2.as_ptr() as u64, 2));
+            assert!(!yak_map_insert(map, key1_copy.as_ptr() as u64, 3));
+            let mut out = 0u64;
+            assert!(yak_map_get(map, key1_copy.as_ptr() as u64, &mut out));
+            assert_eq!(out, 3);
+            assert_eq!(yak_map_len(map), 2);
+            yak_map_free(map);
+        }
+    }
+
+    #[test]
+    fn map_iter_visits_every_entry() {
+        unsafe {
+            let map = yak_map_new(yak_hash_int, yak_eq_int, None, None);
+            for i in 0..20u64 {
+                yak_map_insert(map, i, i + 100);
+            }
+            let iter = yak_map_iter(map);
+            let (mut key, mut value) = (0u64, 0u64);
+            let mut seen = vec![];
+            while yak_map_iter_next(iter, &mut key, &mut value) {
+                assert_eq!(value, key + 100);
+                seen.push(key);
+            }
+            yak_map_iter_free(iter);
+            seen.sort();
+            assert_eq!(seen, (0..20u64).collect::<Vec<u64>>());
+            yak_map_free(map);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+// Exit status for yak panics
+// (distinct from a `:main` exit status or a crash)
+pub const PANIC_EXIT_CODE: i32 = 101;
+
+// The maximum number of frames printed in a backtrace
+const MAX_BACKTRACE_FRAMES: usize = 64;
+
+thread_local! {
+    // Yak function names pushed on function entry and popped on return.
+    // Names point to constant strings emitted by the compiler.
+    static FRAMES: RefCell<Vec<*const c_char>> = const { RefCell::new(Vec::new()) };
+}
+
+/// # Safety
+/// `name` must be a nul terminated string which outlives the frame
+#[no_mangle]
+pub unsafe extern "C" fn yak_frame_push(name: *const c_char) {
+    FRAMES.with(|frames| frames.borrow_mut().push(name));
+}
+
+#[no_mangle]
+pub extern "C" fn yak_frame_pop() {
+    FRAMES.with(|frames| {
+        frames.borrow_mut().pop();
+    });
+}
+
+pub(crate) unsafe fn to_str(s: *const c_char) -> String {
+    if s.is_null() {
+        return "<unknown>".into();
+    }
+    CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+// The current backtrace (outermost frame first)
+pub(crate) fn frames() -> Vec<String> {
+    FRAMES.with(|frames| {
+        frames
+            .borrow()
+            .iter()
+            .map(|name| unsafe { to_str(*name) })
+            .collect()
+    })
+}
+
+// Formats the panic message, the source location and
+// the backtrace (innermost frame first)
+pub(crate) fn format_panic(
+    message: &str,
+    file: &str,
+    line: u64,
+    col: u64,
+    frames: &[String],
+) -> String {
+    let mut out = format!("panic: {}\n  at {}:{}:{}\n", message, file, line, col);
+    out.push_str("backtrace:\n");
+    for (i, frame) in frames.iter().rev().take(MAX_BACKTRACE_FRAMES).enumerate() {
+        out.push_str(&format!("  {}: {}\n", i, frame));
+    }
+    if frames.len() > MAX_BACKTRACE_FRAMES {
+        out.push_str(&format!(
+            "  ... {} more frames\n",
+            frames.len() - MAX_BACKTRACE_FRAMES
+        ));
+    }
+    out
+}
+
+// Prints the panic to stderr and exits with PANIC_EXIT_CODE
+/// # Safety
+/// `message` and `file` must be nul terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn yak_panic(
+    message: *const c_char,
+    file: *const c_char,
+    line: u64,
+    col: u64,
+) -> ! {
+    eprint!(
+        "{}",
+        format_panic(&to_str(message), &to_str(file), line, col, &frames())
+    );
+    std::process::exit(PANIC_EXIT_CODE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn frames_push_pop() {
+        let main = CString::new("my.pkg:main").unwrap();
+        let func = CString::new("my.pkg:func1").unwrap();
+        unsafe {
+            yak_frame_push(main.as_ptr());
+            yak_frame_push(func.as_ptr());
+        }
+        yak_frame_pop();
+        let frames: Vec<String> = FRAMES.with(|frames| {
+            frames
+                .borrow()
+                .iter()
+                .map(|name| unsafe { to_str(*name) })
+                .collect()
+        });
+        assert_eq!(frames, vec!["my.pkg:main".to_string()]);
+        yak_frame_pop();
+    }
+
+    #[test]
+    fn format_panic_backtrace() {
+        let frames = vec!["my.pkg:main".to_string(), "my.pkg#Point:div".to_string()];
+        let out = format_panic("attempt to divide by zero", "src/main.yak", 3, 14, &frames);
+        assert_eq!(
+            out,
+            "panic: attempt to divide by zero\n  at src/main.yak:3:14\nbacktrace:\n  0: my.pkg#Point:div\n  1: my.pkg:main\n"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::hash::{DropFn, EqFn, HashFn};
+use crate::map::{yak_map_iter, yak_map_iter_free, yak_map_iter_next, YakMap, YakMapIter};
+
+// YakSet is the runtime for the builtin `Set[T]` type.
+// Items are stored as map keys with an empty value
+// and boxed items are freed using the drop function.
+pub struct YakSet {
+    map: YakMap,
+}
+
+impl YakSet {
+    pub fn new(hash: HashFn, eq: EqFn, drop_item: DropFn) -> Self {
+        YakSet {
+            map: YakMap::with_drop(hash, eq, drop_item, None),
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn yak_set_new(hash: HashFn, eq: EqFn, drop_item: DropFn) -> *mut YakSet {
+    Box::into_raw(Box::new(YakSet::new(hash, eq, drop_item)))
+}
+
+/// # Safety
+/// `set` must be a pointer returned by `yak_set_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_free(set: *mut YakSet) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+// Returns true if the item didn't already exist
+/// # Safety
+/// `set` must be a pointer returned by `yak_set_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_insert(set: *mut YakSet, item: u64) -> bool {
+    (*set).map.insert(item, 0).is_none()
+}
+
+// Returns true if the item existed
+/// # Safety
+/// `set` must be a pointer returned by `yak_set_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_remove(set: *mut YakSet, item: u64) -> bool {
+    (*set).map.remove(item).is_some()
+}
+
+/// # Safety
+/// `set` must be a pointer returned by `yak_set_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_contains(set: *const YakSet, item: u64) -> bool {
+    (*set).map.contains(item)
+}
+
+/// # Safety
+/// `set` must be a pointer returned by `yak_set_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_len(set: *const YakSet) -> u64 {
+    (*set).map.len() as u64
+}
+
+/// # Safety
+/// `set` must be a pointer returned by `yak_set_new`
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_iter(set: *const YakSet) -> *mut YakMapIter {
+    yak_map_iter(&(*set).map)
+}
+
+/// # Safety
+/// `iter` must be a pointer returned by `yak_set_iter`
+/// and `item_out` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_iter_next(iter: *mut YakMapIter, item_out: *mut u64) -> bool {
+    let mut value = 0u64;
+    yak_map_iter_next(iter, item_out, &mut value)
+}
+
+/// # Safety
+/// `iter` must be a pointer returned by `yak_set_iter`
+#[no_mangle]
+pub unsafe extern "C" fn yak_set_iter_free(iter: *mut YakMapIter) {
+    yak_map_iter_free(iter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{yak_eq_int, yak_hash_int};
+
+    #[test]
+    fn set_insert_contains_remove() {
+        unsafe {
+            let set = yak_set_new(yak_hash_int, yak_eq_int, None);
+            assert!(yak_set_insert(set, 7));
+            assert!(!yak_set_insert(set, 7));
+            assert!(yak_set_insert(set, 8));
+            assert_eq!(yak_set_len(set), 2);
+            assert!(yak_set_contains(set, 7));
+            assert!(yak_set_remove(set, 7));
+            assert!(!yak_set_remove(set, 7));
+            assert!(!yak_set_contains(set, 7));
+
+            let iter = yak_set_iter(set);
+            let mut item = 0u64;
+            assert!(yak_set_iter_next(iter, &mut item));
+            assert_eq!(item, 8);
+            assert!(!yak_set_iter_next(iter, &mut item));
+            yak_set_iter_free(iter);
+            yak_set_free(set);
+        }
+    }
+}